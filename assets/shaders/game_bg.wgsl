@@ -82,7 +82,9 @@ struct VertexOutput {
 }
 
 struct CustomData {
-    time: f32
+    time: f32,
+    danger: f32,
+    flash: f32,
 }
 
 @group(3) @binding(0)
@@ -104,6 +106,8 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         rgb = correct(vec3(0.52941, 0.21176, 0.12941));
     };
     };
+    rgb = mix(rgb, correct(vec3(0.8, 0.05, 0.05)), custom_data.danger * 0.6);
+    rgb = mix(rgb, correct(vec3(1.0, 0.9, 0.6)), custom_data.flash * 0.7);
     return vec4(rgb, in.color.a);
 }
 
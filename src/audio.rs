@@ -0,0 +1,270 @@
+use enum_map::{Enum, EnumMap};
+use ggez::{
+    audio::{self, SoundSource},
+    Context, GameError, GameResult,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    music::{self, MusicDirector},
+    theme::Theme,
+};
+
+#[derive(Clone, Copy, Enum)]
+pub enum Sfx {
+    Move,
+    SoftDrop,
+    /// A move into a wall or the stack was blocked.
+    Bump,
+    /// The falling piece touched down.
+    Land,
+    Rotate,
+    Place,
+    Clear,
+    /// Rows were cleared with a T-spin.
+    TSpinClear,
+    /// Four or more rows were cleared at once.
+    Tetris,
+    /// The stack got close to the top.
+    Warning,
+    /// A number of the countdown before a game starts, and higher for GO.
+    Countdown,
+}
+
+/// Volumes of the music and sound effects, each from 0.0 to 1.0, both scaled by the master
+/// volume.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mixer {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    /// Silences everything without losing the volumes. Only lasts until the game is closed.
+    #[serde(skip)]
+    pub muted: bool,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self {
+            master: 1.,
+            music: 0.5,
+            sfx: 1.,
+            muted: false,
+        }
+    }
+}
+
+impl Mixer {
+    /// How much volume the keys and settings change at a time.
+    pub const STEP: f32 = 0.1;
+
+    fn master(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.master
+        }
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        self.master() * self.music
+    }
+
+    pub fn sfx_volume(&self) -> f32 {
+        self.master() * self.sfx
+    }
+
+    /// Changes a volume by `STEP` in the given direction (-1 or 1), keeping it in range.
+    pub fn adjust(volume: &mut f32, direction: i32) {
+        *volume = (*volume + direction as f32 * Self::STEP).clamp(0., 1.);
+    }
+}
+
+/// The music and every sound effect, loaded at startup and again when the theme changes.
+pub struct Audio {
+    /// Paths of the songs that play one after another.
+    playlist: Vec<String>,
+    /// Index into `playlist` of the song playing.
+    track: usize,
+    music: audio::Source,
+    /// Volume of the music, kept to give to each new song.
+    music_volume: f32,
+    /// Whether songs are picked at random once one ends, rather than in order.
+    pub shuffle: bool,
+    /// Layered music that follows how intense the game is, played instead of the playlist
+    /// when there are stems for it.
+    director: Option<MusicDirector>,
+    /// Whether the music is sped up, because the stack is close to the top.
+    hurried: bool,
+    sfx: EnumMap<Sfx, audio::Source>,
+}
+
+impl Audio {
+    /// Where songs are read from, in the resources or the user data directory, so that
+    /// players can add their own.
+    const MUSIC_DIRECTORY: &'static str = "/music";
+    /// Where the stems of the layered music are, if there are any.
+    const LAYERS_DIRECTORY: &'static str = "/music/layers";
+    const HURRIED_SPEED: f32 = 1.25;
+
+    /// Loads the sounds of `theme`. Its music plays on its own if it has any. Otherwise, its
+    /// layered music plays if it has stems for it, and then the built-in stems if there are
+    /// any. Failing all that, every song in `MUSIC_DIRECTORY` plays, in alphabetical order.
+    pub fn new(ctx: &mut Context, theme: &Theme) -> GameResult<Self> {
+        let music = theme.replacement(&theme.music);
+        let director = match (&music, theme.replacement(&theme.music_layers)) {
+            (Some(_), _) => None,
+            (None, Some(layers)) => MusicDirector::load(ctx, &layers)?,
+            (None, None) => MusicDirector::load(ctx, Self::LAYERS_DIRECTORY)?,
+        };
+        let playlist = match music {
+            Some(music) => vec![music],
+            None => music::songs_in(ctx, Self::MUSIC_DIRECTORY),
+        };
+        let first = playlist
+            .first()
+            .ok_or_else(|| GameError::ResourceNotFound(Self::MUSIC_DIRECTORY.to_owned(), vec![]))?;
+        let music = audio::Source::new(ctx, first)?;
+        let load = |replacement, default| audio::Source::new(ctx, theme.path(replacement, default));
+        Ok(Self {
+            playlist,
+            track: 0,
+            music,
+            music_volume: 1.,
+            shuffle: false,
+            director,
+            hurried: false,
+            // In the same order as `Sfx`
+            sfx: EnumMap::from_array([
+                load(&theme.move_sound, "/sound/move.wav")?,
+                load(&theme.soft_drop_sound, "/sound/soft_drop.wav")?,
+                load(&theme.bump_sound, "/sound/bump.wav")?,
+                load(&theme.land_sound, "/sound/land.wav")?,
+                load(&theme.rotate_sound, "/sound/rotate.ogg")?,
+                load(&theme.place_sound, "/sound/place.ogg")?,
+                load(&theme.clear_sound, "/sound/clear.wav")?,
+                load(&theme.t_spin_sound, "/sound/t_spin.wav")?,
+                load(&theme.tetris_sound, "/sound/tetris.wav")?,
+                load(&theme.warning_sound, "/sound/warning.wav")?,
+                load(&theme.countdown_sound, "/sound/countdown.wav")?,
+            ]),
+        })
+    }
+
+    /// Loads every sound effect again after `path` changed on disk. The music only starts
+    /// over if it was the song playing that changed.
+    #[cfg(debug_assertions)]
+    pub fn reload(&mut self, ctx: &mut Context, theme: &Theme, path: &str) -> GameResult {
+        let reloaded = Self::new(ctx, theme)?;
+        self.sfx = reloaded.sfx;
+        if let Some(director) = &self.director {
+            if director.has_stem(path) {
+                self.director = reloaded.director;
+                self.play_music(ctx)?;
+            }
+        } else if path == self.playlist[self.track] {
+            self.play_track(ctx, self.track)?;
+        }
+        Ok(())
+    }
+
+    fn speed(&self) -> f32 {
+        if self.hurried {
+            Self::HURRIED_SPEED
+        } else {
+            1.
+        }
+    }
+
+    pub fn play_music(&mut self, ctx: &Context) -> GameResult {
+        let speed = self.speed();
+        match &mut self.director {
+            Some(director) => director.play(ctx, speed),
+            None => self.music.play(ctx),
+        }
+    }
+
+    /// Moves on to the next song once the one playing ends, or fades the layered music
+    /// towards the game's intensity. Called every frame.
+    pub fn update(&mut self, ctx: &Context) -> GameResult {
+        if let Some(director) = &mut self.director {
+            director.update(ctx.time.delta(), self.music_volume);
+        } else if self.music.stopped() {
+            self.next_track(ctx)?;
+        }
+        Ok(())
+    }
+
+    /// How intense the game is, from 0.0 to 1.0, which decides how many layers of the
+    /// layered music are heard.
+    pub fn set_intensity(&mut self, intensity: f32) {
+        if let Some(director) = &mut self.director {
+            director.set_intensity(intensity);
+        }
+    }
+
+    /// Skips to the next song, or to a random other one when shuffling. Layered music only
+    /// has one song, so it keeps playing.
+    pub fn next_track(&mut self, ctx: &Context) -> GameResult {
+        if self.director.is_some() {
+            return Ok(());
+        }
+        let count = self.playlist.len();
+        let next = if self.shuffle && count > 1 {
+            (self.track + rand::thread_rng().gen_range(1..count)) % count
+        } else {
+            (self.track + 1) % count
+        };
+        self.play_track(ctx, next)
+    }
+
+    /// Loads and plays a song from the playlist, at the current volume and speed.
+    fn play_track(&mut self, ctx: &Context, track: usize) -> GameResult {
+        let mut music = audio::Source::new(ctx, &self.playlist[track])?;
+        music.set_volume(self.music_volume);
+        music.set_pitch(self.speed());
+        self.music = music;
+        self.track = track;
+        self.play_music(ctx)
+    }
+
+    /// Speeds the music up or brings it back to normal. The music can only change speed
+    /// when it starts, so it starts over whenever this changes.
+    pub fn set_hurried(&mut self, ctx: &Context, hurried: bool) -> GameResult {
+        if hurried == self.hurried {
+            return Ok(());
+        }
+        self.hurried = hurried;
+        self.music.set_pitch(self.speed());
+        self.play_music(ctx)
+    }
+
+    /// A line about the layered music for the debug overlay, if it's playing.
+    pub fn debug_line(&self) -> Option<String> {
+        self.director.as_ref().map(MusicDirector::debug_line)
+    }
+
+    pub fn set_volumes(&mut self, mixer: &Mixer) {
+        self.music_volume = mixer.music_volume();
+        self.music.set_volume(self.music_volume);
+        for (_, source) in &mut self.sfx {
+            source.set_volume(mixer.sfx_volume());
+        }
+    }
+
+    /// Plays a sound effect. Sounds that fail to play are skipped, since the game goes on
+    /// fine without them.
+    pub fn play(&mut self, ctx: &Context, sfx: Sfx) {
+        self.play_pitched(ctx, sfx, 1.);
+    }
+
+    /// Plays a sound effect sped up or slowed down by `ratio`, which raises or lowers its
+    /// pitch.
+    pub fn play_pitched(&mut self, ctx: &Context, sfx: Sfx, ratio: f32) {
+        let source = &mut self.sfx[sfx];
+        source.set_pitch(ratio);
+        let _ = source.play(ctx);
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    io::{self, ErrorKind, Write},
+    process::{Command, Stdio},
+};
+
+// The windowing library has no clipboard, so text goes through the tools each platform
+// comes with, trying each in turn until one is installed.
+#[cfg(target_os = "windows")]
+const COPY: &[&[&str]] = &[&["clip"]];
+#[cfg(target_os = "windows")]
+const PASTE: &[&[&str]] = &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard"]];
+#[cfg(target_os = "macos")]
+const COPY: &[&[&str]] = &[&["pbcopy"]];
+#[cfg(target_os = "macos")]
+const PASTE: &[&[&str]] = &[&["pbpaste"]];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const COPY: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const PASTE: &[&[&str]] = &[
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-o"],
+    &["xsel", "--clipboard", "--output"],
+];
+
+pub fn copy(text: &str) -> io::Result<()> {
+    first_working(COPY, |command| {
+        let mut child = command.stdin(Stdio::piped()).spawn()?;
+        child
+            .stdin
+            .take()
+            .ok_or(ErrorKind::BrokenPipe)?
+            .write_all(text.as_bytes())?;
+        succeeded(child.wait()?.success())
+    })
+}
+
+pub fn paste() -> io::Result<String> {
+    first_working(PASTE, |command| {
+        let output = command.stderr(Stdio::null()).output()?;
+        succeeded(output.status.success())?;
+        String::from_utf8(output.stdout).map_err(|_| ErrorKind::InvalidData.into())
+    })
+}
+
+/// Runs `run` with each of `commands` until one works, failing with the last error if none
+/// do.
+fn first_working<T>(
+    commands: &[&[&str]],
+    mut run: impl FnMut(&mut Command) -> io::Result<T>,
+) -> io::Result<T> {
+    let mut error = io::Error::from(ErrorKind::NotFound);
+    for (program, args) in commands.iter().filter_map(|command| command.split_first()) {
+        let mut command = Command::new(program);
+        command.args(args);
+        match run(&mut command) {
+            Ok(value) => return Ok(value),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+fn succeeded(success: bool) -> io::Result<()> {
+    if success {
+        Ok(())
+    } else {
+        Err(io::Error::other("the clipboard tool failed"))
+    }
+}
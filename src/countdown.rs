@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use ggez::{
+    graphics::{self, DrawParam, Rect, TextAlign, TextLayout},
+    mint::Point2,
+};
+
+use crate::render::ui_text;
+
+/// Counts 3, 2, 1 over the board before a game starts or resumes, while it's held still,
+/// then shows GO for a moment as it gets going.
+pub struct Countdown {
+    /// Real time since the countdown started, since the game doesn't run during it.
+    elapsed: Duration,
+    /// The beat reached so far, counting from 0 for the first number.
+    beat: Option<u32>,
+}
+
+impl Countdown {
+    const BEAT: Duration = Duration::from_millis(600);
+    /// Numbers counted down before GO.
+    pub const COUNT: u32 = 3;
+
+    pub fn new() -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            beat: None,
+        }
+    }
+
+    /// Moves the countdown on by `delta` of real time, returning the beat it reached if it
+    /// reached one: from 0 for the first number to `COUNT` for GO. A long frame counts as
+    /// one beat at most, so that none are skipped.
+    pub fn advance(&mut self, delta: Duration) -> Option<u32> {
+        self.elapsed += delta.min(Self::BEAT);
+        let beat = self.current_beat();
+        (self.beat != Some(beat) && beat <= Self::COUNT).then(|| {
+            self.beat = Some(beat);
+            beat
+        })
+    }
+
+    /// Whether the game is still held, before GO.
+    pub fn is_counting(&self) -> bool {
+        self.current_beat() < Self::COUNT
+    }
+
+    /// Whether GO has been shown for a whole beat, and the countdown can be dropped.
+    pub fn is_over(&self) -> bool {
+        self.current_beat() > Self::COUNT
+    }
+
+    fn current_beat(&self) -> u32 {
+        (self.elapsed.as_millis() / Self::BEAT.as_millis()) as u32
+    }
+
+    /// Draws the number or GO in the middle of `board`.
+    pub fn draw(&self, canvas: &mut graphics::Canvas, board: Rect) {
+        let Some(beat) = self.beat else {
+            return;
+        };
+        let label = if beat < Self::COUNT {
+            (Self::COUNT - beat).to_string()
+        } else {
+            "GO".to_owned()
+        };
+        let mut text = ui_text(label, 48.);
+        text.set_layout(TextLayout {
+            h_align: TextAlign::Middle,
+            v_align: TextAlign::Middle,
+        });
+        canvas.draw(
+            &text,
+            DrawParam::default().dest(Point2 {
+                x: board.center().x,
+                y: board.center().y,
+            }),
+        );
+    }
+}
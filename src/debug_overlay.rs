@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use ggez::{
+    graphics::{self, Color, DrawParam, Quad, Rect},
+    mint::Point2,
+    Context,
+};
+
+use crate::render::ui_text;
+
+/// Numbers to help diagnose performance and logic issues, toggled with F3: frame rate, how
+/// long updating and drawing take, and whatever the current scene reports.
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub shown: bool,
+    /// How long the last update took.
+    pub update_time: Duration,
+    /// How long the last frame took to draw, without the overlay itself.
+    pub draw_time: Duration,
+}
+
+impl DebugOverlay {
+    const LINE_HEIGHT: f32 = 12.;
+
+    /// Draws the overlay in the top right corner of `area`, followed by `scene_lines`.
+    pub fn draw(
+        &self,
+        ctx: &Context,
+        canvas: &mut graphics::Canvas,
+        area: Rect,
+        scene_lines: Vec<String>,
+    ) {
+        let mut lines = vec![
+            format!("FPS {:.0}", ctx.time.fps()),
+            format!("UPDATE {:.2}MS", self.update_time.as_secs_f64() * 1000.),
+            format!("DRAW {:.2}MS", self.draw_time.as_secs_f64() * 1000.),
+        ];
+        lines.extend(scene_lines);
+        let width = 120.;
+        let origin = Point2 {
+            x: area.right() - width - 4.,
+            y: area.y + 4.,
+        };
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest_rect(Rect::new(
+                    origin.x - 4.,
+                    origin.y - 2.,
+                    width + 8.,
+                    lines.len() as f32 * Self::LINE_HEIGHT + 4.,
+                ))
+                .color(Color::new(0., 0., 0., 0.6)),
+        );
+        for (i, line) in lines.into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(line, 10.),
+                DrawParam::default().dest(Point2 {
+                    x: origin.x,
+                    y: origin.y + i as f32 * Self::LINE_HEIGHT,
+                }),
+            );
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use ggez::{
+    graphics::{self, Color, DrawParam, Rect, TextAlign, TextLayout},
+    mint::Point2,
+};
+use tetris_core::{ClearKind, Game, GameEvent};
+
+use crate::render::ui_text;
+
+/// A message shown over the board for a moment after something worth celebrating.
+struct Popup {
+    text: String,
+    /// Game time the popup has been shown for.
+    age: Duration,
+}
+
+/// How fast the game is being played, worked out again every second of game time from what
+/// its events added up to so far.
+#[derive(Default)]
+struct LiveStats {
+    pieces: u32,
+    lines: u32,
+    /// Rows of garbage the clears were worth.
+    attack: u32,
+    /// Game time since the game started.
+    elapsed: Duration,
+    /// Game time since the rates were last worked out.
+    since_update: Duration,
+    pieces_per_second: f32,
+    attack_per_minute: f32,
+    lines_per_minute: f32,
+}
+
+impl LiveStats {
+    const UPDATE_TIME: Duration = Duration::from_secs(1);
+
+    fn step(&mut self, events: &[GameEvent]) {
+        for event in events {
+            match *event {
+                GameEvent::PieceLocked(_) => self.pieces += 1,
+                GameEvent::LinesCleared { count, kind } => {
+                    self.lines += count;
+                    self.attack += Game::attack(count, kind);
+                }
+                _ => {}
+            }
+        }
+        self.elapsed += Game::FRAME_TIME;
+        self.since_update += Game::FRAME_TIME;
+        if self.since_update >= Self::UPDATE_TIME {
+            self.since_update = Duration::ZERO;
+            let seconds = self.elapsed.as_secs_f32();
+            self.pieces_per_second = self.pieces as f32 / seconds;
+            self.attack_per_minute = self.attack as f32 * 60. / seconds;
+            self.lines_per_minute = self.lines as f32 * 60. / seconds;
+        }
+    }
+
+    fn lines(&self) -> [String; 3] {
+        [
+            format!("PPS {:.2}", self.pieces_per_second),
+            format!("APM {:.1}", self.attack_per_minute),
+            format!("LPM {:.1}", self.lines_per_minute),
+        ]
+    }
+}
+
+/// The text drawn around the board during a game: the stats of the mode on the left, and
+/// popups for special clears over the board.
+#[derive(Default)]
+pub struct Hud {
+    popups: Vec<Popup>,
+    live_stats: LiveStats,
+    /// Whether the last line clear was a tetris or a T-spin, which the next one has to be too
+    /// to count as back to back.
+    back_to_back: bool,
+}
+
+impl Hud {
+    const POPUP_TIME: Duration = Duration::from_secs(1);
+    /// How far popups float up over their lifetime.
+    const POPUP_RISE: f32 = 24.;
+
+    /// Shows popups for the events of a step, and ages the ones already shown.
+    pub fn step(&mut self, events: &[GameEvent]) {
+        for popup in &mut self.popups {
+            popup.age += Game::FRAME_TIME;
+        }
+        self.popups.retain(|popup| popup.age < Self::POPUP_TIME);
+        self.live_stats.step(events);
+
+        for event in events {
+            match *event {
+                GameEvent::LinesCleared { count, kind } => {
+                    let name = match count {
+                        1 => "SINGLE",
+                        2 => "DOUBLE",
+                        3 => "TRIPLE",
+                        _ => "TETRIS!",
+                    };
+                    let difficult = count >= 4 || kind == ClearKind::TSpin;
+                    if difficult && self.back_to_back {
+                        self.show("B2B");
+                    }
+                    self.back_to_back = difficult;
+                    match kind {
+                        ClearKind::TSpin => self.show(format!("T-SPIN {}", name)),
+                        ClearKind::Normal if count >= 4 => self.show(name),
+                        ClearKind::Normal => {}
+                    }
+                }
+                GameEvent::Chain { chain } => self.show(format!("{} CHAIN", chain)),
+                GameEvent::LevelUp { level } => self.show(format!("LEVEL {}", level)),
+                _ => {}
+            }
+        }
+    }
+
+    fn show(&mut self, text: impl Into<String>) {
+        self.popups.push(Popup {
+            text: text.into(),
+            age: Duration::ZERO,
+        });
+    }
+
+    /// Draws the stats of `game`, followed by `best` if the mode has a record and how fast
+    /// it's being played if `live_stats` is set, and the popups centered over `board`.
+    pub fn draw(
+        &self,
+        canvas: &mut graphics::Canvas,
+        game: &Game,
+        best: Option<String>,
+        live_stats: bool,
+        board: Rect,
+    ) {
+        let mut lines = game.mode().hud_lines(&game.stats());
+        lines.extend(best.map(|best| format!("BEST {}", best)));
+        let live_stats_y = 24. + lines.len() as f32 * 20.;
+        for (i, line) in lines.into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(line, 16.),
+                DrawParam::default().dest(Point2 {
+                    x: 8.,
+                    y: 16. + i as f32 * 20.,
+                }),
+            );
+        }
+        if live_stats {
+            for (i, line) in self.live_stats.lines().into_iter().enumerate() {
+                canvas.draw(
+                    &ui_text(line, 12.),
+                    DrawParam::default().dest(Point2 {
+                        x: 8.,
+                        y: live_stats_y + i as f32 * 14.,
+                    }),
+                );
+            }
+        }
+
+        // The newest popup is drawn at the bottom, pushing older ones up.
+        let count = self.popups.len();
+        for (i, popup) in self.popups.iter().enumerate() {
+            let t = popup.age.as_secs_f32() / Self::POPUP_TIME.as_secs_f32();
+            let mut text = ui_text(popup.text.as_str(), 16.);
+            text.set_layout(TextLayout {
+                h_align: TextAlign::Middle,
+                v_align: TextAlign::Middle,
+            });
+            let y = board.center().y - (count - 1 - i) as f32 * 20. - t * Self::POPUP_RISE;
+            canvas.draw(
+                &text,
+                DrawParam::default()
+                    .dest(Point2 {
+                        x: board.center().x,
+                        y,
+                    })
+                    .color(Color::new(1., 1., 0.5, 1. - t * t)),
+            );
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use std::{collections::HashSet, time::Duration};
+
+use enum_map::Enum;
+use ggez::{
+    event::{Axis, Button},
+    graphics::Rect,
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context,
+};
+use serde::{Deserialize, Serialize};
+use tetris_core::{Action, FrameInput};
+
+use crate::settings::Settings;
+
+/// Parts of the actions that only matter to this frontend.
+trait ActionExt {
+    /// Gamepad buttons that trigger this action. These aren't rebindable.
+    fn gamepad_buttons(self) -> &'static [Button];
+}
+
+impl ActionExt for Action {
+    fn gamepad_buttons(self) -> &'static [Button] {
+        match self {
+            Action::MoveLeft => &[Button::DPadLeft],
+            Action::MoveRight => &[Button::DPadRight],
+            Action::SoftDrop => &[Button::DPadDown],
+            Action::HardDrop => &[Button::DPadUp],
+            Action::RotateCw => &[Button::South, Button::West],
+            Action::RotateCcw => &[Button::East],
+            Action::Rotate180 => &[Button::LeftTrigger2, Button::RightTrigger2],
+            Action::Hold => &[Button::North, Button::LeftTrigger, Button::RightTrigger],
+            Action::Pause => &[Button::Start],
+            Action::Restart => &[Button::Select],
+            Action::Undo => &[Button::LeftThumb],
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    move_left: VirtualKeyCode,
+    move_right: VirtualKeyCode,
+    soft_drop: VirtualKeyCode,
+    hard_drop: VirtualKeyCode,
+    rotate_cw: VirtualKeyCode,
+    rotate_ccw: VirtualKeyCode,
+    rotate_180: VirtualKeyCode,
+    hold: VirtualKeyCode,
+    pause: VirtualKeyCode,
+    restart: VirtualKeyCode,
+    undo: VirtualKeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            move_left: VirtualKeyCode::Left,
+            move_right: VirtualKeyCode::Right,
+            soft_drop: VirtualKeyCode::Down,
+            hard_drop: VirtualKeyCode::Space,
+            rotate_cw: VirtualKeyCode::Up,
+            rotate_ccw: VirtualKeyCode::Z,
+            rotate_180: VirtualKeyCode::A,
+            hold: VirtualKeyCode::C,
+            pause: VirtualKeyCode::Escape,
+            restart: VirtualKeyCode::R,
+            undo: VirtualKeyCode::Back,
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Keys on the left of the keyboard, for a second player sharing it with the first.
+    pub fn left_hand() -> Self {
+        Self {
+            move_left: VirtualKeyCode::A,
+            move_right: VirtualKeyCode::D,
+            soft_drop: VirtualKeyCode::S,
+            hard_drop: VirtualKeyCode::W,
+            rotate_cw: VirtualKeyCode::E,
+            rotate_ccw: VirtualKeyCode::Q,
+            rotate_180: VirtualKeyCode::R,
+            hold: VirtualKeyCode::LShift,
+            pause: VirtualKeyCode::Escape,
+            restart: VirtualKeyCode::T,
+            undo: VirtualKeyCode::G,
+        }
+    }
+
+    pub fn key(&self, action: Action) -> VirtualKeyCode {
+        match action {
+            Action::MoveLeft => self.move_left,
+            Action::MoveRight => self.move_right,
+            Action::SoftDrop => self.soft_drop,
+            Action::HardDrop => self.hard_drop,
+            Action::RotateCw => self.rotate_cw,
+            Action::RotateCcw => self.rotate_ccw,
+            Action::Rotate180 => self.rotate_180,
+            Action::Hold => self.hold,
+            Action::Pause => self.pause,
+            Action::Restart => self.restart,
+            Action::Undo => self.undo,
+        }
+    }
+
+    fn key_mut(&mut self, action: Action) -> &mut VirtualKeyCode {
+        match action {
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+            Action::SoftDrop => &mut self.soft_drop,
+            Action::HardDrop => &mut self.hard_drop,
+            Action::RotateCw => &mut self.rotate_cw,
+            Action::RotateCcw => &mut self.rotate_ccw,
+            Action::Rotate180 => &mut self.rotate_180,
+            Action::Hold => &mut self.hold,
+            Action::Pause => &mut self.pause,
+            Action::Restart => &mut self.restart,
+            Action::Undo => &mut self.undo,
+        }
+    }
+
+    /// Binds a key to an action. Whichever action was using that key before gets the
+    /// action's old key instead, so that no key triggers two actions.
+    pub fn bind(&mut self, action: Action, key: VirtualKeyCode) {
+        let old_key = self.key(action);
+        for other in (0..Action::LENGTH).map(Action::from_usize) {
+            if self.key(other) == key {
+                *self.key_mut(other) = old_key;
+            }
+        }
+        *self.key_mut(action) = key;
+    }
+}
+
+/// Buttons held on any connected gamepad. The left stick acts as an extra d-pad.
+#[derive(Default)]
+pub struct GamepadState {
+    held: HashSet<Button>,
+    just_pressed: HashSet<Button>,
+}
+
+impl GamepadState {
+    const STICK_THRESHOLD: f32 = 0.5;
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.held.contains(&button)
+    }
+
+    pub fn is_just_pressed(&self, button: Button) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    pub fn any_just_pressed(&self) -> bool {
+        !self.just_pressed.is_empty()
+    }
+
+    pub fn press(&mut self, button: Button) {
+        if self.held.insert(button) {
+            self.just_pressed.insert(button);
+        }
+    }
+
+    pub fn release(&mut self, button: Button) {
+        self.held.remove(&button);
+    }
+
+    pub fn move_axis(&mut self, axis: Axis, value: f32) {
+        // Pushing the stick up doesn't hard drop, since that's too easy to do by accident
+        let directions: &[(Button, bool)] = match axis {
+            Axis::LeftStickX => &[
+                (Button::DPadLeft, value < -Self::STICK_THRESHOLD),
+                (Button::DPadRight, value > Self::STICK_THRESHOLD),
+            ],
+            Axis::LeftStickY => &[(Button::DPadDown, value < -Self::STICK_THRESHOLD)],
+            _ => &[],
+        };
+        for &(button, active) in directions {
+            if active {
+                self.press(button);
+            } else {
+                self.release(button);
+            }
+        }
+    }
+
+    /// Forgets which buttons were just pressed. Called at the end of every update, like
+    /// ggez does for the keyboard.
+    pub fn end_frame(&mut self) {
+        self.just_pressed.clear();
+    }
+}
+
+struct Touch {
+    start: Point2<f32>,
+    /// Time since the game started, from ggez's clock, when the touch began.
+    started: Duration,
+    position: Point2<f32>,
+    /// Where the last horizontal step was taken from.
+    anchor: Point2<f32>,
+    /// Whether the touch has already moved the piece, so it can't count as a tap.
+    moved: bool,
+}
+
+/// Turns touch (or mouse) gestures into actions: swiping sideways moves the piece one cell
+/// per step, dragging down soft drops, flicking down hard drops and tapping rotates.
+#[derive(Default)]
+pub struct TouchState {
+    touch: Option<Touch>,
+    /// Horizontal step taken this frame, -1, 0 or 1.
+    shift: i32,
+    soft_dropping: bool,
+    flicked: bool,
+    tapped: Option<Point2<f32>>,
+    /// Whether the screen has been touched at all, to only show touch buttons when needed.
+    pub used: bool,
+}
+
+impl TouchState {
+    const SHIFT_DISTANCE: f32 = 16.;
+    const SOFT_DROP_DISTANCE: f32 = 24.;
+    const FLICK_DISTANCE: f32 = 48.;
+    const TAP_DISTANCE: f32 = 8.;
+    const GESTURE_TIME: Duration = Duration::from_millis(250);
+
+    /// On-screen buttons for the actions that don't have a gesture.
+    pub const BUTTONS: [(Action, &'static str, Rect); 3] = [
+        (Action::Hold, "HOLD", Rect::new(8., 196., 48., 32.)),
+        (Action::RotateCcw, "CCW", Rect::new(64., 196., 48., 32.)),
+        (Action::Pause, "PAUSE", Rect::new(8., 236., 104., 32.)),
+    ];
+
+    /// `now` is ggez's time since start, which gestures are timed with.
+    pub fn begin(&mut self, position: Point2<f32>, now: Duration) {
+        self.touch = Some(Touch {
+            start: position,
+            started: now,
+            position,
+            anchor: position,
+            moved: false,
+        });
+        self.used = true;
+    }
+
+    pub fn motion(&mut self, position: Point2<f32>) {
+        if let Some(touch) = &mut self.touch {
+            touch.position = position;
+        }
+    }
+
+    pub fn end(&mut self, position: Point2<f32>, now: Duration) {
+        let Some(touch) = self.touch.take() else {
+            return;
+        };
+        let dx = position.x - touch.start.x;
+        let dy = position.y - touch.start.y;
+        let quick = now.saturating_sub(touch.started) < Self::GESTURE_TIME;
+        if !touch.moved && dx.hypot(dy) < Self::TAP_DISTANCE && quick {
+            self.tapped = Some(position);
+        } else if quick && dy > Self::FLICK_DISTANCE && dy > dx.abs() {
+            self.flicked = true;
+        }
+        self.soft_dropping = false;
+    }
+
+    /// Takes at most one horizontal step per frame towards where the finger is, so that
+    /// fast swipes still move the piece cell by cell.
+    pub fn begin_frame(&mut self) {
+        let Some(touch) = &mut self.touch else {
+            return;
+        };
+        let dx = touch.position.x - touch.anchor.x;
+        if dx.abs() >= Self::SHIFT_DISTANCE {
+            self.shift = dx.signum() as i32;
+            touch.anchor.x += self.shift as f32 * Self::SHIFT_DISTANCE;
+            touch.moved = true;
+        }
+        let dy = touch.position.y - touch.anchor.y;
+        self.soft_dropping = dy > Self::SOFT_DROP_DISTANCE && dy > dx.abs();
+        if self.soft_dropping {
+            touch.moved = true;
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        self.shift = 0;
+        self.flicked = false;
+        self.tapped = None;
+    }
+
+    /// Where the screen is being touched, or the mouse held down, if it is.
+    pub fn held(&self) -> Option<Point2<f32>> {
+        self.touch.as_ref().map(|touch| touch.position)
+    }
+
+    /// Where the screen was tapped this frame, if it was.
+    pub fn tapped(&self) -> Option<Point2<f32>> {
+        self.tapped
+    }
+
+    fn tapped_button(&self) -> Option<Action> {
+        let tap = self.tapped?;
+        Self::BUTTONS
+            .iter()
+            .find(|(_, _, rect)| rect.contains(tap))
+            .map(|(action, _, _)| *action)
+    }
+}
+
+/// Keyboard, gamepad and touch input mapped to actions, so that they can be mixed freely.
+pub struct Controls<'a> {
+    ctx: &'a Context,
+    key_bindings: &'a KeyBindings,
+    touch_buttons: bool,
+    gamepad: Option<&'a GamepadState>,
+    touch: Option<&'a TouchState>,
+}
+
+impl<'a> Controls<'a> {
+    pub fn new(
+        ctx: &'a Context,
+        settings: &'a Settings,
+        gamepad: &'a GamepadState,
+        touch: &'a TouchState,
+    ) -> Self {
+        Self {
+            ctx,
+            key_bindings: &settings.key_bindings,
+            touch_buttons: settings.touch_buttons,
+            gamepad: Some(gamepad),
+            touch: Some(touch),
+        }
+    }
+
+    /// Only the keyboard, with its own bindings, for a player sharing it with another.
+    pub fn keyboard(ctx: &'a Context, key_bindings: &'a KeyBindings) -> Self {
+        Self {
+            ctx,
+            key_bindings,
+            touch_buttons: false,
+            gamepad: None,
+            touch: None,
+        }
+    }
+
+    /// The same controls without touch, which can't tell which player is touching.
+    pub fn without_touch(self) -> Self {
+        Self {
+            touch: None,
+            ..self
+        }
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.ctx
+            .keyboard
+            .is_key_pressed(self.key_bindings.key(action))
+            || self.gamepad.is_some_and(|gamepad| {
+                action
+                    .gamepad_buttons()
+                    .iter()
+                    .any(|button| gamepad.is_pressed(*button))
+            })
+            || (action == Action::SoftDrop && self.touch.is_some_and(|touch| touch.soft_dropping))
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.ctx
+            .keyboard
+            .is_key_just_pressed(self.key_bindings.key(action))
+            || self.gamepad.is_some_and(|gamepad| {
+                action
+                    .gamepad_buttons()
+                    .iter()
+                    .any(|button| gamepad.is_just_pressed(*button))
+            })
+            || self.touch_triggered(action)
+    }
+
+    /// Everything pressed right now, as the input of a simulation step.
+    pub fn frame_input(&self) -> FrameInput {
+        FrameInput::from_fn(|action| (self.pressed(action), self.just_pressed(action)))
+    }
+
+    fn touch_triggered(&self, action: Action) -> bool {
+        let Some(touch) = self.touch else {
+            return false;
+        };
+        let button = if self.touch_buttons {
+            touch.tapped_button()
+        } else {
+            None
+        };
+        match action {
+            Action::MoveLeft => touch.shift < 0,
+            Action::MoveRight => touch.shift > 0,
+            Action::HardDrop => touch.flicked,
+            Action::RotateCw => touch.tapped.is_some() && button.is_none(),
+            _ => button == Some(action),
+        }
+    }
+}
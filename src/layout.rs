@@ -0,0 +1,43 @@
+use ggez::{graphics::Rect, mint::Point2};
+
+use crate::SCREEN;
+
+/// How `SCREEN` fits in the window: scaled evenly so that nothing is stretched and centered,
+/// with whatever room is left on the sides, or above and below, showing more background.
+#[derive(Clone, Copy)]
+pub struct Layout {
+    /// The part of the layout coordinates that the window shows, which contains `SCREEN`.
+    visible: Rect,
+    /// Window pixels per layout unit.
+    scale: f32,
+}
+
+impl Layout {
+    /// Lays the screen out in a window whose drawable area is `width` by `height` pixels.
+    pub fn new(width: f32, height: f32) -> Self {
+        // Minimized windows can have no size at all.
+        let scale = (width / SCREEN.w).min(height / SCREEN.h).max(0.01);
+        let (visible_w, visible_h) = (width / scale, height / scale);
+        Self {
+            visible: Rect::new(
+                SCREEN.x - (visible_w - SCREEN.w) / 2.,
+                SCREEN.y - (visible_h - SCREEN.h) / 2.,
+                visible_w,
+                visible_h,
+            ),
+            scale,
+        }
+    }
+
+    pub fn visible(&self) -> Rect {
+        self.visible
+    }
+
+    /// Maps a position in the window, in pixels, to where it is in layout coordinates.
+    pub fn screen_pos(&self, x: f32, y: f32) -> Point2<f32> {
+        Point2 {
+            x: self.visible.x + x / self.scale,
+            y: self.visible.y + y / self.scale,
+        }
+    }
+}
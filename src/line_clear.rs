@@ -0,0 +1,48 @@
+use ggez::graphics::{self, Color, DrawParam, Mesh, Rect};
+use tetris_core::ClearKind;
+
+/// How completed rows are animated while they flash, which depends on the kind of clear so
+/// that the big ones stand out.
+#[derive(Clone, Copy, Default)]
+pub enum ClearAnimation {
+    /// A white bar sweeping across the rows, for plain singles, doubles and triples.
+    #[default]
+    Sweep,
+    /// The rows bursting outwards in a fading glow, for tetrises.
+    Explosion,
+    /// The rows flashing through colors, for T-spins.
+    ColorFlash,
+}
+
+impl ClearAnimation {
+    pub fn of(count: u32, kind: ClearKind) -> Self {
+        match kind {
+            ClearKind::TSpin => ClearAnimation::ColorFlash,
+            ClearKind::Normal if count >= 4 => ClearAnimation::Explosion,
+            ClearKind::Normal => ClearAnimation::Sweep,
+        }
+    }
+
+    /// Draws the animation over a completed `row`, `progress` of the way through it.
+    pub fn draw(self, canvas: &mut graphics::Canvas, quad_mesh: &Mesh, row: Rect, progress: f32) {
+        let (rect, color) = match self {
+            ClearAnimation::Sweep => {
+                // Eased so that the bar slows down as it reaches the far side.
+                let swept = 1. - (1. - progress).powi(3);
+                let rect = Rect::new(row.x, row.y, row.w * swept, row.h);
+                (rect, Color::new(1., 1., 1., 1. - progress * 0.5))
+            }
+            ClearAnimation::Explosion => {
+                let grow = row.h * 2. * progress;
+                let rect = Rect::new(row.x, row.y - grow / 2., row.w, row.h + grow);
+                (rect, Color::new(1., 1., 0.6, 1. - progress))
+            }
+            ClearAnimation::ColorFlash => {
+                // Alternates between magenta and cyan a few times as it fades.
+                let phase = (progress * 6.).fract();
+                (row, Color::new(1. - phase, phase, 1., 1. - progress))
+            }
+        };
+        canvas.draw(quad_mesh, DrawParam::default().dest_rect(rect).color(color));
+    }
+}
@@ -0,0 +1,107 @@
+use std::{collections::HashMap, fmt::Display, io::Read};
+
+use ggez::{Context, GameError, GameResult};
+use tetris_core::GameMode;
+
+/// UI text in the player's language, read from a file of `key = "text"` lines in `/lang`
+/// named after the language's code, like `/lang/es.toml`. Anything the file leaves out is
+/// shown in English, which is built into the game, so that translations can be added a few
+/// lines at a time.
+pub struct Locale {
+    strings: HashMap<String, String>,
+    english: HashMap<String, String>,
+}
+
+impl Locale {
+    const DIRECTORY: &'static str = "/lang";
+    const ENGLISH: &'static str = include_str!("../assets/lang/en.toml");
+
+    pub fn english() -> Self {
+        Self {
+            strings: HashMap::new(),
+            english: toml::from_str(Self::ENGLISH).expect("the English text is valid"),
+        }
+    }
+
+    /// Codes of the languages that can be loaded besides English, in alphabetical order.
+    pub fn list(ctx: &Context) -> Vec<String> {
+        let mut codes: Vec<_> = ctx
+            .fs
+            .read_dir(Self::DIRECTORY)
+            .map(|paths| {
+                paths
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                    .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+                    .filter(|code| code != "en")
+                    .collect()
+            })
+            .unwrap_or_default();
+        codes.sort_unstable();
+        codes
+    }
+
+    /// Loads the language with the code `code`, or English if `code` is empty.
+    pub fn load(ctx: &Context, code: &str) -> GameResult<Self> {
+        let mut locale = Self::english();
+        if code.is_empty() {
+            return Ok(locale);
+        }
+        let mut contents = String::new();
+        ctx.fs
+            .open(format!("{}/{}.toml", Self::DIRECTORY, code))?
+            .read_to_string(&mut contents)?;
+        locale.strings =
+            toml::from_str(&contents).map_err(|e| GameError::ConfigError(e.to_string()))?;
+        Ok(locale)
+    }
+
+    /// The text for `key`, in English if the language doesn't have it, or the key itself if
+    /// there's no such text at all, so that it shows up as missing.
+    pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings
+            .get(key)
+            .or_else(|| self.english.get(key))
+            .map_or(key, String::as_str)
+    }
+
+    /// The text for `key` with each `{}` in it filled in with the next of `values`.
+    pub fn format(&self, key: &str, values: &[&dyn Display]) -> String {
+        let mut values = values.iter();
+        let mut parts = self.text(key).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_owned();
+        for part in parts {
+            if let Some(value) = values.next() {
+                text.push_str(&value.to_string());
+            }
+            text.push_str(part);
+        }
+        text
+    }
+
+    /// The text for `key` with `on` or `off` filled in, for settings that are switched.
+    pub fn toggle(&self, key: &str, on: bool) -> String {
+        self.format(key, &[&self.text(if on { "on" } else { "off" })])
+    }
+
+    pub fn mode_name(&self, mode: GameMode) -> &str {
+        self.text(match mode {
+            GameMode::Endless => "mode-endless",
+            GameMode::Sprint => "mode-sprint",
+            GameMode::Ultra => "mode-ultra",
+            GameMode::Marathon { .. } => "mode-marathon",
+            GameMode::Dig { .. } => "mode-dig",
+            GameMode::Survival => "mode-survival",
+            GameMode::Invisible => "mode-invisible",
+            GameMode::Big => "mode-big",
+            GameMode::Pentomino {
+                with_tetrominoes: false,
+            } => "mode-pentomino",
+            GameMode::Pentomino {
+                with_tetrominoes: true,
+            } => "mode-pentomix",
+            GameMode::Cascade => "mode-cascade",
+            GameMode::Master => "mode-master",
+            GameMode::Classic => "mode-classic",
+        })
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use ggez::mint::Point2;
+use tetris_core::{Game, Piece};
+
+/// Flashes the cells of pieces white for a moment as they lock, so that it's clear when they
+/// do even at high gravity. Kept cell by cell alongside the game's grid.
+pub struct LockFlash {
+    width: usize,
+    /// Game time each cell has left to flash, in row order.
+    remaining: Box<[Duration]>,
+}
+
+impl LockFlash {
+    const TIME: Duration = Duration::from_millis(100);
+
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            remaining: vec![Duration::ZERO; width * height].into_boxed_slice(),
+        }
+    }
+
+    /// Starts flashing the cells a piece locked into.
+    pub fn lock(&mut self, piece: Piece) {
+        for cell in piece.cells() {
+            if cell.x >= 0 && cell.y >= 0 {
+                let i = cell.x as usize + cell.y as usize * self.width;
+                if (cell.x as usize) < self.width && i < self.remaining.len() {
+                    self.remaining[i] = Self::TIME;
+                }
+            }
+        }
+    }
+
+    /// Fades the flashes by one game step.
+    pub fn step(&mut self) {
+        for remaining in self.remaining.iter_mut() {
+            *remaining = remaining.saturating_sub(Game::FRAME_TIME);
+        }
+    }
+
+    /// The cells that are flashing, with how bright they are from 0 to 1. `interpolation` is
+    /// the fraction of a step that has passed since the last one.
+    pub fn cells(&self, interpolation: f32) -> impl Iterator<Item = (Point2<i32>, f32)> + '_ {
+        let step = Game::FRAME_TIME.as_secs_f32() * interpolation;
+        self.remaining
+            .iter()
+            .enumerate()
+            .filter(|(_, remaining)| !remaining.is_zero())
+            .map(move |(i, remaining)| {
+                let pos = Point2 {
+                    x: (i % self.width) as i32,
+                    y: (i / self.width) as i32,
+                };
+                let brightness = (remaining.as_secs_f32() - step) / Self::TIME.as_secs_f32();
+                (pos, brightness.max(0.))
+            })
+    }
+}
@@ -1,761 +1,657 @@
-use std::{
-    env,
-    ops::Range,
-    path,
-    time::{Duration, Instant},
+mod audio;
+mod clipboard;
+mod countdown;
+mod debug_overlay;
+mod hud;
+mod input;
+mod layout;
+mod line_clear;
+mod locale;
+mod lock_flash;
+mod mini_board;
+mod music;
+mod net;
+mod particles;
+#[cfg(debug_assertions)]
+mod reload;
+mod render;
+mod replay_gif;
+mod scenes;
+mod settings;
+mod shake;
+mod slide;
+mod stack_fade;
+mod storage;
+mod theme;
+mod toast;
+
+use std::{env, fs, path, time::Instant};
+
+use clap::Parser;
+use ggez::{
+    conf::{FullscreenType, WindowMode, WindowSetup},
+    event::{self, Axis, Button, ErrorOrigin, GamepadId, MouseButton},
+    filesystem::Filesystem,
+    graphics::{self, DrawParam, Image, ImageFormat, Quad, Rect},
+    input::keyboard::KeyMods,
+    winit::event::VirtualKeyCode,
+    Context, GameError, GameResult,
+};
+use rand::Rng;
+use tetris_core::{
+    Achievements, BoardSize, GameMode, Grid, Heatmap, KickTables, PieceKind, PieceSet, Replay,
+    Setup,
 };
 
-use crevice::std140::AsStd140;
-
-use enum_map::{enum_map, Enum, EnumMap};
-use ggez::{
-    audio::{self, SoundSource},
-    conf::{WindowMode, WindowSetup},
-    event,
-    glam::*,
-    graphics::{self, Color, DrawParam, InstanceArray, Mesh, MeshData, Quad, Rect, Vertex},
-    mint::Point2,
-    Context, GameResult,
+#[cfg(debug_assertions)]
+use crate::reload::{AssetKind, FileWatcher};
+use crate::{
+    audio::{Audio, Mixer},
+    debug_overlay::DebugOverlay,
+    input::{GamepadState, TouchState},
+    layout::Layout,
+    locale::Locale,
+    render::{Assets, ShaderUniform},
+    scenes::{
+        gameplay::Gameplay, menu::MenuScene, replay::ReplayScene, shortcut_pressed, Scene, Shared,
+        Transition,
+    },
+    settings::{Config, Settings},
+    storage::{AchievementsExt, KickTablesExt, PieceSetExt, ReplayExt, SavedGameExt},
+    theme::Theme,
+    toast::Toasts,
 };
-use rand::thread_rng;
-
-#[derive(Clone, Copy, Enum)]
-pub enum PieceRotation {
-    Deg0,
-    Deg90,
-    Deg180,
-    Deg270,
-}
 
-impl PieceRotation {
-    pub fn rotate_cw(self) -> Self {
-        use PieceRotation::*;
-        match self {
-            Deg0 => Deg90,
-            Deg90 => Deg180,
-            Deg180 => Deg270,
-            Deg270 => Deg0,
-        }
-    }
-    pub fn rotate_ccw(self) -> Self {
-        use PieceRotation::*;
-        match self {
-            Deg0 => Deg270,
-            Deg90 => Deg0,
-            Deg180 => Deg90,
-            Deg270 => Deg180,
-        }
-    }
-}
+/// The area everything is laid out in, which gets scaled to fit the window.
+const SCREEN: Rect = Rect::new(0., 0., 400., 300.);
 
-#[derive(Clone, Copy, Enum)]
-pub enum PieceKind {
-    I,
-    J,
-    L,
-    O,
-    S,
-    T,
-    Z,
+fn parse_mode(arg: &str) -> Result<GameMode, String> {
+    GameMode::from_arg(arg).ok_or_else(|| {
+        "expected endless, sprint, ultra, marathon, dig, survival, invisible, big, pentomino, \
+         pentomix, cascade, master or classic"
+            .to_owned()
+    })
 }
 
-impl PieceKind {
-    pub fn random(rng: &mut impl rand::Rng) -> Self {
-        let num = rng.gen_range(0..7);
-        match num {
-            0 => PieceKind::I,
-            1 => PieceKind::J,
-            2 => PieceKind::L,
-            3 => PieceKind::O,
-            4 => PieceKind::S,
-            5 => PieceKind::T,
-            _ => PieceKind::Z,
-        }
-    }
+/// Tetris. The options mostly jump straight into a specific game, for testing and scripting.
+#[derive(Parser)]
+struct Args {
+    /// Start playing this mode right away: endless, sprint, ultra, marathon, dig,
+    /// survival, invisible, big, pentomino, pentomix, cascade, master or classic
+    #[arg(long, value_parser = parse_mode)]
+    mode: Option<GameMode>,
+    /// Level that wins a marathon
+    #[arg(
+        long,
+        default_value_t = GameMode::MARATHON_GOAL_LEVEL,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    goal_level: u32,
+    /// Rows of garbage a dig starts with
+    #[arg(
+        long,
+        default_value_t = GameMode::DIG_GARBAGE_ROWS,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    garbage_rows: u32,
+    /// Board width in cells
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u8)
+            .range(BoardSize::MIN.width as i64..=BoardSize::MAX.width as i64)
+    )]
+    width: Option<u8>,
+    /// Board height in cells
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u8)
+            .range(BoardSize::MIN.height as i64..=BoardSize::MAX.height as i64)
+    )]
+    height: Option<u8>,
+    /// Silence music and sound effects
+    #[arg(long)]
+    mute: bool,
+    /// Fill the whole screen
+    #[arg(long)]
+    fullscreen: bool,
+    /// Use this seed for every game, so that pieces always come in the same order
+    #[arg(long)]
+    seed: Option<u32>,
+    /// Watch a replay file instead of playing
+    #[arg(long, value_name = "PATH", conflicts_with = "mode")]
+    replay: Option<path::PathBuf>,
+    /// Play back a script of inputs, one per line with the step it starts on, instead of
+    /// playing
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["mode", "replay"])]
+    script: Option<path::PathBuf>,
+    /// Practice with the pieces in this file, written as letters like `TLJ SZO I`, dealt
+    /// over and over instead of random ones
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["mode", "replay", "script"])]
+    sequence: Option<path::PathBuf>,
+    /// Render the replay or script to an animated GIF at this path instead of watching it,
+    /// without opening a window
+    #[arg(long, value_name = "PATH")]
+    gif: Option<path::PathBuf>,
 }
 
-impl PieceKind {
-    pub fn get_grid(&self, rotation: PieceRotation) -> Grid {
-        let o = None;
-        let x = Some(Block {
-            color: match self {
-                I => Color::CYAN,
-                J => Color::BLUE,
-                L => Color::RED,
-                O => Color::YELLOW,
-                S => Color::GREEN,
-                T => Color::MAGENTA,
-                Z => Color::WHITE,
-            },
-        });
-        let d = |data: [Option<Block>; 16]| Grid::with_data(4, 4, Box::new(data));
-        use PieceKind::*;
-        use PieceRotation::*;
-        match (self, rotation) {
-            #[rustfmt::skip]
-            (I, Deg0 | Deg180) => d(
-                [
-                o, o, o, o,
-                x, x, x, x,
-                o, o, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (I, Deg90 | Deg270) => d(
-                [
-                o, x, o, o,
-                o, x, o, o,
-                o, x, o, o,
-                o, x, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (J, Deg0) => d(
-                [
-                o, x, o, o,
-                o, x, o, o,
-                x, x, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (J, Deg90) => d(
-                [
-                x, o, o, o,
-                x, x, x, o,
-                o, o, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (J, Deg180) => d(
-                [
-                o, x, x, o,
-                o, x, o, o,
-                o, x, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (J, Deg270) => d(
-                [
-                o, o, o, o,
-                x, x, x, o,
-                o, o, x, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (L, Deg0) => d(
-                [
-                o, x, o, o,
-                o, x, o, o,
-                o, x, x, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (L, Deg90) => d(
-                [
-                o, o, o, o,
-                x, x, x, o,
-                x, o, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (L, Deg180) => d(
-                [
-                x, x, o, o,
-                o, x, o, o,
-                o, x, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (L, Deg270) => d(
-                [
-                o, o, x, o,
-                x, x, x, o,
-                o, o, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (O, _) => d(
-                [
-                x, x, o, o,
-                x, x, o, o,
-                o, o, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (S, Deg0 | Deg180) => d(
-                [
-                o, x, x, o,
-                x, x, o, o,
-                o, o, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (S, Deg90 | Deg270) => d(
-                [
-                x, o, o, o,
-                x, x, o, o,
-                o, x, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (T, Deg0) => d(
-                [
-                o, x, o, o,
-                x, x, x, o,
-                o, o, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (T, Deg90) => d(
-                [
-                o, x, o, o,
-                o, x, x, o,
-                o, x, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (T, Deg180) => d(
-                [
-                o, o, o, o,
-                x, x, x, o,
-                o, x, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (T, Deg270) => d(
-                [
-                o, x, o, o,
-                x, x, o, o,
-                o, x, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (Z, Deg0 | Deg180) => d(
-                [
-                x, x, o, o,
-                o, x, x, o,
-                o, o, o, o,
-                o, o, o, o
-                ],
-            ),
-            #[rustfmt::skip]
-            (Z, Deg90 | Deg270) => d(
-                [
-                o, x, o, o,
-                x, x, o, o,
-                x, o, o, o,
-                o, o, o, o
-                ],
-            ),
-        }
-    }
+/// Drives the scene stack: only the top scene is updated, while it and any overlays
+/// directly below it are drawn.
+struct MainState {
+    shared: Shared,
+    scenes: Vec<Box<dyn Scene>>,
+    layout: Layout,
+    fullscreen: bool,
+    debug: DebugOverlay,
+    /// Whether F12 was pressed, to capture the next frame once it's drawn.
+    screenshot_requested: bool,
+    #[cfg(debug_assertions)]
+    watcher: FileWatcher,
 }
 
-pub struct Piece {
-    pos: Point2<i32>,
-    rotation: PieceRotation,
-    kind: PieceKind,
-}
+impl MainState {
+    fn new(ctx: &mut Context, args: Args) -> GameResult<MainState> {
+        // Before anything is drawn or played, so that no pieces have been used yet.
+        install_custom_rules(&ctx.fs)?;
+        let mut settings = Settings::default();
+        Config::load(ctx).unwrap_or_default().apply(&mut settings);
+        // A theme that was removed or broken since it was picked falls back to the default.
+        let theme = Theme::load(ctx, &settings.theme).unwrap_or_else(|_| {
+            settings.theme.clear();
+            Theme::default()
+        });
+        // The same goes for a language, which falls back to English.
+        let locale = Locale::load(ctx, &settings.language).unwrap_or_else(|_| {
+            settings.language.clear();
+            Locale::english()
+        });
+        let mut shared = Shared {
+            assets: Assets::new(ctx, &theme)?,
+            audio: Audio::new(ctx, &theme)?,
+            locale,
+            settings,
+            gamepad: GamepadState::default(),
+            touch: TouchState::default(),
+            typed: String::new(),
+            seed: args.seed,
+            achievements: Achievements::load(ctx),
+            toasts: Toasts::default(),
+            heatmap: Heatmap::default(),
+        };
+        shared.settings.mixer.muted = args.mute;
+        let board_size = &mut shared.settings.board_size;
+        board_size.width = args.width.map_or(board_size.width, usize::from);
+        board_size.height = args.height.map_or(board_size.height, usize::from);
+
+        shared.audio.play_music(ctx)?;
+        shared.settings.apply(&mut shared.audio);
+
+        let replay = read_replay(&args)?;
+        let first_scene: Box<dyn Scene> = match (replay, args.mode) {
+            (Some(replay), _) => Box::new(ReplayScene::new(ctx, &shared, replay)),
+            (None, Some(GameMode::Marathon { .. })) => {
+                let mode = GameMode::Marathon {
+                    goal_level: args.goal_level,
+                };
+                Box::new(Gameplay::new(ctx, &shared, mode))
+            }
+            (None, Some(GameMode::Dig { .. })) => {
+                let mode = GameMode::Dig {
+                    garbage_rows: args.garbage_rows,
+                };
+                Box::new(Gameplay::new(ctx, &shared, mode))
+            }
+            (None, Some(mode)) => Box::new(Gameplay::new(ctx, &shared, mode)),
+            (None, None) => match read_sequence(&args)? {
+                Some(queue) => {
+                    let setup = Setup {
+                        board: Grid::new(
+                            shared.settings.board_size.width,
+                            shared.settings.board_size.height,
+                        ),
+                        queue,
+                        repeat: true,
+                    };
+                    let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+                    Box::new(Gameplay::training(ctx, &shared, setup.game(seed)))
+                }
+                None => Box::new(MenuScene::new(ctx)),
+            },
+        };
 
-impl Piece {
-    pub fn collides_with(&self, grid: &Grid) -> bool {
-        let piece_grid = self.kind.get_grid(self.rotation);
-        grid.intersects(self.pos.x, self.pos.y, &piece_grid)
-            || !grid.contains(self.pos.x, self.pos.y, &piece_grid)
+        let (width, height) = ctx.gfx.drawable_size();
+        Ok(MainState {
+            shared,
+            scenes: vec![first_scene],
+            layout: Layout::new(width, height),
+            fullscreen: args.fullscreen,
+            debug: DebugOverlay::default(),
+            screenshot_requested: false,
+            #[cfg(debug_assertions)]
+            watcher: FileWatcher::new(resource_dir()),
+        })
     }
-}
-
-#[derive(Clone, Copy)]
-pub struct Block {
-    color: Color,
-}
-
-pub struct Grid {
-    blocks: Box<[Option<Block>]>,
-    width: usize,
-    height: usize,
-}
 
-impl Grid {
-    pub fn new(width: usize, height: usize) -> Self {
-        Self {
-            width,
-            height,
-            blocks: vec![None; width * height].into_boxed_slice(),
+    /// Switches between fullscreen and a window with F11 or Alt+Enter.
+    fn toggle_fullscreen(&mut self, ctx: &mut Context) -> GameResult {
+        let keyboard = &ctx.keyboard;
+        let alt_enter = keyboard.is_mod_active(KeyMods::ALT)
+            && keyboard.is_key_just_pressed(VirtualKeyCode::Return);
+        if !alt_enter && !keyboard.is_key_just_pressed(VirtualKeyCode::F11) {
+            return Ok(());
         }
+        self.fullscreen = !self.fullscreen;
+        ctx.gfx.set_fullscreen(if self.fullscreen {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Windowed
+        })
     }
 
-    pub fn with_data(width: usize, height: usize, blocks: Box<[Option<Block>]>) -> Self {
-        assert_eq!(width * height, blocks.len());
-        Self {
-            width,
-            height,
-            blocks,
+    /// Changes the master volume with F6 and F7, toggles mute with F8 and skips to the next
+    /// song with F9, from anywhere.
+    fn update_volume_keys(&mut self, ctx: &Context) -> GameResult {
+        let keyboard = &ctx.keyboard;
+        if keyboard.is_key_just_pressed(VirtualKeyCode::F9) {
+            self.shared.audio.next_track(ctx)?;
         }
-    }
-
-    pub fn at(&self, x: i32, y: i32) -> &Option<Block> {
-        if self.contains_pos(x, y) {
-            &self.blocks[x as usize + y as usize * self.width]
+        let mixer = &mut self.shared.settings.mixer;
+        if keyboard.is_key_just_pressed(VirtualKeyCode::F8) {
+            mixer.muted = !mixer.muted;
+        } else if keyboard.is_key_just_pressed(VirtualKeyCode::F6) {
+            Mixer::adjust(&mut mixer.master, -1);
+        } else if keyboard.is_key_just_pressed(VirtualKeyCode::F7) {
+            Mixer::adjust(&mut mixer.master, 1);
         } else {
-            &None
+            return Ok(());
         }
+        self.shared.settings.apply(&mut self.shared.audio);
+        let _ = Config::from_settings(&self.shared.settings).save(ctx);
+        Ok(())
     }
 
-    pub fn set(&mut self, x: i32, y: i32, value: Option<Block>) {
-        if self.contains_pos(x, y) {
-            self.blocks[x as usize + y as usize * self.width] = value;
-        } else {
-            panic!()
+    /// Copies the board of the game on screen as a setup for other players to paste, with
+    /// Ctrl+C.
+    fn copy_setup(&mut self) {
+        let Some(setup) = self.scenes.iter().rev().find_map(|scene| scene.setup()) else {
+            return;
+        };
+        let Shared { locale, toasts, .. } = &mut self.shared;
+        match clipboard::copy(&setup.to_text()) {
+            Ok(()) => toasts.push(
+                locale.text("toast-setup-copied"),
+                locale.text("toast-setup-copied-hint"),
+            ),
+            Err(e) => toasts.push(
+                locale.text("toast-setup-not-copied"),
+                e.to_string().to_uppercase(),
+            ),
         }
     }
 
-    pub fn clear_line(&mut self, y: i32) {
-        assert!(y >= 0 && y < self.height as i32);
-
-        for iy in (1..=y as i32).rev() {
-            for x in 0..self.width as i32 {
-                self.set(x, iy, *self.at(x, iy - 1));
-            }
-        }
-        for x in 0..self.width as i32 {
-            self.set(x, 0, None);
-        }
+    /// Saves the frame that was just drawn as a PNG, returning its file name.
+    fn save_screenshot(&self, ctx: &mut Context) -> GameResult<String> {
+        let frame = ctx.gfx.frame().clone();
+        // Copied to an image of a known format first, since the window's depends on the
+        // platform.
+        let image = Image::new_canvas_image(
+            ctx,
+            ImageFormat::Rgba8UnormSrgb,
+            frame.width(),
+            frame.height(),
+            1,
+        );
+        let mut canvas = graphics::Canvas::from_image(ctx, image.clone(), None);
+        canvas.draw(&frame, DrawParam::default());
+        canvas.finish(ctx)?;
+        storage::save_screenshot(ctx, &image.to_pixels(ctx)?, image.width(), image.height())
     }
 
-    pub fn contains_pos(&self, x: i32, y: i32) -> bool {
-        x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32
+    /// Saves the game in progress, if any, for the menu to offer resuming it next time, along
+    /// with the settings, so that nothing is lost when the window closes mid-game.
+    fn save_before_exit(&self, ctx: &Context) {
+        if let Some(saved) = self
+            .scenes
+            .iter()
+            .rev()
+            .find_map(|scene| scene.saved_game())
+        {
+            let _ = saved.save(ctx);
+        }
+        let _ = Config::from_settings(&self.shared.settings).save(ctx);
     }
 
-    pub fn intersects(&self, x: i32, y: i32, other: &Grid) -> bool {
-        for ix in 0..self.width as i32 {
-            for iy in 0..self.height as i32 {
-                if self
-                    .at(ix, iy)
-                    .is_some_and(|_| other.at(ix - x, iy - y).is_some())
-                {
-                    return true;
+    /// Loads the assets whose files changed on disk again, keeping the old ones if the new
+    /// files can't be loaded.
+    #[cfg(debug_assertions)]
+    fn reload_changed_assets(&mut self, ctx: &mut Context) {
+        for path in self.watcher.changed(ctx.time.delta()) {
+            let result = match AssetKind::of(&path) {
+                Some(AssetKind::Shader) => self.shared.assets.reload_bg_shader(ctx),
+                Some(AssetKind::Image) => self.shared.reload_images(ctx),
+                Some(AssetKind::Sound) => self.shared.reload_sounds(ctx, &path),
+                Some(AssetKind::Theme) => self.shared.load_theme(ctx),
+                None => continue,
+            };
+            match result {
+                Ok(()) => {
+                    for scene in &mut self.scenes {
+                        scene.assets_reloaded(ctx, &self.shared);
+                    }
                 }
+                Err(e) => eprintln!("Keeping the old assets after {} changed: {}", path, e),
             }
         }
-        false
     }
+}
 
-    pub fn contains(&self, x: i32, y: i32, other: &Grid) -> bool {
-        for ix in 0..other.width as i32 {
-            for iy in 0..other.height as i32 {
-                if other.at(ix, iy).is_some() && !self.contains_pos(ix + x, iy + y) {
-                    return false;
+impl event::EventHandler<ggez::GameError> for MainState {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        #[cfg(debug_assertions)]
+        self.reload_changed_assets(ctx);
+
+        let started = Instant::now();
+        self.toggle_fullscreen(ctx)?;
+        self.update_volume_keys(ctx)?;
+        self.shared.audio.update(ctx)?;
+        self.shared.toasts.update(ctx.time.delta());
+        if ctx.keyboard.is_key_just_pressed(VirtualKeyCode::F12) {
+            self.screenshot_requested = true;
+        }
+        if shortcut_pressed(ctx, VirtualKeyCode::C) {
+            self.copy_setup();
+        }
+        let savestates = self
+            .scenes
+            .last()
+            .is_some_and(|scene| scene.has_savestates());
+        if ctx.keyboard.is_key_just_pressed(VirtualKeyCode::F3) && !savestates {
+            self.debug.shown = !self.debug.shown;
+        }
+        self.shared.touch.begin_frame();
+        if let Some(scene) = self.scenes.last_mut() {
+            match scene.update(ctx, &mut self.shared)? {
+                Transition::None => (),
+                Transition::Push(scene) => self.scenes.push(scene),
+                Transition::Pop => {
+                    self.scenes.pop();
                 }
+                Transition::Switch(scene) => self.scenes = vec![scene],
             }
         }
-        true
-    }
+        if self.scenes.is_empty() {
+            ctx.request_quit();
+        }
+        self.shared.gamepad.end_frame();
+        self.shared.touch.end_frame();
+        self.shared.typed.clear();
+
+        let danger = self
+            .scenes
+            .iter()
+            .rev()
+            .find_map(|scene| scene.danger())
+            .unwrap_or(0.);
+        self.shared.audio.set_hurried(ctx, danger > 0.)?;
+        let intensity = self
+            .scenes
+            .iter()
+            .rev()
+            .find_map(|scene| scene.intensity())
+            .unwrap_or(0.);
+        self.shared.audio.set_intensity(intensity);
+        let flash = self
+            .scenes
+            .iter()
+            .rev()
+            .find_map(|scene| scene.flash())
+            .unwrap_or(0.);
+        self.shared.assets.bg_shader_params.set_uniforms(
+            ctx,
+            &ShaderUniform {
+                time: ctx.time.time_since_start().as_secs_f32() / 10.,
+                danger,
+                flash,
+            },
+        );
 
-    pub fn height(&self) -> usize {
-        self.height
+        self.debug.update_time = started.elapsed();
+        Ok(())
     }
 
-    pub fn width(&self) -> usize {
-        self.width
-    }
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let started = Instant::now();
+        let mut canvas = graphics::Canvas::from_frame(ctx, self.shared.assets.clear_color);
+        // Always lay the game out in the same coordinates, scaled to fit the window.
+        canvas.set_screen_coordinates(self.layout.visible());
+
+        canvas.set_shader(&self.shared.assets.bg_shader);
+        canvas.set_shader_params(&self.shared.assets.bg_shader_params);
+        canvas.draw(&Quad, DrawParam::new().dest_rect(self.layout.visible()));
+        canvas.set_default_shader();
 
-    pub fn overlay(&mut self, x: i32, y: i32, other: Grid) {
-        for ix in 0..self.width as i32 {
-            for iy in 0..self.height as i32 {
-                if let Some(block) = other.at(ix - x, iy - y) {
-                    self.set(ix, iy, Some(*block));
-                }
-            }
+        let first_drawn = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        for scene in &mut self.scenes[first_drawn..] {
+            scene.draw(ctx, &mut canvas, &self.shared)?;
+        }
+        self.shared.toasts.draw(&mut canvas);
+
+        if self.debug.shown {
+            self.debug.draw_time = started.elapsed();
+            let mut scene_lines = self
+                .scenes
+                .iter()
+                .rev()
+                .map(|scene| scene.debug_lines())
+                .find(|lines| !lines.is_empty())
+                .unwrap_or_default();
+            scene_lines.extend(self.shared.audio.debug_line());
+            self.debug
+                .draw(ctx, &mut canvas, self.layout.visible(), scene_lines);
         }
-    }
-}
-
-#[derive(AsStd140)]
-struct ShaderUniform {
-    time: f32,
-}
-
-pub struct LineDestroyAnimation {
-    lines_to_destroy: Vec<Range<u32>>,
-    // 0.0 to 1.0
-    progress: f32,
-}
-
-struct MainState {
-    grid: Grid,
-    grid_batch: InstanceArray,
-
-    // TODO: Access ggez gfx ctx quad mesh
-    quad_mesh: Mesh,
-
-    time_last_moved_piece: Instant,
-
-    rotate_sfx: audio::Source,
-    place_sfx: audio::Source,
-    clear_sfx: audio::Source,
-    music: audio::Source,
-
-    bg: graphics::Image,
-    game_over_text: graphics::Image,
-    bg_shader: graphics::Shader,
-    bg_shader_params: graphics::ShaderParams<ShaderUniform>,
-
-    game_over: bool,
-
-    piece_meshes: EnumMap<PieceKind, EnumMap<PieceRotation, InstanceArray>>,
-
-    piece_falling: Piece,
-
-    line_destroy_animations: Option<LineDestroyAnimation>,
-}
 
-impl MainState {
-    fn new(ctx: &mut Context) -> GameResult<MainState> {
-        let grid = Grid::new(10, 16);
-
-        let block_texture = graphics::Image::from_path(ctx, "/textures/block.png")?;
-        let grid_batch = InstanceArray::new(ctx, block_texture.clone());
-
-        let bg_shader_params =
-            graphics::ShaderParamsBuilder::new(&ShaderUniform { time: 0. }).build(ctx);
-
-        let mut state = MainState {
-            game_over: false,
-
-            grid,
-            grid_batch,
-            rotate_sfx: audio::Source::new(ctx, "/sound/rotate.ogg")?,
-            place_sfx: audio::Source::new(ctx, "/sound/place.ogg")?,
-            clear_sfx: audio::Source::new(ctx, "/sound/clear.wav")?,
-            music: audio::Source::new(ctx, "/music/game.mp3")?,
-            bg: graphics::Image::from_path(ctx, "/textures/game_bg.png")?,
-            game_over_text: graphics::Image::from_path(ctx, "/textures/game_over.png")?,
-            bg_shader: graphics::ShaderBuilder::from_path("/shaders/game_bg.wgsl").build(ctx)?,
-            bg_shader_params,
-            piece_meshes: {
-                use PieceKind::*;
-                use PieceRotation::*;
-                let generate_piece_mesh =
-                    |piece: PieceKind, rotation: PieceRotation| -> InstanceArray {
-                        let grid = piece.get_grid(rotation);
-                        let mut batch = InstanceArray::new(ctx, block_texture.clone());
-                        for x in 0..grid.width() {
-                            for y in 0..grid.height() {
-                                if let Some(block) = grid.at(x as i32, y as i32) {
-                                    batch.push(
-                                        DrawParam::new()
-                                            .dest(Point2 {
-                                                x: x as f32,
-                                                y: y as f32,
-                                            })
-                                            .color(block.color),
-                                    );
-                                }
-                            }
-                        }
-                        batch
-                    };
-                EnumMap::from_array([I, J, L, O, S, T, Z].map(|piece| {
-                    EnumMap::from_array(
-                        [Deg0, Deg90, Deg180, Deg270]
-                            .map(|rotation| generate_piece_mesh(piece, rotation)),
-                    )
-                }))
-            },
-            quad_mesh: Mesh::from_data(
-                &ctx.gfx,
-                MeshData {
-                    vertices: &[
-                        Vertex {
-                            position: [0., 0.],
-                            uv: [0., 0.],
-                            color: [1.; 4],
-                        },
-                        Vertex {
-                            position: [1., 0.],
-                            uv: [1., 0.],
-                            color: [1.; 4],
-                        },
-                        Vertex {
-                            position: [0., 1.],
-                            uv: [0., 1.],
-                            color: [1.; 4],
-                        },
-                        Vertex {
-                            position: [1., 1.],
-                            uv: [1., 1.],
-                            color: [1.; 4],
-                        },
-                    ],
-                    indices: &[0, 2, 1, 2, 3, 1],
-                },
-            ),
-            piece_falling: Piece {
-                pos: Point2 { x: 3, y: 0 },
-                kind: PieceKind::J,
-                rotation: PieceRotation::Deg90,
-            },
-            time_last_moved_piece: std::time::Instant::now(),
-            line_destroy_animations: None,
-        };
+        canvas.finish(ctx)?;
 
-        state.music.play(ctx)?;
-        state.music.set_volume(0.); // Comment to enable music
-        state.update_grid_batch();
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+            let saved = self.save_screenshot(ctx);
+            let Shared { locale, toasts, .. } = &mut self.shared;
+            match saved {
+                Ok(name) => toasts.push(locale.text("toast-screenshot-saved"), name.to_uppercase()),
+                Err(e) => toasts.push(
+                    locale.text("toast-screenshot-not-saved"),
+                    e.to_string().to_uppercase(),
+                ),
+            }
+        }
 
-        Ok(state)
+        Ok(())
     }
 
-    fn update_grid_batch(&mut self) {
-        self.grid_batch.clear();
-        for x in 0..self.grid.width() {
-            for y in 0..self.grid.height() {
-                if let Some(block) = self.grid.at(x as i32, y as i32) {
-                    self.grid_batch.push(
-                        DrawParam::new()
-                            .dest(Point2 {
-                                x: x as f32,
-                                y: y as f32,
-                            })
-                            .color(block.color),
-                    );
-                }
-            }
-        }
+    fn quit_event(&mut self, ctx: &mut Context) -> GameResult<bool> {
+        self.save_before_exit(ctx);
+        Ok(false)
     }
 
-    fn place_current_piece(&mut self, ctx: &Context) {
-        let piece_grid = self
-            .piece_falling
-            .kind
-            .get_grid(self.piece_falling.rotation);
+    /// Saves what it can before an error ends the game, as when quitting.
+    fn on_error(&mut self, ctx: &mut Context, origin: ErrorOrigin, e: ggez::GameError) -> bool {
+        eprintln!("{:?} failed: {}", origin, e);
+        self.save_before_exit(ctx);
+        true
+    }
 
-        self.grid.overlay(
-            self.piece_falling.pos.x,
-            self.piece_falling.pos.y,
-            piece_grid,
-        );
-        self.piece_falling = Piece {
-            pos: Point2 { x: 3, y: 0 },
-            kind: PieceKind::random(&mut thread_rng()),
-            rotation: PieceRotation::Deg0,
-        };
-        if self.piece_falling.collides_with(&self.grid) {
-            self.game_over = true;
-        }
-        let _ = self.place_sfx.play(ctx);
-        self.check_lines(ctx);
+    fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) -> GameResult {
+        self.layout = Layout::new(width, height);
+        Ok(())
     }
 
-    fn check_lines(&mut self, ctx: &Context) {
-        let mut last_line_to_destroy = None;
-        let mut lines_to_destroy = vec![];
-        for y in 0..self.grid.height() as u32 {
-            if (0..self.grid.width() as i32).all(|x| self.grid.at(x, y as i32).is_some()) {
-                if last_line_to_destroy.is_none() {
-                    last_line_to_destroy = Some(y);
-                }
-            } else if let Some(l) = last_line_to_destroy {
-                last_line_to_destroy = None;
-                lines_to_destroy.push(l..y);
-            }
-        }
-        if let Some(l) = last_line_to_destroy {
-            lines_to_destroy.push(l..self.grid.height() as u32);
-        }
-        if !lines_to_destroy.is_empty() {
-            self.line_destroy_animations = Some(LineDestroyAnimation {
-                lines_to_destroy: lines_to_destroy,
-                progress: 0.,
-            });
-            let _ = self.clear_sfx.play(ctx);
-        }
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) -> GameResult {
+        self.shared.typed.push(character);
+        Ok(())
     }
-}
 
-impl event::EventHandler<ggez::GameError> for MainState {
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
-        if let Some(anim) = &mut self.line_destroy_animations {
-            anim.progress += ctx.time.delta().as_secs_f32() * 2.;
-            if anim.progress >= 1. {
-                for lines in &anim.lines_to_destroy {
-                    for line in lines.clone() {
-                        self.grid.clear_line(line as i32);
-                    }
-                }
-                self.line_destroy_animations = None;
-            }
-        } else if !self.game_over {
-            if ctx
-                .keyboard
-                .is_key_just_pressed(ggez::winit::event::VirtualKeyCode::Left)
-            {
-                self.piece_falling.pos.x -= 1;
-                if self.piece_falling.collides_with(&self.grid) {
-                    self.piece_falling.pos.x += 1;
-                }
-            }
-            if ctx
-                .keyboard
-                .is_key_just_pressed(ggez::winit::event::VirtualKeyCode::Right)
-            {
-                self.piece_falling.pos.x += 1;
-                if self.piece_falling.collides_with(&self.grid) {
-                    self.piece_falling.pos.x -= 1;
-                }
-            }
-            if ctx
-                .keyboard
-                .is_key_just_pressed(ggez::winit::event::VirtualKeyCode::Up)
-            {
-                self.piece_falling.rotation = self.piece_falling.rotation.rotate_cw();
-                if self.piece_falling.collides_with(&self.grid) {
-                    self.piece_falling.rotation = self.piece_falling.rotation.rotate_ccw();
-                } else {
-                    let _ = self.rotate_sfx.play(ctx);
-                }
-            }
-            let time_per_fall = if ctx
-                .keyboard
-                .is_key_pressed(ggez::winit::event::VirtualKeyCode::Down)
-            {
-                Duration::from_millis(100)
-            } else {
-                Duration::from_millis(500)
-            };
-            if ctx
-                .keyboard
-                .is_key_just_pressed(ggez::winit::event::VirtualKeyCode::Space)
-            {
-                self.time_last_moved_piece = std::time::Instant::now();
-                while !self.piece_falling.collides_with(&self.grid) {
-                    self.piece_falling.pos.y += 1;
-                }
-                self.piece_falling.pos.y -= 1;
-                self.place_current_piece(ctx);
-                self.update_grid_batch();
-            }
-            if std::time::Instant::now() > self.time_last_moved_piece + time_per_fall {
-                self.time_last_moved_piece = std::time::Instant::now();
-                self.piece_falling.pos.y += 1;
-                if self.piece_falling.collides_with(&self.grid) {
-                    self.piece_falling.pos.y -= 1;
-                    self.place_current_piece(ctx);
-                    self.update_grid_batch();
-                }
-            }
+    fn mouse_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult {
+        if button == MouseButton::Left {
+            self.shared
+                .touch
+                .begin(self.layout.screen_pos(x, y), ctx.time.time_since_start());
         }
-
-        self.bg_shader_params.set_uniforms(
-            ctx,
-            &ShaderUniform {
-                time: ctx.time.time_since_start().as_secs_f32() / 10.,
-            },
-        );
-
         Ok(())
     }
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        let mut canvas =
-            graphics::Canvas::from_frame(ctx, graphics::Color::from([0.1, 0.2, 0.3, 1.0]));
-
-        canvas.set_shader(&self.bg_shader);
-        canvas.set_shader_params(&self.bg_shader_params);
-        canvas.draw(
-            &Quad,
-            DrawParam::new().dest_rect(Rect::new(0., 0., 400., 300.)),
-        );
-        canvas.set_default_shader();
-        canvas.draw(&self.bg, DrawParam::new());
-
-        canvas.draw_instanced_mesh(
-            self.quad_mesh.clone(),
-            &self.grid_batch,
-            DrawParam::default().dest_rect(Rect::new(120., 16., 16., 16.)),
-        );
-        canvas.draw_instanced_mesh(
-            self.quad_mesh.clone(),
-            &self.piece_meshes[self.piece_falling.kind][self.piece_falling.rotation],
-            DrawParam::default().dest_rect(Rect::new(
-                120. + self.piece_falling.pos.x as f32 * 16.,
-                16. + self.piece_falling.pos.y as f32 * 16.,
-                16.,
-                16.,
-            )),
-        );
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> GameResult {
+        self.shared.touch.motion(self.layout.screen_pos(x, y));
+        Ok(())
+    }
 
-        if self.game_over {
-            canvas.draw(
-                &Quad,
-                DrawParam::default()
-                    .dest_rect(Rect::new(
-                        120.,
-                        16.,
-                        self.grid.width() as f32 * 16.,
-                        self.grid.height() as f32 * 16.,
-                    ))
-                    .color(Color::new(0., 0., 0., 0.7)),
-            );
-            canvas.draw(
-                &self.game_over_text,
-                DrawParam::default().dest(Point2 { x: 120., y: 16. }),
-            );
+    fn mouse_button_up_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult {
+        if button == MouseButton::Left {
+            self.shared
+                .touch
+                .end(self.layout.screen_pos(x, y), ctx.time.time_since_start());
         }
+        Ok(())
+    }
 
-        if let Some(anim) = &self.line_destroy_animations {
-            for lines in &anim.lines_to_destroy {
-                for line in lines.clone() {
-                    canvas.draw(
-                        &self.quad_mesh,
-                        DrawParam::default().dest_rect(Rect::new(
-                            120.,
-                            16. + 16. * line as f32,
-                            self.grid.width() as f32 * 16.,
-                            16.,
-                        )),
-                    );
-                }
-            }
-        }
+    fn gamepad_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        btn: Button,
+        _id: GamepadId,
+    ) -> GameResult {
+        self.shared.gamepad.press(btn);
+        Ok(())
+    }
 
-        canvas.finish(ctx)?;
+    fn gamepad_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        btn: Button,
+        _id: GamepadId,
+    ) -> GameResult {
+        self.shared.gamepad.release(btn);
+        Ok(())
+    }
 
+    fn gamepad_axis_event(
+        &mut self,
+        _ctx: &mut Context,
+        axis: Axis,
+        value: f32,
+        _id: GamepadId,
+    ) -> GameResult {
+        self.shared.gamepad.move_axis(axis, value);
         Ok(())
     }
 }
 
-pub fn main() -> GameResult {
-    let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
+/// Names the user data and config directories are found by.
+const GAME_ID: &str = "tetris";
+const AUTHOR: &str = "aleok";
+
+/// Where the assets are read from: the source tree when run through cargo, or next to the
+/// working directory otherwise.
+fn resource_dir() -> path::PathBuf {
+    if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
         let mut path = path::PathBuf::from(manifest_dir);
         path.push("assets");
         path
     } else {
         path::PathBuf::from("./assets")
+    }
+}
+
+/// Installs the player's own pieces and kicks, if they have any, which has to happen before
+/// any piece is used.
+fn install_custom_rules(fs: &Filesystem) -> GameResult {
+    if let Some(pieces) = PieceSet::load(fs)? {
+        pieces.install();
+    }
+    if let Some(kicks) = KickTables::load(fs)? {
+        kicks.install();
+    }
+    Ok(())
+}
+
+/// The replay or script of inputs given to play back, if any.
+fn read_replay(args: &Args) -> GameResult<Option<Replay>> {
+    Ok(match (&args.replay, &args.script) {
+        (Some(path), _) => Some(Replay::parse(&fs::read(path)?)?),
+        (None, Some(path)) => Some(Replay::parse_script(&fs::read_to_string(path)?)?),
+        (None, None) => None,
+    })
+}
+
+/// The pieces given to practice with over and over, if any.
+fn read_sequence(args: &Args) -> GameResult<Option<Vec<PieceKind>>> {
+    let Some(path) = &args.sequence else {
+        return Ok(None);
     };
+    let queue = Setup::parse_queue(&fs::read_to_string(path)?)
+        .map_err(|e| GameError::CustomError(e.to_string()))?;
+    if queue.is_empty() {
+        return Err(GameError::CustomError(
+            "--sequence needs at least one piece".to_owned(),
+        ));
+    }
+    Ok(Some(queue))
+}
 
-    let cb = ggez::ContextBuilder::new("tetris", "aleok")
+/// Renders the replay given to a GIF without opening a window, with the same pieces and
+/// kicks the game would play it with.
+fn export_gif(args: &Args, path: &path::Path) -> GameResult {
+    let fs = Filesystem::new(GAME_ID, AUTHOR, "resources", "resources.zip")?;
+    fs.mount(&resource_dir(), true);
+    install_custom_rules(&fs)?;
+    let replay = read_replay(args)?.ok_or_else(|| {
+        GameError::CustomError("--gif needs a --replay or --script to render".to_owned())
+    })?;
+    replay_gif::export(&replay, path)
+}
+
+pub fn main() -> GameResult {
+    let args = Args::parse();
+    if let Some(path) = &args.gif {
+        return export_gif(&args, path);
+    }
+
+    let cb = ggez::ContextBuilder::new(GAME_ID, AUTHOR)
         .window_setup(WindowSetup::default().title("Tetris"))
-        .window_mode(WindowMode::default().dimensions(400., 300.))
-        .add_resource_path(resource_dir);
+        .window_mode(
+            WindowMode::default()
+                .dimensions(SCREEN.w, SCREEN.h)
+                .min_dimensions(SCREEN.w / 2., SCREEN.h / 2.)
+                .resizable(true)
+                .fullscreen_type(if args.fullscreen {
+                    FullscreenType::Desktop
+                } else {
+                    FullscreenType::Windowed
+                }),
+        )
+        .add_resource_path(resource_dir());
     let (mut ctx, event_loop) = cb.build()?;
-    let state = MainState::new(&mut ctx)?;
+    let state = MainState::new(&mut ctx, args)?;
     event::run(ctx, event_loop, state)
 }
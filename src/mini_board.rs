@@ -0,0 +1,111 @@
+use enum_map::{enum_map, EnumMap};
+use ggez::{
+    graphics::{self, Color, DrawParam, InstanceArray, Mesh, Rect},
+    mint::Point2,
+    Context,
+};
+use tetris_core::{Grid, PieceKind};
+
+use crate::render::{BlockSprites, GARBAGE_COLOR};
+
+/// Draws small grids, like the pieces in the side panel, scaled to fit in a panel and
+/// centered in it by their blocks rather than by their whole box.
+pub struct MiniBoard {
+    batch: InstanceArray,
+    /// How each piece's blocks are drawn, kept from the assets so that grids can be drawn
+    /// without them.
+    block_sprites: EnumMap<PieceKind, DrawParam>,
+}
+
+impl MiniBoard {
+    pub fn new(ctx: &Context, blocks: &BlockSprites) -> Self {
+        Self {
+            batch: InstanceArray::new(ctx, blocks.texture.clone()),
+            block_sprites: enum_map! { kind => blocks.param(kind) },
+        }
+    }
+
+    /// Draws the blocks of `grid` as big as fits in `panel`, but no bigger than `max_cell`
+    /// pixels each, tinted by `color`. Each board keeps one batch, so it draws a single grid
+    /// a frame.
+    pub fn draw(
+        &mut self,
+        canvas: &mut graphics::Canvas,
+        quad_mesh: &Mesh,
+        grid: &Grid,
+        panel: Rect,
+        max_cell: f32,
+        color: Color,
+    ) {
+        let Some(bounds) = Self::bounds(grid) else {
+            return;
+        };
+        let cell = (panel.w / bounds.w)
+            .min(panel.h / bounds.h)
+            .min(max_cell)
+            .floor()
+            .max(1.);
+        let origin = Point2 {
+            x: (panel.x + (panel.w - bounds.w * cell) / 2. - bounds.x * cell).round(),
+            y: (panel.y + (panel.h - bounds.h * cell) / 2. - bounds.y * cell).round(),
+        };
+        self.draw_cells(canvas, quad_mesh, grid, origin, cell, color);
+    }
+
+    /// Draws the blocks of `grid` with its top left corner at `origin`, `cell` pixels each,
+    /// tinted by `color`.
+    pub fn draw_cells(
+        &mut self,
+        canvas: &mut graphics::Canvas,
+        quad_mesh: &Mesh,
+        grid: &Grid,
+        origin: Point2<f32>,
+        cell: f32,
+        color: Color,
+    ) {
+        self.batch.clear();
+        for (x, y, block) in grid.iter() {
+            if let Some(block) = block {
+                let param = self.block_sprites[block.kind];
+                let param = if block.garbage {
+                    param.color(GARBAGE_COLOR)
+                } else {
+                    param
+                };
+                self.batch.push(param.dest(Point2 {
+                    x: x as f32,
+                    y: y as f32,
+                }));
+            }
+        }
+        canvas.draw_instanced_mesh(
+            quad_mesh.clone(),
+            &self.batch,
+            DrawParam::default()
+                .dest_rect(Rect::new(origin.x, origin.y, cell, cell))
+                .color(color),
+        );
+    }
+
+    /// The cells spanned by the blocks of `grid`, or `None` if it's empty.
+    fn bounds(grid: &Grid) -> Option<Rect> {
+        let filled = (0..grid.height())
+            .flat_map(|y| (0..grid.width()).map(move |x| (x, y)))
+            .filter(|&(x, y)| grid.at(x as i32, y as i32).is_some());
+        let (mut left, mut top, mut right, mut bottom) = (usize::MAX, usize::MAX, 0, 0);
+        for (x, y) in filled {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x + 1);
+            bottom = bottom.max(y + 1);
+        }
+        (right > left).then(|| {
+            Rect::new(
+                left as f32,
+                top as f32,
+                (right - left) as f32,
+                (bottom - top) as f32,
+            )
+        })
+    }
+}
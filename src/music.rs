@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use ggez::{
+    audio::{self, SoundSource},
+    Context, GameResult,
+};
+
+/// Extensions of the audio files that can be played as music.
+const EXTENSIONS: [&str; 4] = ["mp3", "ogg", "wav", "flac"];
+
+/// Paths of the audio files in a resource directory, sorted by name.
+pub fn songs_in(ctx: &Context, dir: &str) -> Vec<String> {
+    let mut songs: Vec<_> = ctx
+        .fs
+        .read_dir(dir)
+        .map(|paths| {
+            paths
+                .filter(|path| {
+                    path.extension()
+                        .and_then(|extension| extension.to_str())
+                        .is_some_and(|extension| EXTENSIONS.contains(&extension))
+                })
+                .filter_map(|path| Some(path.to_str()?.to_owned()))
+                .collect()
+        })
+        .unwrap_or_default();
+    songs.sort_unstable();
+    songs.dedup();
+    songs
+}
+
+/// Plays a song split into stems that all loop together, bringing more of them in as the
+/// game gets more intense: only the first stem plays when nothing is happening, and every
+/// stem plays once the stack is about to top out.
+pub struct MusicDirector {
+    /// Paths the stems were loaded from, in the order they come in.
+    paths: Vec<String>,
+    stems: Vec<audio::Source>,
+    /// How loud each stem is right now, from 0.0 to 1.0, before the music volume.
+    gains: Vec<f32>,
+    /// How intense the game is, from 0.0 to 1.0.
+    intensity: f32,
+}
+
+impl MusicDirector {
+    /// How long a stem takes to fade in or out.
+    const FADE: Duration = Duration::from_secs(2);
+
+    /// Loads every stem in `dir`, ordered by name. Returns `None` if there aren't any.
+    pub fn load(ctx: &Context, dir: &str) -> GameResult<Option<Self>> {
+        let paths = songs_in(ctx, dir);
+        if paths.is_empty() {
+            return Ok(None);
+        }
+        let stems = paths
+            .iter()
+            .map(|path| {
+                let mut stem = audio::Source::new(ctx, path)?;
+                stem.set_repeat(true);
+                Ok(stem)
+            })
+            .collect::<GameResult<Vec<_>>>()?;
+        let mut gains = vec![0.; stems.len()];
+        gains[0] = 1.;
+        Ok(Some(Self {
+            paths,
+            stems,
+            gains,
+            intensity: 0.,
+        }))
+    }
+
+    pub fn has_stem(&self, path: &str) -> bool {
+        self.paths.iter().any(|stem| stem == path)
+    }
+
+    /// Starts every stem over at once, at `speed`, so that they stay in sync.
+    pub fn play(&mut self, ctx: &Context, speed: f32) -> GameResult {
+        for stem in &mut self.stems {
+            stem.set_pitch(speed);
+            stem.play(ctx)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity.clamp(0., 1.);
+    }
+
+    /// Whether a stem should be heard at the current intensity. The first one always is,
+    /// and the rest come in at even steps up to full intensity.
+    fn is_audible(&self, stem: usize) -> bool {
+        stem == 0 || self.intensity >= stem as f32 / (self.stems.len() - 1) as f32
+    }
+
+    /// Fades the stems in or out towards the current intensity, over `delta`.
+    pub fn update(&mut self, delta: Duration, volume: f32) {
+        let fade = delta.as_secs_f32() / Self::FADE.as_secs_f32();
+        for i in 0..self.stems.len() {
+            let gain = if self.is_audible(i) {
+                (self.gains[i] + fade).min(1.)
+            } else {
+                (self.gains[i] - fade).max(0.)
+            };
+            self.gains[i] = gain;
+            self.stems[i].set_volume(gain * volume);
+        }
+    }
+
+    /// A line about how loud each stem is, for the debug overlay.
+    pub fn debug_line(&self) -> String {
+        let gains: Vec<_> = self
+            .gains
+            .iter()
+            .map(|gain| format!("{:.0}%", gain * 100.))
+            .collect();
+        format!(
+            "music intensity: {:.2} stems: {}",
+            self.intensity,
+            gains.join(" ")
+        )
+    }
+}
@@ -0,0 +1,506 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use tetris_core::{
+    BoardSize, FrameInput, GameMode, Handling, Leaderboard, RotationSystemKind, RulesHash,
+    SoftDrop, TopOut,
+};
+use tetris_lobby::Lobby;
+
+/// Port games are hosted on unless another one is given.
+pub const DEFAULT_PORT: u16 = 7777;
+/// Bumped whenever messages change, so that different versions of the game refuse to play
+/// together rather than falling out of sync.
+const PROTOCOL_VERSION: u8 = 9;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Something sent to the other player of an online match.
+#[derive(Clone, Copy)]
+pub enum Message {
+    /// Sent by both sides once connected. The host decides the seed and board size, and each
+    /// side tells the other its handling so that it can simulate the other's game. Both have
+    /// to play with the same rules, or their games would play differently on each side.
+    Hello {
+        version: u8,
+        rules_hash: RulesHash,
+        seed: u32,
+        board_size: BoardSize,
+        handling: Handling,
+    },
+    /// The input of the sender for one step of the match.
+    Input { frame: u32, input: FrameInput },
+    /// The sender wants to play again once the match is over.
+    Rematch,
+}
+
+impl Message {
+    const HELLO: u8 = 0;
+    const INPUT: u8 = 1;
+    const REMATCH: u8 = 2;
+
+    /// Length of the message starting with `tag`, tag included, or `None` if no message
+    /// starts with it.
+    fn len(tag: u8) -> Option<usize> {
+        match tag {
+            Self::HELLO => Some(1 + 1 + 8 + 4 + 2 + 12 + 3),
+            Self::INPUT => Some(1 + 4 + 2 + 2),
+            Self::REMATCH => Some(1),
+            _ => None,
+        }
+    }
+
+    fn write(self, bytes: &mut Vec<u8>) {
+        match self {
+            Message::Hello {
+                version,
+                rules_hash,
+                seed,
+                board_size,
+                handling,
+            } => {
+                let millis = |duration: Duration| (duration.as_millis() as u16).to_le_bytes();
+                bytes.push(Self::HELLO);
+                bytes.push(version);
+                bytes.extend(rules_hash.0.to_le_bytes());
+                bytes.extend(seed.to_le_bytes());
+                bytes.push(board_size.width as u8);
+                bytes.push(board_size.height as u8);
+                bytes.extend(millis(handling.das));
+                bytes.extend(millis(handling.arr));
+                bytes.extend(millis(handling.dcd));
+                bytes.extend(handling.soft_drop.to_bits().to_le_bytes());
+                bytes.extend(millis(handling.are));
+                bytes.extend(millis(handling.line_clear_delay));
+                bytes.push(handling.rotation_system as u8);
+                bytes.push(handling.top_out.to_bits());
+                bytes.push(
+                    u8::from(handling.initial_rotation)
+                        | u8::from(handling.initial_hold) << 1
+                        | u8::from(handling.hold) << 2,
+                );
+            }
+            Message::Input { frame, input } => {
+                bytes.push(Self::INPUT);
+                bytes.extend(frame.to_le_bytes());
+                bytes.extend(input.pressed.to_le_bytes());
+                bytes.extend(input.just_pressed.to_le_bytes());
+            }
+            Message::Rematch => bytes.push(Self::REMATCH),
+        }
+    }
+
+    /// Reads a whole message of the length given by `len`, or `None` if it holds something
+    /// no game can be played with.
+    fn read(bytes: &[u8]) -> Option<Self> {
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let millis_at = |i: usize| Duration::from_millis(u16_at(i).into());
+        let message = match bytes[0] {
+            Self::HELLO => Message::Hello {
+                version: bytes[1],
+                rules_hash: RulesHash(u64::from_le_bytes(bytes[2..10].try_into().unwrap())),
+                seed: u32_at(10),
+                board_size: {
+                    let board_size = BoardSize {
+                        width: bytes[14].into(),
+                        height: bytes[15].into(),
+                    };
+                    if !board_size.is_allowed() {
+                        return None;
+                    }
+                    board_size
+                },
+                handling: Handling {
+                    das: millis_at(16),
+                    arr: millis_at(18),
+                    dcd: millis_at(20),
+                    soft_drop: SoftDrop::from_bits(u16_at(22)),
+                    are: millis_at(24),
+                    line_clear_delay: millis_at(26),
+                    rotation_system: RotationSystemKind::ALL
+                        .get(bytes[28] as usize)
+                        .copied()
+                        .unwrap_or_default(),
+                    top_out: TopOut::from_bits(bytes[29]),
+                    initial_rotation: bytes[30] & 1 != 0,
+                    initial_hold: bytes[30] & 1 << 1 != 0,
+                    hold: bytes[30] & 1 << 2 != 0,
+                },
+            },
+            Self::INPUT => Message::Input {
+                frame: u32_at(1),
+                input: FrameInput {
+                    pressed: u16_at(5),
+                    just_pressed: u16_at(7),
+                },
+            },
+            _ => Message::Rematch,
+        };
+        Some(message)
+    }
+
+    pub fn hello(seed: u32, board_size: BoardSize, handling: Handling) -> Self {
+        Message::Hello {
+            version: PROTOCOL_VERSION,
+            rules_hash: RulesHash::installed(),
+            seed,
+            board_size,
+            handling,
+        }
+    }
+
+    /// Whether a `Hello` came from a version of the game that can play with this one.
+    pub fn is_compatible(&self) -> bool {
+        matches!(self, Message::Hello { version, .. } if *version == PROTOCOL_VERSION)
+    }
+
+    /// Whether a `Hello` came from a game playing with the same rules as this one.
+    pub fn has_installed_rules(&self) -> bool {
+        matches!(self, Message::Hello { rules_hash, .. } if *rules_hash == RulesHash::installed())
+    }
+}
+
+/// A connection to the other player, which never blocks: messages are queued to be sent and
+/// whatever has arrived is read once per frame.
+pub struct Connection {
+    stream: TcpStream,
+    /// Bytes received that don't make up a whole message yet.
+    incoming: Vec<u8>,
+    /// Bytes that couldn't be sent yet without blocking.
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        // Inputs are tiny and have to arrive as soon as possible.
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            incoming: vec![],
+            outgoing: vec![],
+        })
+    }
+
+    pub fn send(&mut self, message: Message) {
+        message.write(&mut self.outgoing);
+    }
+
+    /// Sends what was queued and returns the messages that arrived since the last call.
+    /// Fails once the other player is gone.
+    pub fn poll(&mut self) -> io::Result<Vec<Message>> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(sent) => {
+                    self.outgoing.drain(..sent);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut buffer = [0; 1024];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(ErrorKind::ConnectionAborted.into()),
+                Ok(read) => self.incoming.extend(&buffer[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        let mut messages = vec![];
+        while let Some(&tag) = self.incoming.first() {
+            let len = Message::len(tag).ok_or(ErrorKind::InvalidData)?;
+            if self.incoming.len() < len {
+                break;
+            }
+            messages.push(Message::read(&self.incoming[..len]).ok_or(ErrorKind::InvalidData)?);
+            self.incoming.drain(..len);
+        }
+        Ok(messages)
+    }
+}
+
+/// Waits for another player to join a hosted game, without blocking.
+pub struct Host {
+    listener: TcpListener,
+}
+
+impl Host {
+    pub fn new(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener })
+    }
+
+    /// The player that joined, once one has.
+    pub fn accept(&self) -> io::Result<Option<Connection>> {
+        match self.listener.accept() {
+            Ok((stream, _)) => Connection::new(stream).map(Some),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Runs `task` on a thread of its own, for things that block for a while.
+fn in_background<T: Send + 'static>(
+    task: impl FnOnce() -> io::Result<T> + Send + 'static,
+) -> Receiver<io::Result<T>> {
+    let (sender, result) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(task());
+    });
+    result
+}
+
+/// Connects to the first of the addresses `address` resolves to that answers.
+fn connect(address: &str) -> io::Result<TcpStream> {
+    let addresses: Vec<SocketAddr> = address.to_socket_addrs()?.collect();
+    let mut error = io::Error::from(ErrorKind::AddrNotAvailable);
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+/// What a task run with `in_background` returned, once it has.
+fn finished<T>(result: &Receiver<io::Result<T>>) -> io::Result<Option<T>> {
+    match result.try_recv() {
+        Ok(value) => value.map(Some),
+        Err(TryRecvError::Empty) => Ok(None),
+        Err(TryRecvError::Disconnected) => Err(ErrorKind::ConnectionAborted.into()),
+    }
+}
+
+/// Connects to a hosted game in the background, since connecting can take a while.
+pub struct Join {
+    result: Receiver<io::Result<TcpStream>>,
+}
+
+impl Join {
+    /// Starts connecting to `address`, a host name or IP with an optional port.
+    pub fn new(address: &str) -> Self {
+        let address = if address.contains(':') {
+            address.to_owned()
+        } else {
+            format!("{}:{}", address, DEFAULT_PORT)
+        };
+        Self {
+            result: in_background(move || connect(&address)),
+        }
+    }
+
+    /// The connection to the host, once it's been made.
+    pub fn poll(&self) -> io::Result<Option<Connection>> {
+        match finished(&self.result)? {
+            Some(stream) => Connection::new(stream).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Fetches the games listed on the lobby server in the background.
+pub struct LobbyList {
+    result: Receiver<io::Result<Vec<Lobby>>>,
+}
+
+impl LobbyList {
+    pub fn new(server: &str) -> Self {
+        let server = server.to_owned();
+        Self {
+            result: in_background(move || tetris_lobby::list(&server)),
+        }
+    }
+
+    /// The games listed, once the server answered.
+    pub fn poll(&self) -> io::Result<Option<Vec<Lobby>>> {
+        finished(&self.result)
+    }
+}
+
+/// Lists a hosted game on the lobby server in the background, keeping it listed until this
+/// is dropped.
+pub struct Listing {
+    result: Receiver<io::Result<TcpStream>>,
+    /// Connection to the server, which keeps the game listed while it's open.
+    stream: Option<TcpStream>,
+    error: Option<String>,
+}
+
+impl Listing {
+    pub fn new(server: &str, port: u16, name: &str) -> Self {
+        let (server, name) = (server.to_owned(), name.to_owned());
+        Self {
+            result: in_background(move || tetris_lobby::host(&server, port, &name)),
+            stream: None,
+            error: None,
+        }
+    }
+
+    /// Checks whether the server answered yet.
+    pub fn update(&mut self) {
+        if self.stream.is_some() || self.error.is_some() {
+            return;
+        }
+        match finished(&self.result) {
+            Ok(stream) => self.stream = stream,
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    pub fn is_listed(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Why the game couldn't be listed, if it couldn't.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// A finished run to send to the online leaderboard.
+pub struct Score {
+    pub mode: GameMode,
+    pub seed: u32,
+    pub value: u64,
+    pub name: String,
+    /// `Replay::hash` of the run's replay, for the server to check the replay against if it
+    /// asks for it.
+    pub replay_hash: u64,
+}
+
+/// Sends a score to the online leaderboard at `server` in the background.
+pub struct ScoreSubmission {
+    result: Receiver<io::Result<String>>,
+    sent: bool,
+    error: Option<String>,
+}
+
+impl ScoreSubmission {
+    pub fn new(server: &str, score: Score) -> Self {
+        let url = format!("{}/scores", server.trim_end_matches('/'));
+        let body = format!(
+            "mode={}&seed={}&value={}&name={}&replay={:016x}",
+            score.mode.arg(),
+            score.seed,
+            score.value,
+            form_value(&score.name),
+            score.replay_hash,
+        );
+        Self {
+            result: in_background(move || http_request("POST", &url, &body)),
+            sent: false,
+            error: None,
+        }
+    }
+
+    /// Checks whether the server answered yet.
+    pub fn update(&mut self) {
+        if self.sent || self.error.is_some() {
+            return;
+        }
+        match finished(&self.result) {
+            Ok(answer) => self.sent = answer.is_some(),
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    pub fn is_sent(&self) -> bool {
+        self.sent
+    }
+
+    /// Why the score couldn't be sent, if it couldn't.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Fetches the best scores of a mode from the online leaderboard in the background.
+pub struct TopScores {
+    result: Receiver<io::Result<String>>,
+    mode: GameMode,
+}
+
+impl TopScores {
+    pub fn new(server: &str, mode: GameMode) -> Self {
+        let url = format!(
+            "{}/scores?mode={}",
+            server.trim_end_matches('/'),
+            mode.arg()
+        );
+        Self {
+            result: in_background(move || http_request("GET", &url, "")),
+            mode,
+        }
+    }
+
+    /// The table, once the server answered. The server lists scores as `Leaderboard` writes
+    /// them.
+    pub fn poll(&self) -> io::Result<Option<Leaderboard>> {
+        Ok(finished(&self.result)?.map(|body| Leaderboard::parse(&body, self.mode.record_order())))
+    }
+}
+
+/// Makes a request to `url`, which has to start with `http://`, and returns the body of the
+/// answer if it was successful. HTTP/1.0 is spoken so that the body comes as it is, without
+/// chunks.
+fn http_request(method: &str, url: &str, body: &str) -> io::Result<String> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        io::Error::new(ErrorKind::InvalidInput, "only http:// servers can be used")
+    })?;
+    let (host, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') {
+        host.to_owned()
+    } else {
+        format!("{}:80", host)
+    };
+    let mut stream = connect(&address)?;
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    write!(
+        stream,
+        "{} {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/x-www-form-urlencoded\r\n\
+         Content-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        host,
+        body.len(),
+        body
+    )?;
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer)?;
+    let (head, body) = answer.split_once("\r\n\r\n").unwrap_or((&answer, ""));
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or(ErrorKind::InvalidData)?;
+    if !(200..300).contains(&status) {
+        return Err(io::Error::other(format!("the server answered {}", status)));
+    }
+    Ok(body.to_owned())
+}
+
+/// `value` with everything but letters and digits percent-encoded, to send in a form.
+fn form_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
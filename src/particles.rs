@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use ggez::{
+    graphics::{self, DrawParam, Image, InstanceArray, Mesh},
+    mint::{Point2, Vector2},
+    Context,
+};
+use rand::Rng;
+use tetris_core::Game;
+
+struct Particle {
+    pos: Point2<f32>,
+    /// In pixels per second.
+    velocity: Vector2<f32>,
+    rotation: f32,
+    /// In radians per second.
+    spin: f32,
+    size: f32,
+    /// The part of the texture and the color the particle starts with.
+    sprite: DrawParam,
+    age: Duration,
+    lifetime: Duration,
+}
+
+/// Shards of blocks flying off the board, which fall and fade out on their own.
+pub struct Particles {
+    particles: Vec<Particle>,
+    batch: InstanceArray,
+}
+
+impl Particles {
+    /// Downwards acceleration of every particle, in pixels per second squared.
+    const GRAVITY: f32 = 400.;
+    const SHARDS_PER_BLOCK: usize = 3;
+    const SPARKS_PER_FIREWORK: usize = 24;
+
+    pub fn new(ctx: &Context, texture: Image) -> Self {
+        Self {
+            particles: vec![],
+            batch: InstanceArray::new(ctx, texture),
+        }
+    }
+
+    /// Breaks a block of `size` pixels at `pos` on screen, drawn with `sprite`, into shards
+    /// flying outwards.
+    pub fn burst(&mut self, pos: Point2<f32>, size: f32, sprite: DrawParam) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..Self::SHARDS_PER_BLOCK {
+            let shard = size * rng.gen_range(0.25..0.5);
+            self.particles.push(Particle {
+                pos: Point2 {
+                    x: pos.x + rng.gen_range(0. ..size - shard),
+                    y: pos.y + rng.gen_range(0. ..size - shard),
+                },
+                velocity: Vector2 {
+                    x: rng.gen_range(-80. ..80.),
+                    y: rng.gen_range(-160. ..-40.),
+                },
+                rotation: 0.,
+                spin: rng.gen_range(-8. ..8.),
+                size: shard,
+                sprite,
+                age: Duration::ZERO,
+                lifetime: Duration::from_millis(rng.gen_range(400..800)),
+            });
+        }
+    }
+
+    /// Bursts a firework at `pos` on screen, with sparks drawn with `sprite` flying out
+    /// evenly all around.
+    pub fn firework(&mut self, pos: Point2<f32>, sprite: DrawParam) {
+        let mut rng = rand::thread_rng();
+        for i in 0..Self::SPARKS_PER_FIREWORK {
+            let angle = i as f32 / Self::SPARKS_PER_FIREWORK as f32 * std::f32::consts::TAU;
+            let speed = rng.gen_range(80. ..160.);
+            self.particles.push(Particle {
+                pos,
+                velocity: Vector2 {
+                    x: angle.cos() * speed,
+                    y: angle.sin() * speed,
+                },
+                rotation: 0.,
+                spin: rng.gen_range(-8. ..8.),
+                size: rng.gen_range(3. ..5.),
+                sprite,
+                age: Duration::ZERO,
+                lifetime: Duration::from_millis(rng.gen_range(600..1000)),
+            });
+        }
+    }
+
+    /// Moves the particles on by one game step, so that they freeze when the game is paused.
+    pub fn step(&mut self) {
+        let dt = Game::FRAME_TIME.as_secs_f32();
+        for particle in &mut self.particles {
+            particle.velocity.y += Self::GRAVITY * dt;
+            particle.pos.x += particle.velocity.x * dt;
+            particle.pos.y += particle.velocity.y * dt;
+            particle.rotation += particle.spin * dt;
+            particle.age += Game::FRAME_TIME;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+    }
+
+    pub fn draw(&mut self, canvas: &mut graphics::Canvas, quad_mesh: &Mesh) {
+        if self.particles.is_empty() {
+            return;
+        }
+        self.batch.set(self.particles.iter().map(|particle| {
+            let t = particle.age.as_secs_f32() / particle.lifetime.as_secs_f32();
+            let mut param = particle
+                .sprite
+                .dest(particle.pos)
+                .rotation(particle.rotation)
+                .scale([particle.size, particle.size]);
+            param.color.a *= 1. - t;
+            param
+        }));
+        canvas.draw_instanced_mesh(quad_mesh.clone(), &self.batch, DrawParam::default());
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// What a changed asset file holds, which decides what has to be loaded again.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    /// The background shader, the only one there is.
+    Shader,
+    Image,
+    Sound,
+    /// A theme's manifest, which can point any asset somewhere else.
+    Theme,
+}
+
+impl AssetKind {
+    /// Goes by the extension of a resource path, returning `None` for files that are only
+    /// read at startup, like fonts.
+    pub fn of(path: &str) -> Option<Self> {
+        match Path::new(path).extension()?.to_str()? {
+            "wgsl" => Some(AssetKind::Shader),
+            "png" => Some(AssetKind::Image),
+            "ogg" | "wav" | "mp3" | "flac" => Some(AssetKind::Sound),
+            "toml" => Some(AssetKind::Theme),
+            _ => None,
+        }
+    }
+}
+
+/// Notices when asset files change on disk, by checking when they were last modified every
+/// so often. Only used in debug builds, which read the assets straight from the source tree.
+pub struct FileWatcher {
+    /// Directory that the resource paths being watched are in.
+    root: PathBuf,
+    /// Last modification time of each watched resource path, if it could be read.
+    files: HashMap<String, Option<SystemTime>>,
+    since_check: Duration,
+}
+
+impl FileWatcher {
+    const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Watches every file under `root`. Files added later aren't noticed.
+    pub fn new(root: PathBuf) -> Self {
+        let mut watcher = Self {
+            root,
+            files: HashMap::new(),
+            since_check: Duration::ZERO,
+        };
+        watcher.watch_dir("/");
+        watcher
+    }
+
+    /// Starts watching a resource path, such as `/shaders/game_bg.wgsl`.
+    fn watch(&mut self, path: &str) {
+        let modified = self.modified(path);
+        self.files.insert(path.to_owned(), modified);
+    }
+
+    /// Starts watching every file in a resource directory and the directories in it.
+    fn watch_dir(&mut self, dir: &str) {
+        let Ok(entries) = fs::read_dir(self.root.join(dir.trim_start_matches('/'))) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let Some(name) = entry.file_name().to_str().map(str::to_owned) else {
+                continue;
+            };
+            let path = format!("{}/{}", dir.trim_end_matches('/'), name);
+            if entry.path().is_dir() {
+                self.watch_dir(&path);
+            } else {
+                self.watch(&path);
+            }
+        }
+    }
+
+    fn modified(&self, path: &str) -> Option<SystemTime> {
+        let path = self.root.join(path.trim_start_matches('/'));
+        fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    /// Resource paths that changed since the last check, which is done once `delta` adds up
+    /// to `CHECK_INTERVAL` so that the disk isn't read every frame.
+    pub fn changed(&mut self, delta: Duration) -> Vec<String> {
+        self.since_check += delta;
+        if self.since_check < Self::CHECK_INTERVAL {
+            return vec![];
+        }
+        self.since_check = Duration::ZERO;
+        let mut changed = vec![];
+        for path in self.files.keys() {
+            let modified = self.modified(path);
+            if modified != self.files[path] {
+                changed.push(path.clone());
+            }
+        }
+        for path in &changed {
+            self.watch(path);
+        }
+        changed
+    }
+}
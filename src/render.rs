@@ -0,0 +1,301 @@
+use crevice::std140::AsStd140;
+use enum_map::Enum;
+use ggez::{
+    glam::*,
+    graphics::{
+        self, Color, DrawParam, Mesh, MeshData, PxScale, Rect, Text, TextAlign, TextLayout, Vertex,
+    },
+    mint::Point2,
+    Context, GameResult,
+};
+use tetris_core::{GameMode, Leaderboard, PieceKind};
+
+use crate::theme::Theme;
+
+/// Tint of garbage blocks, which didn't come from any piece.
+pub const GARBAGE_COLOR: Color = Color::new(0.45, 0.45, 0.45, 1.);
+
+fn block_color(kind: PieceKind) -> Color {
+    let [r, g, b] = kind.color();
+    Color::from_rgb(r, g, b)
+}
+
+/// How blocks are drawn: from a tileset with a sprite for each piece, laid out in a row in
+/// `PieceKind` order, or if there is none by tinting a single block texture.
+pub struct BlockSprites {
+    pub texture: graphics::Image,
+    per_piece: bool,
+}
+
+impl BlockSprites {
+    fn load(ctx: &Context, theme: &Theme) -> GameResult<Self> {
+        let tileset = theme.path(&theme.blocks, "/textures/blocks.png");
+        Ok(match graphics::Image::from_path(ctx, tileset) {
+            Ok(texture) => Self {
+                texture,
+                per_piece: true,
+            },
+            Err(_) => Self {
+                texture: graphics::Image::from_path(ctx, "/textures/block.png")?,
+                per_piece: false,
+            },
+        })
+    }
+
+    /// The part of `texture` and the color to draw a block of `kind` with.
+    pub fn param(&self, kind: PieceKind) -> DrawParam {
+        if self.per_piece {
+            let width = 1. / PieceKind::TETROMINOES.len() as f32;
+            let x = kind.palette().into_usize() as f32 * width;
+            DrawParam::new().src(Rect::new(x, 0., width, 1.))
+        } else {
+            DrawParam::new().color(block_color(kind))
+        }
+    }
+}
+
+#[derive(AsStd140)]
+pub struct ShaderUniform {
+    pub time: f32,
+    /// How red the background is tinted to warn that the stack is near the top, from 0.0
+    /// to 1.0.
+    pub danger: f32,
+    /// How bright the background flashes, as after a tetris, from 0.0 to 1.0.
+    pub flash: f32,
+}
+
+/// Resources that are loaded once and shared by every scene.
+pub struct Assets {
+    pub blocks: BlockSprites,
+    pub cursor: graphics::Image,
+    /// Nine-slice frame for on-screen buttons.
+    pub button: graphics::Image,
+
+    // TODO: Access ggez gfx ctx quad mesh
+    pub quad_mesh: Mesh,
+
+    pub bg: graphics::Image,
+    pub game_over_text: graphics::Image,
+    pub bg_shader: graphics::Shader,
+    pub bg_shader_params: graphics::ShaderParams<ShaderUniform>,
+    pub clear_color: Color,
+}
+
+impl Assets {
+    pub const BG_SHADER: &'static str = "/shaders/game_bg.wgsl";
+
+    pub fn new(ctx: &mut Context, theme: &Theme) -> GameResult<Self> {
+        ctx.gfx.add_font(
+            "Big Apple 3PM",
+            graphics::FontData::from_path(ctx, "/fonts/Big Apple 3PM.ttf")?,
+        );
+        let bg_shader = graphics::ShaderBuilder::from_path(Self::BG_SHADER).build(ctx)?;
+        Self::with_shader(ctx, theme, bg_shader)
+    }
+
+    /// Loads everything but the background shader, which is given already built.
+    fn with_shader(
+        ctx: &mut Context,
+        theme: &Theme,
+        bg_shader: graphics::Shader,
+    ) -> GameResult<Self> {
+        Ok(Self {
+            blocks: BlockSprites::load(ctx, theme)?,
+            cursor: graphics::Image::from_path(
+                ctx,
+                theme.path(&theme.cursor, "/textures/cursor.png"),
+            )?,
+            button: graphics::Image::from_path(
+                ctx,
+                theme.path(&theme.button, "/textures/button9rect.png"),
+            )?,
+            bg: graphics::Image::from_path(
+                ctx,
+                theme.path(&theme.background, "/textures/game_bg.png"),
+            )?,
+            game_over_text: graphics::Image::from_path(
+                ctx,
+                theme.path(&theme.game_over, "/textures/game_over.png"),
+            )?,
+            bg_shader,
+            bg_shader_params: graphics::ShaderParamsBuilder::new(&ShaderUniform {
+                time: 0.,
+                danger: 0.,
+                flash: 0.,
+            })
+            .build(ctx),
+            clear_color: theme.clear_color(),
+            quad_mesh: Mesh::from_data(
+                &ctx.gfx,
+                MeshData {
+                    vertices: &[
+                        Vertex {
+                            position: [0., 0.],
+                            uv: [0., 0.],
+                            color: [1.; 4],
+                        },
+                        Vertex {
+                            position: [1., 0.],
+                            uv: [1., 0.],
+                            color: [1.; 4],
+                        },
+                        Vertex {
+                            position: [0., 1.],
+                            uv: [0., 1.],
+                            color: [1.; 4],
+                        },
+                        Vertex {
+                            position: [1., 1.],
+                            uv: [1., 1.],
+                            color: [1.; 4],
+                        },
+                    ],
+                    indices: &[0, 2, 1, 2, 3, 1],
+                },
+            ),
+        })
+    }
+
+    /// Loads the images again from `theme`, for when their files changed on disk. The
+    /// background shader is kept as it is.
+    #[cfg(debug_assertions)]
+    pub fn reload_images(&mut self, ctx: &mut Context, theme: &Theme) -> GameResult {
+        *self = Self::with_shader(ctx, theme, self.bg_shader.clone())?;
+        Ok(())
+    }
+
+    /// Rebuilds the background shader from its file, keeping the current one if the new code
+    /// doesn't compile.
+    #[cfg(debug_assertions)]
+    pub fn reload_bg_shader(&mut self, ctx: &mut Context) -> GameResult {
+        use std::io::Read;
+
+        use ggez::GameError;
+        use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+        let mut source = String::new();
+        ctx.fs.open(Self::BG_SHADER)?.read_to_string(&mut source)?;
+        // ggez panics on shaders that don't compile, so they are checked here first.
+        let module = naga::front::wgsl::parse_str(&source)
+            .map_err(|e| GameError::CustomError(e.emit_to_string(&source)))?;
+        Validator::new(ValidationFlags::all(), Capabilities::all())
+            .validate(&module)
+            .map_err(|e| GameError::CustomError(e.emit_to_string(&source)))?;
+        self.bg_shader = graphics::ShaderBuilder::from_code(&source).build(ctx)?;
+        Ok(())
+    }
+}
+
+pub fn ui_text(text: impl Into<graphics::TextFragment>, size: f32) -> Text {
+    let mut text = Text::new(text);
+    text.set_font("Big Apple 3PM")
+        .set_scale(PxScale::from(size));
+    text
+}
+
+/// Draws a button framed by the nine-slice button texture, stretching only its middle parts.
+pub fn draw_button(canvas: &mut graphics::Canvas, assets: &Assets, rect: Rect, label: &str) {
+    const CORNER: f32 = 16.;
+    let columns = [
+        (0., CORNER),
+        (CORNER, rect.w - 2. * CORNER),
+        (rect.w - CORNER, CORNER),
+    ];
+    let rows = [
+        (0., CORNER),
+        (CORNER, rect.h - 2. * CORNER),
+        (rect.h - CORNER, CORNER),
+    ];
+    for (i, (x, w)) in columns.into_iter().enumerate() {
+        for (j, (y, h)) in rows.into_iter().enumerate() {
+            if w <= 0. || h <= 0. {
+                continue;
+            }
+            canvas.draw(
+                &assets.button,
+                DrawParam::default()
+                    .src(Rect::new(i as f32 / 3., j as f32 / 3., 1. / 3., 1. / 3.))
+                    .dest(Point2 {
+                        x: rect.x + x,
+                        y: rect.y + y,
+                    })
+                    .scale(Vec2::new(w / CORNER, h / CORNER)),
+            );
+        }
+    }
+    let mut text = ui_text(label, 16.);
+    text.set_layout(TextLayout::center());
+    canvas.draw(&text, DrawParam::default().dest(rect.center()));
+}
+
+/// Draws a high score table in rows of `size` pixels, with the row at `highlight` picked out.
+pub fn draw_leaderboard(
+    canvas: &mut graphics::Canvas,
+    leaderboard: &Leaderboard,
+    mode: GameMode,
+    highlight: Option<usize>,
+    rect: Rect,
+    size: f32,
+) {
+    if leaderboard.entries().is_empty() {
+        canvas.draw(
+            &ui_text("NO SCORES YET", size),
+            DrawParam::default().dest(rect.point()),
+        );
+    }
+    for (i, entry) in leaderboard.entries().iter().enumerate() {
+        let y = rect.y + i as f32 * size;
+        let color = if highlight == Some(i) {
+            Color::YELLOW
+        } else {
+            Color::WHITE
+        };
+        let mut value = ui_text(mode.format_record(entry.value), size);
+        value.set_layout(TextLayout {
+            h_align: TextAlign::End,
+            v_align: TextAlign::Begin,
+        });
+        for (text, x) in [
+            (ui_text((i + 1).to_string(), size), rect.x),
+            (ui_text(entry.name.as_str(), size), rect.x + size * 2.),
+            (value, rect.right()),
+        ] {
+            canvas.draw(
+                &text,
+                DrawParam::default().dest(Point2 { x, y }).color(color),
+            );
+        }
+    }
+}
+
+/// A mesh of solid rectangles in one color, for lines and frames that only have to be built
+/// once for a size.
+pub fn rect_mesh(ctx: &Context, rects: &[Rect], color: Color) -> Mesh {
+    let color = [color.r, color.g, color.b, color.a];
+    let vertices: Vec<_> = rects
+        .iter()
+        .flat_map(|rect| {
+            [
+                [rect.x, rect.y],
+                [rect.right(), rect.y],
+                [rect.x, rect.bottom()],
+                [rect.right(), rect.bottom()],
+            ]
+        })
+        .map(|position| Vertex {
+            position,
+            uv: [0., 0.],
+            color,
+        })
+        .collect();
+    let indices: Vec<_> = (0..rects.len() as u32)
+        .flat_map(|i| [0, 2, 1, 2, 3, 1].map(|corner| i * 4 + corner))
+        .collect();
+    Mesh::from_data(
+        &ctx.gfx,
+        MeshData {
+            vertices: &vertices,
+            indices: &indices,
+        },
+    )
+}
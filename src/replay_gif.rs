@@ -0,0 +1,109 @@
+use std::{fs, path::Path, time::Duration};
+
+use ggez::{GameError, GameResult};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, Rgba, RgbaImage,
+};
+use tetris_core::{FrameInput, Game, Piece, Replay};
+
+/// Pixels across and down each cell of the board takes.
+const CELL_SIZE: u32 = 8;
+/// Steps of the game each frame of the GIF shows, which makes for 20 frames a second.
+const STEPS_PER_FRAME: u32 = 3;
+/// How long the board stays up once the replay is over, before the GIF starts over.
+const END_HOLD: Duration = Duration::from_secs(2);
+const BACKGROUND: Rgba<u8> = Rgba([18, 18, 24, 255]);
+const GARBAGE: Rgba<u8> = Rgba([115, 115, 115, 255]);
+
+/// Simulates a replay again without a window and writes the board as it went into an
+/// animated GIF, for sharing runs without recording the screen.
+pub fn export(replay: &Replay, path: &Path) -> GameResult {
+    let file = fs::File::create(path)?;
+    let mut encoder = GifEncoder::new_with_speed(file, 10);
+    encoder.set_repeat(Repeat::Infinite).map_err(gif_error)?;
+    let frame_delay = Delay::from_saturating_duration(Game::FRAME_TIME * STEPS_PER_FRAME);
+
+    let mut game = Game::new(replay.mode, replay.seed, replay.board_size);
+    let mut next_input = 0;
+    let mut input = FrameInput::default();
+    // As the replay scene plays it, so that the game comes out the same.
+    while !game.is_over() && game.frame() < replay.length {
+        if let Some((frame, recorded)) = replay.inputs.get(next_input) {
+            if *frame == game.frame() {
+                input = *recorded;
+                next_input += 1;
+            }
+        }
+        if game.frame().is_multiple_of(STEPS_PER_FRAME) {
+            encoder
+                .encode_frame(Frame::from_parts(render(&game), 0, 0, frame_delay))
+                .map_err(gif_error)?;
+        }
+        game.step(replay.handling_at(game.frame()), input);
+        input = input.held();
+    }
+    let end_delay = Delay::from_saturating_duration(END_HOLD);
+    encoder
+        .encode_frame(Frame::from_parts(render(&game), 0, 0, end_delay))
+        .map_err(gif_error)
+}
+
+/// The visible part of the board with the falling piece and its ghost, as in the game.
+fn render(game: &Game) -> RgbaImage {
+    let grid = game.grid();
+    let top = grid.hidden_rows() as i32;
+    let mut image = RgbaImage::from_pixel(
+        grid.width() as u32 * CELL_SIZE,
+        grid.visible_height() as u32 * CELL_SIZE,
+        BACKGROUND,
+    );
+    for (x, y, block) in grid.iter().filter(|&(_, y, _)| y >= top) {
+        if let Some(block) = block {
+            let color = if block.garbage {
+                GARBAGE
+            } else {
+                piece_color(block.kind.color(), 1.)
+            };
+            fill_cell(&mut image, x, y - top, color);
+        }
+    }
+    if !game.is_over() && !game.is_entering() {
+        let piece = game.piece();
+        draw_piece(&mut image, &game.ghost_piece(), top, 0.35);
+        draw_piece(&mut image, &piece, top, 1.);
+    }
+    image
+}
+
+fn draw_piece(image: &mut RgbaImage, piece: &Piece, top: i32, brightness: f32) {
+    let color = piece_color(piece.kind.color(), brightness);
+    for cell in piece.cells().filter(|cell| cell.y >= top) {
+        fill_cell(image, cell.x, cell.y - top, color);
+    }
+}
+
+/// Fills a cell, with a darker edge on its right and bottom to tell blocks apart.
+fn fill_cell(image: &mut RgbaImage, x: i32, y: i32, color: Rgba<u8>) {
+    let Rgba([r, g, b, a]) = color;
+    let edge = Rgba([r / 2, g / 2, b / 2, a]);
+    for dy in 0..CELL_SIZE {
+        for dx in 0..CELL_SIZE {
+            let pixel = if dx == CELL_SIZE - 1 || dy == CELL_SIZE - 1 {
+                edge
+            } else {
+                color
+            };
+            image.put_pixel(x as u32 * CELL_SIZE + dx, y as u32 * CELL_SIZE + dy, pixel);
+        }
+    }
+}
+
+fn piece_color([r, g, b]: [u8; 3], brightness: f32) -> Rgba<u8> {
+    let scale = |channel: u8| (channel as f32 * brightness) as u8;
+    Rgba([scale(r), scale(g), scale(b), 255])
+}
+
+fn gif_error(e: image::ImageError) -> GameError {
+    GameError::CustomError(e.to_string())
+}
@@ -0,0 +1,79 @@
+use ggez::{
+    graphics::{self, DrawParam},
+    mint::Point2,
+    Context, GameResult,
+};
+use tetris_core::Achievement;
+
+use super::{
+    back_pressed, confirm_pressed, draw_options, navigate_options, Scene, Shared, Transition,
+};
+use crate::render::ui_text;
+
+/// Lists every achievement and whether it has been unlocked, with what it takes to unlock
+/// the one selected.
+#[derive(Default)]
+pub struct AchievementsScene {
+    selected: usize,
+}
+
+impl AchievementsScene {
+    const OPTIONS_ORIGIN: Point2<f32> = Point2 { x: 120., y: 64. };
+}
+
+impl Scene for AchievementsScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        let gamepad = &shared.gamepad;
+        navigate_options(ctx, gamepad, &mut self.selected, Achievement::ALL.len());
+        if back_pressed(ctx, gamepad)
+            || confirm_pressed(ctx, gamepad)
+            || shared.touch.tapped().is_some()
+        {
+            return Ok(Transition::Pop);
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        let achievements = &shared.achievements;
+        canvas.draw(
+            &ui_text(
+                format!(
+                    "ACHIEVEMENTS {}/{}",
+                    achievements.unlocked_count(),
+                    Achievement::ALL.len()
+                ),
+                24.,
+            ),
+            DrawParam::default().dest(Point2 { x: 80., y: 16. }),
+        );
+        let options: Vec<_> = Achievement::ALL
+            .iter()
+            .map(|&achievement| {
+                let state = if achievements.is_unlocked(achievement) {
+                    "DONE"
+                } else {
+                    "LOCKED"
+                };
+                format!("{} {}", achievement.name(), state)
+            })
+            .collect();
+        draw_options(
+            canvas,
+            shared,
+            &options,
+            self.selected,
+            Self::OPTIONS_ORIGIN,
+        );
+        canvas.draw(
+            &ui_text(Achievement::ALL[self.selected].description(), 12.),
+            DrawParam::default().dest(Point2 { x: 40., y: 268. }),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use ggez::{
+    event::Button,
+    graphics::{self, DrawParam},
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use rand::Rng;
+use tetris_core::{Action, ComboDrill, ComboPieces, GameEvent, Setup};
+
+use super::{
+    back_pressed, confirm_pressed, draw_options, gameplay::Gameplay, menu_pressed,
+    navigate_options, settings::SettingsScene, tap_option, Scene, Shared, Transition,
+};
+use crate::{input::Controls, render::ui_text};
+
+/// Picks the width of the well and the pieces dealt before starting a combo drill.
+#[derive(Default)]
+pub struct ComboSetupScene {
+    drill: ComboDrill,
+    selected: usize,
+}
+
+impl ComboSetupScene {
+    const OPTIONS_ORIGIN: Point2<f32> = Point2 { x: 120., y: 64. };
+    const WELL_OPTION: usize = 0;
+    const PIECES_OPTION: usize = 1;
+    const START_OPTION: usize = 2;
+    const BACK_OPTION: usize = 3;
+
+    /// Moves `value` by `direction` through `choices`, wrapping around at the ends.
+    fn cycle<T: Copy + PartialEq>(choices: &[T], value: T, direction: i32) -> T {
+        let i = choices.iter().position(|&c| c == value).unwrap_or(0) as i32;
+        choices[(i + direction).rem_euclid(choices.len() as i32) as usize]
+    }
+}
+
+impl Scene for ComboSetupScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        let gamepad = &shared.gamepad;
+        navigate_options(ctx, gamepad, &mut self.selected, Self::BACK_OPTION + 1);
+        if back_pressed(ctx, gamepad) {
+            return Ok(Transition::Pop);
+        }
+        let confirmed = confirm_pressed(ctx, gamepad)
+            || tap_option(
+                &shared.touch,
+                &mut self.selected,
+                Self::BACK_OPTION + 1,
+                Self::OPTIONS_ORIGIN,
+            );
+        let direction = if menu_pressed(ctx, gamepad, VirtualKeyCode::Left, Button::DPadLeft) {
+            -1
+        } else if menu_pressed(ctx, gamepad, VirtualKeyCode::Right, Button::DPadRight) || confirmed
+        {
+            1
+        } else {
+            0
+        };
+        match self.selected {
+            Self::WELL_OPTION if direction != 0 => {
+                self.drill.well_width =
+                    Self::cycle(&ComboDrill::WELL_WIDTHS, self.drill.well_width, direction);
+            }
+            Self::PIECES_OPTION if direction != 0 => {
+                self.drill.pieces = Self::cycle(&ComboPieces::ALL, self.drill.pieces, direction);
+            }
+            Self::START_OPTION if confirmed => {
+                return Ok(Transition::Switch(Box::new(ComboScene::new(
+                    ctx, shared, self.drill,
+                ))));
+            }
+            Self::BACK_OPTION if confirmed => return Ok(Transition::Pop),
+            _ => {}
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        canvas.draw(
+            &ui_text("COMBO TRAINER", 32.),
+            DrawParam::default().dest(Point2 { x: 120., y: 16. }),
+        );
+        let options = [
+            format!("WELL     {} WIDE", self.drill.well_width),
+            format!("PIECES   {}", self.drill.pieces.name()),
+            "START".to_owned(),
+            "BACK".to_owned(),
+        ];
+        draw_options(
+            canvas,
+            shared,
+            &options,
+            self.selected,
+            Self::OPTIONS_ORIGIN,
+        );
+        Ok(())
+    }
+}
+
+/// Combo drills one after another, starting over whenever one tops out and keeping the
+/// longest combo made.
+pub struct ComboScene {
+    drill: ComboDrill,
+    gameplay: Gameplay,
+    pending_presses: u16,
+    /// Longest combo of any attempt so far.
+    best: u32,
+    attempts: u32,
+}
+
+impl ComboScene {
+    pub fn new(ctx: &mut Context, shared: &Shared, drill: ComboDrill) -> Self {
+        Self {
+            gameplay: Self::new_attempt(ctx, shared, drill),
+            drill,
+            pending_presses: 0,
+            best: 0,
+            attempts: 1,
+        }
+    }
+
+    fn new_attempt(ctx: &mut Context, shared: &Shared, drill: ComboDrill) -> Gameplay {
+        let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        Gameplay::training(ctx, shared, drill.start(seed, shared.settings.board_size))
+    }
+
+    fn step(&mut self, events: &[GameEvent]) {
+        if events
+            .iter()
+            .any(|event| matches!(event, GameEvent::LinesCleared { .. }))
+        {
+            self.best = self.best.max(self.gameplay.game.combo());
+        }
+    }
+}
+
+impl Scene for ComboScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        if self.gameplay.game.is_over() {
+            self.gameplay = Self::new_attempt(ctx, shared, self.drill);
+            self.attempts += 1;
+        }
+
+        let controls = Controls::new(ctx, &shared.settings, &shared.gamepad, &shared.touch);
+        let paused = controls.just_pressed(Action::Pause);
+        let mut input = controls.frame_input();
+        input.just_pressed |= self.pending_presses;
+        let handling = shared.settings.handling;
+
+        self.gameplay
+            .timer
+            .accumulate(ctx.time.delta(), Gameplay::MAX_CATCH_UP);
+        self.pending_presses = input.just_pressed;
+        while !self.gameplay.game.is_over() && self.gameplay.timer.take_step() {
+            let events = self.gameplay.step(ctx, &mut shared.audio, handling, input);
+            self.step(&events);
+            input = input.held();
+            self.pending_presses = 0;
+        }
+
+        if paused {
+            return Ok(Transition::Push(Box::new(SettingsScene::in_game())));
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        self.gameplay.draw(ctx, canvas, shared)?;
+        let game = &self.gameplay.game;
+        let lines = [
+            format!("{}-WIDE COMBO", self.drill.well_width),
+            format!("COMBO   {}", game.combo()),
+            format!("LONGEST {}", game.max_combo()),
+            format!("BEST    {}", self.best),
+            format!("TRY     {}", self.attempts),
+        ];
+        for (i, line) in lines.into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(line, 12.),
+                DrawParam::default().dest(Point2 {
+                    x: 296.,
+                    y: 196. + i as f32 * 14.,
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    fn debug_lines(&self) -> Vec<String> {
+        self.gameplay.debug_lines()
+    }
+
+    fn danger(&self) -> Option<f32> {
+        self.gameplay.danger()
+    }
+
+    fn intensity(&self) -> Option<f32> {
+        self.gameplay.intensity()
+    }
+
+    fn flash(&self) -> Option<f32> {
+        self.gameplay.flash()
+    }
+
+    fn setup(&self) -> Option<Setup> {
+        self.gameplay.setup()
+    }
+
+    #[cfg(debug_assertions)]
+    fn assets_reloaded(&mut self, ctx: &mut Context, shared: &Shared) {
+        self.gameplay.assets_reloaded(ctx, shared);
+    }
+}
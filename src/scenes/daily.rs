@@ -0,0 +1,165 @@
+use ggez::{
+    event::Button,
+    graphics::{self, Color, DrawParam, Quad, Rect},
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use tetris_core::{DailyChallenge, DailyResults};
+
+use super::{
+    back_pressed, confirm_pressed, gameplay::Gameplay, menu_pressed, Scene, Shared, Transition,
+};
+use crate::{
+    render::ui_text,
+    storage::{self, DailyResultsExt},
+};
+
+/// A calendar of the daily challenges played so far, a month at a time, from which the
+/// day's challenge is started.
+pub struct DailyScene {
+    today: DailyChallenge,
+    /// The day picked in the calendar, which can't be after today.
+    selected: DailyChallenge,
+    results: DailyResults,
+}
+
+impl DailyScene {
+    const CALENDAR_ORIGIN: Point2<f32> = Point2 { x: 88., y: 84. };
+    const CELL: Point2<f32> = Point2 { x: 32., y: 24. };
+    const PLAYED_COLOR: Color = Color::new(0.3, 0.8, 0.3, 1.);
+    const NO_RESULT_COLOR: Color = Color::new(0.6, 0.6, 0.6, 1.);
+
+    pub fn new(ctx: &Context) -> Self {
+        let today = DailyChallenge::at(storage::timestamp());
+        Self {
+            today,
+            selected: today,
+            results: DailyResults::load(ctx),
+        }
+    }
+
+    /// Moves the selection by `days`, as far as today at most.
+    fn move_selection(&mut self, days: i32) {
+        let day = (self.selected.day as i64 + days as i64).clamp(0, self.today.day as i64);
+        self.selected = DailyChallenge { day: day as u32 };
+    }
+
+    /// What the selected day's challenge was and how it went.
+    fn selected_lines(&self) -> [String; 2] {
+        let mode = self.selected.mode();
+        let result = match self.results.get(self.selected) {
+            Some(Some(value)) => mode.format_record(value),
+            Some(None) => "NO RESULT".to_owned(),
+            None if self.selected == self.today => "PRESS ENTER TO PLAY".to_owned(),
+            None => "NOT PLAYED".to_owned(),
+        };
+        let (year, month, day) = self.selected.date();
+        [
+            format!("{}-{:02}-{:02} {}", year, month, day, mode.name()),
+            result,
+        ]
+    }
+}
+
+impl Scene for DailyScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        let gamepad = &shared.gamepad;
+        if back_pressed(ctx, gamepad) {
+            return Ok(Transition::Pop);
+        }
+        if confirm_pressed(ctx, gamepad) && self.selected == self.today {
+            return Ok(Transition::Switch(Box::new(Gameplay::daily(
+                ctx, shared, self.today,
+            ))));
+        }
+        let moves = [
+            (VirtualKeyCode::Left, Button::DPadLeft, -1),
+            (VirtualKeyCode::Right, Button::DPadRight, 1),
+            (VirtualKeyCode::Up, Button::DPadUp, -7),
+            (VirtualKeyCode::Down, Button::DPadDown, 7),
+        ];
+        for (key, button, days) in moves {
+            if menu_pressed(ctx, gamepad, key, button) {
+                self.move_selection(days);
+            }
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        _shared: &Shared,
+    ) -> GameResult {
+        canvas.draw(
+            &ui_text("DAILY CHALLENGE", 32.),
+            DrawParam::default().dest(Point2 { x: 72., y: 16. }),
+        );
+        let (year, month, _) = self.selected.date();
+        let first = DailyChallenge::first_of_month(year, month);
+        let origin = Self::CALENDAR_ORIGIN;
+        canvas.draw(
+            &ui_text(format!("{}-{:02}", year, month), 16.),
+            DrawParam::default().dest(Point2 {
+                x: origin.x,
+                y: origin.y - 28.,
+            }),
+        );
+        for (column, name) in ["S", "M", "T", "W", "T", "F", "S"].into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(name, 12.),
+                DrawParam::default().dest(Point2 {
+                    x: origin.x + column as f32 * Self::CELL.x,
+                    y: origin.y - 12.,
+                }),
+            );
+        }
+        for date in 0..DailyChallenge::days_in_month(year, month) {
+            let challenge = DailyChallenge {
+                day: first.day + date,
+            };
+            let slot = first.weekday() + date;
+            let cell = Rect::new(
+                origin.x + (slot % 7) as f32 * Self::CELL.x,
+                origin.y + (slot / 7) as f32 * Self::CELL.y,
+                Self::CELL.x - 4.,
+                Self::CELL.y - 4.,
+            );
+            if challenge == self.selected {
+                canvas.draw(
+                    &Quad,
+                    DrawParam::default()
+                        .dest_rect(cell)
+                        .color(Color::new(1., 1., 1., 0.3)),
+                );
+            }
+            let color = match self.results.get(challenge) {
+                Some(Some(_)) => Self::PLAYED_COLOR,
+                Some(None) => Self::NO_RESULT_COLOR,
+                None if challenge.day > self.today.day => Color::new(1., 1., 1., 0.3),
+                None => Color::WHITE,
+            };
+            canvas.draw(
+                &ui_text((date + 1).to_string(), 12.),
+                DrawParam::default()
+                    .dest(Point2 {
+                        x: cell.x + 2.,
+                        y: cell.y + 2.,
+                    })
+                    .color(color),
+            );
+        }
+        for (i, line) in self.selected_lines().into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(line, 16.),
+                DrawParam::default().dest(Point2 {
+                    x: 72.,
+                    y: 236. + i as f32 * 20.,
+                }),
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use ggez::{
+    graphics::{self, Color, DrawParam, Quad, Rect, TextAlign, TextLayout},
+    mint::Point2,
+    Context, GameResult,
+};
+use tetris_core::{Bot, Difficulty, GameMode, Handling};
+
+use super::{any_pressed, gameplay::Gameplay, Scene, Shared, Transition};
+use crate::{render::ui_text, SCREEN};
+
+/// A game the computer plays by itself after the title screen has been left alone for a
+/// while, like arcade machines do to draw players in. Anything pressed goes back to the
+/// title screen.
+pub struct DemoScene {
+    gameplay: Gameplay,
+    bot: Bot,
+}
+
+impl DemoScene {
+    /// How often the prompt blinks, in seconds.
+    const BLINK_PERIOD: f32 = 1.;
+
+    pub fn new(ctx: &mut Context, shared: &Shared) -> Self {
+        let gameplay = Gameplay::demo(ctx, shared, GameMode::ALL[0]);
+        Self {
+            bot: Bot::new(Difficulty::Hard, rand::random()),
+            gameplay,
+        }
+    }
+}
+
+impl Scene for DemoScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        if any_pressed(ctx, shared) || self.gameplay.game.is_over() {
+            return Ok(Transition::Pop);
+        }
+        self.gameplay
+            .timer
+            .accumulate(ctx.time.delta(), Gameplay::MAX_CATCH_UP);
+        while !self.gameplay.game.is_over() && self.gameplay.timer.take_step() {
+            let input = self.bot.input(&self.gameplay.game);
+            self.gameplay
+                .step(ctx, &mut shared.audio, Handling::default(), input);
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        self.gameplay.draw(ctx, canvas, shared)?;
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest_rect(Rect::new(SCREEN.x, 124., SCREEN.w, 52.))
+                .color(Color::new(0., 0., 0., 0.7)),
+        );
+        let blink_on = ctx.time.time_since_start().as_secs_f32() % Self::BLINK_PERIOD
+            < Self::BLINK_PERIOD / 2.;
+        let lines = [("DEMO", 24.), ("PRESS ANY KEY", 12.)];
+        for (i, (line, size)) in lines.into_iter().enumerate() {
+            if i == 1 && !blink_on {
+                continue;
+            }
+            let mut text = ui_text(line, size);
+            text.set_layout(TextLayout {
+                h_align: TextAlign::Middle,
+                v_align: TextAlign::Middle,
+            });
+            canvas.draw(
+                &text,
+                DrawParam::default().dest(Point2 {
+                    x: SCREEN.center().x,
+                    y: 140. + i as f32 * 24.,
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    fn danger(&self) -> Option<f32> {
+        self.gameplay.danger()
+    }
+
+    fn intensity(&self) -> Option<f32> {
+        self.gameplay.intensity()
+    }
+
+    fn flash(&self) -> Option<f32> {
+        self.gameplay.flash()
+    }
+
+    #[cfg(debug_assertions)]
+    fn assets_reloaded(&mut self, ctx: &mut Context, shared: &Shared) {
+        self.gameplay.assets_reloaded(ctx, shared);
+    }
+}
@@ -0,0 +1,247 @@
+use ggez::{
+    graphics::{self, Color, DrawParam, Quad},
+    mint::Point2,
+    Context, GameResult,
+};
+use rand::Rng;
+use tetris_core::{Action, Drill, DrillOutcome, GameEvent, Setup};
+
+use super::{
+    back_pressed, confirm_pressed, draw_options, gameplay::Gameplay, navigate_options,
+    settings::SettingsScene, tap_option, Scene, Shared, Transition,
+};
+use crate::{audio::Sfx, input::Controls, render::ui_text, storage::DrillExt};
+
+/// Lists the drills that come with the game so that one can be practiced.
+pub struct DrillListScene {
+    drills: Vec<Drill>,
+    selected: usize,
+}
+
+impl DrillListScene {
+    const OPTIONS_ORIGIN: Point2<f32> = Point2 { x: 120., y: 64. };
+
+    pub fn new(ctx: &Context) -> Self {
+        let mut paths: Vec<_> = ctx
+            .fs
+            .read_dir(Drill::DIRECTORY)
+            .map(Iterator::collect)
+            .unwrap_or_default();
+        paths.sort_unstable();
+        Self {
+            drills: paths
+                .iter()
+                .filter_map(|path| Drill::load(ctx, path).ok())
+                .collect(),
+            selected: 0,
+        }
+    }
+
+    fn back_option(&self) -> usize {
+        self.drills.len()
+    }
+}
+
+impl Scene for DrillListScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        let count = self.back_option() + 1;
+        navigate_options(ctx, &shared.gamepad, &mut self.selected, count);
+        if back_pressed(ctx, &shared.gamepad) {
+            return Ok(Transition::Pop);
+        }
+        let tapped = tap_option(
+            &shared.touch,
+            &mut self.selected,
+            count,
+            Self::OPTIONS_ORIGIN,
+        );
+        if !confirm_pressed(ctx, &shared.gamepad) && !tapped {
+            return Ok(Transition::None);
+        }
+        Ok(match self.drills.get(self.selected) {
+            Some(drill) => {
+                Transition::Switch(Box::new(DrillScene::new(ctx, shared, drill.clone())))
+            }
+            None => Transition::Pop,
+        })
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        canvas.draw(
+            &ui_text("DRILLS", 32.),
+            DrawParam::default().dest(Point2 { x: 120., y: 16. }),
+        );
+        let mut options: Vec<_> = self.drills.iter().map(|drill| drill.name.clone()).collect();
+        options.push("BACK".to_owned());
+        draw_options(
+            canvas,
+            shared,
+            &options,
+            self.selected,
+            Self::OPTIONS_ORIGIN,
+        );
+        Ok(())
+    }
+}
+
+/// Attempts at a drill, one after another. Any piece placed outside the shape starts the
+/// next attempt straight away, and every attempt is counted as a success or a failure.
+pub struct DrillScene {
+    drill: Drill,
+    gameplay: Gameplay,
+    pending_presses: u16,
+    successes: u32,
+    failures: u32,
+}
+
+impl DrillScene {
+    /// Pitch of the bump sound played for failed attempts, low enough to tell them apart.
+    const FAILURE_PITCH: f32 = 0.5;
+
+    pub fn new(ctx: &mut Context, shared: &Shared, drill: Drill) -> Self {
+        Self {
+            gameplay: Self::new_attempt(ctx, shared, &drill),
+            drill,
+            pending_presses: 0,
+            successes: 0,
+            failures: 0,
+        }
+    }
+
+    fn new_attempt(ctx: &mut Context, shared: &Shared, drill: &Drill) -> Gameplay {
+        let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        Gameplay::training(ctx, shared, drill.start(seed))
+    }
+
+    /// How the attempt has gone after a step, if it's over.
+    fn judge(&self, events: &[GameEvent]) -> Option<DrillOutcome> {
+        let locked = events
+            .iter()
+            .any(|event| matches!(event, GameEvent::PieceLocked(_)));
+        if locked || self.gameplay.game.is_over() {
+            self.drill.judge(&self.gameplay.game)
+        } else {
+            None
+        }
+    }
+}
+
+impl Scene for DrillScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        let controls = Controls::new(ctx, &shared.settings, &shared.gamepad, &shared.touch);
+        let paused = controls.just_pressed(Action::Pause);
+        let mut input = controls.frame_input();
+        input.just_pressed |= self.pending_presses;
+        let handling = shared.settings.handling;
+
+        self.gameplay
+            .timer
+            .accumulate(ctx.time.delta(), Gameplay::MAX_CATCH_UP);
+        self.pending_presses = input.just_pressed;
+        while self.gameplay.timer.take_step() {
+            let events = self.gameplay.step(ctx, &mut shared.audio, handling, input);
+            input = input.held();
+            self.pending_presses = 0;
+            match self.judge(&events) {
+                Some(DrillOutcome::Success) => self.successes += 1,
+                Some(DrillOutcome::Mistake) => {
+                    self.failures += 1;
+                    shared
+                        .audio
+                        .play_pitched(ctx, Sfx::Bump, Self::FAILURE_PITCH);
+                }
+                None => continue,
+            }
+            self.gameplay = Self::new_attempt(ctx, shared, &self.drill);
+            break;
+        }
+
+        if paused {
+            return Ok(Transition::Push(Box::new(SettingsScene::in_game())));
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        self.gameplay.draw(ctx, canvas, shared)?;
+        let grid = self.gameplay.game.grid();
+        for &pos in self.drill.target() {
+            let pos = Point2 {
+                x: pos.x,
+                y: pos.y + grid.hidden_rows() as i32,
+            };
+            if grid.at(pos.x, pos.y).is_none() {
+                canvas.draw(
+                    &Quad,
+                    DrawParam::default()
+                        .dest_rect(self.gameplay.cell_rect(pos))
+                        .color(Color::new(1., 1., 1., 0.15)),
+                );
+            }
+        }
+
+        let queue: Vec<_> = self
+            .gameplay
+            .game
+            .queue()
+            .map(|piece| format!("{:?}", piece))
+            .collect();
+        let attempts = self.successes + self.failures;
+        let mut lines = vec![
+            self.drill.name.clone(),
+            format!("SUCCESS {}", self.successes),
+            format!("FAIL    {}", self.failures),
+        ];
+        if let Some(rate) = (self.successes * 100).checked_div(attempts) {
+            lines.push(format!("RATE    {}%", rate));
+        }
+        if !queue.is_empty() {
+            lines.push(format!("NEXT {}", queue.join(" ")));
+        }
+        for (i, line) in lines.into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(line, 12.),
+                DrawParam::default().dest(Point2 {
+                    x: 296.,
+                    y: 196. + i as f32 * 14.,
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    fn debug_lines(&self) -> Vec<String> {
+        self.gameplay.debug_lines()
+    }
+
+    fn danger(&self) -> Option<f32> {
+        self.gameplay.danger()
+    }
+
+    fn intensity(&self) -> Option<f32> {
+        self.gameplay.intensity()
+    }
+
+    fn flash(&self) -> Option<f32> {
+        self.gameplay.flash()
+    }
+
+    fn setup(&self) -> Option<Setup> {
+        self.gameplay.setup()
+    }
+
+    #[cfg(debug_assertions)]
+    fn assets_reloaded(&mut self, ctx: &mut Context, shared: &Shared) {
+        self.gameplay.assets_reloaded(ctx, shared);
+    }
+}
@@ -0,0 +1,322 @@
+use ggez::{
+    event::Button,
+    graphics::{self, Color, DrawParam, Quad, Rect},
+    input::keyboard::KeyMods,
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use rand::Rng;
+use tetris_core::{Block, Grid, PieceKind, Setup};
+
+use super::{
+    back_pressed, gameplay::Gameplay, menu_pressed, shortcut_pressed, Scene, Shared, Transition,
+};
+use crate::{clipboard, mini_board::MiniBoard, render::ui_text};
+
+/// Paints a board block by block and picks the pieces that come onto it, to play from there
+/// and practice a particular situation.
+pub struct EditorScene {
+    board: Grid,
+    /// Starting with the falling piece, after which random pieces come.
+    queue: Vec<PieceKind>,
+    /// Whether the queue is dealt over and over instead of random pieces coming after it.
+    repeat: bool,
+    cursor: Point2<i32>,
+    /// Index into `BRUSHES`.
+    brush: usize,
+    /// Whether the mouse or finger held down is erasing rather than painting, decided by
+    /// the cell it was first held on.
+    erasing: Option<bool>,
+    board_view: MiniBoard,
+    brush_view: MiniBoard,
+}
+
+impl EditorScene {
+    /// Blocks that can be painted: one of each tetromino, then garbage.
+    const BRUSHES: [Option<PieceKind>; 8] = [
+        Some(PieceKind::I),
+        Some(PieceKind::J),
+        Some(PieceKind::L),
+        Some(PieceKind::O),
+        Some(PieceKind::S),
+        Some(PieceKind::T),
+        Some(PieceKind::Z),
+        None,
+    ];
+    const BOARD_AREA: Rect = Rect::new(16., 16., 200., 268.);
+    const PANEL_X: f32 = 232.;
+    /// Longest queue that can be picked.
+    const QUEUE_LENGTH: usize = 14;
+
+    pub fn new(ctx: &Context, shared: &Shared) -> Self {
+        let size = shared.settings.board_size;
+        Self {
+            board: Grid::new(size.width, size.height),
+            queue: vec![],
+            repeat: false,
+            cursor: Point2 {
+                x: size.width as i32 / 2,
+                y: size.height as i32 - 1,
+            },
+            brush: 0,
+            erasing: None,
+            board_view: MiniBoard::new(ctx, &shared.assets.blocks),
+            brush_view: MiniBoard::new(ctx, &shared.assets.blocks),
+        }
+    }
+
+    fn current_setup(&self) -> Setup {
+        Setup {
+            board: self.board.clone(),
+            queue: self.queue.clone(),
+            repeat: self.repeat && !self.queue.is_empty(),
+        }
+    }
+
+    fn brush_block(&self) -> Block {
+        Self::BRUSHES[self.brush].map_or(Block::GARBAGE, Block::of)
+    }
+
+    /// Pixels each cell takes, as big as the board fits.
+    fn cell_size(&self) -> f32 {
+        (Self::BOARD_AREA.w / self.board.width() as f32)
+            .min(Self::BOARD_AREA.h / self.board.height() as f32)
+            .floor()
+    }
+
+    fn board_rect(&self) -> Rect {
+        let cell = self.cell_size();
+        let w = cell * self.board.width() as f32;
+        let h = cell * self.board.height() as f32;
+        let area = Self::BOARD_AREA;
+        Rect::new(
+            (area.x + (area.w - w) / 2.).round(),
+            (area.y + (area.h - h) / 2.).round(),
+            w,
+            h,
+        )
+    }
+
+    /// The cell under a point on the screen, if there's one.
+    fn cell_at(&self, point: Point2<f32>) -> Option<Point2<i32>> {
+        let rect = self.board_rect();
+        let cell = self.cell_size();
+        // Points on the far edges of the board are in its rect but past its last cells.
+        rect.contains(point)
+            .then(|| Point2 {
+                x: ((point.x - rect.x) / cell) as i32,
+                y: ((point.y - rect.y) / cell) as i32,
+            })
+            .filter(|pos| self.board.contains_pos(pos.x, pos.y))
+    }
+
+    fn paint(&mut self, cell: Point2<i32>, erase: bool) {
+        let block = (!erase).then(|| self.brush_block());
+        let _ = self.board.try_set(cell.x, cell.y, block);
+    }
+
+    /// Paints or erases the cells dragged over, depending on whether the first one was
+    /// filled.
+    fn update_mouse(&mut self, shared: &Shared) {
+        let Some(point) = shared.touch.held() else {
+            self.erasing = None;
+            return;
+        };
+        let Some(cell) = self.cell_at(point) else {
+            return;
+        };
+        self.cursor = cell;
+        let erasing = *self
+            .erasing
+            .get_or_insert_with(|| self.board.at(cell.x, cell.y).is_some());
+        self.paint(cell, erasing);
+    }
+
+    fn update_cursor(&mut self, ctx: &Context, shared: &Shared) {
+        let gamepad = &shared.gamepad;
+        let moves = [
+            (VirtualKeyCode::Left, Button::DPadLeft, -1, 0),
+            (VirtualKeyCode::Right, Button::DPadRight, 1, 0),
+            (VirtualKeyCode::Up, Button::DPadUp, 0, -1),
+            (VirtualKeyCode::Down, Button::DPadDown, 0, 1),
+        ];
+        for (key, button, dx, dy) in moves {
+            if menu_pressed(ctx, gamepad, key, button) {
+                self.cursor.x = (self.cursor.x + dx).clamp(0, self.board.width() as i32 - 1);
+                self.cursor.y = (self.cursor.y + dy).clamp(0, self.board.height() as i32 - 1);
+            }
+        }
+        if menu_pressed(ctx, gamepad, VirtualKeyCode::Space, Button::South) {
+            self.paint(self.cursor, false);
+        }
+        if menu_pressed(ctx, gamepad, VirtualKeyCode::Delete, Button::West) {
+            self.paint(self.cursor, true);
+        }
+        if menu_pressed(ctx, gamepad, VirtualKeyCode::Tab, Button::RightTrigger) {
+            self.brush = (self.brush + 1) % Self::BRUSHES.len();
+        }
+    }
+
+    /// Typing a piece's letter adds it to the queue, backspace takes the last one out and R
+    /// makes it repeat or not.
+    fn update_queue(&mut self, ctx: &Context, shared: &Shared) {
+        if ctx.keyboard.is_mod_active(KeyMods::CTRL) || ctx.keyboard.is_mod_active(KeyMods::LOGO) {
+            return;
+        }
+        for kind in shared
+            .typed
+            .chars()
+            .filter_map(|c| PieceKind::from_letter(c.to_ascii_uppercase()))
+        {
+            if self.queue.len() < Self::QUEUE_LENGTH {
+                self.queue.push(kind);
+            }
+        }
+        if ctx.keyboard.is_key_just_pressed(VirtualKeyCode::Back) {
+            self.queue.pop();
+        }
+        if ctx.keyboard.is_key_just_pressed(VirtualKeyCode::R) {
+            self.repeat = !self.repeat;
+        }
+    }
+
+    /// Edits the setup on the clipboard instead, written as `Setup::to_text` writes it.
+    fn paste(&mut self, shared: &mut Shared) {
+        let setup = clipboard::paste()
+            .map_err(|e| e.to_string())
+            .and_then(|text| Setup::parse(&text).map_err(|e| e.to_string()));
+        match setup {
+            Ok(setup) => {
+                self.board = setup.board;
+                self.queue = setup.queue;
+                self.queue.truncate(Self::QUEUE_LENGTH);
+                self.repeat = setup.repeat;
+                self.cursor.x = self.cursor.x.min(self.board.width() as i32 - 1);
+                self.cursor.y = self.cursor.y.min(self.board.height() as i32 - 1);
+            }
+            Err(e) => shared
+                .toasts
+                .push("COULDN'T PASTE A SETUP", e.to_uppercase()),
+        }
+    }
+}
+
+impl Scene for EditorScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        if back_pressed(ctx, &shared.gamepad) {
+            return Ok(Transition::Pop);
+        }
+        if menu_pressed(ctx, &shared.gamepad, VirtualKeyCode::Return, Button::Start) {
+            let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+            let game = self.current_setup().game(seed);
+            return Ok(Transition::Push(Box::new(Gameplay::training(
+                ctx, shared, game,
+            ))));
+        }
+        if shortcut_pressed(ctx, VirtualKeyCode::V) {
+            self.paste(shared);
+        }
+        self.update_mouse(shared);
+        self.update_cursor(ctx, shared);
+        self.update_queue(ctx, shared);
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        let rect = self.board_rect();
+        let cell = self.cell_size();
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest_rect(rect)
+                .color(Color::new(0., 0., 0., 0.7)),
+        );
+        self.board_view.draw_cells(
+            canvas,
+            &shared.assets.quad_mesh,
+            &self.board,
+            rect.point(),
+            cell,
+            Color::WHITE,
+        );
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest_rect(Rect::new(
+                    rect.x + self.cursor.x as f32 * cell,
+                    rect.y + self.cursor.y as f32 * cell,
+                    cell,
+                    cell,
+                ))
+                .color(Color::new(1., 1., 1., 0.4)),
+        );
+
+        canvas.draw(
+            &ui_text("EDITOR", 32.),
+            DrawParam::default().dest(Point2 {
+                x: Self::PANEL_X,
+                y: 16.,
+            }),
+        );
+        canvas.draw(
+            &ui_text("BRUSH", 16.),
+            DrawParam::default().dest(Point2 {
+                x: Self::PANEL_X,
+                y: 60.,
+            }),
+        );
+        let mut brush = Grid::new(1, 1);
+        brush.set(0, 0, Some(self.brush_block()));
+        self.brush_view.draw_cells(
+            canvas,
+            &shared.assets.quad_mesh,
+            &brush,
+            Point2 {
+                x: Self::PANEL_X + 64.,
+                y: 60.,
+            },
+            16.,
+            Color::WHITE,
+        );
+        let queue: String = self.queue.iter().filter_map(|kind| kind.letter()).collect();
+        let repeat = if self.repeat { " (REPEATS)" } else { "" };
+        canvas.draw(
+            &ui_text(format!("QUEUE {}{}", queue, repeat), 12.),
+            DrawParam::default().dest(Point2 {
+                x: Self::PANEL_X,
+                y: 84.,
+            }),
+        );
+        let hints = [
+            "ARROWS: MOVE",
+            "SPACE: PAINT",
+            "DELETE: ERASE",
+            "TAB: CHANGE BRUSH",
+            "IJLOSTZ: ADD TO QUEUE",
+            "BACKSPACE: REMOVE",
+            "R: REPEAT QUEUE",
+            "ENTER: PLAY",
+            "ESC: BACK",
+        ];
+        for (i, hint) in hints.into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(hint, 12.),
+                DrawParam::default().dest(Point2 {
+                    x: Self::PANEL_X,
+                    y: 120. + i as f32 * 16.,
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    fn setup(&self) -> Option<Setup> {
+        Some(self.current_setup())
+    }
+}
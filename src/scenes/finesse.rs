@@ -0,0 +1,153 @@
+use ggez::{
+    graphics::{self, DrawParam},
+    mint::Point2,
+    Context, GameResult,
+};
+use rand::Rng;
+use tetris_core::{Action, Game, GameEvent, GameMode, Setup};
+
+use super::{gameplay::Gameplay, settings::SettingsScene, Scene, Shared, Transition};
+use crate::{audio::Sfx, input::Controls, render::ui_text};
+
+/// Endless play that points out every piece placed with more presses than it needed, and
+/// takes it back to be placed again if `Settings::finesse_redo` is on. Games that top out
+/// start over, keeping the count.
+pub struct FinesseScene {
+    gameplay: Gameplay,
+    pending_presses: u16,
+    /// The game as it was when the falling piece spawned, which it's taken back to.
+    piece_start: Game,
+    placed: u32,
+    faults: u32,
+    /// Presses the last fault took, and how many it needed.
+    last_fault: Option<(u32, u32)>,
+}
+
+impl FinesseScene {
+    /// Pitch of the bump sound played for faults, low enough to tell them apart.
+    const FAULT_PITCH: f32 = 0.5;
+
+    pub fn new(ctx: &mut Context, shared: &Shared) -> Self {
+        let gameplay = Self::new_game(ctx, shared);
+        Self {
+            piece_start: gameplay.game.clone(),
+            gameplay,
+            pending_presses: 0,
+            placed: 0,
+            faults: 0,
+            last_fault: None,
+        }
+    }
+
+    fn new_game(ctx: &mut Context, shared: &Shared) -> Gameplay {
+        let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let game = Game::new(GameMode::Endless, seed, shared.settings.board_size);
+        Gameplay::training(ctx, shared, game)
+    }
+
+    fn step(&mut self, ctx: &Context, shared: &mut Shared, events: &[GameEvent]) {
+        let fault = events.iter().find_map(|event| match *event {
+            GameEvent::FinesseFault { presses, needed } => Some((presses, needed)),
+            _ => None,
+        });
+        if let Some(fault) = fault {
+            self.faults += 1;
+            self.last_fault = Some(fault);
+            shared.audio.play_pitched(ctx, Sfx::Bump, Self::FAULT_PITCH);
+            if shared.settings.finesse_redo {
+                self.gameplay.replace_game(self.piece_start.clone());
+                return;
+            }
+        }
+        if events
+            .iter()
+            .any(|event| matches!(event, GameEvent::PieceLocked(_)))
+        {
+            self.placed += 1;
+            self.piece_start = self.gameplay.game.clone();
+        }
+    }
+}
+
+impl Scene for FinesseScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        if self.gameplay.game.is_over() {
+            self.gameplay = Self::new_game(ctx, shared);
+            self.piece_start = self.gameplay.game.clone();
+        }
+
+        let controls = Controls::new(ctx, &shared.settings, &shared.gamepad, &shared.touch);
+        let paused = controls.just_pressed(Action::Pause);
+        let mut input = controls.frame_input();
+        input.just_pressed |= self.pending_presses;
+        let handling = shared.settings.handling;
+
+        self.gameplay
+            .timer
+            .accumulate(ctx.time.delta(), Gameplay::MAX_CATCH_UP);
+        self.pending_presses = input.just_pressed;
+        while !self.gameplay.game.is_over() && self.gameplay.timer.take_step() {
+            let events = self.gameplay.step(ctx, &mut shared.audio, handling, input);
+            self.step(ctx, shared, &events);
+            input = input.held();
+            self.pending_presses = 0;
+        }
+
+        if paused {
+            return Ok(Transition::Push(Box::new(SettingsScene::in_game())));
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        self.gameplay.draw(ctx, canvas, shared)?;
+        let mut lines = vec![
+            "FINESSE".to_owned(),
+            format!("PIECES {}", self.placed),
+            format!("FAULTS {}", self.faults),
+        ];
+        if let Some((presses, needed)) = self.last_fault {
+            lines.push(format!("KEYS {}/{}", presses, needed));
+        }
+        for (i, line) in lines.into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(line, 12.),
+                DrawParam::default().dest(Point2 {
+                    x: 296.,
+                    y: 196. + i as f32 * 14.,
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    fn debug_lines(&self) -> Vec<String> {
+        self.gameplay.debug_lines()
+    }
+
+    fn danger(&self) -> Option<f32> {
+        self.gameplay.danger()
+    }
+
+    fn intensity(&self) -> Option<f32> {
+        self.gameplay.intensity()
+    }
+
+    fn flash(&self) -> Option<f32> {
+        self.gameplay.flash()
+    }
+
+    fn setup(&self) -> Option<Setup> {
+        self.gameplay.setup()
+    }
+
+    #[cfg(debug_assertions)]
+    fn assets_reloaded(&mut self, ctx: &mut Context, shared: &Shared) {
+        self.gameplay.assets_reloaded(ctx, shared);
+    }
+}
@@ -0,0 +1,1591 @@
+use std::{
+    collections::VecDeque,
+    f32::consts::{FRAC_PI_2, TAU},
+    time::Duration,
+};
+
+use enum_map::{enum_map, EnumMap};
+use ggez::{
+    event::Button,
+    glam::*,
+    graphics::{
+        self, Color, DrawMode, DrawParam, InstanceArray, Mesh, Quad, Rect, TextAlign, TextLayout,
+    },
+    input::keyboard::KeyMods,
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use rand::Rng;
+use tetris_core::{
+    Achievements, Action, Block, BoardSize, Bot, ClearKind, DailyChallenge, DailyResults,
+    FrameInput, Game, GameEvent, GameMode, GameStats, GameSummary, Grid, Handling, Heatmap,
+    InvalidSave, Leaderboard, Piece, PieceKind, PieceRotation, Replay, Rules, RulesHash, SavedGame,
+    Setup,
+};
+
+use super::{
+    back_pressed, confirm_pressed, menu::MenuScene, menu_pressed, results::ResultsScene,
+    settings::SettingsScene, Scene, Shared, Transition,
+};
+use crate::{
+    audio::{Audio, Sfx},
+    countdown::Countdown,
+    hud::Hud,
+    input::{Controls, TouchState},
+    line_clear::ClearAnimation,
+    locale::Locale,
+    lock_flash::LockFlash,
+    mini_board::MiniBoard,
+    net::{Score, ScoreSubmission},
+    particles::Particles,
+    render::{
+        draw_button, draw_leaderboard, rect_mesh, ui_text, Assets, BlockSprites, GARBAGE_COLOR,
+    },
+    settings::{Config, Settings},
+    shake::ScreenShake,
+    slide::PieceSlide,
+    stack_fade::StackFade,
+    storage::{self, AchievementsExt, DailyResultsExt, GameSummaryExt, LeaderboardExt, ReplayExt},
+    toast::Toasts,
+    SCREEN,
+};
+
+/// Turns real time into fixed simulation steps, keeping what is left over for later frames.
+#[derive(Default)]
+pub struct StepTimer {
+    unsimulated_time: Duration,
+}
+
+impl StepTimer {
+    /// Adds real time to simulate, keeping at most `max_steps` worth so that a long stall
+    /// doesn't fast forward the game.
+    pub fn accumulate(&mut self, delta: Duration, max_steps: u32) {
+        self.unsimulated_time = (self.unsimulated_time + delta).min(Game::FRAME_TIME * max_steps);
+    }
+
+    /// Takes the time of one step, returning whether there was enough of it.
+    pub fn take_step(&mut self) -> bool {
+        if self.unsimulated_time >= Game::FRAME_TIME {
+            self.unsimulated_time -= Game::FRAME_TIME;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The fraction of a step that real time is past the last simulated one, to draw
+    /// animations in between steps.
+    pub fn interpolation(&self) -> f32 {
+        self.unsimulated_time.as_secs_f32() / Game::FRAME_TIME.as_secs_f32()
+    }
+}
+
+/// What games played for practice keep to go back to.
+struct Practice {
+    /// The game as it was when it started, or was last loaded, and after each of the last
+    /// pieces locked, newest last, for undo to go back to.
+    rewind: VecDeque<Game>,
+    /// Games saved with Shift and one of `SLOT_KEYS`, to load again with the key alone.
+    slots: [Option<Game>; Practice::SLOT_KEYS.len()],
+}
+
+impl Practice {
+    const SLOT_KEYS: [VirtualKeyCode; 4] = [
+        VirtualKeyCode::F1,
+        VirtualKeyCode::F2,
+        VirtualKeyCode::F3,
+        VirtualKeyCode::F4,
+    ];
+    /// Most pieces that undo can take back in a row.
+    const REWIND_DEPTH: usize = 100;
+
+    fn new(game: &Game) -> Self {
+        Self {
+            rewind: VecDeque::from([game.clone()]),
+            slots: Default::default(),
+        }
+    }
+
+    /// Keeps the game as it is after a piece locked, for undo to go back to.
+    fn remember_lock(&mut self, game: &Game) {
+        if self.rewind.len() > Self::REWIND_DEPTH {
+            self.rewind.pop_front();
+        }
+        self.rewind.push_back(game.clone());
+    }
+
+    /// The game as it was before the last piece locked, if there's a piece to take back.
+    fn undo(&mut self) -> Option<Game> {
+        if self.rewind.len() < 2 {
+            return None;
+        }
+        self.rewind.pop_back();
+        self.rewind.back().cloned()
+    }
+
+    /// The game in a slot to play on from, which undo can't go back past.
+    fn load(&mut self, slot: usize) -> Option<Game> {
+        let game = self.slots[slot].clone()?;
+        self.rewind = VecDeque::from([game.clone()]);
+        Some(game)
+    }
+}
+
+pub struct Gameplay {
+    pub game: Game,
+    grid_batch: InstanceArray,
+    /// How each piece's blocks are drawn, kept from the assets so that batches can be
+    /// updated without them.
+    block_sprites: EnumMap<PieceKind, DrawParam>,
+
+    pub timer: StepTimer,
+    /// Presses from frames too short to simulate a step in, kept for the next step.
+    pending_presses: u16,
+    leaderboard: Leaderboard,
+    hud: Hud,
+    particles: Particles,
+    shake: ScreenShake,
+    /// Space the board is fit in, which is `BOARD_AREA` unless the game shares the screen.
+    board_area: Rect,
+    /// How far the board is moved by `shake` this frame, if shaking is enabled.
+    board_offset: Vec2,
+    /// The frame around the board and the lines between its cells, drawn from its corner and
+    /// built for its size.
+    board_frame: Mesh,
+    grid_lines: Mesh,
+    slide: PieceSlide,
+    lock_flash: LockFlash,
+    /// How much of the stack shows, in the invisible mode.
+    stack_fade: Option<StackFade>,
+    /// Whether the stack is within `DANGER_ROWS` of the top.
+    in_danger: bool,
+    /// How strongly the danger warning shows, fading in and out, from 0.0 to 1.0.
+    danger: f32,
+    /// How intense the game is, from 0.0 to 1.0, going by the level and the height of the
+    /// stack.
+    intensity: f32,
+    /// How bright the background flashes after a tetris, fading out from 1.0.
+    flash: f32,
+    /// Blocks of the rows that are flashing, which burst into particles once they're removed.
+    clearing_blocks: Vec<(Point2<i32>, Block)>,
+    /// How the rows that are flashing are animated, going by the kind of clear.
+    clear_animation: ClearAnimation,
+
+    piece_meshes: EnumMap<PieceKind, EnumMap<PieceRotation, InstanceArray>>,
+    /// One for each piece coming next that can be shown, and one for the held piece.
+    next_boards: Vec<MiniBoard>,
+    hold_board: MiniBoard,
+    /// One for each row of the piece statistics.
+    stat_boards: Vec<MiniBoard>,
+
+    /// Counts down before the game starts or resumes, holding it until GO.
+    countdown: Option<Countdown>,
+    /// How long restart has been held for, which has to reach `RESTART_HOLD`.
+    restart_hold: Duration,
+
+    /// The day's challenge this game is, whose result is kept if it's the first try.
+    daily: Option<DailyChallenge>,
+    /// Recording of this game, if it was played from the start.
+    replay: Option<Replay>,
+    /// Undo history and savestates, for games played for practice.
+    practice: Option<Practice>,
+    /// Where the bot would drop the falling piece, worked out once for each piece and hold,
+    /// which are what it's kept for.
+    hint: Option<((u32, bool), Option<Piece>)>,
+    /// Whether sounds are skipped, for when a replay is fast forwarded.
+    pub muted: bool,
+}
+
+impl Gameplay {
+    /// Space between the HUD and the side panel that the board has to fit in.
+    const BOARD_AREA: Rect = Rect::new(120., 16., 160., 268.);
+    /// One empty cell of the board baked into the background.
+    const BG_TILE: Rect = Rect::new(136., 32., 16., 16.);
+    /// Where the side panel of the background starts, right of its baked board.
+    const BG_PANEL_X: f32 = 282.;
+    /// How close to the top the stack has to get to warn about it.
+    const DANGER_ROWS: usize = 4;
+    /// How long the danger warning takes to fade in or out.
+    const DANGER_FADE: Duration = Duration::from_millis(500);
+    /// Level at which the game is as intense as it gets, no matter the stack.
+    const MAX_INTENSITY_LEVEL: u32 = 15;
+    const FRAME_COLOR: Color = Color::new(0.53, 0.21, 0.13, 1.);
+    const GRID_LINE_COLOR: Color = Color::new(0., 0., 0., 0.3);
+    const HINT_COLOR: Color = Color::new(1., 0.9, 0.2, 0.9);
+    /// Space in the side panel, above the held piece, that the pieces coming next share.
+    const NEXT_AREA: Rect = Rect::new(304., 40., 88., 80.);
+    /// Space in the side panel, under its label, that the held piece is centered in.
+    const HOLD_AREA: Rect = Rect::new(304., 148., 64., 48.);
+    /// Space left of the board, under the HUD, that the piece statistics are listed in.
+    const STATS_AREA: Rect = Rect::new(8., 186., 104., 98.);
+    /// Most kinds of pieces the statistics list, which modes with more only list the most
+    /// dealt of.
+    const STATS_ROWS: usize = 7;
+    /// How long restart has to be held for, so that games aren't thrown away by accident.
+    const RESTART_HOLD: Duration = Duration::from_millis(500);
+    /// How long the background takes to fade back after flashing for a tetris.
+    const FLASH_FADE: Duration = Duration::from_millis(600);
+    const FIREWORKS_PER_TETRIS: usize = 4;
+    /// Most steps simulated in one frame, so that a long stall doesn't fast forward the game.
+    pub const MAX_CATCH_UP: u32 = 5;
+
+    pub fn new(ctx: &mut Context, shared: &Shared, mode: GameMode) -> Self {
+        let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        Self::with_seed(ctx, shared, mode, seed, shared.settings.board_size)
+    }
+
+    pub fn with_seed(
+        ctx: &mut Context,
+        shared: &Shared,
+        mode: GameMode,
+        seed: u32,
+        board_size: BoardSize,
+    ) -> Self {
+        let replay = Replay::new(mode, seed, shared.settings.handling, board_size);
+        let game = Game::new(mode, seed, board_size);
+        let mut gameplay = Self::from_game(ctx, shared, game, Some(replay));
+        gameplay.countdown = Some(Countdown::new());
+        gameplay
+    }
+
+    /// Plays a day's challenge, on the default board so that it's the same for everyone.
+    pub fn daily(ctx: &mut Context, shared: &Shared, challenge: DailyChallenge) -> Self {
+        let mode = challenge.mode();
+        let mut gameplay =
+            Self::with_seed(ctx, shared, mode, challenge.seed(), BoardSize::default());
+        gameplay.daily = Some(challenge);
+        gameplay
+    }
+
+    /// Starts the game a replay was recorded from, without recording it again.
+    pub fn from_replay(ctx: &mut Context, shared: &Shared, replay: &Replay) -> Self {
+        let game = Game::new(replay.mode, replay.seed, replay.board_size);
+        Self::from_game(ctx, shared, game, None)
+    }
+
+    pub fn from_saved(
+        ctx: &mut Context,
+        shared: &Shared,
+        saved: SavedGame,
+    ) -> Result<Self, InvalidSave> {
+        // The inputs before the save weren't kept, so the rest of the game can't be replayed.
+        let mut gameplay = Self::from_game(ctx, shared, Game::from_saved(saved)?, None);
+        gameplay.countdown = Some(Countdown::new());
+        Ok(gameplay)
+    }
+
+    /// A game played by the computer to show off on the title screen, which isn't recorded
+    /// and makes no sound.
+    pub fn demo(ctx: &mut Context, shared: &Shared, mode: GameMode) -> Self {
+        let seed = rand::thread_rng().gen();
+        let game = Game::new(mode, seed, shared.settings.board_size);
+        let mut gameplay = Self::from_game(ctx, shared, game, None);
+        gameplay.muted = true;
+        gameplay
+    }
+
+    /// A game played to practice something, which isn't recorded and whose pieces can be
+    /// taken back, or saved and loaded in slots to try again.
+    pub fn training(ctx: &mut Context, shared: &Shared, game: Game) -> Self {
+        let practice = Practice::new(&game);
+        let mut gameplay = Self::from_game(ctx, shared, game, None);
+        gameplay.practice = Some(practice);
+        gameplay
+    }
+
+    /// A game played in `board_area` to try something out, which isn't recorded.
+    pub fn preview(ctx: &mut Context, shared: &Shared, game: Game, board_area: Rect) -> Self {
+        let mut gameplay = Self::from_game(ctx, shared, game, None);
+        gameplay.set_board_area(ctx, board_area);
+        gameplay
+    }
+
+    /// One of the games of a versus match, with its board fit in `board_area`. It isn't
+    /// recorded, since its replay couldn't reproduce the garbage sent to it.
+    pub fn versus(
+        ctx: &mut Context,
+        shared: &Shared,
+        seed: u32,
+        board_size: BoardSize,
+        board_area: Rect,
+    ) -> Self {
+        let game = Game::new(GameMode::Endless, seed, board_size);
+        let mut gameplay = Self::from_game(ctx, shared, game, None);
+        gameplay.set_board_area(ctx, board_area);
+        gameplay
+    }
+
+    fn from_game(ctx: &mut Context, shared: &Shared, game: Game, replay: Option<Replay>) -> Self {
+        let blocks = &shared.assets.blocks;
+        let grid_batch = InstanceArray::new(ctx, blocks.texture.clone());
+        let (board_frame, grid_lines) = Self::board_meshes(ctx, Self::BOARD_AREA, game.grid());
+
+        let mut state = Gameplay {
+            timer: StepTimer::default(),
+            pending_presses: 0,
+            leaderboard: Leaderboard::load(ctx, game.mode()),
+            hud: Hud::default(),
+            particles: Particles::new(ctx, blocks.texture.clone()),
+            clearing_blocks: vec![],
+            clear_animation: ClearAnimation::default(),
+            shake: ScreenShake::default(),
+            slide: PieceSlide::default(),
+            lock_flash: LockFlash::new(game.grid().width(), game.grid().height()),
+            stack_fade: (game.mode() == GameMode::Invisible)
+                .then(|| StackFade::new(game.grid().width(), game.grid().height())),
+            in_danger: false,
+            danger: 0.,
+            intensity: 0.,
+            flash: 0.,
+            board_area: Self::BOARD_AREA,
+            board_offset: Vec2::ZERO,
+            board_frame,
+            grid_lines,
+
+            game,
+            grid_batch,
+            block_sprites: enum_map! { kind => blocks.param(kind) },
+            piece_meshes: Self::piece_meshes(ctx, blocks),
+            next_boards: Self::next_boards(ctx, blocks),
+            hold_board: MiniBoard::new(ctx, blocks),
+            stat_boards: Self::stat_boards(ctx, blocks),
+            countdown: None,
+            restart_hold: Duration::ZERO,
+            daily: None,
+            replay,
+            practice: None,
+            hint: None,
+            muted: false,
+        };
+
+        state.update_grid_batch();
+
+        state
+    }
+
+    /// Fits the board in `board_area` instead, as when the game shares the screen.
+    fn set_board_area(&mut self, ctx: &Context, board_area: Rect) {
+        self.board_area = board_area;
+        (self.board_frame, self.grid_lines) = Self::board_meshes(ctx, board_area, self.game.grid());
+    }
+
+    /// The frame around a board for `grid` fit in `board_area`, and the lines between its
+    /// cells, from the board's corner.
+    fn board_meshes(ctx: &Context, board_area: Rect, grid: &Grid) -> (Mesh, Mesh) {
+        let board = Self::fit_board(board_area, grid);
+        let (width, height) = (board.w, board.h);
+        let frame = [
+            Rect::new(-2., -2., width + 4., 2.),
+            Rect::new(-2., height, width + 4., 2.),
+            Rect::new(-2., 0., 2., height),
+            Rect::new(width, 0., 2., height),
+        ];
+        let cell = width / grid.width() as f32;
+        let columns = (1..grid.width()).map(|x| Rect::new(x as f32 * cell, 0., 1., height));
+        let rows = (1..grid.visible_height()).map(|y| Rect::new(0., y as f32 * cell, width, 1.));
+        let lines: Vec<_> = columns.chain(rows).collect();
+        (
+            rect_mesh(ctx, &frame, Self::FRAME_COLOR),
+            rect_mesh(ctx, &lines, Self::GRID_LINE_COLOR),
+        )
+    }
+
+    /// A batch for every rotation of every piece, with its blocks one unit apart.
+    fn piece_meshes(
+        ctx: &Context,
+        blocks: &BlockSprites,
+    ) -> EnumMap<PieceKind, EnumMap<PieceRotation, InstanceArray>> {
+        let generate_piece_mesh = |piece: PieceKind, rotation: PieceRotation| -> InstanceArray {
+            let grid = piece.get_grid(rotation);
+            let mut batch = InstanceArray::new(ctx, blocks.texture.clone());
+            for x in 0..grid.width() {
+                for y in 0..grid.height() {
+                    if let Some(block) = grid.at(x as i32, y as i32) {
+                        batch.push(blocks.param(block.kind).dest(Point2 {
+                            x: x as f32,
+                            y: y as f32,
+                        }));
+                    }
+                }
+            }
+            batch
+        };
+        enum_map! {
+            piece => enum_map! { rotation => generate_piece_mesh(piece, rotation) },
+        }
+    }
+
+    fn next_boards(ctx: &Context, blocks: &BlockSprites) -> Vec<MiniBoard> {
+        (0..Rules::MODERN.preview)
+            .map(|_| MiniBoard::new(ctx, blocks))
+            .collect()
+    }
+
+    fn stat_boards(ctx: &Context, blocks: &BlockSprites) -> Vec<MiniBoard> {
+        (0..Self::STATS_ROWS)
+            .map(|_| MiniBoard::new(ctx, blocks))
+            .collect()
+    }
+
+    /// Builds every batch again from the block texture, for when it was reloaded. Particles
+    /// in flight are dropped.
+    #[cfg(debug_assertions)]
+    fn reload_blocks(&mut self, ctx: &Context, blocks: &BlockSprites) {
+        self.grid_batch = InstanceArray::new(ctx, blocks.texture.clone());
+        self.block_sprites = enum_map! { kind => blocks.param(kind) };
+        self.piece_meshes = Self::piece_meshes(ctx, blocks);
+        self.next_boards = Self::next_boards(ctx, blocks);
+        self.hold_board = MiniBoard::new(ctx, blocks);
+        self.stat_boards = Self::stat_boards(ctx, blocks);
+        self.particles = Particles::new(ctx, blocks.texture.clone());
+        self.update_grid_batch();
+    }
+
+    /// Rebuilds the stack in sight, for when rows have moved or blocks have faded.
+    fn update_grid_batch(&mut self) {
+        self.grid_batch.clear();
+        let grid = self.game.grid();
+        // Rows turn gray from the bottom up once the game tops out.
+        let collapsed_rows = self.game.collapse_progress(0.).map_or(0, |progress| {
+            (progress * grid.visible_height() as f32).ceil() as usize
+        });
+        for x in 0..grid.width() {
+            for y in grid.hidden_rows()..grid.height() {
+                if let Some(block) = grid.at(x as i32, y as i32) {
+                    let visibility = self
+                        .stack_fade
+                        .as_ref()
+                        .map_or(1., |fade| fade.visibility(x, y));
+                    if visibility <= 0. {
+                        continue;
+                    }
+                    let mut param = if grid.height() - y <= collapsed_rows {
+                        self.block_sprites[block.kind].color(GARBAGE_COLOR)
+                    } else {
+                        self.block_param(block)
+                    };
+                    param.color.a *= visibility;
+                    self.grid_batch.push(param.dest(Point2 {
+                        x: x as f32,
+                        y: (y - grid.hidden_rows()) as f32,
+                    }));
+                }
+            }
+        }
+    }
+
+    /// How a block of the stack is drawn, greyed out if it's garbage.
+    fn block_param(&self, block: &Block) -> DrawParam {
+        let param = self.block_sprites[block.kind];
+        if block.garbage {
+            param.color(GARBAGE_COLOR)
+        } else {
+            param
+        }
+    }
+
+    /// Adds the blocks of a piece that just locked to the stack, leaving the rest of it as is.
+    fn add_to_grid_batch(&mut self, piece: Piece) {
+        let grid = self.game.grid();
+        let hidden = grid.hidden_rows() as i32;
+        for cell in piece.cells() {
+            if grid.contains_pos(cell.x, cell.y) && cell.y >= hidden {
+                self.grid_batch
+                    .push(self.block_sprites[piece.kind].dest(Point2 {
+                        x: cell.x as f32,
+                        y: (cell.y - hidden) as f32,
+                    }));
+            }
+        }
+    }
+
+    /// Takes back the last piece, saves the game in a slot or loads one, as the player asks
+    /// in a game played for practice.
+    fn update_practice(
+        &mut self,
+        ctx: &Context,
+        controls: &Controls,
+        locale: &Locale,
+        toasts: &mut Toasts,
+    ) {
+        let Some(practice) = &mut self.practice else {
+            return;
+        };
+        let mut game = None;
+        if controls.just_pressed(Action::Undo) {
+            game = practice.undo();
+        }
+        let keyboard = &ctx.keyboard;
+        for (slot, &key) in Practice::SLOT_KEYS.iter().enumerate() {
+            if !keyboard.is_key_just_pressed(key) {
+                continue;
+            }
+            let number = slot + 1;
+            if keyboard.is_mod_active(KeyMods::SHIFT) {
+                practice.slots[slot] = Some(self.game.clone());
+                toasts.push(
+                    locale.format("toast-state-saved", &[&number]),
+                    locale.text("toast-state-saved-hint"),
+                );
+            } else {
+                game = practice.load(slot);
+                if game.is_none() {
+                    toasts.push(
+                        locale.format("toast-state-empty", &[&number]),
+                        locale.text("toast-state-empty-hint"),
+                    );
+                }
+            }
+        }
+        if let Some(game) = game {
+            self.clearing_blocks.clear();
+            self.replace_game(game);
+        }
+    }
+
+    /// Swaps the game for another version of it, as when an online match rolls back and
+    /// simulates it again, and redraws the stack to match.
+    pub fn replace_game(&mut self, game: Game) {
+        self.game = game;
+        self.hint = None;
+        self.update_grid_batch();
+        self.update_intensity();
+    }
+
+    /// Advances the game by one fixed step, recording its input and handling and playing its
+    /// sounds. Returns what happened, for scenes that react to it too.
+    pub fn step(
+        &mut self,
+        ctx: &Context,
+        audio: &mut Audio,
+        handling: Handling,
+        input: FrameInput,
+    ) -> Vec<GameEvent> {
+        if let Some(replay) = self.replay.as_mut().filter(|_| !self.game.is_over()) {
+            replay.record(self.game.frame(), input);
+            replay.record_handling(self.game.frame(), handling);
+        }
+        let was_entering = self.game.is_entering();
+        let was_collapsing = self.game.is_collapsing();
+        let events = self.game.step(handling, input);
+        self.hud.step(&events);
+        self.particles.step();
+        self.shake.step();
+        let new_piece = was_entering
+            || events
+                .iter()
+                .any(|event| matches!(event, GameEvent::PieceLocked(_) | GameEvent::Held));
+        self.slide.step(self.game.piece(), new_piece);
+        self.lock_flash.step();
+        if let Some(fade) = &mut self.stack_fade {
+            fade.step();
+        }
+        self.update_danger(ctx, audio);
+        self.flash =
+            (self.flash - Game::FRAME_TIME.as_secs_f32() / Self::FLASH_FADE.as_secs_f32()).max(0.);
+        if events.iter().any(|event| {
+            matches!(
+                event,
+                GameEvent::PieceLocked(_)
+                    | GameEvent::LinesRemoved
+                    | GameEvent::BlocksFell
+                    | GameEvent::LevelUp { .. }
+                    | GameEvent::GarbageReceived { .. }
+            )
+        }) {
+            self.update_intensity();
+        }
+        for &event in &events {
+            match event {
+                GameEvent::Moved => self.play_sound(ctx, audio, Sfx::Move),
+                GameEvent::Bumped => self.play_sound(ctx, audio, Sfx::Bump),
+                GameEvent::SoftDropped => self.play_sound(ctx, audio, Sfx::SoftDrop),
+                GameEvent::Landed => self.play_sound(ctx, audio, Sfx::Land),
+                GameEvent::Rotated => self.play_sound(ctx, audio, Sfx::Rotate),
+                GameEvent::PieceLocked(piece) => {
+                    self.play_sound(ctx, audio, Sfx::Place);
+                    self.add_to_grid_batch(piece);
+                    self.lock_flash.lock(piece);
+                    if let Some(fade) = &mut self.stack_fade {
+                        fade.lock(piece);
+                    }
+                }
+                GameEvent::HardDropped { rows } => {
+                    self.shake.add(0.2 + rows as f32 * 0.01);
+                }
+                GameEvent::LinesCleared { count, kind } => {
+                    self.play_clear_sound(ctx, audio, count, kind);
+                    if let Some(fade) = &mut self.stack_fade {
+                        fade.reveal();
+                    }
+                    self.clear_animation = ClearAnimation::of(count, kind);
+                    if count >= 4 {
+                        self.celebrate_tetris();
+                    }
+                    if count >= 4 || kind == ClearKind::TSpin {
+                        self.shake.add(0.6);
+                    } else if count >= 2 {
+                        self.shake.add(0.2 * count as f32);
+                    }
+                    let grid = self.game.grid();
+                    self.clearing_blocks = self
+                        .game
+                        .clearing_lines()
+                        .flat_map(|y| {
+                            (0..grid.width() as i32).filter_map(move |x| {
+                                let pos = Point2 { x, y: y as i32 };
+                                grid.at(pos.x, pos.y).map(|block| (pos, block))
+                            })
+                        })
+                        .collect();
+                }
+                GameEvent::LinesRemoved => {
+                    for (pos, block) in std::mem::take(&mut self.clearing_blocks) {
+                        let cell = self.cell_rect(pos);
+                        let param = self.block_param(&block);
+                        self.particles.burst(cell.point(), cell.w, param);
+                    }
+                    // The stack only has to be rebuilt when rows move, which is much rarer
+                    // than pieces locking.
+                    self.update_grid_batch();
+                }
+                GameEvent::BlocksFell => self.update_grid_batch(),
+                GameEvent::GarbageReceived { rows } => {
+                    if let Some(fade) = &mut self.stack_fade {
+                        fade.reveal();
+                    }
+                    self.play_sound(ctx, audio, Sfx::Bump);
+                    self.shake.add(0.2 + rows as f32 * 0.05);
+                    self.update_grid_batch();
+                }
+                // The stack shows for good once the game is over, since it stops stepping.
+                GameEvent::GameOver { .. } => {
+                    if let Some(fade) = &mut self.stack_fade {
+                        fade.reveal();
+                    }
+                }
+                _ => {}
+            }
+        }
+        if self.stack_fade.is_some() || was_collapsing {
+            // Fading and collapsing blocks change every step, unlike the rest of the stack.
+            self.update_grid_batch();
+        }
+        events
+    }
+
+    /// Warns when the stack gets close to the top, fading the warning in, and fades it out
+    /// once the stack is back down.
+    fn update_danger(&mut self, ctx: &Context, audio: &mut Audio) {
+        let grid = self.game.grid();
+        let in_danger =
+            !self.game.is_over() && grid.visible_height() < grid.stack_height() + Self::DANGER_ROWS;
+        if in_danger && !self.in_danger {
+            self.play_sound(ctx, audio, Sfx::Warning);
+        }
+        self.in_danger = in_danger;
+        let fade = Game::FRAME_TIME.as_secs_f32() / Self::DANGER_FADE.as_secs_f32();
+        self.danger = if in_danger {
+            (self.danger + fade).min(1.)
+        } else {
+            (self.danger - fade).max(0.)
+        };
+    }
+
+    /// Works out how intense the game is, which only changes when the stack or the level do.
+    fn update_intensity(&mut self) {
+        let grid = self.game.grid();
+        let stack = grid.stack_height() as f32 / grid.visible_height() as f32;
+        let level = self.game.mode().level(self.game.stats().lines_cleared);
+        let level = (level - 1) as f32 / (Self::MAX_INTENSITY_LEVEL - 1) as f32;
+        self.intensity = if self.game.is_over() {
+            0.
+        } else {
+            stack.max(level).min(1.)
+        };
+    }
+
+    /// Unlocks whatever achievements a step earned, announcing each of them.
+    fn unlock_achievements(
+        &self,
+        ctx: &Context,
+        achievements: &mut Achievements,
+        locale: &Locale,
+        toasts: &mut Toasts,
+        events: &[GameEvent],
+    ) {
+        let unlocked = achievements.observe(&self.game, events);
+        if unlocked.is_empty() {
+            return;
+        }
+        for achievement in unlocked {
+            toasts.push(locale.text("toast-achievement"), achievement.name());
+        }
+        let _ = achievements.save(ctx);
+    }
+
+    /// Opens the pause menu, and counts down again for when the game resumes.
+    fn pause(&mut self) -> Transition {
+        self.countdown = Some(Countdown::new());
+        Transition::Push(Box::new(SettingsScene::in_game()))
+    }
+
+    /// Flashes the background and sets off fireworks on either side of the board.
+    fn celebrate_tetris(&mut self) {
+        self.flash = 1.;
+        let board = self.board_rect();
+        let mut rng = rand::thread_rng();
+        // Alternating sides, in the space between the board and the edge of the screen.
+        let sides = [
+            SCREEN.x + 16. ..board.x - 16.,
+            board.right() + 16. ..SCREEN.right() - 16.,
+        ];
+        for i in 0..Self::FIREWORKS_PER_TETRIS {
+            let side = &sides[i % 2];
+            if side.is_empty() {
+                continue;
+            }
+            let x = rng.gen_range(side.clone());
+            let pos = Point2 {
+                x,
+                y: rng.gen_range(board.y + 32. ..board.center().y),
+            };
+            let kind = PieceKind::TETROMINOES[rng.gen_range(0..PieceKind::TETROMINOES.len())];
+            self.particles.firework(pos, self.block_sprites[kind]);
+        }
+    }
+
+    fn play_sound(&self, ctx: &Context, audio: &mut Audio, sfx: Sfx) {
+        if !self.muted {
+            audio.play(ctx, sfx);
+        }
+    }
+
+    /// Plays the clear sound higher the more rows were cleared, the T-spin sound, or the sting
+    /// for a tetris.
+    fn play_clear_sound(&self, ctx: &Context, audio: &mut Audio, count: u32, kind: ClearKind) {
+        if self.muted {
+            return;
+        }
+        if count >= 4 {
+            audio.play(ctx, Sfx::Tetris);
+            return;
+        }
+        let sfx = match kind {
+            ClearKind::Normal => Sfx::Clear,
+            ClearKind::TSpin => Sfx::TSpinClear,
+        };
+        // A whole tone up for each extra row.
+        let ratio = 2f32.powf((count - 1) as f32 * 2. / 12.);
+        audio.play_pitched(ctx, sfx, ratio);
+    }
+
+    /// Where the field is drawn: cells are as big as they can be while fitting `board_area`,
+    /// and the board is centered in it horizontally, then moved by the screen shake. The
+    /// hidden rows are above it, out of the board.
+    pub fn board_rect(&self) -> Rect {
+        let mut board = Self::fit_board(self.board_area, self.game.grid());
+        board.translate(self.board_offset);
+        board
+    }
+
+    /// Where the field of `grid` is drawn in `area` when the screen isn't shaking.
+    fn fit_board(area: Rect, grid: &Grid) -> Rect {
+        let width = grid.width() as f32;
+        let height = grid.visible_height() as f32;
+        let cell = (area.w / width).min(area.h / height).floor().max(1.);
+        Rect::new(
+            area.x + ((area.w - cell * width) / 2.).floor(),
+            area.y,
+            cell * width,
+            cell * height,
+        )
+    }
+
+    /// The cell at `pos` of the grid on screen, hidden rows included. Piece meshes are drawn
+    /// by scaling them to it.
+    pub fn cell_rect(&self, pos: Point2<i32>) -> Rect {
+        let board = self.board_rect();
+        let grid = self.game.grid();
+        let cell = board.w / grid.width() as f32;
+        Rect::new(
+            board.x + pos.x as f32 * cell,
+            board.y + (pos.y - grid.hidden_rows() as i32) as f32 * cell,
+            cell,
+            cell,
+        )
+    }
+
+    /// What the mesh of `piece` is scaled to on screen, which is one of its blocks at its
+    /// position, moved by `offset`, measured in cells.
+    fn piece_rect(&self, piece: Piece, offset: Vec2) -> Rect {
+        let mut cell = self.cell_rect(piece.pos);
+        cell.translate(offset * cell.w);
+        cell.scale(piece.scale as f32, piece.scale as f32);
+        cell
+    }
+
+    /// Where the bot would drop the falling piece, or the held one, worked out again only
+    /// once another piece comes in or is held.
+    fn hint(&mut self) -> Option<Piece> {
+        let key = (self.game.pieces_placed(), self.game.hold_used());
+        match self.hint {
+            Some((hint_key, hint)) if hint_key == key => hint,
+            _ => {
+                let hint = Bot::hint(&self.game);
+                self.hint = Some((key, hint));
+                hint
+            }
+        }
+    }
+
+    /// Draws a line around the blocks of `piece`, over the board.
+    fn draw_outline(&self, canvas: &mut graphics::Canvas, piece: Piece) {
+        let grid = piece.grid();
+        let hidden = self.game.grid().hidden_rows() as i32;
+        let filled = |x: i32, y: i32| grid.contains_pos(x, y) && grid.at(x, y).is_some();
+        for y in 0..grid.height() as i32 {
+            for x in 0..grid.width() as i32 {
+                if !filled(x, y) || piece.pos.y + y < hidden {
+                    continue;
+                }
+                let cell = self.cell_rect(Point2 {
+                    x: piece.pos.x + x,
+                    y: piece.pos.y + y,
+                });
+                let edges = [
+                    (!filled(x, y - 1), Rect::new(cell.x, cell.y, cell.w, 1.)),
+                    (
+                        !filled(x, y + 1),
+                        Rect::new(cell.x, cell.bottom() - 1., cell.w, 1.),
+                    ),
+                    (!filled(x - 1, y), Rect::new(cell.x, cell.y, 1., cell.h)),
+                    (
+                        !filled(x + 1, y),
+                        Rect::new(cell.right() - 1., cell.y, 1., cell.h),
+                    ),
+                ];
+                for (_, edge) in edges.into_iter().filter(|(open, _)| *open) {
+                    canvas.draw(
+                        &Quad,
+                        DrawParam::default().dest_rect(edge).color(Self::HINT_COLOR),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Draws the next `count` pieces in the side panel, in a second column past three and
+    /// smaller the more there are.
+    fn draw_next(&mut self, canvas: &mut graphics::Canvas, assets: &Assets, count: usize) {
+        if count == 0 {
+            return;
+        }
+        canvas.draw(
+            &ui_text("NEXT", 16.),
+            DrawParam::default().dest(Point2 { x: 304., y: 16. }),
+        );
+        let area = Self::NEXT_AREA;
+        let columns = if count > 3 { 2 } else { 1 };
+        let rows = count.div_ceil(columns);
+        let slot = Vec2::new(area.w / columns as f32, area.h / rows as f32);
+        // Room for four blocks across and three down, which most pieces fit in, so that
+        // every piece is drawn at the same size.
+        let cell = (slot.x / 4.).min(slot.y / 3.).min(16.).floor();
+        let pieces = self.game.next_pieces(count);
+        for (i, (kind, board)) in pieces.into_iter().zip(&mut self.next_boards).enumerate() {
+            let (column, row) = (i / rows, i % rows);
+            board.draw(
+                canvas,
+                &assets.quad_mesh,
+                &kind.get_grid(PieceRotation::Deg0),
+                Rect::new(
+                    area.x + column as f32 * slot.x,
+                    area.y + row as f32 * slot.y,
+                    slot.x,
+                    slot.y,
+                ),
+                cell,
+                Color::WHITE,
+            );
+        }
+    }
+
+    /// Lists how many of each piece were dealt this game, each next to its shape.
+    fn draw_piece_stats(&mut self, canvas: &mut graphics::Canvas, assets: &Assets) {
+        let game = &self.game;
+        let mut kinds = game
+            .mode()
+            .piece_kinds()
+            .unwrap_or_else(|| PieceKind::TETROMINOES.to_vec());
+        if kinds.len() > Self::STATS_ROWS {
+            kinds.sort_by_key(|&kind| std::cmp::Reverse(game.dealt(kind)));
+            kinds.truncate(Self::STATS_ROWS);
+        }
+        let area = Self::STATS_AREA;
+        let row_height = area.h / Self::STATS_ROWS as f32;
+        for (i, (kind, board)) in kinds.into_iter().zip(&mut self.stat_boards).enumerate() {
+            let y = area.y + i as f32 * row_height;
+            board.draw(
+                canvas,
+                &assets.quad_mesh,
+                &kind.get_grid(PieceRotation::Deg0),
+                Rect::new(area.x, y, 24., row_height - 2.),
+                4.,
+                Color::WHITE,
+            );
+            canvas.draw(
+                &ui_text(format!("{:03}", game.dealt(kind)), 12.),
+                DrawParam::default().dest(Point2 { x: area.x + 32., y }),
+            );
+        }
+    }
+
+    /// Draws a ring over the board that fills up as restart is held.
+    fn draw_restart_progress(&self, ctx: &Context, canvas: &mut graphics::Canvas) -> GameResult {
+        const RADIUS: f32 = 24.;
+        const SEGMENTS: usize = 32;
+        let progress = (self.restart_hold.as_secs_f32() / Self::RESTART_HOLD.as_secs_f32()).min(1.);
+        let center = self.board_rect().center();
+        let track = Mesh::new_circle(
+            ctx,
+            DrawMode::stroke(4.),
+            center,
+            RADIUS,
+            0.5,
+            Color::new(0., 0., 0., 0.5),
+        )?;
+        canvas.draw(&track, DrawParam::default());
+        // Clockwise from the top.
+        let points: Vec<_> = (0..=SEGMENTS)
+            .map(|i| {
+                let angle = progress * TAU * i as f32 / SEGMENTS as f32 - FRAC_PI_2;
+                Vec2::new(
+                    center.x + RADIUS * angle.cos(),
+                    center.y + RADIUS * angle.sin(),
+                )
+            })
+            .collect();
+        let ring = Mesh::new_polyline(ctx, DrawMode::stroke(4.), &points, Color::WHITE)?;
+        canvas.draw(&ring, DrawParam::default());
+        let mut text = ui_text("RESTART", 12.);
+        text.set_layout(TextLayout {
+            h_align: TextAlign::Middle,
+            v_align: TextAlign::Middle,
+        });
+        canvas.draw(
+            &text,
+            DrawParam::default().dest(Point2 {
+                x: center.x,
+                y: center.y + RADIUS + 16.,
+            }),
+        );
+        Ok(())
+    }
+
+    /// Draws the empty board out of tiles of the background image, since the background
+    /// only has room for the default size.
+    fn draw_board(&self, canvas: &mut graphics::Canvas, assets: &Assets, grid_lines: bool) {
+        let board = self.board_rect();
+        let (bg_width, bg_height) = (assets.bg.width() as f32, assets.bg.height() as f32);
+        canvas.draw(&self.board_frame, DrawParam::default().dest(board.point()));
+        let tile = Self::BG_TILE;
+        let src = Rect::new(
+            tile.x / bg_width,
+            tile.y / bg_height,
+            tile.w / bg_width,
+            tile.h / bg_height,
+        );
+        let grid = self.game.grid();
+        for x in 0..grid.width() as i32 {
+            for y in grid.hidden_rows() as i32..grid.height() as i32 {
+                let cell = self.cell_rect(Point2 { x, y });
+                canvas.draw(
+                    &assets.bg,
+                    DrawParam::default()
+                        .src(src)
+                        .dest(cell.point())
+                        .scale(Vec2::new(cell.w / tile.w, cell.h / tile.h)),
+                );
+            }
+        }
+        if grid_lines {
+            canvas.draw(&self.grid_lines, DrawParam::default().dest(board.point()));
+        }
+    }
+
+    /// Draws the board with everything on it: the stack, the falling piece and the
+    /// animations over them. `interpolation` is how far real time is past the last step.
+    pub fn draw_playfield(
+        &mut self,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+        interpolation: f32,
+    ) {
+        let assets = &shared.assets;
+        self.board_offset = if shared.settings.screen_shake {
+            self.shake.offset()
+        } else {
+            Vec2::ZERO
+        };
+
+        self.draw_board(canvas, assets, shared.settings.grid_lines);
+
+        canvas.draw_instanced_mesh(
+            assets.quad_mesh.clone(),
+            &self.grid_batch,
+            DrawParam::default().dest_rect(self.cell_rect(Point2 {
+                x: 0,
+                y: self.game.grid().hidden_rows() as i32,
+            })),
+        );
+        for (pos, brightness) in self.lock_flash.cells(interpolation) {
+            canvas.draw(
+                &assets.quad_mesh,
+                DrawParam::default()
+                    .dest_rect(self.cell_rect(pos))
+                    .color(Color::new(1., 1., 1., brightness)),
+            );
+        }
+        if !self.game.is_over() && !self.game.is_entering() {
+            // The piece slides between cells, and the ghost follows it sideways.
+            let slide = self.slide.offset(interpolation);
+            if shared.settings.ghost_piece {
+                let ghost = self.game.ghost_piece();
+                canvas.draw_instanced_mesh(
+                    assets.quad_mesh.clone(),
+                    &self.piece_meshes[ghost.kind][ghost.rotation],
+                    DrawParam::default()
+                        .dest_rect(self.piece_rect(ghost, Vec2::new(slide.x, 0.)))
+                        .color(Color::new(1., 1., 1., 0.3)),
+                );
+            }
+            if shared.settings.placement_hints {
+                if let Some(hint) = self.hint() {
+                    self.draw_outline(canvas, hint);
+                }
+            }
+            let piece = self.game.piece();
+            canvas.draw_instanced_mesh(
+                assets.quad_mesh.clone(),
+                &self.piece_meshes[piece.kind][piece.rotation],
+                DrawParam::default().dest_rect(self.piece_rect(piece, slide)),
+            );
+        }
+
+        let board = self.board_rect();
+        // A bar along the left of the board as tall as the garbage about to rise.
+        let garbage =
+            self.game.pending_garbage() as f32 * board.h / self.game.grid().visible_height() as f32;
+        if garbage > 0. {
+            canvas.draw(
+                &Quad,
+                DrawParam::default()
+                    .dest_rect(Rect::new(
+                        board.x - 6.,
+                        board.bottom() - garbage.min(board.h),
+                        3.,
+                        garbage.min(board.h),
+                    ))
+                    .color(Color::RED),
+            );
+        }
+
+        // Interpolated so that the animation stays smooth above the step rate.
+        let progress = self.game.line_clear_progress(interpolation);
+        for line in self.game.clearing_lines() {
+            let row = self.cell_rect(Point2 {
+                x: 0,
+                y: line as i32,
+            });
+            self.clear_animation.draw(
+                canvas,
+                &assets.quad_mesh,
+                Rect::new(board.x, row.y, board.w, row.h),
+                progress,
+            );
+        }
+        self.particles.draw(canvas, &assets.quad_mesh);
+    }
+}
+
+impl Scene for Gameplay {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        // Only a fresh press counts, so that the key still held from the last restart doesn't
+        // restart the new game too.
+        let controls = Controls::new(ctx, &shared.settings, &shared.gamepad, &shared.touch);
+        let restarting = controls.just_pressed(Action::Restart)
+            || (!self.restart_hold.is_zero() && controls.pressed(Action::Restart));
+        if restarting {
+            self.restart_hold += ctx.time.delta();
+            if self.restart_hold >= Self::RESTART_HOLD {
+                let seed = rand::thread_rng().gen();
+                let board_size = shared.settings.board_size;
+                let gameplay = Self::with_seed(ctx, shared, self.game.mode(), seed, board_size);
+                return Ok(Transition::Switch(Box::new(gameplay)));
+            }
+        } else {
+            self.restart_hold = Duration::ZERO;
+        }
+
+        let Shared {
+            audio,
+            locale,
+            settings,
+            gamepad,
+            touch,
+            achievements,
+            toasts,
+            heatmap,
+            ..
+        } = shared;
+        let controls = Controls::new(ctx, settings, gamepad, touch);
+
+        if let Some(countdown) = &mut self.countdown {
+            let beat = countdown.advance(ctx.time.delta());
+            if let (Some(beat), false) = (beat, self.muted) {
+                let ratio = if beat < Countdown::COUNT { 1. } else { 1.5 };
+                audio.play_pitched(ctx, Sfx::Countdown, ratio);
+            }
+            if countdown.is_over() {
+                self.countdown = None;
+            } else if countdown.is_counting() {
+                // Presses during the countdown are dropped rather than kept for the start.
+                if controls.just_pressed(Action::Pause) {
+                    return Ok(self.pause());
+                }
+                return Ok(Transition::None);
+            }
+        }
+
+        self.update_practice(ctx, &controls, locale, toasts);
+
+        // The game is simulated in fixed steps, catching up with real time. New presses only
+        // count for the first step of a frame.
+        self.timer.accumulate(ctx.time.delta(), Self::MAX_CATCH_UP);
+        let mut input = controls.frame_input();
+        input.just_pressed |= self.pending_presses;
+        self.pending_presses = input.just_pressed;
+        // Steps go on while the stack turns gray after topping out.
+        while (!self.game.is_over() || self.game.is_collapsing()) && self.timer.take_step() {
+            let events = self.step(ctx, audio, settings.handling, input);
+            if events
+                .iter()
+                .any(|event| matches!(event, GameEvent::PieceLocked(_)))
+            {
+                if let Some(practice) = &mut self.practice {
+                    practice.remember_lock(&self.game);
+                }
+            }
+            self.unlock_achievements(ctx, achievements, locale, toasts, &events);
+            input = input.held();
+            self.pending_presses = 0;
+        }
+
+        if self.game.is_over() && !self.game.is_collapsing() {
+            if let Some(replay) = &self.replay {
+                let _ = replay.save(ctx);
+            }
+            let summary = GameSummary::new(&self.game, storage::timestamp());
+            let _ = summary.append(ctx);
+            heatmap.add(self.game.heatmap());
+            // Everyone plays the same challenge, which custom rules would make easier or
+            // harder.
+            if let Some(challenge) = self.daily.filter(|_| !RulesHash::is_custom()) {
+                let mut results = DailyResults::load(ctx);
+                let value = challenge
+                    .mode()
+                    .record_value(&self.game.stats(), self.game.is_completed());
+                if results.record(challenge, value) {
+                    let _ = results.save(ctx);
+                }
+            }
+            return Ok(Transition::Push(Box::new(GameOverScene::new(
+                self, summary, settings,
+            ))));
+        }
+        if controls.just_pressed(Action::Pause) {
+            return Ok(self.pause());
+        }
+
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        let assets = &shared.assets;
+        let bg_width = assets.bg.width() as f32;
+        canvas.draw(
+            &assets.bg,
+            DrawParam::default()
+                .src(Rect::new(
+                    Self::BG_PANEL_X / bg_width,
+                    0.,
+                    1. - Self::BG_PANEL_X / bg_width,
+                    1.,
+                ))
+                .dest(Point2 {
+                    x: Self::BG_PANEL_X,
+                    y: 0.,
+                }),
+        );
+        self.draw_playfield(canvas, shared, self.timer.interpolation());
+        if let Some(countdown) = &self.countdown {
+            countdown.draw(canvas, self.board_rect());
+        }
+        if !self.restart_hold.is_zero() {
+            self.draw_restart_progress(ctx, canvas)?;
+        }
+
+        let best = self.leaderboard.best();
+        let best = best.map(|best| self.game.mode().format_record(best));
+        self.hud.draw(
+            canvas,
+            &self.game,
+            best,
+            shared.settings.live_stats,
+            self.board_rect(),
+        );
+        if shared.settings.touch_buttons && shared.touch.used {
+            for (_, label, rect) in TouchState::BUTTONS {
+                draw_button(canvas, assets, rect, label);
+            }
+        }
+
+        let next_count = shared
+            .settings
+            .next_count
+            .min(self.game.mode().rules().preview);
+        self.draw_next(canvas, assets, next_count);
+        if shared.settings.piece_stats {
+            self.draw_piece_stats(canvas, assets);
+        }
+        if self.game.can_hold() {
+            canvas.draw(
+                &ui_text("HOLD", 16.),
+                DrawParam::default().dest(Point2 { x: 304., y: 128. }),
+            );
+            if let Some(kind) = self.game.held_piece() {
+                self.hold_board.draw(
+                    canvas,
+                    &assets.quad_mesh,
+                    &kind.get_grid(PieceRotation::Deg0),
+                    Self::HOLD_AREA,
+                    16.,
+                    if self.game.hold_used() {
+                        Color::new(0.5, 0.5, 0.5, 1.)
+                    } else {
+                        Color::WHITE
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(debug_assertions)]
+    fn assets_reloaded(&mut self, ctx: &mut Context, shared: &Shared) {
+        self.reload_blocks(ctx, &shared.assets.blocks);
+    }
+
+    fn debug_lines(&self) -> Vec<String> {
+        let piece = self.game.piece();
+        let level = self.game.mode().level(self.game.stats().lines_cleared);
+        let instances = self.grid_batch.instances().len()
+            + self.lock_flash.cells(0.).count()
+            + self.game.clearing_lines().count();
+        vec![
+            format!("PIECE {:?} {:?}", piece.kind, piece.rotation).to_uppercase(),
+            format!("POS {} {}", piece.pos.x, piece.pos.y),
+            format!(
+                "LEVEL {} GRAVITY {}MS",
+                level,
+                self.game.mode().gravity(level).as_millis()
+            ),
+            format!("FRAME {}", self.game.frame()),
+            format!("INSTANCES {}", instances),
+        ]
+    }
+
+    fn danger(&self) -> Option<f32> {
+        Some(self.danger)
+    }
+
+    fn intensity(&self) -> Option<f32> {
+        Some(self.intensity)
+    }
+
+    fn flash(&self) -> Option<f32> {
+        Some(self.flash)
+    }
+
+    fn saved_game(&self) -> Option<SavedGame> {
+        self.game.saved()
+    }
+
+    fn setup(&self) -> Option<Setup> {
+        Setup::of(&self.game)
+    }
+
+    fn has_savestates(&self) -> bool {
+        self.practice.is_some()
+    }
+}
+
+pub struct GameOverScene {
+    mode: GameMode,
+    stats: GameStats,
+    completed: bool,
+    board: Rect,
+    leaderboard: Leaderboard,
+    /// Name being typed for a run that made the table, until it is confirmed.
+    name_entry: Option<String>,
+    /// Rank in `leaderboard` of the run that just finished, once it has been recorded.
+    record_rank: Option<usize>,
+    /// Shown on the results screen that comes next.
+    summary: GameSummary,
+    heatmap: Heatmap,
+    /// The run to send to the online leaderboard once it has a name, if one is used.
+    online_score: Option<Score>,
+    submission: Option<ScoreSubmission>,
+}
+
+impl GameOverScene {
+    /// Letters and digits the gamepad cycles through when entering a name.
+    const NAME_CHARACTERS: &'static str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+    fn new(gameplay: &Gameplay, summary: GameSummary, settings: &Settings) -> Self {
+        let game = &gameplay.game;
+        let value = game.mode().record_value(&game.stats(), game.is_completed());
+        let makes_table = value
+            .and_then(|value| gameplay.leaderboard.rank(value))
+            .is_some();
+        // Runs without a replay, like resumed ones, aren't sent, since they couldn't be
+        // checked, and neither are runs with custom rules, which can't be compared.
+        let online_score = value
+            .zip(gameplay.replay.as_ref())
+            .filter(|_| settings.leaderboard_server().is_some() && !RulesHash::is_custom())
+            .map(|(value, replay)| Score {
+                mode: game.mode(),
+                seed: game.seed(),
+                value,
+                name: String::new(),
+                replay_hash: replay.hash(),
+            });
+        Self {
+            mode: game.mode(),
+            stats: game.stats(),
+            completed: game.is_completed(),
+            board: gameplay.board_rect(),
+            leaderboard: gameplay.leaderboard.clone(),
+            name_entry: makes_table.then(|| settings.player_name.clone()),
+            record_rank: None,
+            summary,
+            heatmap: game.heatmap().clone(),
+            online_score,
+            submission: None,
+        }
+    }
+
+    fn record_value(&self) -> Option<u64> {
+        self.mode.record_value(&self.stats, self.completed)
+    }
+
+    /// Edits the name being entered: typing replaces the name, while up and down cycle the
+    /// last character so that it can be entered with a gamepad too.
+    fn update_name_entry(&mut self, ctx: &mut Context, shared: &mut Shared) {
+        let Some(name) = &mut self.name_entry else {
+            return;
+        };
+        let gamepad = &shared.gamepad;
+        for c in shared.typed.chars().filter(char::is_ascii_alphanumeric) {
+            if name.len() < Leaderboard::NAME_LENGTH {
+                name.push(c.to_ascii_uppercase());
+            }
+        }
+        if menu_pressed(ctx, gamepad, VirtualKeyCode::Back, Button::DPadLeft) {
+            name.pop();
+        }
+        if menu_pressed(ctx, gamepad, VirtualKeyCode::Right, Button::DPadRight)
+            && name.len() < Leaderboard::NAME_LENGTH
+        {
+            name.push('A');
+        }
+        let cycle = if menu_pressed(ctx, gamepad, VirtualKeyCode::Up, Button::DPadUp) {
+            Self::NAME_CHARACTERS.len() - 1
+        } else if menu_pressed(ctx, gamepad, VirtualKeyCode::Down, Button::DPadDown) {
+            1
+        } else {
+            0
+        };
+        if cycle != 0 {
+            let last = name.pop().unwrap_or('A');
+            let index = Self::NAME_CHARACTERS.find(last).unwrap_or(0);
+            let next = (index + cycle) % Self::NAME_CHARACTERS.len();
+            name.push(Self::NAME_CHARACTERS.as_bytes()[next] as char);
+        }
+
+        if back_pressed(ctx, gamepad) {
+            self.name_entry = None;
+        } else if (confirm_pressed(ctx, gamepad) || shared.touch.tapped().is_some())
+            && !name.is_empty()
+        {
+            let name = name.clone();
+            if let Some(value) = self.record_value() {
+                self.record_rank = self.leaderboard.record(name.clone(), value);
+                let _ = self.leaderboard.save(ctx, self.mode);
+            }
+            shared.settings.player_name = name;
+            let _ = Config::from_settings(&shared.settings).save(ctx);
+            self.name_entry = None;
+        }
+    }
+
+    /// Sends the run to the online leaderboard once its name is known, and checks on it.
+    fn update_submission(&mut self, settings: &Settings) {
+        if self.name_entry.is_none() {
+            if let (Some(mut score), Some(server)) =
+                (self.online_score.take(), settings.leaderboard_server())
+            {
+                score.name = settings.player_name.clone();
+                self.submission = Some(ScoreSubmission::new(server, score));
+            }
+        }
+        if let Some(submission) = &mut self.submission {
+            submission.update();
+        }
+    }
+}
+
+impl Scene for GameOverScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        self.update_submission(&shared.settings);
+        if self.name_entry.is_some() {
+            self.update_name_entry(ctx, shared);
+            return Ok(Transition::None);
+        }
+        if confirm_pressed(ctx, &shared.gamepad) || shared.touch.tapped().is_some() {
+            return Ok(Transition::Switch(Box::new(ResultsScene::new(
+                self.summary.clone(),
+                self.heatmap.clone(),
+            ))));
+        }
+        if back_pressed(ctx, &shared.gamepad) {
+            return Ok(Transition::Switch(Box::new(MenuScene::new(ctx))));
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest_rect(self.board)
+                .color(Color::new(0., 0., 0., 0.7)),
+        );
+        let table_y = if self.completed {
+            let title = match self.mode {
+                GameMode::Ultra => "TIME UP",
+                GameMode::Marathon { .. } => "VICTORY!",
+                GameMode::Dig { .. } => "ALL CLEAR!",
+                _ => "FINISHED",
+            };
+            let mut lines = vec![title.to_owned()];
+            if let Some(value) = self.record_value() {
+                lines.push(self.mode.format_record(value));
+            }
+            match self.record_rank {
+                Some(0) => lines.push("NEW BEST!".to_owned()),
+                Some(rank) => lines.push(format!("RANK #{}", rank + 1)),
+                None => (),
+            }
+            for (i, line) in lines.into_iter().enumerate() {
+                canvas.draw(
+                    &ui_text(line, 16.),
+                    DrawParam::default().dest(Point2 {
+                        x: self.board.x + 16.,
+                        y: self.board.y + 16. + i as f32 * 20.,
+                    }),
+                );
+            }
+            self.board.y + 84.
+        } else {
+            let image = &shared.assets.game_over_text;
+            // Shrink the image to fit narrow boards.
+            let scale = (self.board.w / image.width() as f32).min(1.);
+            canvas.draw(
+                image,
+                DrawParam::default()
+                    .dest(self.board.point())
+                    .scale(Vec2::splat(scale)),
+            );
+            self.board.y + image.height() as f32 * scale + 7.
+        };
+
+        // While a name is being entered, show where it would end up in the table.
+        let (leaderboard, highlight) = match (&self.name_entry, self.record_value()) {
+            (Some(name), Some(value)) => {
+                let mut preview = self.leaderboard.clone();
+                let rank = preview.record(format!("{}_", name), value);
+                (preview, rank)
+            }
+            _ => (self.leaderboard.clone(), self.record_rank),
+        };
+        draw_leaderboard(
+            canvas,
+            &leaderboard,
+            self.mode,
+            highlight,
+            Rect::new(self.board.x + 8., table_y, self.board.w - 16., 120.),
+            12.,
+        );
+
+        if let Some(submission) = &self.submission {
+            let status = if submission.is_sent() {
+                "SENT ONLINE"
+            } else if submission.error().is_some() {
+                "COULDN'T SEND ONLINE"
+            } else {
+                "SENDING ONLINE..."
+            };
+            canvas.draw(
+                &ui_text(status, 12.),
+                DrawParam::default().dest(Point2 {
+                    x: self.board.x + 16.,
+                    y: self.board.bottom() - 48.,
+                }),
+            );
+        }
+
+        let hints = if self.name_entry.is_some() {
+            ["TYPE YOUR NAME", "ENTER: OK"]
+        } else {
+            ["ENTER: RESULTS", "ESC: MENU"]
+        };
+        for (i, hint) in hints.into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(hint, 12.),
+                DrawParam::default().dest(Point2 {
+                    x: self.board.x + 16.,
+                    y: self.board.bottom() - 30. + i as f32 * 14.,
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
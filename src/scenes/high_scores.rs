@@ -0,0 +1,155 @@
+use ggez::{
+    event::Button,
+    graphics::{self, DrawParam, Rect},
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use tetris_core::{GameMode, Leaderboard};
+
+use super::{back_pressed, confirm_pressed, menu_pressed, Scene, Shared, Transition};
+use crate::{
+    net::TopScores,
+    render::{draw_button, draw_leaderboard, ui_text},
+    storage::LeaderboardExt,
+};
+
+/// A mode's table on the online leaderboard, fetched once it's first shown.
+enum OnlineTable {
+    Fetching(TopScores),
+    Fetched(Leaderboard),
+    Failed,
+}
+
+/// Shows the high score table of each mode, one at a time, either the one kept on this
+/// computer or the one on the online leaderboard if the player opted into it.
+pub struct HighScoresScene {
+    leaderboards: Vec<Leaderboard>,
+    /// Online tables by mode, for those that were shown.
+    online_tables: Vec<Option<OnlineTable>>,
+    /// Index into `GameMode::ALL`.
+    mode: usize,
+    online: bool,
+}
+
+impl HighScoresScene {
+    const BACK_BUTTON: Rect = Rect::new(8., 260., 64., 32.);
+
+    pub fn new(ctx: &Context) -> Self {
+        Self {
+            leaderboards: GameMode::ALL
+                .iter()
+                .map(|&mode| Leaderboard::load(ctx, mode))
+                .collect(),
+            online_tables: GameMode::ALL.iter().map(|_| None).collect(),
+            mode: 0,
+            online: false,
+        }
+    }
+
+    /// Starts fetching the online table of the mode shown if it hasn't been yet, and checks
+    /// on it.
+    fn update_online_table(&mut self, server: &str) {
+        let table = self.online_tables[self.mode].get_or_insert_with(|| {
+            OnlineTable::Fetching(TopScores::new(server, GameMode::ALL[self.mode]))
+        });
+        if let OnlineTable::Fetching(top_scores) = table {
+            match top_scores.poll() {
+                Ok(Some(leaderboard)) => *table = OnlineTable::Fetched(leaderboard),
+                Ok(None) => (),
+                Err(_) => *table = OnlineTable::Failed,
+            }
+        }
+    }
+}
+
+impl Scene for HighScoresScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        let gamepad = &shared.gamepad;
+        let count = GameMode::ALL.len();
+        let tap = shared.touch.tapped();
+        if back_pressed(ctx, gamepad)
+            || confirm_pressed(ctx, gamepad)
+            || tap.is_some_and(|tap| Self::BACK_BUTTON.contains(tap))
+        {
+            return Ok(Transition::Pop);
+        }
+        if menu_pressed(ctx, gamepad, VirtualKeyCode::Left, Button::DPadLeft)
+            || tap.is_some_and(|tap| tap.x < 200.)
+        {
+            self.mode = (self.mode + count - 1) % count;
+        } else if menu_pressed(ctx, gamepad, VirtualKeyCode::Right, Button::DPadRight)
+            || tap.is_some()
+        {
+            self.mode = (self.mode + 1) % count;
+        }
+        if let Some(server) = shared.settings.leaderboard_server() {
+            if menu_pressed(ctx, gamepad, VirtualKeyCode::Up, Button::DPadUp)
+                || menu_pressed(ctx, gamepad, VirtualKeyCode::Down, Button::DPadDown)
+            {
+                self.online = !self.online;
+            }
+            if self.online {
+                self.update_online_table(server);
+            }
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        let mode = GameMode::ALL[self.mode];
+        canvas.draw(
+            &ui_text("HIGH SCORES", 32.),
+            DrawParam::default().dest(Point2 { x: 120., y: 16. }),
+        );
+        canvas.draw(
+            &ui_text(format!("< {} >", mode.name()), 16.),
+            DrawParam::default().dest(Point2 { x: 120., y: 56. }),
+        );
+        let table = Rect::new(120., 84., 200., 160.);
+        let leaderboard = if self.online {
+            match &self.online_tables[self.mode] {
+                Some(OnlineTable::Fetched(leaderboard)) => Some(leaderboard),
+                Some(OnlineTable::Failed) => {
+                    canvas.draw(
+                        &ui_text("COULDN'T FETCH SCORES", 16.),
+                        DrawParam::default().dest(table.point()),
+                    );
+                    None
+                }
+                _ => {
+                    canvas.draw(
+                        &ui_text("FETCHING...", 16.),
+                        DrawParam::default().dest(table.point()),
+                    );
+                    None
+                }
+            }
+        } else {
+            Some(&self.leaderboards[self.mode])
+        };
+        if let Some(leaderboard) = leaderboard {
+            draw_leaderboard(canvas, leaderboard, mode, None, table, 16.);
+        }
+        if shared.settings.leaderboard_server().is_some() {
+            let source = if self.online {
+                "ONLINE - UP/DOWN: LOCAL"
+            } else {
+                "LOCAL - UP/DOWN: ONLINE"
+            };
+            canvas.draw(
+                &ui_text(source, 12.),
+                DrawParam::default().dest(Point2 { x: 120., y: 260. }),
+            );
+        }
+        if shared.touch.used {
+            draw_button(canvas, &shared.assets, Self::BACK_BUTTON, "BACK");
+        }
+        Ok(())
+    }
+}
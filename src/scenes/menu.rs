@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use ggez::{
+    glam::*,
+    graphics::{self, DrawParam},
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use rand::Rng;
+use tetris_core::{Difficulty, GameMode, SavedGame, Setup};
+
+use super::{
+    achievements::AchievementsScene, any_pressed, combo::ComboSetupScene, confirm_pressed,
+    daily::DailyScene, demo::DemoScene, draw_options, drill::DrillListScene, editor::EditorScene,
+    finesse::FinesseScene, gameplay::Gameplay, high_scores::HighScoresScene, navigate_options,
+    online::OnlineScene, replay::ReplayListScene, settings::SettingsScene, shortcut_pressed,
+    tap_option, versus::VersusScene, Scene, Shared, Transition,
+};
+use crate::{clipboard, locale::Locale, render::ui_text, storage::SavedGameExt};
+
+#[derive(Clone, Copy)]
+enum MenuItem {
+    Continue,
+    Play(GameMode),
+    Daily,
+    Versus,
+    VersusBot(Difficulty),
+    Finesse,
+    Drills,
+    Editor,
+    Combo,
+    Online,
+    HighScores,
+    Achievements,
+    Replays,
+    Settings,
+    Quit,
+}
+
+impl MenuItem {
+    const ALL: [MenuItem; 29] = [
+        MenuItem::Continue,
+        MenuItem::Play(GameMode::ALL[0]),
+        MenuItem::Play(GameMode::ALL[1]),
+        MenuItem::Play(GameMode::ALL[2]),
+        MenuItem::Play(GameMode::ALL[3]),
+        MenuItem::Play(GameMode::ALL[4]),
+        MenuItem::Play(GameMode::ALL[5]),
+        MenuItem::Play(GameMode::ALL[6]),
+        MenuItem::Play(GameMode::ALL[7]),
+        MenuItem::Play(GameMode::ALL[8]),
+        MenuItem::Play(GameMode::ALL[9]),
+        MenuItem::Play(GameMode::ALL[10]),
+        MenuItem::Play(GameMode::ALL[11]),
+        MenuItem::Play(GameMode::ALL[12]),
+        MenuItem::Daily,
+        MenuItem::Versus,
+        MenuItem::VersusBot(Difficulty::ALL[0]),
+        MenuItem::VersusBot(Difficulty::ALL[1]),
+        MenuItem::VersusBot(Difficulty::ALL[2]),
+        MenuItem::Finesse,
+        MenuItem::Drills,
+        MenuItem::Editor,
+        MenuItem::Combo,
+        MenuItem::Online,
+        MenuItem::HighScores,
+        MenuItem::Achievements,
+        MenuItem::Replays,
+        MenuItem::Settings,
+        MenuItem::Quit,
+    ];
+
+    fn label(self, locale: &Locale) -> &str {
+        locale.text(match self {
+            MenuItem::Continue => "menu-continue",
+            MenuItem::Play(mode) => return locale.mode_name(mode),
+            MenuItem::Daily => "menu-daily",
+            MenuItem::Versus => "menu-versus",
+            MenuItem::VersusBot(Difficulty::Easy) => "menu-versus-easy",
+            MenuItem::VersusBot(Difficulty::Medium) => "menu-versus-medium",
+            MenuItem::VersusBot(Difficulty::Hard) => "menu-versus-hard",
+            MenuItem::Finesse => "menu-finesse",
+            MenuItem::Drills => "menu-drills",
+            MenuItem::Editor => "menu-editor",
+            MenuItem::Combo => "menu-combo",
+            MenuItem::Online => "menu-online",
+            MenuItem::HighScores => "menu-high-scores",
+            MenuItem::Achievements => "menu-achievements",
+            MenuItem::Replays => "menu-replays",
+            MenuItem::Settings => "menu-settings",
+            MenuItem::Quit => "menu-quit",
+        })
+    }
+}
+
+pub struct MenuScene {
+    selected: usize,
+    items: Vec<MenuItem>,
+    /// Time since anything was last pressed.
+    idle: Duration,
+}
+
+impl MenuScene {
+    const OPTIONS_ORIGIN: Point2<f32> = Point2 { x: 160., y: 96. };
+    /// How long the menu has to be left alone before the demo starts.
+    const DEMO_DELAY: Duration = Duration::from_secs(30);
+
+    /// Builds the menu, only offering to continue if there is a saved game.
+    pub fn new(ctx: &Context) -> Self {
+        let has_save = SavedGame::exists(ctx);
+        Self {
+            selected: 0,
+            items: MenuItem::ALL
+                .into_iter()
+                .filter(|item| has_save || !matches!(item, MenuItem::Continue))
+                .collect(),
+            idle: Duration::ZERO,
+        }
+    }
+
+    /// Starts practicing the setup on the clipboard, written as `Setup::to_text` writes it.
+    fn practice_pasted_setup(ctx: &mut Context, shared: &mut Shared) -> Transition {
+        let setup = clipboard::paste()
+            .map_err(|e| e.to_string())
+            .and_then(|text| Setup::parse(&text).map_err(|e| e.to_string()));
+        match setup {
+            Ok(setup) => {
+                let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+                let game = setup.game(seed);
+                Transition::Switch(Box::new(Gameplay::training(ctx, shared, game)))
+            }
+            Err(e) => {
+                let title = shared.locale.text("toast-setup-not-pasted");
+                shared.toasts.push(title, e.to_uppercase());
+                Transition::None
+            }
+        }
+    }
+}
+
+impl Scene for MenuScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        if any_pressed(ctx, shared) {
+            self.idle = Duration::ZERO;
+        } else {
+            self.idle += ctx.time.delta();
+        }
+        if self.idle >= Self::DEMO_DELAY {
+            self.idle = Duration::ZERO;
+            return Ok(Transition::Push(Box::new(DemoScene::new(ctx, shared))));
+        }
+        navigate_options(ctx, &shared.gamepad, &mut self.selected, self.items.len());
+        if ctx.keyboard.is_key_just_pressed(VirtualKeyCode::Escape) {
+            ctx.request_quit();
+        }
+        if shortcut_pressed(ctx, VirtualKeyCode::V) {
+            return Ok(Self::practice_pasted_setup(ctx, shared));
+        }
+        let tapped = tap_option(
+            &shared.touch,
+            &mut self.selected,
+            self.items.len(),
+            Self::OPTIONS_ORIGIN,
+        );
+        if !confirm_pressed(ctx, &shared.gamepad) && !tapped {
+            return Ok(Transition::None);
+        }
+        Ok(match self.items[self.selected] {
+            MenuItem::Continue => {
+                let gameplay = SavedGame::load(ctx)
+                    .map_err(|e| e.to_string())
+                    .and_then(|saved| {
+                        Gameplay::from_saved(ctx, shared, saved).map_err(|e| e.to_string())
+                    });
+                // Drop a save that can't be continued rather than offering it again.
+                let _ = SavedGame::delete(ctx);
+                match gameplay {
+                    Ok(gameplay) => Transition::Switch(Box::new(gameplay)),
+                    Err(e) => {
+                        let title = shared.locale.text("toast-save-not-continued");
+                        shared.toasts.push(title, e.to_uppercase());
+                        Transition::Switch(Box::new(MenuScene::new(ctx)))
+                    }
+                }
+            }
+            MenuItem::Play(mode) => Transition::Switch(Box::new(Gameplay::new(ctx, shared, mode))),
+            MenuItem::Daily => Transition::Push(Box::new(DailyScene::new(ctx))),
+            MenuItem::Versus => Transition::Switch(Box::new(VersusScene::new(ctx, shared))),
+            MenuItem::VersusBot(difficulty) => {
+                Transition::Switch(Box::new(VersusScene::bot(ctx, shared, difficulty)))
+            }
+            MenuItem::Finesse => Transition::Switch(Box::new(FinesseScene::new(ctx, shared))),
+            MenuItem::Drills => Transition::Push(Box::new(DrillListScene::new(ctx))),
+            MenuItem::Editor => Transition::Push(Box::new(EditorScene::new(ctx, shared))),
+            MenuItem::Combo => Transition::Push(Box::new(ComboSetupScene::default())),
+            MenuItem::Online => Transition::Push(Box::new(OnlineScene::default())),
+            MenuItem::HighScores => Transition::Push(Box::new(HighScoresScene::new(ctx))),
+            MenuItem::Achievements => Transition::Push(Box::new(AchievementsScene::default())),
+            MenuItem::Replays => Transition::Push(Box::new(ReplayListScene::new(ctx))),
+            MenuItem::Settings => Transition::Push(Box::new(SettingsScene::default())),
+            MenuItem::Quit => {
+                ctx.request_quit();
+                Transition::None
+            }
+        })
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        canvas.draw(
+            &ui_text("TETRIS", 48.),
+            DrawParam::default().dest(Point2 { x: 120., y: 24. }),
+        );
+        let options: Vec<_> = self
+            .items
+            .iter()
+            .map(|item| item.label(&shared.locale).to_owned())
+            .collect();
+        draw_options(
+            canvas,
+            shared,
+            &options,
+            self.selected,
+            Self::OPTIONS_ORIGIN,
+        );
+        Ok(())
+    }
+}
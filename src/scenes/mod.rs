@@ -0,0 +1,274 @@
+pub mod achievements;
+pub mod combo;
+pub mod daily;
+pub mod demo;
+pub mod drill;
+pub mod editor;
+pub mod finesse;
+pub mod gameplay;
+pub mod high_scores;
+pub mod menu;
+pub mod online;
+pub mod replay;
+pub mod results;
+pub mod settings;
+pub mod tuning;
+pub mod versus;
+
+use ggez::{
+    event::Button,
+    graphics::{self, DrawParam},
+    input::keyboard::KeyMods,
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use tetris_core::{Achievements, Heatmap, SavedGame, Setup};
+
+use crate::{
+    audio::Audio,
+    input::{GamepadState, TouchState},
+    locale::Locale,
+    render::{ui_text, Assets},
+    settings::Settings,
+    theme::Theme,
+    toast::Toasts,
+    SCREEN,
+};
+
+/// State available to every scene.
+pub struct Shared {
+    pub assets: Assets,
+    pub audio: Audio,
+    /// Text in the language of `settings.language`.
+    pub locale: Locale,
+    pub settings: Settings,
+    pub gamepad: GamepadState,
+    pub touch: TouchState,
+    /// Characters typed this frame.
+    pub typed: String,
+    /// Seed given on the command line, used for every game instead of a random one so that
+    /// the same pieces come in the same order.
+    pub seed: Option<u32>,
+    pub achievements: Achievements,
+    /// Notices shown over every scene, such as for achievements being unlocked.
+    pub toasts: Toasts,
+    /// Where pieces were locked in the games finished since the game was started.
+    pub heatmap: Heatmap,
+}
+
+impl Shared {
+    /// Swaps the assets and audio for those of `settings.theme`. Nothing changes if the theme
+    /// can't be loaded.
+    pub fn load_theme(&mut self, ctx: &mut Context) -> GameResult {
+        let theme = Theme::load(ctx, &self.settings.theme)?;
+        let assets = Assets::new(ctx, &theme)?;
+        let audio = Audio::new(ctx, &theme)?;
+        self.assets = assets;
+        self.audio = audio;
+        self.audio.play_music(ctx)?;
+        self.settings.apply(&mut self.audio);
+        Ok(())
+    }
+
+    /// Swaps the text for that of `settings.language`. Nothing changes if the language can't
+    /// be loaded.
+    pub fn load_locale(&mut self, ctx: &Context) -> GameResult {
+        self.locale = Locale::load(ctx, &self.settings.language)?;
+        Ok(())
+    }
+
+    /// Loads the images of the current theme again, for when their files changed on disk.
+    #[cfg(debug_assertions)]
+    pub fn reload_images(&mut self, ctx: &mut Context) -> GameResult {
+        let theme = Theme::load(ctx, &self.settings.theme)?;
+        self.assets.reload_images(ctx, &theme)
+    }
+
+    /// Loads the sounds of the current theme again after `path` changed on disk.
+    #[cfg(debug_assertions)]
+    pub fn reload_sounds(&mut self, ctx: &mut Context, path: &str) -> GameResult {
+        let theme = Theme::load(ctx, &self.settings.theme)?;
+        self.audio.reload(ctx, &theme, path)?;
+        self.settings.apply(&mut self.audio);
+        Ok(())
+    }
+}
+
+/// What the scene stack should do after a scene has been updated.
+pub enum Transition {
+    None,
+    Push(Box<dyn Scene>),
+    Pop,
+    /// Clears the whole stack and starts over from the given scene.
+    Switch(Box<dyn Scene>),
+}
+
+pub trait Scene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition>;
+
+    fn draw(
+        &mut self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult;
+
+    /// Overlays are drawn on top of the scene below them instead of replacing it.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// Called once `Shared::assets` have been reloaded, so that anything built from the old
+    /// textures can be built again.
+    #[cfg(debug_assertions)]
+    fn assets_reloaded(&mut self, _ctx: &mut Context, _shared: &Shared) {}
+
+    /// Lines about the scene's state for the debug overlay.
+    fn debug_lines(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// How close a game in this scene is to topping out, from 0.0 to 1.0, for the background
+    /// and music to warn about it.
+    fn danger(&self) -> Option<f32> {
+        None
+    }
+
+    /// How bright the background flashes for something a game in this scene celebrates,
+    /// from 0.0 to 1.0.
+    fn flash(&self) -> Option<f32> {
+        None
+    }
+
+    /// How intense a game in this scene is, from 0.0 to 1.0, for the layered music to follow.
+    fn intensity(&self) -> Option<f32> {
+        None
+    }
+
+    /// A game in progress that should be saved if the window is closed during this scene.
+    fn saved_game(&self) -> Option<SavedGame> {
+        None
+    }
+
+    /// The board of a game in this scene, to copy with Ctrl+C and share.
+    fn setup(&self) -> Option<Setup> {
+        None
+    }
+
+    /// Whether F1 to F4 save and load states of a game in this scene, so that F3 doesn't
+    /// toggle the debug overlay too.
+    fn has_savestates(&self) -> bool {
+        false
+    }
+}
+
+/// Draws a vertical list of options with the cursor next to the selected one.
+pub fn draw_options(
+    canvas: &mut graphics::Canvas,
+    shared: &Shared,
+    options: &[String],
+    selected: usize,
+    origin: Point2<f32>,
+) {
+    let first = first_visible_option(selected, options.len(), origin);
+    let visible = visible_options(origin);
+    for (i, option) in options.iter().enumerate().skip(first).take(visible) {
+        let y = origin.y + (i - first) as f32 * 20.;
+        canvas.draw(
+            &ui_text(option.as_str(), 16.),
+            DrawParam::default().dest(Point2 { x: origin.x, y }),
+        );
+        if i == selected {
+            canvas.draw(
+                &shared.assets.cursor,
+                DrawParam::default().dest(Point2 {
+                    x: origin.x - 12.,
+                    y: y + 4.,
+                }),
+            );
+        }
+    }
+}
+
+/// Selects the option that was tapped this frame, if any, in a list drawn by `draw_options`.
+pub fn tap_option(
+    touch: &TouchState,
+    selected: &mut usize,
+    count: usize,
+    origin: Point2<f32>,
+) -> bool {
+    let Some(tap) = touch.tapped() else {
+        return false;
+    };
+    let row = (tap.y - origin.y) / 20.;
+    let first = first_visible_option(*selected, count, origin);
+    let visible = visible_options(origin).min(count - first);
+    if tap.x < origin.x - 16. || row < 0. || row >= visible as f32 {
+        return false;
+    }
+    *selected = first + row as usize;
+    true
+}
+
+/// How many options `draw_options` fits between `origin` and the bottom of the screen.
+fn visible_options(origin: Point2<f32>) -> usize {
+    ((SCREEN.bottom() - origin.y) / 20.).floor().max(1.) as usize
+}
+
+/// The first option `draw_options` shows, scrolling long lists so that the selected option
+/// stays on screen.
+fn first_visible_option(selected: usize, count: usize, origin: Point2<f32>) -> usize {
+    let visible = visible_options(origin);
+    (selected + 1)
+        .saturating_sub(visible)
+        .min(count.saturating_sub(visible))
+}
+
+/// Moves a menu selection with the up and down keys, wrapping around at the ends.
+pub fn navigate_options(ctx: &Context, gamepad: &GamepadState, selected: &mut usize, count: usize) {
+    if menu_pressed(ctx, gamepad, VirtualKeyCode::Up, Button::DPadUp) {
+        *selected = (*selected + count - 1) % count;
+    }
+    if menu_pressed(ctx, gamepad, VirtualKeyCode::Down, Button::DPadDown) {
+        *selected = (*selected + 1) % count;
+    }
+}
+
+/// Menu controls are fixed so that the player can't lock themselves out of them.
+pub fn menu_pressed(
+    ctx: &Context,
+    gamepad: &GamepadState,
+    key: VirtualKeyCode,
+    button: Button,
+) -> bool {
+    ctx.keyboard.is_key_just_pressed(key) || gamepad.is_just_pressed(button)
+}
+
+/// Ctrl, or Command on macOS, along with `key`, as for copying and pasting.
+pub fn shortcut_pressed(ctx: &Context, key: VirtualKeyCode) -> bool {
+    (ctx.keyboard.is_mod_active(KeyMods::CTRL) || ctx.keyboard.is_mod_active(KeyMods::LOGO))
+        && ctx.keyboard.is_key_just_pressed(key)
+}
+
+/// Enter doesn't confirm while Alt is held, since Alt+Enter toggles fullscreen.
+pub fn confirm_pressed(ctx: &Context, gamepad: &GamepadState) -> bool {
+    (menu_pressed(ctx, gamepad, VirtualKeyCode::Return, Button::South)
+        && !ctx.keyboard.is_mod_active(KeyMods::ALT))
+        || menu_pressed(ctx, gamepad, VirtualKeyCode::Space, Button::Start)
+}
+
+/// Whether any key or button was pressed, or the screen tapped, this frame.
+pub fn any_pressed(ctx: &Context, shared: &Shared) -> bool {
+    let keyboard = &ctx.keyboard;
+    keyboard
+        .pressed_keys()
+        .iter()
+        .any(|key| keyboard.is_key_just_pressed(*key))
+        || shared.gamepad.any_just_pressed()
+        || shared.touch.tapped().is_some()
+}
+
+pub fn back_pressed(ctx: &Context, gamepad: &GamepadState) -> bool {
+    menu_pressed(ctx, gamepad, VirtualKeyCode::Escape, Button::East)
+}
@@ -0,0 +1,351 @@
+use ggez::{
+    graphics::{self, DrawParam},
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use rand::Rng;
+use tetris_lobby::Lobby;
+
+use super::{
+    back_pressed, confirm_pressed, draw_options, navigate_options, tap_option, versus::VersusScene,
+    Scene, Shared, Transition,
+};
+use crate::{
+    net::{Connection, Host, Join, Listing, LobbyList, Message, DEFAULT_PORT},
+    render::ui_text,
+};
+
+/// Where setting up an online match is at.
+#[derive(Default)]
+enum State {
+    /// Choosing whether to host or join a match.
+    #[default]
+    Choosing,
+    /// Waiting for a player to join, while listed on the lobby server for them to find.
+    Hosting {
+        host: Host,
+        listing: Listing,
+    },
+    Joining(Join),
+    Browsing(Browser),
+    /// Connected and waiting for the other player to say hello. The host picked `seed`.
+    Greeting {
+        connection: Connection,
+        is_host: bool,
+        seed: u32,
+    },
+    /// Something went wrong, with a message for the player.
+    Failed(String),
+}
+
+/// Games listed on the lobby server, to pick one to join.
+struct Browser {
+    list: LobbyList,
+    /// The games listed, once they've been fetched.
+    lobbies: Option<Vec<Lobby>>,
+    selected: usize,
+}
+
+impl Browser {
+    fn new(shared: &Shared) -> Self {
+        Self {
+            list: LobbyList::new(shared.settings.lobby_server()),
+            lobbies: None,
+            selected: 0,
+        }
+    }
+}
+
+/// Hosts a versus match for another player to join, or joins one at an address, typed in or
+/// picked from the games listed on the lobby server.
+#[derive(Default)]
+pub struct OnlineScene {
+    selected: usize,
+    /// Host name or IP, with an optional port, to join.
+    address: String,
+    state: State,
+}
+
+impl OnlineScene {
+    const OPTIONS_ORIGIN: Point2<f32> = Point2 { x: 120., y: 96. };
+    const HOST: usize = 0;
+    const JOIN: usize = 1;
+    const BROWSE: usize = 2;
+    const BACK: usize = 3;
+    const OPTION_COUNT: usize = 4;
+    const BROWSER_ORIGIN: Point2<f32> = Point2 { x: 64., y: 96. };
+    /// Longest address that can be typed in.
+    const ADDRESS_LENGTH: usize = 21;
+
+    /// Types into the address while it's selected. Only characters that can be part of an
+    /// address are taken.
+    fn update_address(&mut self, ctx: &Context, shared: &Shared) {
+        let typed = shared
+            .typed
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || ".:-[]".contains(*c));
+        for c in typed {
+            if self.address.len() < Self::ADDRESS_LENGTH {
+                self.address.push(c);
+            }
+        }
+        if ctx.keyboard.is_key_just_pressed(VirtualKeyCode::Back) {
+            self.address.pop();
+        }
+    }
+
+    fn update_choosing(&mut self, ctx: &Context, shared: &Shared) -> Transition {
+        let gamepad = &shared.gamepad;
+        navigate_options(ctx, gamepad, &mut self.selected, Self::OPTION_COUNT);
+        if self.selected == Self::JOIN {
+            self.update_address(ctx, shared);
+        }
+        if back_pressed(ctx, gamepad) {
+            return Transition::Pop;
+        }
+        let tapped = tap_option(
+            &shared.touch,
+            &mut self.selected,
+            Self::OPTION_COUNT,
+            Self::OPTIONS_ORIGIN,
+        );
+        if !confirm_pressed(ctx, gamepad) && !tapped {
+            return Transition::None;
+        }
+        match self.selected {
+            Self::HOST => {
+                self.state = match Host::new(DEFAULT_PORT) {
+                    Ok(host) => State::Hosting {
+                        host,
+                        listing: Self::listing(shared),
+                    },
+                    Err(e) => State::Failed(e.to_string()),
+                }
+            }
+            Self::JOIN if !self.address.is_empty() => {
+                self.state = State::Joining(Join::new(&self.address));
+            }
+            Self::BROWSE => self.state = State::Browsing(Browser::new(shared)),
+            Self::BACK => return Transition::Pop,
+            _ => (),
+        }
+        Transition::None
+    }
+
+    /// Lists the game being hosted on the lobby server, under the player's name.
+    fn listing(shared: &Shared) -> Listing {
+        let settings = &shared.settings;
+        let name = if settings.player_name.is_empty() {
+            "PLAYER"
+        } else {
+            &settings.player_name
+        };
+        Listing::new(settings.lobby_server(), DEFAULT_PORT, name)
+    }
+
+    fn update_browsing(&mut self, ctx: &Context, shared: &Shared, mut browser: Browser) -> State {
+        let Some(lobbies) = &browser.lobbies else {
+            return match browser.list.poll() {
+                Ok(lobbies) => {
+                    browser.lobbies = lobbies;
+                    State::Browsing(browser)
+                }
+                Err(e) => State::Failed(e.to_string()),
+            };
+        };
+        // The games, then an option to fetch them again.
+        let count = lobbies.len() + 1;
+        navigate_options(ctx, &shared.gamepad, &mut browser.selected, count);
+        let tapped = tap_option(
+            &shared.touch,
+            &mut browser.selected,
+            count,
+            Self::BROWSER_ORIGIN,
+        );
+        if !confirm_pressed(ctx, &shared.gamepad) && !tapped {
+            return State::Browsing(browser);
+        }
+        match lobbies.get(browser.selected) {
+            Some(lobby) => {
+                self.address = lobby.address.to_string();
+                State::Joining(Join::new(&self.address))
+            }
+            None => State::Browsing(Browser::new(shared)),
+        }
+    }
+
+    /// Says hello to the other player once connected.
+    fn greet(shared: &Shared, mut connection: Connection, is_host: bool) -> State {
+        let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let settings = &shared.settings;
+        connection.send(Message::hello(seed, settings.board_size, settings.handling));
+        State::Greeting {
+            connection,
+            is_host,
+            seed,
+        }
+    }
+}
+
+impl Scene for OnlineScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        if !matches!(self.state, State::Choosing) && back_pressed(ctx, &shared.gamepad) {
+            // Dropping the host or connection closes it.
+            self.state = State::Choosing;
+            return Ok(Transition::None);
+        }
+        let state = std::mem::take(&mut self.state);
+        self.state = match state {
+            State::Choosing => return Ok(self.update_choosing(ctx, shared)),
+            // Once a player joined, the listing is dropped so that nobody else tries to.
+            State::Hosting { host, mut listing } => match host.accept() {
+                Ok(Some(connection)) => Self::greet(shared, connection, true),
+                Ok(None) => {
+                    listing.update();
+                    State::Hosting { host, listing }
+                }
+                Err(e) => State::Failed(e.to_string()),
+            },
+            State::Joining(join) => match join.poll() {
+                Ok(Some(connection)) => Self::greet(shared, connection, false),
+                Ok(None) => State::Joining(join),
+                Err(e) => State::Failed(e.to_string()),
+            },
+            State::Browsing(browser) => self.update_browsing(ctx, shared, browser),
+            State::Greeting {
+                mut connection,
+                is_host,
+                seed,
+            } => match connection.poll() {
+                Ok(messages) => match messages.into_iter().next() {
+                    Some(hello) if !hello.is_compatible() => {
+                        State::Failed("THE RIVAL HAS ANOTHER VERSION".to_owned())
+                    }
+                    Some(hello) if !hello.has_installed_rules() => {
+                        State::Failed("THE RIVAL PLAYS WITH OTHER PIECES OR KICKS".to_owned())
+                    }
+                    Some(Message::Hello {
+                        seed: host_seed,
+                        board_size,
+                        handling,
+                        ..
+                    }) => {
+                        // The host decides what the match is played with.
+                        let (seed, board_size) = if is_host {
+                            (seed, shared.settings.board_size)
+                        } else {
+                            (host_seed, board_size)
+                        };
+                        return Ok(Transition::Switch(Box::new(VersusScene::online(
+                            ctx, shared, connection, seed, board_size, handling,
+                        ))));
+                    }
+                    _ => State::Greeting {
+                        connection,
+                        is_host,
+                        seed,
+                    },
+                },
+                Err(e) => State::Failed(e.to_string()),
+            },
+            State::Failed(message) => {
+                if confirm_pressed(ctx, &shared.gamepad) {
+                    State::Choosing
+                } else {
+                    State::Failed(message)
+                }
+            }
+        };
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        canvas.draw(
+            &ui_text("ONLINE", 32.),
+            DrawParam::default().dest(Point2 { x: 120., y: 24. }),
+        );
+        let status = match &self.state {
+            State::Choosing => {
+                let cursor = if self.selected == Self::JOIN { "_" } else { "" };
+                let options = [
+                    format!("HOST ON PORT {}", DEFAULT_PORT),
+                    format!("JOIN {}{}", self.address, cursor),
+                    "BROWSE GAMES".to_owned(),
+                    "BACK".to_owned(),
+                ];
+                draw_options(
+                    canvas,
+                    shared,
+                    &options,
+                    self.selected,
+                    Self::OPTIONS_ORIGIN,
+                );
+                return Ok(());
+            }
+            State::Hosting { listing, .. } => vec![
+                format!("WAITING ON PORT {}", DEFAULT_PORT),
+                match listing.error() {
+                    Some(error) => format!("NOT LISTED: {}", error.to_uppercase()),
+                    None if listing.is_listed() => "LISTED ON THE LOBBY SERVER".to_owned(),
+                    None => "LISTING...".to_owned(),
+                },
+                "ESC: CANCEL".to_owned(),
+            ],
+            State::Browsing(Browser {
+                lobbies: Some(lobbies),
+                selected,
+                ..
+            }) => {
+                let title = if lobbies.is_empty() {
+                    "NO GAMES BEING HOSTED"
+                } else {
+                    "GAMES BEING HOSTED"
+                };
+                canvas.draw(
+                    &ui_text(title, 12.),
+                    DrawParam::default().dest(Point2 {
+                        x: Self::BROWSER_ORIGIN.x - 12.,
+                        y: Self::BROWSER_ORIGIN.y - 24.,
+                    }),
+                );
+                let options: Vec<_> = lobbies
+                    .iter()
+                    .map(|lobby| lobby.name.to_uppercase())
+                    .chain(["REFRESH".to_owned()])
+                    .collect();
+                draw_options(canvas, shared, &options, *selected, Self::BROWSER_ORIGIN);
+                return Ok(());
+            }
+            State::Browsing(_) => vec![
+                format!("ASKING {}", shared.settings.lobby_server().to_uppercase()),
+                "ESC: CANCEL".to_owned(),
+            ],
+            State::Joining(_) => vec![
+                format!("CONNECTING TO {}", self.address),
+                "ESC: CANCEL".to_owned(),
+            ],
+            State::Greeting { .. } => vec!["CONNECTED".to_owned()],
+            State::Failed(message) => vec![
+                "COULDN'T CONNECT".to_owned(),
+                message.to_uppercase(),
+                "ENTER: OK".to_owned(),
+            ],
+        };
+        for (i, line) in status.into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(line, 12.),
+                DrawParam::default().dest(Point2 {
+                    x: Self::OPTIONS_ORIGIN.x - 96.,
+                    y: Self::OPTIONS_ORIGIN.y + i as f32 * 16.,
+                }),
+            );
+        }
+        Ok(())
+    }
+}
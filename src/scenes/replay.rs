@@ -0,0 +1,289 @@
+use ggez::{
+    event::Button,
+    glam::*,
+    graphics::{self, DrawParam},
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use tetris_core::{format_time, FrameInput, Game, Replay, Setup};
+
+use super::{
+    back_pressed, confirm_pressed, draw_options, gameplay::Gameplay, menu_pressed,
+    navigate_options, tap_option, Scene, Shared, Transition,
+};
+use crate::{audio::Audio, render::ui_text, storage::ReplayExt};
+
+/// Lists the most recent replays so that one can be watched.
+pub struct ReplayListScene {
+    replays: Vec<Replay>,
+    selected: usize,
+}
+
+impl ReplayListScene {
+    const MAX_LISTED: usize = 10;
+    const OPTIONS_ORIGIN: Point2<f32> = Point2 { x: 120., y: 64. };
+
+    pub fn new(ctx: &Context) -> Self {
+        let mut paths: Vec<_> = ctx
+            .fs
+            .read_dir(Replay::DIRECTORY)
+            .map(Iterator::collect)
+            .unwrap_or_default();
+        // Replays are named after when they were recorded, so this puts the newest first.
+        paths.sort_unstable_by(|a, b| b.cmp(a));
+        Self {
+            replays: paths
+                .iter()
+                .filter_map(|path| Replay::load(ctx, path).ok())
+                .take(Self::MAX_LISTED)
+                .collect(),
+            selected: 0,
+        }
+    }
+
+    fn back_option(&self) -> usize {
+        self.replays.len()
+    }
+}
+
+impl Scene for ReplayListScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        let count = self.back_option() + 1;
+        navigate_options(ctx, &shared.gamepad, &mut self.selected, count);
+        if back_pressed(ctx, &shared.gamepad) {
+            return Ok(Transition::Pop);
+        }
+        let tapped = tap_option(
+            &shared.touch,
+            &mut self.selected,
+            count,
+            Self::OPTIONS_ORIGIN,
+        );
+        if !confirm_pressed(ctx, &shared.gamepad) && !tapped {
+            return Ok(Transition::None);
+        }
+        Ok(match self.replays.get(self.selected) {
+            Some(replay) => {
+                Transition::Push(Box::new(ReplayScene::new(ctx, shared, replay.clone())))
+            }
+            None => Transition::Pop,
+        })
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        canvas.draw(
+            &ui_text("REPLAYS", 32.),
+            DrawParam::default().dest(Point2 { x: 120., y: 16. }),
+        );
+        let mut options: Vec<_> = self
+            .replays
+            .iter()
+            .map(|replay| format!("{} {}", replay.mode.name(), format_time(replay.duration())))
+            .collect();
+        options.push("BACK".to_owned());
+        draw_options(
+            canvas,
+            shared,
+            &options,
+            self.selected,
+            Self::OPTIONS_ORIGIN,
+        );
+        Ok(())
+    }
+}
+
+/// Everything needed to go back to a step of the replay.
+struct SaveState {
+    game: Game,
+    next_input: usize,
+    input: FrameInput,
+}
+
+/// Plays a replay back by simulating its game again from the recorded inputs. While paused
+/// it can be stepped one step at a time, and a step can be saved to go back to later, which
+/// is what scripted runs are put together with.
+pub struct ReplayScene {
+    replay: Replay,
+    gameplay: Gameplay,
+    /// Index of the next recorded input to apply.
+    next_input: usize,
+    input: FrameInput,
+    paused: bool,
+    /// Index into `SPEEDS`.
+    speed: usize,
+    save_state: Option<SaveState>,
+}
+
+impl ReplayScene {
+    const SPEEDS: [f32; 5] = [0.25, 0.5, 1., 2., 4.];
+    const SEEK_FRAMES: u32 = 5 * 60;
+
+    pub fn new(ctx: &mut Context, shared: &Shared, replay: Replay) -> Self {
+        Self {
+            gameplay: Gameplay::from_replay(ctx, shared, &replay),
+            replay,
+            next_input: 0,
+            input: FrameInput::default(),
+            paused: false,
+            speed: 2,
+            save_state: None,
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.gameplay.game.is_over() || self.gameplay.game.frame() >= self.replay.length
+    }
+
+    fn advance(&mut self, ctx: &Context, audio: &mut Audio) {
+        if let Some((frame, input)) = self.replay.inputs.get(self.next_input) {
+            if *frame == self.gameplay.game.frame() {
+                self.input = *input;
+                self.next_input += 1;
+            }
+        }
+        let handling = self.replay.handling_at(self.gameplay.game.frame());
+        self.gameplay.step(ctx, audio, handling, self.input);
+        self.input = self.input.held();
+    }
+
+    /// Silently simulates up to the given step. Going backwards restarts the game, since it
+    /// can only be simulated forwards.
+    fn seek(&mut self, ctx: &mut Context, shared: &mut Shared, frame: u32) {
+        if frame < self.gameplay.game.frame() {
+            self.gameplay = Gameplay::from_replay(ctx, shared, &self.replay);
+            self.next_input = 0;
+            self.input = FrameInput::default();
+        }
+        self.gameplay.muted = true;
+        while self.gameplay.game.frame() < frame && !self.finished() {
+            self.advance(ctx, &mut shared.audio);
+        }
+        self.gameplay.muted = false;
+    }
+}
+
+impl Scene for ReplayScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        let gamepad = &shared.gamepad;
+        if back_pressed(ctx, gamepad) {
+            return Ok(Transition::Pop);
+        }
+        if confirm_pressed(ctx, gamepad) || shared.touch.tapped().is_some() {
+            self.paused = !self.paused;
+        }
+        if menu_pressed(ctx, gamepad, VirtualKeyCode::Up, Button::DPadUp) {
+            self.speed = (self.speed + 1).min(Self::SPEEDS.len() - 1);
+        }
+        if menu_pressed(ctx, gamepad, VirtualKeyCode::Down, Button::DPadDown) {
+            self.speed = self.speed.saturating_sub(1);
+        }
+        if menu_pressed(ctx, gamepad, VirtualKeyCode::Left, Button::DPadLeft) {
+            let frame = self.gameplay.game.frame().saturating_sub(Self::SEEK_FRAMES);
+            self.seek(ctx, shared, frame);
+        } else if menu_pressed(ctx, gamepad, VirtualKeyCode::Right, Button::DPadRight) {
+            let frame = self.gameplay.game.frame() + Self::SEEK_FRAMES;
+            self.seek(ctx, shared, frame);
+        }
+
+        if menu_pressed(ctx, &shared.gamepad, VirtualKeyCode::S, Button::North) {
+            self.save_state = Some(SaveState {
+                game: self.gameplay.game.clone(),
+                next_input: self.next_input,
+                input: self.input,
+            });
+        }
+        if menu_pressed(ctx, &shared.gamepad, VirtualKeyCode::L, Button::West) {
+            if let Some(state) = &self.save_state {
+                self.gameplay.replace_game(state.game.clone());
+                self.next_input = state.next_input;
+                self.input = state.input;
+            }
+        }
+        if menu_pressed(
+            ctx,
+            &shared.gamepad,
+            VirtualKeyCode::F,
+            Button::RightTrigger,
+        ) {
+            self.paused = true;
+            if !self.finished() {
+                self.advance(ctx, &mut shared.audio);
+            }
+        }
+
+        if !self.paused {
+            let speed = Self::SPEEDS[self.speed];
+            self.gameplay
+                .timer
+                .accumulate(ctx.time.delta().mul_f32(speed), Gameplay::MAX_CATCH_UP * 4);
+            while !self.finished() && self.gameplay.timer.take_step() {
+                self.advance(ctx, &mut shared.audio);
+            }
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        self.gameplay.draw(ctx, canvas, shared)?;
+        let status = if self.finished() {
+            "END".to_owned()
+        } else if self.paused {
+            "PAUSED".to_owned()
+        } else {
+            format!("SPEED X{}", Self::SPEEDS[self.speed])
+        };
+        let lines = [
+            "REPLAY".to_owned(),
+            status,
+            format_time(self.gameplay.game.stats().elapsed),
+            format!("/ {}", format_time(self.replay.duration())),
+            format!("STEP {}", self.gameplay.game.frame()),
+        ];
+        for (i, line) in lines.into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(line, 12.),
+                DrawParam::default().dest(Point2 {
+                    x: 296.,
+                    y: 196. + i as f32 * 14.,
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    fn debug_lines(&self) -> Vec<String> {
+        self.gameplay.debug_lines()
+    }
+
+    fn danger(&self) -> Option<f32> {
+        self.gameplay.danger()
+    }
+
+    fn intensity(&self) -> Option<f32> {
+        self.gameplay.intensity()
+    }
+
+    fn flash(&self) -> Option<f32> {
+        self.gameplay.flash()
+    }
+
+    fn setup(&self) -> Option<Setup> {
+        self.gameplay.setup()
+    }
+
+    #[cfg(debug_assertions)]
+    fn assets_reloaded(&mut self, ctx: &mut Context, shared: &Shared) {
+        self.gameplay.assets_reloaded(ctx, shared);
+    }
+}
@@ -0,0 +1,282 @@
+use ggez::{
+    event::Button,
+    graphics::{self, Color, DrawParam, Quad, Rect},
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use tetris_core::{format_time, GameMode, GameSummary, Heatmap};
+
+use super::{
+    back_pressed, confirm_pressed, gameplay::Gameplay, menu::MenuScene, menu_pressed, Scene,
+    Shared, Transition,
+};
+use crate::{
+    locale::Locale,
+    render::{draw_button, ui_text},
+};
+
+/// What the results screen shows, switched between with left and right.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ResultsPage {
+    Stats,
+    /// Where the pieces of the game were locked and the holes they left.
+    Heatmap,
+    /// The same, for every game finished since the game was started.
+    SessionHeatmap,
+}
+
+impl ResultsPage {
+    const ALL: [ResultsPage; 3] = [
+        ResultsPage::Stats,
+        ResultsPage::Heatmap,
+        ResultsPage::SessionHeatmap,
+    ];
+
+    fn name(self, locale: &Locale) -> &str {
+        locale.text(match self {
+            ResultsPage::Stats => "results-stats",
+            ResultsPage::Heatmap => "results-heatmap",
+            ResultsPage::SessionHeatmap => "results-session-heatmap",
+        })
+    }
+}
+
+/// Goes through how a finished game went, before playing again or going back to the menu.
+pub struct ResultsScene {
+    summary: GameSummary,
+    heatmap: Heatmap,
+    /// Index into `ResultsPage::ALL`.
+    page: usize,
+}
+
+impl ResultsScene {
+    const RETRY_BUTTON: Rect = Rect::new(248., 260., 64., 32.);
+    const MENU_BUTTON: Rect = Rect::new(328., 260., 64., 32.);
+    /// Space the field of a heatmap is fit in, with the bars of its columns under it.
+    const HEATMAP_AREA: Rect = Rect::new(48., 84., 176., 148.);
+    const HEATMAP_BAR_HEIGHT: f32 = 20.;
+    const HOLES_COLOR: Color = Color::new(1., 0.2, 0.1, 1.);
+    const LOCKS_COLOR: Color = Color::new(0.3, 0.6, 1., 1.);
+
+    pub fn new(summary: GameSummary, heatmap: Heatmap) -> Self {
+        Self {
+            summary,
+            heatmap,
+            page: 0,
+        }
+    }
+
+    /// Lines of the left column, about how rows were cleared.
+    fn clear_lines(&self, locale: &Locale) -> Vec<String> {
+        let summary = &self.summary;
+        let clears = summary.clears;
+        vec![
+            locale.text("results-clears").to_owned(),
+            locale.format("results-single", &[&clears.singles]),
+            locale.format("results-double", &[&clears.doubles]),
+            locale.format("results-triple", &[&clears.triples]),
+            locale.format("results-tetris", &[&clears.tetrises]),
+            locale.format("results-t-spin", &[&clears.t_spins]),
+            locale.format("results-lines", &[&summary.lines_cleared]),
+            locale.format("results-combo", &[&summary.max_combo]),
+            locale.format("results-attack", &[&summary.attack]),
+        ]
+    }
+
+    /// Lines of the right column, about pace and score.
+    fn score_lines(&self, locale: &Locale) -> Vec<String> {
+        let summary = &self.summary;
+        let score = summary.score_breakdown;
+        let pps = format!("{:.2}", summary.pieces_per_second());
+        let mut lines = vec![
+            locale.text("results-pace").to_owned(),
+            locale.format("results-time", &[&format_time(summary.duration)]),
+            locale.format("results-pieces", &[&summary.pieces_placed]),
+            locale.format("results-pps", &[&pps]),
+            locale.text("results-score").to_owned(),
+            locale.format("results-line-points", &[&score.lines]),
+            locale.format("results-soft-drop-points", &[&score.soft_drop]),
+            locale.format("results-hard-drop-points", &[&score.hard_drop]),
+        ];
+        if summary.mode == GameMode::Survival {
+            lines.push(locale.format("results-survival-points", &[&score.survival]));
+        }
+        lines.push(locale.format("results-total", &[&summary.score]));
+        if summary.mode == GameMode::Master {
+            let grade =
+                GameMode::master_grade(summary.score, summary.lines_cleared, summary.duration);
+            lines.push(locale.format("results-grade", &[&grade]));
+        }
+        lines
+    }
+
+    /// Draws the field of `heatmap`, redder the more holes were left in each cell, with a
+    /// bar under each column as long as the blocks locked in it.
+    fn draw_heatmap(canvas: &mut graphics::Canvas, heatmap: &Heatmap, locale: &Locale) {
+        let (width, height) = (heatmap.width(), heatmap.height());
+        if width == 0 || height == 0 {
+            canvas.draw(
+                &ui_text(locale.text("results-no-games"), 12.),
+                DrawParam::default().dest(Self::HEATMAP_AREA.point()),
+            );
+            return;
+        }
+        let area = Self::HEATMAP_AREA;
+        let cell = (area.w / width as f32)
+            .min((area.h - Self::HEATMAP_BAR_HEIGHT) / height as f32)
+            .floor()
+            .max(1.);
+        let field = Rect::new(area.x, area.y, cell * width as f32, cell * height as f32);
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest_rect(field)
+                .color(Color::new(0., 0., 0., 0.7)),
+        );
+        let max_holes = heatmap.max_holes().max(1) as f32;
+        let max_locks = heatmap.max_locks().max(1) as f32;
+        for x in 0..width {
+            let left = field.x + x as f32 * cell;
+            for y in 0..height {
+                let holes = heatmap.holes(x, y);
+                if holes == 0 {
+                    continue;
+                }
+                let mut color = Self::HOLES_COLOR;
+                color.a = 0.2 + 0.8 * holes as f32 / max_holes;
+                canvas.draw(
+                    &Quad,
+                    DrawParam::default()
+                        .dest_rect(Rect::new(left, field.y + y as f32 * cell, cell, cell))
+                        .color(color),
+                );
+            }
+            let bar = Self::HEATMAP_BAR_HEIGHT * heatmap.locks(x) as f32 / max_locks;
+            canvas.draw(
+                &Quad,
+                DrawParam::default()
+                    .dest_rect(Rect::new(
+                        left,
+                        field.bottom() + 2.,
+                        (cell - 1.).max(1.),
+                        bar,
+                    ))
+                    .color(Self::LOCKS_COLOR),
+            );
+        }
+
+        let busiest = (0..width).max_by_key(|&x| heatmap.locks(x)).unwrap_or(0);
+        let holes: u32 = (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .map(|(x, y)| heatmap.holes(x, y))
+            .sum();
+        let legend = [
+            ("RED: HOLES LEFT".to_owned(), Self::HOLES_COLOR),
+            ("BARS: BLOCKS LOCKED".to_owned(), Self::LOCKS_COLOR),
+            (format!("BUSIEST COLUMN {}", busiest + 1), Color::WHITE),
+            (format!("HOLES LEFT {}", holes), Color::WHITE),
+        ];
+        for (i, (line, color)) in legend.into_iter().enumerate() {
+            canvas.draw(
+                &ui_text(line, 12.),
+                DrawParam::default()
+                    .dest(Point2 {
+                        x: 240.,
+                        y: area.y + i as f32 * 16.,
+                    })
+                    .color(color),
+            );
+        }
+    }
+}
+
+impl Scene for ResultsScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        let gamepad = &shared.gamepad;
+        let tap = shared.touch.tapped();
+        if confirm_pressed(ctx, gamepad) || tap.is_some_and(|tap| Self::RETRY_BUTTON.contains(tap))
+        {
+            return Ok(Transition::Switch(Box::new(Gameplay::new(
+                ctx,
+                shared,
+                self.summary.mode,
+            ))));
+        }
+        if back_pressed(ctx, gamepad) || tap.is_some_and(|tap| Self::MENU_BUTTON.contains(tap)) {
+            return Ok(Transition::Switch(Box::new(MenuScene::new(ctx))));
+        }
+        let count = ResultsPage::ALL.len();
+        if menu_pressed(ctx, gamepad, VirtualKeyCode::Left, Button::DPadLeft) {
+            self.page = (self.page + count - 1) % count;
+        }
+        if menu_pressed(ctx, gamepad, VirtualKeyCode::Right, Button::DPadRight) {
+            self.page = (self.page + 1) % count;
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        let locale = &shared.locale;
+        canvas.draw(
+            &ui_text(locale.text("results-title"), 32.),
+            DrawParam::default().dest(Point2 { x: 120., y: 16. }),
+        );
+        canvas.draw(
+            &ui_text(locale.mode_name(self.summary.mode), 16.),
+            DrawParam::default().dest(Point2 { x: 120., y: 56. }),
+        );
+        let page = ResultsPage::ALL[self.page];
+        canvas.draw(
+            &ui_text(format!("< {} >", page.name(locale)), 12.),
+            DrawParam::default().dest(Point2 { x: 224., y: 60. }),
+        );
+        match page {
+            ResultsPage::Stats => {
+                let columns = [
+                    (48., self.clear_lines(locale)),
+                    (224., self.score_lines(locale)),
+                ];
+                for (x, lines) in columns {
+                    for (i, line) in lines.into_iter().enumerate() {
+                        canvas.draw(
+                            &ui_text(line, 12.),
+                            DrawParam::default().dest(Point2 {
+                                x,
+                                y: 84. + i as f32 * 16.,
+                            }),
+                        );
+                    }
+                }
+            }
+            ResultsPage::Heatmap => Self::draw_heatmap(canvas, &self.heatmap, locale),
+            ResultsPage::SessionHeatmap => Self::draw_heatmap(canvas, &shared.heatmap, locale),
+        }
+        if shared.touch.used {
+            let assets = &shared.assets;
+            draw_button(
+                canvas,
+                assets,
+                Self::RETRY_BUTTON,
+                locale.text("results-retry"),
+            );
+            draw_button(
+                canvas,
+                assets,
+                Self::MENU_BUTTON,
+                locale.text("results-menu"),
+            );
+        } else {
+            canvas.draw(
+                &ui_text(locale.text("results-keys"), 12.),
+                DrawParam::default().dest(Point2 { x: 120., y: 276. }),
+            );
+        }
+        Ok(())
+    }
+}
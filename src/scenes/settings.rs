@@ -0,0 +1,537 @@
+use ggez::{
+    event::Button,
+    glam::*,
+    graphics::{self, DrawParam},
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use std::time::Duration;
+
+use enum_map::Enum;
+use tetris_core::{Action, BoardSize, RotationSystemKind};
+
+use super::{
+    back_pressed, confirm_pressed, draw_options, menu::MenuScene, menu_pressed, navigate_options,
+    tap_option, tuning::TuningScene, Scene, Shared, Transition,
+};
+use crate::{
+    audio::Mixer,
+    input::KeyBindings,
+    locale::Locale,
+    render::ui_text,
+    settings::{Config, HandlingPreset, Settings},
+    theme::Theme,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SettingsOption {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    Mute,
+    ShuffleMusic,
+    GhostPiece,
+    PlacementHints,
+    GridLines,
+    NextCount,
+    Hold,
+    ScreenShake,
+    LiveStats,
+    PieceStats,
+    FinesseRedo,
+    HandlingPreset,
+    TuneHandling,
+    Are,
+    LineClearDelay,
+    InitialRotation,
+    InitialHold,
+    RotationSystem,
+    BlockOut,
+    LockOut,
+    GarbageOut,
+    TouchButtons,
+    BoardSize,
+    Theme,
+    Language,
+    Controls,
+    Back,
+    MainMenu,
+}
+
+impl SettingsOption {
+    /// Whether this can't change in the middle of a game: the falling piece would have to be
+    /// turned another system's way, and the board is already laid out. The rest of the
+    /// handling can, as replays record when it changes.
+    fn fixed_in_game(self) -> bool {
+        matches!(
+            self,
+            SettingsOption::RotationSystem | SettingsOption::BoardSize
+        )
+    }
+
+    fn label(self, settings: &Settings, in_game: bool, locale: &Locale) -> String {
+        let percent = |volume: f32| (volume * 100.).round() as u32;
+        let handling = &settings.handling;
+        match self {
+            SettingsOption::MasterVolume => {
+                locale.format("settings-volume", &[&percent(settings.mixer.master)])
+            }
+            SettingsOption::MusicVolume => {
+                locale.format("settings-music", &[&percent(settings.mixer.music)])
+            }
+            SettingsOption::SfxVolume => {
+                locale.format("settings-sfx", &[&percent(settings.mixer.sfx)])
+            }
+            SettingsOption::Mute => locale.toggle("settings-mute", settings.mixer.muted),
+            SettingsOption::ShuffleMusic => {
+                locale.toggle("settings-shuffle", settings.shuffle_music)
+            }
+            SettingsOption::GhostPiece => locale.toggle("settings-ghost", settings.ghost_piece),
+            SettingsOption::PlacementHints => {
+                locale.toggle("settings-hints", settings.placement_hints)
+            }
+            SettingsOption::GridLines => locale.toggle("settings-grid", settings.grid_lines),
+            SettingsOption::NextCount => locale.format("settings-next", &[&settings.next_count]),
+            SettingsOption::Hold => locale.toggle("settings-hold", handling.hold),
+            SettingsOption::ScreenShake => locale.toggle("settings-shake", settings.screen_shake),
+            SettingsOption::LiveStats => locale.toggle("settings-live-stats", settings.live_stats),
+            SettingsOption::PieceStats => {
+                locale.toggle("settings-piece-stats", settings.piece_stats)
+            }
+            SettingsOption::FinesseRedo => {
+                locale.toggle("settings-finesse-redo", settings.finesse_redo)
+            }
+            SettingsOption::HandlingPreset => {
+                locale.format("settings-handling", &[&settings.handling_preset.name()])
+            }
+            SettingsOption::TuneHandling => locale.text("settings-tune-handling").to_owned(),
+            SettingsOption::Are => locale.format("settings-are", &[&handling.are.as_millis()]),
+            SettingsOption::LineClearDelay => locale.format(
+                "settings-clear-delay",
+                &[&handling.line_clear_delay.as_millis()],
+            ),
+            SettingsOption::InitialRotation => {
+                locale.toggle("settings-irs", handling.initial_rotation)
+            }
+            SettingsOption::InitialHold => locale.toggle("settings-ihs", handling.initial_hold),
+            SettingsOption::RotationSystem => {
+                locale.format("settings-rotation", &[&handling.rotation_system.name()])
+            }
+            SettingsOption::BlockOut => {
+                locale.toggle("settings-block-out", handling.top_out.block_out)
+            }
+            SettingsOption::LockOut => {
+                locale.toggle("settings-lock-out", handling.top_out.lock_out)
+            }
+            SettingsOption::GarbageOut => {
+                locale.toggle("settings-garbage-out", handling.top_out.garbage_out)
+            }
+            SettingsOption::TouchButtons => {
+                locale.toggle("settings-touch-buttons", settings.touch_buttons)
+            }
+            SettingsOption::BoardSize => locale.format(
+                "settings-board",
+                &[&settings.board_size.width, &settings.board_size.height],
+            ),
+            SettingsOption::Theme if settings.theme.is_empty() => {
+                locale.format("settings-theme", &[&locale.text("settings-theme-default")])
+            }
+            SettingsOption::Theme => {
+                locale.format("settings-theme", &[&settings.theme.to_uppercase()])
+            }
+            SettingsOption::Language => {
+                locale.format("settings-language", &[&locale.text("language")])
+            }
+            SettingsOption::Controls => locale.text("settings-controls").to_owned(),
+            SettingsOption::Back if in_game => locale.text("settings-resume").to_owned(),
+            SettingsOption::Back => locale.text("back").to_owned(),
+            SettingsOption::MainMenu => locale.text("settings-main-menu").to_owned(),
+        }
+    }
+
+    /// Changes the setting by one step in the given direction (-1 or 1).
+    fn adjust(self, settings: &mut Settings, direction: i32) {
+        let duration = |duration: &mut Duration, step: u64, max: u64| {
+            let millis = duration.as_millis() as i64 + direction as i64 * step as i64;
+            *duration = Duration::from_millis(millis.clamp(0, max as i64) as u64);
+        };
+        match self {
+            SettingsOption::MasterVolume => Mixer::adjust(&mut settings.mixer.master, direction),
+            SettingsOption::MusicVolume => Mixer::adjust(&mut settings.mixer.music, direction),
+            SettingsOption::SfxVolume => Mixer::adjust(&mut settings.mixer.sfx, direction),
+            SettingsOption::Mute => settings.mixer.muted = !settings.mixer.muted,
+            SettingsOption::ShuffleMusic => settings.shuffle_music = !settings.shuffle_music,
+            SettingsOption::GhostPiece => settings.ghost_piece = !settings.ghost_piece,
+            SettingsOption::PlacementHints => {
+                settings.placement_hints = !settings.placement_hints;
+            }
+            SettingsOption::GridLines => settings.grid_lines = !settings.grid_lines,
+            SettingsOption::NextCount => {
+                settings.next_count = (settings.next_count as i32 + direction)
+                    .clamp(0, Settings::MAX_NEXT_COUNT as i32)
+                    as usize;
+            }
+            SettingsOption::Hold => settings.handling.hold = !settings.handling.hold,
+            SettingsOption::ScreenShake => settings.screen_shake = !settings.screen_shake,
+            SettingsOption::LiveStats => settings.live_stats = !settings.live_stats,
+            SettingsOption::PieceStats => settings.piece_stats = !settings.piece_stats,
+            SettingsOption::FinesseRedo => settings.finesse_redo = !settings.finesse_redo,
+            SettingsOption::HandlingPreset => {
+                let presets = &HandlingPreset::ALL;
+                let i = presets
+                    .iter()
+                    .position(|&preset| preset == settings.handling_preset)
+                    .unwrap_or(0) as i32;
+                settings.set_handling_preset(
+                    presets[(i + direction).rem_euclid(presets.len() as i32) as usize],
+                );
+            }
+            SettingsOption::Are => {
+                duration(&mut settings.handling.are, 10, 500);
+                settings.customize_handling();
+            }
+            SettingsOption::LineClearDelay => {
+                duration(&mut settings.handling.line_clear_delay, 50, 1000);
+                settings.customize_handling();
+            }
+            SettingsOption::InitialRotation => {
+                settings.handling.initial_rotation = !settings.handling.initial_rotation;
+            }
+            SettingsOption::InitialHold => {
+                settings.handling.initial_hold = !settings.handling.initial_hold;
+            }
+            SettingsOption::RotationSystem => {
+                let systems = &RotationSystemKind::ALL;
+                let i = systems
+                    .iter()
+                    .position(|&system| system == settings.handling.rotation_system)
+                    .unwrap_or(0) as i32;
+                settings.handling.rotation_system =
+                    systems[(i + direction).rem_euclid(systems.len() as i32) as usize];
+            }
+            SettingsOption::BlockOut => {
+                let top_out = &mut settings.handling.top_out;
+                top_out.block_out = !top_out.block_out;
+            }
+            SettingsOption::LockOut => {
+                let top_out = &mut settings.handling.top_out;
+                top_out.lock_out = !top_out.lock_out;
+            }
+            SettingsOption::GarbageOut => {
+                let top_out = &mut settings.handling.top_out;
+                top_out.garbage_out = !top_out.garbage_out;
+            }
+            SettingsOption::TouchButtons => settings.touch_buttons = !settings.touch_buttons,
+            SettingsOption::BoardSize => {
+                let presets = &BoardSize::PRESETS;
+                let next = match presets.iter().position(|size| *size == settings.board_size) {
+                    Some(i) => (i as i32 + direction).rem_euclid(presets.len() as i32) as usize,
+                    None => 0,
+                };
+                settings.board_size = presets[next];
+            }
+            // Themes and languages have to be looked up and loaded, which `SettingsScene`
+            // does itself.
+            SettingsOption::Theme
+            | SettingsOption::Language
+            | SettingsOption::TuneHandling
+            | SettingsOption::Controls
+            | SettingsOption::Back
+            | SettingsOption::MainMenu => (),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SettingsScene {
+    selected: usize,
+    /// Whether the settings were opened from a running game, which stays paused below.
+    in_game: bool,
+}
+
+impl SettingsScene {
+    const OPTIONS_ORIGIN: Point2<f32> = Point2 { x: 120., y: 72. };
+
+    pub fn in_game() -> Self {
+        Self {
+            selected: 0,
+            in_game: true,
+        }
+    }
+
+    /// Switches to the next theme in the given direction (-1 or 1), with the built-in
+    /// assets coming first. Themes that fail to load are left out.
+    fn change_theme(ctx: &mut Context, shared: &mut Shared, direction: i32) {
+        let mut themes = vec![String::new()];
+        themes.extend(Theme::list(ctx));
+        let current = themes
+            .iter()
+            .position(|theme| *theme == shared.settings.theme)
+            .unwrap_or(0);
+        let previous = shared.settings.theme.clone();
+        for i in 1..themes.len() {
+            let next = (current as i32 + direction * i as i32).rem_euclid(themes.len() as i32);
+            shared.settings.theme = themes[next as usize].clone();
+            if shared.load_theme(ctx).is_ok() {
+                let _ = Config::from_settings(&shared.settings).save(ctx);
+                return;
+            }
+        }
+        shared.settings.theme = previous;
+    }
+
+    /// Switches to the next language in the given direction (-1 or 1), with English coming
+    /// first. Languages that fail to load are left out.
+    fn change_language(ctx: &mut Context, shared: &mut Shared, direction: i32) {
+        let mut languages = vec![String::new()];
+        languages.extend(Locale::list(ctx));
+        let current = languages
+            .iter()
+            .position(|language| *language == shared.settings.language)
+            .unwrap_or(0);
+        let previous = shared.settings.language.clone();
+        for i in 1..languages.len() {
+            let next = (current as i32 + direction * i as i32).rem_euclid(languages.len() as i32);
+            shared.settings.language = languages[next as usize].clone();
+            if shared.load_locale(ctx).is_ok() {
+                let _ = Config::from_settings(&shared.settings).save(ctx);
+                return;
+            }
+        }
+        shared.settings.language = previous;
+    }
+
+    fn options(&self) -> Vec<SettingsOption> {
+        use SettingsOption::*;
+        let mut options = vec![
+            MasterVolume,
+            MusicVolume,
+            SfxVolume,
+            Mute,
+            ShuffleMusic,
+            GhostPiece,
+            PlacementHints,
+            GridLines,
+            NextCount,
+            Hold,
+            ScreenShake,
+            LiveStats,
+            PieceStats,
+            FinesseRedo,
+            HandlingPreset,
+            TuneHandling,
+            Are,
+            LineClearDelay,
+            InitialRotation,
+            InitialHold,
+            RotationSystem,
+            BlockOut,
+            LockOut,
+            GarbageOut,
+            TouchButtons,
+            BoardSize,
+            Language,
+            Controls,
+            Back,
+        ];
+        if self.in_game {
+            options.retain(|option| !option.fixed_in_game());
+            options.push(MainMenu);
+        } else {
+            // The game below would keep drawing with the old theme's textures.
+            options.insert(options.len() - 3, Theme);
+        }
+        options
+    }
+}
+
+impl Scene for SettingsScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        let options = self.options();
+        let gamepad = &shared.gamepad;
+        navigate_options(ctx, gamepad, &mut self.selected, options.len());
+        if back_pressed(ctx, gamepad) {
+            return Ok(Transition::Pop);
+        }
+        let confirmed = confirm_pressed(ctx, gamepad)
+            || tap_option(
+                &shared.touch,
+                &mut self.selected,
+                options.len(),
+                Self::OPTIONS_ORIGIN,
+            );
+        let option = options[self.selected];
+        let direction = if menu_pressed(ctx, gamepad, VirtualKeyCode::Left, Button::DPadLeft) {
+            -1
+        } else if menu_pressed(ctx, gamepad, VirtualKeyCode::Right, Button::DPadRight) || confirmed
+        {
+            1
+        } else {
+            0
+        };
+        if direction != 0 && option == SettingsOption::Theme {
+            Self::change_theme(ctx, shared, direction);
+        } else if direction != 0 && option == SettingsOption::Language {
+            Self::change_language(ctx, shared, direction);
+        } else if direction != 0 {
+            option.adjust(&mut shared.settings, direction);
+            shared.settings.apply(&mut shared.audio);
+            let _ = Config::from_settings(&shared.settings).save(ctx);
+        }
+        if confirmed {
+            match option {
+                SettingsOption::TuneHandling => {
+                    return Ok(Transition::Push(Box::new(TuningScene::new(ctx, shared))))
+                }
+                SettingsOption::Controls => {
+                    return Ok(Transition::Push(Box::new(KeyBindingsScene::default())))
+                }
+                SettingsOption::Back => return Ok(Transition::Pop),
+                SettingsOption::MainMenu => {
+                    return Ok(Transition::Switch(Box::new(MenuScene::new(ctx))))
+                }
+                _ => (),
+            }
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        canvas.draw(
+            &ui_text(shared.locale.text("settings-title"), 32.),
+            DrawParam::default().dest(Point2 { x: 120., y: 24. }),
+        );
+        let options: Vec<_> = self
+            .options()
+            .into_iter()
+            .map(|option| option.label(&shared.settings, self.in_game, &shared.locale))
+            .collect();
+        draw_options(
+            canvas,
+            shared,
+            &options,
+            self.selected,
+            Self::OPTIONS_ORIGIN,
+        );
+        Ok(())
+    }
+}
+
+/// Lists every action with its key, and lets the player rebind them.
+#[derive(Default)]
+pub struct KeyBindingsScene {
+    selected: usize,
+    /// Whether the next key pressed will be bound to the selected action.
+    waiting_for_key: bool,
+}
+
+impl KeyBindingsScene {
+    const RESET_OPTION: usize = Action::LENGTH;
+    const BACK_OPTION: usize = Action::LENGTH + 1;
+    const OPTIONS_ORIGIN: Point2<f32> = Point2 { x: 100., y: 64. };
+}
+
+impl Scene for KeyBindingsScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        if self.waiting_for_key {
+            if ctx.keyboard.is_key_just_pressed(VirtualKeyCode::Escape) {
+                self.waiting_for_key = false;
+            } else if let Some(key) = ctx
+                .keyboard
+                .pressed_keys()
+                .iter()
+                .find(|key| ctx.keyboard.is_key_just_pressed(**key))
+            {
+                shared
+                    .settings
+                    .key_bindings
+                    .bind(Action::from_usize(self.selected), *key);
+                let _ = Config::from_settings(&shared.settings).save(ctx);
+                self.waiting_for_key = false;
+            }
+            return Ok(Transition::None);
+        }
+
+        navigate_options(
+            ctx,
+            &shared.gamepad,
+            &mut self.selected,
+            Self::BACK_OPTION + 1,
+        );
+        if back_pressed(ctx, &shared.gamepad) {
+            return Ok(Transition::Pop);
+        }
+        let tapped = tap_option(
+            &shared.touch,
+            &mut self.selected,
+            Self::BACK_OPTION + 1,
+            Self::OPTIONS_ORIGIN,
+        );
+        if confirm_pressed(ctx, &shared.gamepad) || tapped {
+            match self.selected {
+                Self::RESET_OPTION => {
+                    shared.settings.key_bindings = KeyBindings::default();
+                    let _ = Config::from_settings(&shared.settings).save(ctx);
+                }
+                Self::BACK_OPTION => return Ok(Transition::Pop),
+                _ => self.waiting_for_key = true,
+            }
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        canvas.draw(
+            &ui_text(shared.locale.text("controls-title"), 32.),
+            DrawParam::default().dest(Point2 { x: 120., y: 16. }),
+        );
+        let mut options: Vec<_> = (0..Action::LENGTH)
+            .map(Action::from_usize)
+            .map(|action| {
+                let key = if self.waiting_for_key && action == Action::from_usize(self.selected) {
+                    "...".to_owned()
+                } else {
+                    format!("{:?}", shared.settings.key_bindings.key(action)).to_uppercase()
+                };
+                format!("{} {}", action_label(&shared.locale, action), key)
+            })
+            .collect();
+        options.push(shared.locale.text("controls-reset").to_owned());
+        options.push(shared.locale.text("back").to_owned());
+        draw_options(
+            canvas,
+            shared,
+            &options,
+            self.selected,
+            Self::OPTIONS_ORIGIN,
+        );
+        Ok(())
+    }
+}
+
+fn action_label(locale: &Locale, action: Action) -> &str {
+    locale.text(match action {
+        Action::MoveLeft => "action-move-left",
+        Action::MoveRight => "action-move-right",
+        Action::SoftDrop => "action-soft-drop",
+        Action::HardDrop => "action-hard-drop",
+        Action::RotateCw => "action-rotate-cw",
+        Action::RotateCcw => "action-rotate-ccw",
+        Action::Rotate180 => "action-rotate-180",
+        Action::Hold => "action-hold",
+        Action::Pause => "action-pause",
+        Action::Restart => "action-restart",
+        Action::Undo => "action-undo",
+    })
+}
@@ -0,0 +1,241 @@
+use ggez::{
+    event::Button,
+    graphics::{self, DrawParam, Rect},
+    mint::Point2,
+    winit::event::VirtualKeyCode,
+    Context, GameResult,
+};
+use rand::Rng;
+use tetris_core::{Action, BoardSize, Game, GameMode, SoftDrop};
+
+use super::{
+    back_pressed, confirm_pressed, draw_options, gameplay::Gameplay, menu_pressed,
+    navigate_options, tap_option, Scene, Shared, Transition,
+};
+use crate::{
+    input::Controls,
+    render::ui_text,
+    settings::{Config, HandlingPreset, Tuning},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TuningOption {
+    Das,
+    Arr,
+    Dcd,
+    SoftDrop,
+    Are,
+    LineClearDelay,
+    Try,
+    Save,
+    Back,
+}
+
+impl TuningOption {
+    const ALL: [TuningOption; 9] = [
+        TuningOption::Das,
+        TuningOption::Arr,
+        TuningOption::Dcd,
+        TuningOption::SoftDrop,
+        TuningOption::Are,
+        TuningOption::LineClearDelay,
+        TuningOption::Try,
+        TuningOption::Save,
+        TuningOption::Back,
+    ];
+    /// Soft drop speeds to pick from, slowest first.
+    const SOFT_DROP_SPEEDS: [SoftDrop; 6] = [
+        SoftDrop::Factor(2),
+        SoftDrop::Factor(5),
+        SoftDrop::Factor(10),
+        SoftDrop::Factor(20),
+        SoftDrop::Factor(40),
+        SoftDrop::Instant,
+    ];
+
+    fn label(self, tuning: Tuning) -> String {
+        match self {
+            TuningOption::Das => format!("DAS {}MS", tuning.das),
+            TuningOption::Arr => format!("ARR {}MS", tuning.arr),
+            TuningOption::Dcd => format!("DCD {}MS", tuning.dcd),
+            TuningOption::SoftDrop => match SoftDrop::from_bits(tuning.soft_drop) {
+                SoftDrop::Factor(factor) => format!("SOFT DROP {}X", factor),
+                SoftDrop::Instant => "SOFT DROP INSTANT".to_owned(),
+            },
+            TuningOption::Are => format!("ARE {}MS", tuning.are),
+            TuningOption::LineClearDelay => format!("LINE CLEAR {}MS", tuning.line_clear_delay),
+            TuningOption::Try => "TRY IT".to_owned(),
+            TuningOption::Save => "SAVE".to_owned(),
+            TuningOption::Back => "BACK".to_owned(),
+        }
+    }
+
+    /// Changes the timing by one step in the given direction (-1 or 1).
+    fn adjust(self, tuning: &mut Tuning, direction: i32) {
+        let millis = |millis: &mut u64, step: i64, max: i64| {
+            *millis = (*millis as i64 + direction as i64 * step).clamp(0, max) as u64;
+        };
+        match self {
+            TuningOption::Das => millis(&mut tuning.das, 10, 500),
+            TuningOption::Arr => millis(&mut tuning.arr, 5, 200),
+            TuningOption::Dcd => millis(&mut tuning.dcd, 5, 200),
+            TuningOption::SoftDrop => {
+                let speeds = &Self::SOFT_DROP_SPEEDS;
+                let i = speeds
+                    .iter()
+                    .position(|speed| speed.to_bits() == tuning.soft_drop)
+                    .unwrap_or(2) as i32;
+                let i = (i + direction).clamp(0, speeds.len() as i32 - 1);
+                tuning.soft_drop = speeds[i as usize].to_bits();
+            }
+            TuningOption::Are => millis(&mut tuning.are, 10, 500),
+            TuningOption::LineClearDelay => millis(&mut tuning.line_clear_delay, 50, 1000),
+            TuningOption::Try | TuningOption::Save | TuningOption::Back => (),
+        }
+    }
+}
+
+/// Adjusts DAS, ARR, DCD, soft drop, ARE and line clear delay next to a board to try them out on, and saves them
+/// as the custom handling preset.
+pub struct TuningScene {
+    selected: usize,
+    /// The timings being tried, which only replace the player's once saved.
+    tuning: Tuning,
+    gameplay: Gameplay,
+    /// Whether the controls play the board rather than pick options, until paused.
+    trying: bool,
+    pending_presses: u16,
+}
+
+impl TuningScene {
+    const OPTIONS_ORIGIN: Point2<f32> = Point2 { x: 40., y: 72. };
+    const BOARD_AREA: Rect = Rect::new(232., 24., 144., 260.);
+
+    pub fn new(ctx: &mut Context, shared: &Shared) -> Self {
+        Self {
+            selected: 0,
+            tuning: Tuning::of(&shared.settings.handling),
+            gameplay: Self::new_game(ctx, shared),
+            trying: false,
+            pending_presses: 0,
+        }
+    }
+
+    fn new_game(ctx: &mut Context, shared: &Shared) -> Gameplay {
+        let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let game = Game::new(GameMode::Endless, seed, BoardSize::default());
+        Gameplay::preview(ctx, shared, game, Self::BOARD_AREA)
+    }
+
+    /// Plays the board with the timings being tried.
+    fn update_trying(&mut self, ctx: &mut Context, shared: &mut Shared) {
+        if self.gameplay.game.is_over() {
+            self.gameplay = Self::new_game(ctx, shared);
+        }
+        let controls = Controls::new(ctx, &shared.settings, &shared.gamepad, &shared.touch);
+        if controls.just_pressed(Action::Pause) || back_pressed(ctx, &shared.gamepad) {
+            self.trying = false;
+            return;
+        }
+        let mut handling = shared.settings.handling;
+        self.tuning.apply(&mut handling);
+
+        let mut input = controls.frame_input();
+        input.just_pressed |= self.pending_presses;
+        self.gameplay
+            .timer
+            .accumulate(ctx.time.delta(), Gameplay::MAX_CATCH_UP);
+        self.pending_presses = input.just_pressed;
+        while !self.gameplay.game.is_over() && self.gameplay.timer.take_step() {
+            self.gameplay.step(ctx, &mut shared.audio, handling, input);
+            input = input.held();
+            self.pending_presses = 0;
+        }
+    }
+}
+
+impl Scene for TuningScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        if self.trying {
+            self.update_trying(ctx, shared);
+            return Ok(Transition::None);
+        }
+        let count = TuningOption::ALL.len();
+        let gamepad = &shared.gamepad;
+        navigate_options(ctx, gamepad, &mut self.selected, count);
+        if back_pressed(ctx, gamepad) {
+            return Ok(Transition::Pop);
+        }
+        let confirmed = confirm_pressed(ctx, gamepad)
+            || tap_option(
+                &shared.touch,
+                &mut self.selected,
+                count,
+                Self::OPTIONS_ORIGIN,
+            );
+        let option = TuningOption::ALL[self.selected];
+        let direction = if menu_pressed(ctx, gamepad, VirtualKeyCode::Left, Button::DPadLeft) {
+            -1
+        } else if menu_pressed(ctx, gamepad, VirtualKeyCode::Right, Button::DPadRight) || confirmed
+        {
+            1
+        } else {
+            0
+        };
+        if direction != 0 {
+            option.adjust(&mut self.tuning, direction);
+        }
+        if confirmed {
+            match option {
+                TuningOption::Try => self.trying = true,
+                TuningOption::Save => {
+                    let settings = &mut shared.settings;
+                    settings.custom_tuning = self.tuning;
+                    settings.set_handling_preset(HandlingPreset::Custom);
+                    let _ = Config::from_settings(settings).save(ctx);
+                    return Ok(Transition::Pop);
+                }
+                TuningOption::Back => return Ok(Transition::Pop),
+                _ => (),
+            }
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        canvas.draw(
+            &ui_text("HANDLING", 32.),
+            DrawParam::default().dest(Point2 { x: 40., y: 24. }),
+        );
+        let options: Vec<_> = TuningOption::ALL
+            .iter()
+            .map(|option| option.label(self.tuning))
+            .collect();
+        draw_options(
+            canvas,
+            shared,
+            &options,
+            self.selected,
+            Self::OPTIONS_ORIGIN,
+        );
+        if self.trying {
+            canvas.draw(
+                &ui_text("PAUSE TO STOP", 12.),
+                DrawParam::default().dest(Point2 { x: 40., y: 260. }),
+            );
+        }
+        let interpolation = self.gameplay.timer.interpolation();
+        self.gameplay.draw_playfield(canvas, shared, interpolation);
+        Ok(())
+    }
+
+    #[cfg(debug_assertions)]
+    fn assets_reloaded(&mut self, ctx: &mut Context, shared: &Shared) {
+        self.gameplay.assets_reloaded(ctx, shared);
+    }
+}
@@ -0,0 +1,601 @@
+use std::collections::VecDeque;
+
+use ggez::{
+    graphics::{self, Color, DrawParam, Quad, Rect, TextAlign, TextLayout},
+    mint::Point2,
+    Context, GameResult,
+};
+use rand::Rng;
+use tetris_core::{Action, BoardSize, Bot, Difficulty, FrameInput, Game, GameEvent, Handling};
+
+use super::{
+    back_pressed, confirm_pressed,
+    gameplay::{Gameplay, StepTimer},
+    menu::MenuScene,
+    settings::SettingsScene,
+    Scene, Shared, Transition,
+};
+use crate::{
+    input::{Controls, KeyBindings},
+    net::{Connection, Message},
+    render::ui_text,
+    SCREEN,
+};
+
+/// Who the player on the right is playing against.
+enum Opponent {
+    /// Another player sharing the keyboard, on the left, with its own keys.
+    Local(KeyBindings),
+    /// A player over the network, whose game is simulated here too from their inputs.
+    Remote(Box<RemoteOpponent>),
+    /// The computer, playing with the same pieces.
+    Bot(Box<Bot>),
+}
+
+/// Both games as they were before a step, and the inputs it was simulated with.
+struct Snapshot {
+    games: [Game; 2],
+    inputs: [FrameInput; 2],
+}
+
+/// Keeps both games of an online match in sync without waiting for the other player: steps
+/// are simulated right away with a guess of their input, and once it arrives, the games are
+/// rolled back to before the first wrong guess and simulated again. Local inputs are sent
+/// `INPUT_DELAY` steps ahead of when they're simulated, so that the other player's guesses
+/// are usually right.
+struct RemoteOpponent {
+    connection: Connection,
+    /// The other player's handling, which their game is simulated with.
+    handling: Handling,
+    /// This player's handling as it was told to the other player, who simulates this
+    /// player's game with it, so it's kept even if the settings change during the match.
+    local_handling: Handling,
+    seed: u32,
+    board_size: BoardSize,
+    /// Local inputs that haven't been simulated yet, oldest first.
+    local_inputs: VecDeque<FrameInput>,
+    /// The other player's inputs from the oldest step that was only guessed on, including
+    /// those of steps that weren't simulated yet.
+    remote_inputs: VecDeque<FrameInput>,
+    /// The other player's input for the last step that's known for sure.
+    last_confirmed: FrameInput,
+    /// Steps simulated with a guess of the other player's input, oldest first, which can
+    /// still be rolled back.
+    unconfirmed: VecDeque<Snapshot>,
+    /// Step of the next input received from the other player.
+    remote_frame: u32,
+    /// Step of the next local input sent.
+    local_frame: u32,
+    /// Whether each player asked for a rematch, this player first.
+    rematch: [bool; 2],
+    /// Whether the connection was lost, which ends the match.
+    disconnected: bool,
+}
+
+impl RemoteOpponent {
+    /// Steps inputs are delayed by, so that they usually arrive before they have to be
+    /// guessed.
+    const INPUT_DELAY: u32 = 2;
+    /// How many steps can be simulated on guesses before waiting for the other player.
+    const MAX_ROLLBACK: usize = 30;
+
+    /// Starts a match over, both sides beginning with `INPUT_DELAY` steps of no input.
+    fn reset(&mut self) {
+        let delay = (0..Self::INPUT_DELAY).map(|_| FrameInput::default());
+        self.local_inputs = delay.clone().collect();
+        self.remote_inputs = delay.collect();
+        self.last_confirmed = FrameInput::default();
+        self.unconfirmed.clear();
+        self.local_frame = Self::INPUT_DELAY;
+        self.remote_frame = Self::INPUT_DELAY;
+        self.rematch = [false; 2];
+    }
+
+    /// Sends and receives messages, noting if the other player is gone.
+    fn poll(&mut self) {
+        let Ok(messages) = self.connection.poll() else {
+            self.disconnected = true;
+            return;
+        };
+        for message in messages {
+            match message {
+                Message::Input { frame, input } if frame == self.remote_frame => {
+                    self.remote_inputs.push_back(input);
+                    self.remote_frame += 1;
+                }
+                // Inputs arrive in order, so one out of place means the games fell apart.
+                Message::Input { .. } => self.disconnected = true,
+                Message::Rematch => self.rematch[1] = true,
+                Message::Hello { .. } => (),
+            }
+        }
+    }
+
+    /// Sends the input of this step, and returns the one to simulate now, which was sent
+    /// `INPUT_DELAY` steps ago.
+    fn send_input(&mut self, input: FrameInput) -> FrameInput {
+        self.connection.send(Message::Input {
+            frame: self.local_frame,
+            input,
+        });
+        self.local_inputs.push_back(input);
+        self.local_frame += 1;
+        self.local_inputs.pop_front().unwrap_or_default()
+    }
+
+    /// The other player's input for the step `ahead` steps after the oldest unconfirmed
+    /// one. Until it arrives, they're guessed to keep holding what they held last, since
+    /// inputs rarely change from one step to the next.
+    fn remote_input(&self, ahead: usize) -> FrameInput {
+        self.remote_inputs.get(ahead).copied().unwrap_or_else(|| {
+            self.remote_inputs
+                .back()
+                .unwrap_or(&self.last_confirmed)
+                .held()
+        })
+    }
+
+    /// Checks the guesses made against the inputs that arrived since, and simulates the
+    /// games again from the first wrong guess on. Returns the games as they should be now
+    /// if any guess was wrong.
+    fn reconcile(&mut self, handling: [Handling; 2]) -> Option<[Game; 2]> {
+        let first_wrong = (0..self.unconfirmed.len())
+            .find(|&i| self.unconfirmed[i].inputs[1] != self.remote_input(i));
+        let games = first_wrong.map(|first| {
+            let mut games = self.unconfirmed[first].games.clone();
+            for i in first..self.unconfirmed.len() {
+                let input = self.remote_input(i);
+                let snapshot = &mut self.unconfirmed[i];
+                snapshot.games = games.clone();
+                snapshot.inputs[1] = input;
+                step_games(&mut games, snapshot.inputs, handling);
+            }
+            games
+        });
+
+        // Steps whose inputs are all known can't be rolled back anymore.
+        let confirmed = self.unconfirmed.len().min(self.remote_inputs.len());
+        self.unconfirmed.drain(..confirmed);
+        if let Some(last) = self.remote_inputs.drain(..confirmed).next_back() {
+            self.last_confirmed = last;
+        }
+        games
+    }
+
+    /// Whether this player is waiting for the other's inputs to catch up.
+    fn is_waiting(&self) -> bool {
+        self.unconfirmed.len() >= Self::MAX_ROLLBACK
+    }
+}
+
+/// Rows of garbage sent during a step.
+fn garbage_sent(events: &[GameEvent]) -> u32 {
+    events
+        .iter()
+        .map(|event| match event {
+            GameEvent::GarbageSent { rows } => *rows,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Steps both games like `VersusScene::step` does, but without showing anything, for
+/// simulating steps again after a rollback.
+fn step_games(games: &mut [Game; 2], inputs: [FrameInput; 2], handling: [Handling; 2]) {
+    let sent = [0, 1].map(|i| garbage_sent(&games[i].step(handling[i], inputs[i])));
+    games[1].receive_garbage(sent[0]);
+    games[0].receive_garbage(sent[1]);
+}
+
+/// Two players, each with a board of their own. Clearing rows sends garbage to the other
+/// player, and the first to top out loses.
+///
+/// In a local match, the player on the left uses the keys on the left of the keyboard, and
+/// the one on the right uses the usual key bindings and the gamepads. Online or against the
+/// computer, this player is on the left with every control.
+pub struct VersusScene {
+    players: [Gameplay; 2],
+    timer: StepTimer,
+    /// Presses that haven't been simulated yet, as in `Gameplay`.
+    pending_presses: [u16; 2],
+    opponent: Opponent,
+    /// Matches won by each player since the scene was opened.
+    wins: [u32; 2],
+}
+
+impl VersusScene {
+    /// Space each player's board is fit in, leaving room above for their names.
+    const BOARD_AREAS: [Rect; 2] = [
+        Rect::new(24., 24., 168., 260.),
+        Rect::new(208., 24., 168., 260.),
+    ];
+
+    pub fn new(ctx: &mut Context, shared: &Shared) -> Self {
+        let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        Self {
+            players: Self::new_match(ctx, shared, seed, shared.settings.board_size),
+            timer: StepTimer::default(),
+            pending_presses: [0; 2],
+            opponent: Opponent::Local(KeyBindings::left_hand()),
+            wins: [0; 2],
+        }
+    }
+
+    /// A match against the computer, playing at `difficulty`.
+    pub fn bot(ctx: &mut Context, shared: &Shared, difficulty: Difficulty) -> Self {
+        let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        Self {
+            players: Self::new_match(ctx, shared, seed, shared.settings.board_size),
+            timer: StepTimer::default(),
+            pending_presses: [0; 2],
+            opponent: Opponent::Bot(Box::new(Bot::new(difficulty, seed))),
+            wins: [0; 2],
+        }
+    }
+
+    /// A match against a player over `connection`, who plays with `handling`. Both sides
+    /// have to start with the same `seed` and `board_size`.
+    pub fn online(
+        ctx: &mut Context,
+        shared: &Shared,
+        connection: Connection,
+        seed: u32,
+        board_size: BoardSize,
+        handling: Handling,
+    ) -> Self {
+        let mut remote = RemoteOpponent {
+            connection,
+            handling,
+            local_handling: shared.settings.handling,
+            seed,
+            board_size,
+            local_inputs: VecDeque::new(),
+            remote_inputs: VecDeque::new(),
+            last_confirmed: FrameInput::default(),
+            unconfirmed: VecDeque::new(),
+            remote_frame: 0,
+            local_frame: 0,
+            rematch: [false; 2],
+            disconnected: false,
+        };
+        remote.reset();
+        Self {
+            players: Self::new_match(ctx, shared, seed, board_size),
+            timer: StepTimer::default(),
+            pending_presses: [0; 2],
+            opponent: Opponent::Remote(Box::new(remote)),
+            wins: [0; 2],
+        }
+    }
+
+    /// Both players' games, which get the same pieces in the same order to be fair.
+    fn new_match(
+        ctx: &mut Context,
+        shared: &Shared,
+        seed: u32,
+        board_size: BoardSize,
+    ) -> [Gameplay; 2] {
+        Self::BOARD_AREAS.map(|area| Gameplay::versus(ctx, shared, seed, board_size, area))
+    }
+
+    fn names(&self) -> [&'static str; 2] {
+        match self.opponent {
+            Opponent::Local(_) => ["P1", "P2"],
+            Opponent::Remote(_) => ["YOU", "RIVAL"],
+            Opponent::Bot(_) => ["YOU", "CPU"],
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        self.players.iter().any(|player| player.game.is_over())
+    }
+
+    /// Whether the match is over for sure, and not only on a guess of the other player's
+    /// inputs that might still be rolled back.
+    fn is_decided(&self) -> bool {
+        self.is_over()
+            && !matches!(&self.opponent, Opponent::Remote(remote) if !remote.unconfirmed.is_empty())
+    }
+
+    /// The player that won the match, or `None` for a draw. Only meaningful once it's over.
+    fn winner(&self) -> Option<usize> {
+        match self.players.each_ref().map(|player| player.game.is_over()) {
+            [false, true] => Some(0),
+            [true, false] => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Steps both games with the given inputs and handling, then hands each the garbage
+    /// the other sent. Garbage is only handed over once both have stepped, so neither gets
+    /// it first.
+    fn step(
+        players: &mut [Gameplay; 2],
+        ctx: &Context,
+        shared: &mut Shared,
+        inputs: [FrameInput; 2],
+        handling: [Handling; 2],
+    ) {
+        let mut sent = [0; 2];
+        for (i, player) in players.iter_mut().enumerate() {
+            sent[i] = garbage_sent(&player.step(ctx, &mut shared.audio, handling[i], inputs[i]));
+        }
+        players[1].game.receive_garbage(sent[0]);
+        players[0].game.receive_garbage(sent[1]);
+    }
+
+    /// Starts the next match once it's over: right away locally, and once both players
+    /// asked for it online.
+    fn update_rematch(&mut self, ctx: &mut Context, shared: &Shared) {
+        let requested = confirm_pressed(ctx, &shared.gamepad);
+        let (seed, board_size) = match &mut self.opponent {
+            Opponent::Local(_) if requested => (
+                shared.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+                shared.settings.board_size,
+            ),
+            Opponent::Local(_) => return,
+            Opponent::Bot(bot) if requested => {
+                let seed = shared.seed.unwrap_or_else(|| rand::thread_rng().gen());
+                **bot = Bot::new(bot.difficulty(), seed);
+                (seed, shared.settings.board_size)
+            }
+            Opponent::Bot(_) => return,
+            Opponent::Remote(remote) => {
+                if requested && !remote.rematch[0] {
+                    remote.rematch[0] = true;
+                    remote.connection.send(Message::Rematch);
+                }
+                if remote.rematch != [true; 2] {
+                    return;
+                }
+                // Both sides move on to the same seed without having to agree on one.
+                remote.seed = remote.seed.wrapping_add(1);
+                remote.reset();
+                (remote.seed, remote.board_size)
+            }
+        };
+        self.players = Self::new_match(ctx, shared, seed, board_size);
+        self.timer = StepTimer::default();
+        self.pending_presses = [0; 2];
+    }
+
+    /// Steps both games as real time passes, returning whether either player paused.
+    fn update_local(&mut self, ctx: &Context, shared: &mut Shared) -> bool {
+        let Opponent::Local(left_keys) = &self.opponent else {
+            return false;
+        };
+        let controls = [
+            Controls::keyboard(ctx, left_keys),
+            Controls::new(ctx, &shared.settings, &shared.gamepad, &shared.touch).without_touch(),
+        ];
+        let paused = controls
+            .iter()
+            .any(|controls| controls.just_pressed(Action::Pause));
+        let mut inputs = [0, 1].map(|i| {
+            let mut input = controls[i].frame_input();
+            input.just_pressed |= self.pending_presses[i];
+            input
+        });
+        let handling = [shared.settings.handling; 2];
+
+        self.timer
+            .accumulate(ctx.time.delta(), Gameplay::MAX_CATCH_UP);
+        self.pending_presses = inputs.map(|input| input.just_pressed);
+        while !self.is_over() && self.timer.take_step() {
+            Self::step(&mut self.players, ctx, shared, inputs, handling);
+            inputs = inputs.map(FrameInput::held);
+            self.pending_presses = [0; 2];
+        }
+        paused
+    }
+
+    /// Steps both games as real time passes, with the computer's inputs for the player on the
+    /// right. Returns whether this player paused.
+    fn update_bot(&mut self, ctx: &Context, shared: &mut Shared) -> bool {
+        let Opponent::Bot(bot) = &mut self.opponent else {
+            return false;
+        };
+        let controls = Controls::new(ctx, &shared.settings, &shared.gamepad, &shared.touch);
+        let paused = controls.just_pressed(Action::Pause);
+        let mut input = controls.frame_input();
+        input.just_pressed |= self.pending_presses[0];
+        let handling = [shared.settings.handling; 2];
+
+        self.timer
+            .accumulate(ctx.time.delta(), Gameplay::MAX_CATCH_UP);
+        self.pending_presses[0] = input.just_pressed;
+        while !self.players.iter().any(|player| player.game.is_over()) && self.timer.take_step() {
+            let inputs = [input, bot.input(&self.players[1].game)];
+            Self::step(&mut self.players, ctx, shared, inputs, handling);
+            input = input.held();
+            self.pending_presses[0] = 0;
+        }
+        paused
+    }
+
+    /// Sends this player's inputs as real time passes and steps both games with them,
+    /// guessing the other player's inputs until they arrive. Returns whether this player
+    /// paused.
+    fn update_remote(&mut self, ctx: &Context, shared: &mut Shared) -> bool {
+        let Opponent::Remote(remote) = &mut self.opponent else {
+            return false;
+        };
+        let controls = Controls::new(ctx, &shared.settings, &shared.gamepad, &shared.touch);
+        let paused = controls.just_pressed(Action::Pause);
+        let mut input = controls.frame_input();
+        input.just_pressed |= self.pending_presses[0];
+        let handling = [remote.local_handling, remote.handling];
+
+        if let Some(games) = remote.reconcile(handling) {
+            for (player, game) in self.players.iter_mut().zip(games) {
+                player.replace_game(game);
+            }
+        }
+
+        self.timer
+            .accumulate(ctx.time.delta(), Gameplay::MAX_CATCH_UP);
+        self.pending_presses[0] = input.just_pressed;
+        while !self.players.iter().any(|player| player.game.is_over())
+            && !remote.is_waiting()
+            && self.timer.take_step()
+        {
+            let inputs = [
+                remote.send_input(input),
+                remote.remote_input(remote.unconfirmed.len()),
+            ];
+            remote.unconfirmed.push_back(Snapshot {
+                games: self.players.each_ref().map(|player| player.game.clone()),
+                inputs,
+            });
+            Self::step(&mut self.players, ctx, shared, inputs, handling);
+            input = input.held();
+            self.pending_presses[0] = 0;
+        }
+        paused
+    }
+
+    fn is_disconnected(&self) -> bool {
+        matches!(&self.opponent, Opponent::Remote(remote) if remote.disconnected)
+    }
+
+    /// Who won the match that just ended.
+    fn result(&self) -> String {
+        match self.winner() {
+            Some(winner) => format!("{} WINS!", self.names()[winner]),
+            None => "DRAW".to_owned(),
+        }
+    }
+}
+
+impl Scene for VersusScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<Transition> {
+        if let Opponent::Remote(remote) = &mut self.opponent {
+            remote.poll();
+        }
+        if self.is_decided() || self.is_disconnected() {
+            if back_pressed(ctx, &shared.gamepad) {
+                return Ok(Transition::Switch(Box::new(MenuScene::new(ctx))));
+            }
+            if !self.is_disconnected() {
+                self.update_rematch(ctx, shared);
+            }
+            return Ok(Transition::None);
+        }
+
+        let paused = match self.opponent {
+            Opponent::Local(_) => self.update_local(ctx, shared),
+            Opponent::Remote(_) => self.update_remote(ctx, shared),
+            Opponent::Bot(_) => self.update_bot(ctx, shared),
+        };
+
+        if self.is_decided() {
+            if let Some(winner) = self.winner() {
+                self.wins[winner] += 1;
+            }
+        } else if paused {
+            return Ok(Transition::Push(Box::new(SettingsScene::in_game())));
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(
+        &mut self,
+        _ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        shared: &Shared,
+    ) -> GameResult {
+        let interpolation = self.timer.interpolation();
+        let names = self.names();
+        for (i, player) in self.players.iter_mut().enumerate() {
+            player.draw_playfield(canvas, shared, interpolation);
+            let board = player.board_rect();
+            canvas.draw(
+                &ui_text(format!("{} - {}", names[i], self.wins[i]), 16.),
+                DrawParam::default().dest(Point2 {
+                    x: board.x,
+                    y: board.y - 22.,
+                }),
+            );
+        }
+
+        let (title, hint) = match &self.opponent {
+            Opponent::Remote(remote) if remote.disconnected => {
+                ("CONNECTION LOST".to_owned(), "ESC: MENU")
+            }
+            Opponent::Remote(remote) if self.is_decided() && remote.rematch[0] => {
+                (self.result(), "WAITING FOR RIVAL...")
+            }
+            _ if self.is_decided() => (self.result(), "ENTER: REMATCH  ESC: MENU"),
+            Opponent::Remote(remote) if remote.is_waiting() => {
+                ("WAITING...".to_owned(), "THE RIVAL IS LAGGING")
+            }
+            _ => return Ok(()),
+        };
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest_rect(Rect::new(SCREEN.x, 110., SCREEN.w, 80.))
+                .color(Color::new(0., 0., 0., 0.7)),
+        );
+        for (i, (line, size)) in [(title.as_str(), 24.), (hint, 12.)].into_iter().enumerate() {
+            let mut text = ui_text(line, size);
+            text.set_layout(TextLayout {
+                h_align: TextAlign::Middle,
+                v_align: TextAlign::Middle,
+            });
+            canvas.draw(
+                &text,
+                DrawParam::default().dest(Point2 {
+                    x: SCREEN.center().x,
+                    y: 138. + i as f32 * 32.,
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    fn debug_lines(&self) -> Vec<String> {
+        let mut lines: Vec<_> = self
+            .players
+            .iter()
+            .zip(self.names())
+            .map(|(player, name)| {
+                format!(
+                    "{} FRAME {} GARBAGE {}",
+                    name,
+                    player.game.frame(),
+                    player.game.pending_garbage()
+                )
+            })
+            .collect();
+        if let Opponent::Remote(remote) = &self.opponent {
+            lines.push(format!(
+                "GUESSED STEPS {} INPUTS AHEAD {}",
+                remote.unconfirmed.len(),
+                remote.remote_inputs.len()
+            ));
+        }
+        lines
+    }
+
+    fn danger(&self) -> Option<f32> {
+        self.players
+            .iter()
+            .filter_map(|player| player.danger())
+            .reduce(f32::max)
+    }
+
+    fn intensity(&self) -> Option<f32> {
+        self.players
+            .iter()
+            .filter_map(|player| player.intensity())
+            .reduce(f32::max)
+    }
+
+    fn flash(&self) -> Option<f32> {
+        self.players
+            .iter()
+            .filter_map(|player| player.flash())
+            .reduce(f32::max)
+    }
+}
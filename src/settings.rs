@@ -0,0 +1,328 @@
+use std::{
+    io::{Read, Write},
+    time::Duration,
+};
+
+use ggez::{Context, GameError, GameResult};
+use serde::{Deserialize, Serialize};
+use tetris_core::{BoardSize, Handling, RotationSystemKind, SoftDrop, TopOut};
+
+use crate::{
+    audio::{Audio, Mixer},
+    input::KeyBindings,
+};
+
+pub struct Settings {
+    pub mixer: Mixer,
+    /// Whether songs play in a random order.
+    pub shuffle_music: bool,
+    pub ghost_piece: bool,
+    /// Whether the place the bot would drop the falling piece is outlined on the board.
+    pub placement_hints: bool,
+    /// Whether lines are drawn between the cells of the board.
+    pub grid_lines: bool,
+    /// How many of the pieces coming next are shown, as far as the mode allows.
+    pub next_count: usize,
+    pub screen_shake: bool,
+    /// Whether pieces per second, attack per minute and lines per minute are shown during
+    /// games.
+    pub live_stats: bool,
+    /// Whether how many of each piece were dealt is shown during games, as in the classic
+    /// games' statistics column.
+    pub piece_stats: bool,
+    /// Whether the finesse trainer takes back pieces placed with too many presses.
+    pub finesse_redo: bool,
+    pub handling: Handling,
+    /// Where the handling's DAS, ARR, DCD, soft drop, ARE and line clear delay come from.
+    pub handling_preset: HandlingPreset,
+    /// The player's own timings, used by the custom preset.
+    pub custom_tuning: Tuning,
+    pub board_size: BoardSize,
+    pub key_bindings: KeyBindings,
+    /// Whether to show on-screen buttons once the screen has been touched.
+    pub touch_buttons: bool,
+    /// Last name entered for the high score table, offered again for the next one.
+    pub player_name: String,
+    /// Folder of the theme in use, or empty for the built-in assets.
+    pub theme: String,
+    /// Code of the language text is shown in, like `es`, or empty for English.
+    pub language: String,
+    /// Address of the lobby server online games are listed on, or empty for one on this
+    /// computer.
+    pub lobby_server: String,
+    /// URL of the online leaderboard scores are sent to and fetched from, like
+    /// `http://example.com/tetris`, or empty to keep scores on this computer only.
+    pub leaderboard_server: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mixer: Mixer::default(),
+            shuffle_music: false,
+            ghost_piece: true,
+            placement_hints: false,
+            grid_lines: false,
+            next_count: 5,
+            screen_shake: true,
+            live_stats: false,
+            piece_stats: false,
+            finesse_redo: false,
+            handling: Handling::default(),
+            handling_preset: HandlingPreset::default(),
+            custom_tuning: Tuning::default(),
+            board_size: BoardSize::default(),
+            key_bindings: KeyBindings::default(),
+            touch_buttons: true,
+            player_name: String::new(),
+            theme: String::new(),
+            language: String::new(),
+            lobby_server: String::new(),
+            leaderboard_server: String::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Most pieces coming next that can be shown.
+    pub const MAX_NEXT_COUNT: usize = 6;
+
+    /// The lobby server to use, with the default one filled in.
+    pub fn lobby_server(&self) -> &str {
+        if self.lobby_server.is_empty() {
+            "localhost"
+        } else {
+            &self.lobby_server
+        }
+    }
+
+    /// The online leaderboard to use, if the player opted into one.
+    pub fn leaderboard_server(&self) -> Option<&str> {
+        Some(self.leaderboard_server.as_str()).filter(|server| !server.is_empty())
+    }
+
+    pub fn apply(&self, audio: &mut Audio) {
+        audio.set_volumes(&self.mixer);
+        audio.shuffle = self.shuffle_music;
+    }
+
+    /// Switches to `preset`, taking its timings into the handling.
+    pub fn set_handling_preset(&mut self, preset: HandlingPreset) {
+        self.handling_preset = preset;
+        preset.tuning(self.custom_tuning).apply(&mut self.handling);
+    }
+
+    /// Keeps the handling's timings as the custom preset, after one of them was changed
+    /// outside of the tuning screen.
+    pub fn customize_handling(&mut self) {
+        self.custom_tuning = Tuning::of(&self.handling);
+        self.handling_preset = HandlingPreset::Custom;
+    }
+}
+
+/// The timings of the handling that are down to taste, which presets set and the tuning
+/// screen adjusts.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tuning {
+    /// DAS, ARR and DCD in milliseconds.
+    pub das: u64,
+    pub arr: u64,
+    pub dcd: u64,
+    /// Times as fast as gravity, or 0 for instant.
+    pub soft_drop: u16,
+    /// ARE and line clear delay in milliseconds.
+    pub are: u64,
+    pub line_clear_delay: u64,
+}
+
+impl Tuning {
+    pub fn of(handling: &Handling) -> Self {
+        Self {
+            das: handling.das.as_millis() as u64,
+            arr: handling.arr.as_millis() as u64,
+            dcd: handling.dcd.as_millis() as u64,
+            soft_drop: handling.soft_drop.to_bits(),
+            are: handling.are.as_millis() as u64,
+            line_clear_delay: handling.line_clear_delay.as_millis() as u64,
+        }
+    }
+
+    pub fn apply(self, handling: &mut Handling) {
+        handling.das = Duration::from_millis(self.das);
+        handling.arr = Duration::from_millis(self.arr);
+        handling.dcd = Duration::from_millis(self.dcd);
+        handling.soft_drop = SoftDrop::from_bits(self.soft_drop);
+        handling.are = Duration::from_millis(self.are);
+        handling.line_clear_delay = Duration::from_millis(self.line_clear_delay);
+    }
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self::of(&Handling::default())
+    }
+}
+
+/// Named timings to pick from instead of tuning each one.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HandlingPreset {
+    #[default]
+    Default,
+    /// Quick enough to move pieces across the board at once.
+    Fast,
+    /// The player's own, from the tuning screen.
+    Custom,
+}
+
+impl HandlingPreset {
+    pub const ALL: [HandlingPreset; 3] = [
+        HandlingPreset::Default,
+        HandlingPreset::Fast,
+        HandlingPreset::Custom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HandlingPreset::Default => "DEFAULT",
+            HandlingPreset::Fast => "FAST",
+            HandlingPreset::Custom => "CUSTOM",
+        }
+    }
+
+    /// The preset's timings, with `custom` being the player's own.
+    pub fn tuning(self, custom: Tuning) -> Tuning {
+        match self {
+            HandlingPreset::Default => Tuning::default(),
+            HandlingPreset::Fast => Tuning {
+                das: 100,
+                arr: 0,
+                dcd: 0,
+                soft_drop: SoftDrop::Instant.to_bits(),
+                are: 0,
+                line_clear_delay: 100,
+            },
+            HandlingPreset::Custom => custom,
+        }
+    }
+}
+
+/// Settings persisted to `config.toml` in the user config directory. Anything missing from
+/// it, as in configs saved by older versions, is left as it is by default.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    shuffle_music: bool,
+    ghost_piece: bool,
+    placement_hints: bool,
+    grid_lines: bool,
+    next_count: usize,
+    screen_shake: bool,
+    live_stats: bool,
+    piece_stats: bool,
+    finesse_redo: bool,
+    touch_buttons: bool,
+    player_name: String,
+    theme: String,
+    language: String,
+    lobby_server: String,
+    leaderboard_server: String,
+    handling_preset: HandlingPreset,
+    /// The rest of the handling, past what the preset sets.
+    initial_rotation: bool,
+    initial_hold: bool,
+    hold: bool,
+    rotation_system: RotationSystemKind,
+    // TOML can't have plain values after tables, so the fields that are tables come last.
+    mixer: Mixer,
+    board_size: BoardSize,
+    top_out: TopOut,
+    custom_tuning: Tuning,
+    key_bindings: KeyBindings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::from_settings(&Settings::default())
+    }
+}
+
+impl Config {
+    pub const PATH: &'static str = "/config.toml";
+
+    /// Reads the config file, or returns the default config if there is none yet.
+    pub fn load(ctx: &Context) -> GameResult<Self> {
+        if !ctx.fs.exists(Self::PATH) {
+            return Ok(Self::default());
+        }
+        let mut contents = String::new();
+        ctx.fs.open(Self::PATH)?.read_to_string(&mut contents)?;
+        toml::from_str(&contents).map_err(|e| GameError::ConfigError(e.to_string()))
+    }
+
+    pub fn save(&self, ctx: &Context) -> GameResult {
+        let contents = toml::to_string(self).map_err(|e| GameError::ConfigError(e.to_string()))?;
+        ctx.fs.create(Self::PATH)?.write_all(contents.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            mixer: settings.mixer,
+            shuffle_music: settings.shuffle_music,
+            ghost_piece: settings.ghost_piece,
+            placement_hints: settings.placement_hints,
+            grid_lines: settings.grid_lines,
+            next_count: settings.next_count,
+            screen_shake: settings.screen_shake,
+            live_stats: settings.live_stats,
+            piece_stats: settings.piece_stats,
+            finesse_redo: settings.finesse_redo,
+            touch_buttons: settings.touch_buttons,
+            board_size: settings.board_size,
+            key_bindings: settings.key_bindings.clone(),
+            player_name: settings.player_name.clone(),
+            theme: settings.theme.clone(),
+            language: settings.language.clone(),
+            lobby_server: settings.lobby_server.clone(),
+            leaderboard_server: settings.leaderboard_server.clone(),
+            handling_preset: settings.handling_preset,
+            custom_tuning: settings.custom_tuning,
+            initial_rotation: settings.handling.initial_rotation,
+            initial_hold: settings.handling.initial_hold,
+            hold: settings.handling.hold,
+            rotation_system: settings.handling.rotation_system,
+            top_out: settings.handling.top_out,
+        }
+    }
+
+    pub fn apply(self, settings: &mut Settings) {
+        settings.mixer = self.mixer;
+        settings.shuffle_music = self.shuffle_music;
+        settings.ghost_piece = self.ghost_piece;
+        settings.placement_hints = self.placement_hints;
+        settings.grid_lines = self.grid_lines;
+        settings.next_count = self.next_count.min(Settings::MAX_NEXT_COUNT);
+        settings.screen_shake = self.screen_shake;
+        settings.live_stats = self.live_stats;
+        settings.piece_stats = self.piece_stats;
+        settings.finesse_redo = self.finesse_redo;
+        settings.touch_buttons = self.touch_buttons;
+        if self.board_size.is_allowed() {
+            settings.board_size = self.board_size;
+        }
+        settings.key_bindings = self.key_bindings;
+        settings.player_name = self.player_name;
+        settings.theme = self.theme;
+        settings.language = self.language;
+        settings.lobby_server = self.lobby_server;
+        settings.leaderboard_server = self.leaderboard_server;
+        settings.handling.initial_rotation = self.initial_rotation;
+        settings.handling.initial_hold = self.initial_hold;
+        settings.handling.hold = self.hold;
+        settings.handling.rotation_system = self.rotation_system;
+        settings.handling.top_out = self.top_out;
+        settings.custom_tuning = self.custom_tuning;
+        settings.set_handling_preset(self.handling_preset);
+    }
+}
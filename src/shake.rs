@@ -0,0 +1,36 @@
+use ggez::glam::Vec2;
+use rand::Rng;
+use tetris_core::Game;
+
+/// Offsets the board for a moment after heavy hits. Shakes build up when they happen in
+/// quick succession and die down on their own.
+#[derive(Default)]
+pub struct ScreenShake {
+    /// From 0.0 to 1.0
+    intensity: f32,
+    offset: Vec2,
+}
+
+impl ScreenShake {
+    /// Farthest the board moves, in pixels, at full intensity.
+    const MAX_OFFSET: f32 = 6.;
+    /// Intensity lost per second.
+    const DECAY: f32 = 2.5;
+
+    pub fn add(&mut self, intensity: f32) {
+        self.intensity = (self.intensity + intensity).min(1.);
+    }
+
+    /// Dies down by one game step and picks where the board is for it.
+    pub fn step(&mut self) {
+        self.intensity = (self.intensity - Self::DECAY * Game::FRAME_TIME.as_secs_f32()).max(0.);
+        // Squaring keeps small shakes subtle while big ones still hit hard.
+        let magnitude = Self::MAX_OFFSET * self.intensity * self.intensity;
+        let mut rng = rand::thread_rng();
+        self.offset = Vec2::new(rng.gen_range(-1. ..=1.), rng.gen_range(-1. ..=1.)) * magnitude;
+    }
+
+    pub fn offset(&self) -> Vec2 {
+        self.offset
+    }
+}
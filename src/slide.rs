@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use ggez::glam::Vec2;
+use tetris_core::{Game, Piece};
+
+/// Draws the falling piece sliding into the cell it moved to, rather than jumping a whole
+/// cell at a time. Only the drawing lags behind: the piece is already in its new cell.
+#[derive(Default)]
+pub struct PieceSlide {
+    /// How far from its cell the piece was drawn right after it last moved, in cells.
+    from: Vec2,
+    /// Game time since the piece last moved.
+    age: Duration,
+    last: Option<Piece>,
+}
+
+impl PieceSlide {
+    const TIME: Duration = Duration::from_millis(60);
+
+    /// Follows the falling piece for one game step. New pieces, spawned or swapped in from
+    /// hold, appear right in their cell.
+    pub fn step(&mut self, piece: Piece, new_piece: bool) {
+        self.age += Game::FRAME_TIME;
+        match self.last {
+            Some(last) if !new_piece => {
+                let moved = Vec2::new(
+                    (last.pos.x - piece.pos.x) as f32,
+                    (last.pos.y - piece.pos.y) as f32,
+                );
+                if moved != Vec2::ZERO {
+                    self.from = self.offset(0.) + moved;
+                    self.age = Duration::ZERO;
+                }
+            }
+            _ => self.from = Vec2::ZERO,
+        }
+        self.last = Some(piece);
+    }
+
+    /// How far from its cell the piece is drawn, in cells. `interpolation` is the fraction of
+    /// a step that has passed since the last one.
+    pub fn offset(&self, interpolation: f32) -> Vec2 {
+        let age = self.age.as_secs_f32() + interpolation * Game::FRAME_TIME.as_secs_f32();
+        self.from * (1. - age / Self::TIME.as_secs_f32()).max(0.)
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use tetris_core::{Game, Piece};
+
+/// Fades the blocks of pieces out shortly after they lock, for the invisible mode. Clearing
+/// rows shows the whole stack again for a moment. Kept cell by cell alongside the game's
+/// grid.
+pub struct StackFade {
+    width: usize,
+    /// Game time each cell has left to show, in row order.
+    remaining: Box<[Duration]>,
+}
+
+impl StackFade {
+    /// How long a piece shows for after it locks, fading out over the end of it.
+    const LOCK_TIME: Duration = Duration::from_millis(1000);
+    /// How long the whole stack shows for after rows are cleared.
+    const REVEAL_TIME: Duration = Duration::from_millis(1500);
+    const FADE_TIME: Duration = Duration::from_millis(400);
+
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            remaining: vec![Duration::ZERO; width * height].into_boxed_slice(),
+        }
+    }
+
+    /// Shows the cells a piece locked into.
+    pub fn lock(&mut self, piece: Piece) {
+        for cell in piece.cells() {
+            if cell.x >= 0 && cell.y >= 0 {
+                let i = cell.x as usize + cell.y as usize * self.width;
+                if (cell.x as usize) < self.width && i < self.remaining.len() {
+                    self.remaining[i] = Self::LOCK_TIME;
+                }
+            }
+        }
+    }
+
+    /// Shows every cell, for when rows are cleared or move. Since all of them show for as
+    /// long, it doesn't matter which rows moved where.
+    pub fn reveal(&mut self) {
+        self.remaining.fill(Self::REVEAL_TIME);
+    }
+
+    /// Fades the cells by one game step.
+    pub fn step(&mut self) {
+        for remaining in self.remaining.iter_mut() {
+            *remaining = remaining.saturating_sub(Game::FRAME_TIME);
+        }
+    }
+
+    /// How visible the block in a cell is, from 0 to 1.
+    pub fn visibility(&self, x: usize, y: usize) -> f32 {
+        self.remaining
+            .get(x + y * self.width)
+            .map_or(0., |remaining| {
+                (remaining.as_secs_f32() / Self::FADE_TIME.as_secs_f32()).min(1.)
+            })
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ggez::{filesystem::Filesystem, Context, GameError, GameResult};
+use tetris_core::{
+    Achievements, DailyResults, Drill, GameMode, GameSummary, KickTables, Leaderboard, PieceSet,
+    Replay, SavedGame,
+};
+
+/// Where a mode's records are stored in the user data directory.
+fn leaderboard_path(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Endless => "/endless_scores.txt",
+        GameMode::Sprint => "/sprint_times.txt",
+        GameMode::Ultra => "/ultra_scores.txt",
+        GameMode::Marathon { .. } => "/marathon_scores.txt",
+        GameMode::Dig { .. } => "/dig_times.txt",
+        GameMode::Survival => "/survival_scores.txt",
+        GameMode::Invisible => "/invisible_scores.txt",
+        GameMode::Big => "/big_scores.txt",
+        GameMode::Pentomino {
+            with_tetrominoes: false,
+        } => "/pentomino_scores.txt",
+        GameMode::Pentomino {
+            with_tetrominoes: true,
+        } => "/pentomix_scores.txt",
+        GameMode::Cascade => "/cascade_scores.txt",
+        GameMode::Master => "/master_scores.txt",
+        GameMode::Classic => "/classic_scores.txt",
+    }
+}
+
+/// Storage of high score tables.
+pub trait LeaderboardExt {
+    /// Loads a mode's table from the user data directory, or starts an empty one if it can't
+    /// be read.
+    fn load(ctx: &Context, mode: GameMode) -> Self;
+
+    fn save(&self, ctx: &Context, mode: GameMode) -> GameResult;
+}
+
+impl LeaderboardExt for Leaderboard {
+    fn load(ctx: &Context, mode: GameMode) -> Self {
+        let mut contents = String::new();
+        if let Ok(mut file) = ctx.fs.open(leaderboard_path(mode)) {
+            if file.read_to_string(&mut contents).is_err() {
+                contents.clear();
+            }
+        }
+        Leaderboard::parse(&contents, mode.record_order())
+    }
+
+    fn save(&self, ctx: &Context, mode: GameMode) -> GameResult {
+        let mut file = create_user_data_file(ctx, leaderboard_path(mode))?;
+        file.write_all(self.to_text().as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Saves a screenshot in the screenshots folder of the user data directory, named after
+/// when it was taken, and returns its file name.
+pub fn save_screenshot(ctx: &Context, rgba: &[u8], width: u32, height: u32) -> GameResult<String> {
+    let directory = user_data_path(ctx, "/screenshots");
+    fs::create_dir_all(&directory)?;
+    let stamp = timestamp();
+    // Screenshots taken within the same second are numbered instead of replacing each other.
+    let name = (1..)
+        .map(|n| match n {
+            1 => format!("screenshot-{}.png", stamp),
+            n => format!("screenshot-{}-{}.png", stamp, n),
+        })
+        .find(|name| !directory.join(name).exists())
+        .expect("some name is free");
+    image::save_buffer(
+        directory.join(&name),
+        rgba,
+        width,
+        height,
+        image::ColorType::Rgba8,
+    )
+    .map_err(|e| GameError::CustomError(e.to_string()))?;
+    Ok(name)
+}
+
+/// Seconds since the Unix epoch, which files are named and stamped with.
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Creates a file in the user data directory. ggez only lets us write to the config directory,
+/// so this goes through `std::fs` instead; the data directory is still mounted for reading.
+fn create_user_data_file(ctx: &Context, path: &str) -> GameResult<fs::File> {
+    let path = user_data_path(ctx, path);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(fs::File::create(path)?)
+}
+
+/// Opens a file in the user data directory to add to its end, creating it if needed.
+fn append_user_data_file(ctx: &Context, path: &str) -> GameResult<fs::File> {
+    let path = user_data_path(ctx, path);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?)
+}
+
+fn user_data_path(ctx: &Context, path: &str) -> path::PathBuf {
+    ctx.fs.user_data_dir().join(path.trim_start_matches('/'))
+}
+
+/// Reading and writing of replay files, which are kept in the user data directory.
+pub trait ReplayExt: Sized {
+    const DIRECTORY: &'static str;
+
+    fn parse(bytes: &[u8]) -> GameResult<Self>;
+
+    /// Reads a replay written as a script of inputs, see `Replay::from_script`.
+    fn parse_script(script: &str) -> GameResult<Self>;
+
+    fn load(ctx: &Context, path: &path::Path) -> GameResult<Self>;
+
+    /// Writes the replay to a new file in the user data directory, named after the current
+    /// time so that replays never overwrite each other.
+    fn save(&self, ctx: &Context) -> GameResult;
+}
+
+impl ReplayExt for Replay {
+    const DIRECTORY: &'static str = "/replays";
+
+    fn parse(bytes: &[u8]) -> GameResult<Self> {
+        let replay =
+            Replay::from_bytes(bytes).map_err(|e| GameError::ResourceLoadError(e.to_string()))?;
+        if !replay.has_installed_rules() {
+            return Err(GameError::ResourceLoadError(
+                "the replay was played with other pieces or kicks".to_owned(),
+            ));
+        }
+        Ok(replay)
+    }
+
+    fn parse_script(script: &str) -> GameResult<Self> {
+        Replay::from_script(script).map_err(|e| GameError::ResourceLoadError(e.to_string()))
+    }
+
+    fn load(ctx: &Context, path: &path::Path) -> GameResult<Self> {
+        let mut bytes = vec![];
+        ctx.fs.open(path)?.read_to_end(&mut bytes)?;
+        Self::parse(&bytes)
+    }
+
+    fn save(&self, ctx: &Context) -> GameResult {
+        let path = format!("{}/{}.replay", Self::DIRECTORY, timestamp());
+        let mut file = create_user_data_file(ctx, &path)?;
+        file.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+}
+
+/// Reading of drills, which come with the game in the resource directory.
+pub trait DrillExt: Sized {
+    const DIRECTORY: &'static str;
+
+    fn load(ctx: &Context, path: &path::Path) -> GameResult<Self>;
+}
+
+impl DrillExt for Drill {
+    const DIRECTORY: &'static str = "/drills";
+
+    fn load(ctx: &Context, path: &path::Path) -> GameResult<Self> {
+        let mut contents = String::new();
+        ctx.fs.open(path)?.read_to_string(&mut contents)?;
+        Drill::parse(&contents).map_err(|e| GameError::ResourceLoadError(e.to_string()))
+    }
+}
+
+/// Reading of custom pieces, from a file that can be put in the resource or user data
+/// directory to replace the standard ones.
+pub trait PieceSetExt: Sized {
+    const PATH: &'static str;
+
+    /// Loads the custom pieces, or `None` if there are none.
+    fn load(fs: &Filesystem) -> GameResult<Option<Self>>;
+}
+
+impl PieceSetExt for PieceSet {
+    const PATH: &'static str = "/pieces.toml";
+
+    fn load(fs: &Filesystem) -> GameResult<Option<Self>> {
+        if !fs.is_file(Self::PATH) {
+            return Ok(None);
+        }
+        let mut contents = String::new();
+        fs.open(Self::PATH)?.read_to_string(&mut contents)?;
+        PieceSet::parse(&contents)
+            .map(Some)
+            .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", Self::PATH, e)))
+    }
+}
+
+pub trait KickTablesExt: Sized {
+    const PATH: &'static str;
+
+    /// Loads the custom kicks, or `None` if there are none.
+    fn load(fs: &Filesystem) -> GameResult<Option<Self>>;
+}
+
+impl KickTablesExt for KickTables {
+    const PATH: &'static str = "/kicks.toml";
+
+    fn load(fs: &Filesystem) -> GameResult<Option<Self>> {
+        if !fs.is_file(Self::PATH) {
+            return Ok(None);
+        }
+        let mut contents = String::new();
+        fs.open(Self::PATH)?.read_to_string(&mut contents)?;
+        KickTables::parse(&contents)
+            .map(Some)
+            .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", Self::PATH, e)))
+    }
+}
+
+/// Storage of the stats history, a CSV file with a line for every game finished.
+pub trait GameSummaryExt {
+    const PATH: &'static str;
+
+    /// Adds the summary to the end of the history, starting it if there is none yet.
+    fn append(&self, ctx: &Context) -> GameResult;
+}
+
+impl GameSummaryExt for GameSummary {
+    const PATH: &'static str = "/stats.csv";
+
+    fn append(&self, ctx: &Context) -> GameResult {
+        let mut file = append_user_data_file(ctx, Self::PATH)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", Self::CSV_HEADER)?;
+        }
+        writeln!(file, "{}", self.to_csv())?;
+        Ok(())
+    }
+}
+
+/// Storage of the achievements unlocked so far.
+pub trait AchievementsExt {
+    const PATH: &'static str;
+
+    /// Loads the achievements from the user data directory, or starts with none if they
+    /// can't be read.
+    fn load(ctx: &Context) -> Self;
+
+    fn save(&self, ctx: &Context) -> GameResult;
+}
+
+impl AchievementsExt for Achievements {
+    const PATH: &'static str = "/achievements.txt";
+
+    fn load(ctx: &Context) -> Self {
+        let mut contents = String::new();
+        if let Ok(mut file) = ctx.fs.open(Self::PATH) {
+            if file.read_to_string(&mut contents).is_err() {
+                contents.clear();
+            }
+        }
+        Achievements::parse(&contents)
+    }
+
+    fn save(&self, ctx: &Context) -> GameResult {
+        let mut file = create_user_data_file(ctx, Self::PATH)?;
+        file.write_all(self.to_text().as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Storage of the result of each day's challenge.
+pub trait DailyResultsExt {
+    const PATH: &'static str;
+
+    /// Loads the results from the user data directory, or starts with none if they can't be
+    /// read.
+    fn load(ctx: &Context) -> Self;
+
+    fn save(&self, ctx: &Context) -> GameResult;
+}
+
+impl DailyResultsExt for DailyResults {
+    const PATH: &'static str = "/daily.txt";
+
+    fn load(ctx: &Context) -> Self {
+        let mut contents = String::new();
+        if let Ok(mut file) = ctx.fs.open(Self::PATH) {
+            if file.read_to_string(&mut contents).is_err() {
+                contents.clear();
+            }
+        }
+        DailyResults::parse(&contents)
+    }
+
+    fn save(&self, ctx: &Context) -> GameResult {
+        let mut file = create_user_data_file(ctx, Self::PATH)?;
+        file.write_all(self.to_text().as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Storage of the game saved on quit, so it can be continued from the main menu.
+pub trait SavedGameExt: Sized {
+    const PATH: &'static str;
+
+    fn exists(ctx: &Context) -> bool;
+
+    fn load(ctx: &Context) -> GameResult<Self>;
+
+    fn save(&self, ctx: &Context) -> GameResult;
+
+    /// Removes the save once it has been continued, so that the same game can't be resumed
+    /// twice.
+    fn delete(ctx: &Context) -> GameResult;
+}
+
+impl SavedGameExt for SavedGame {
+    const PATH: &'static str = "/savegame.toml";
+
+    fn exists(ctx: &Context) -> bool {
+        ctx.fs.exists(Self::PATH)
+    }
+
+    fn load(ctx: &Context) -> GameResult<Self> {
+        let mut contents = String::new();
+        ctx.fs.open(Self::PATH)?.read_to_string(&mut contents)?;
+        toml::from_str(&contents).map_err(|e| GameError::ConfigError(e.to_string()))
+    }
+
+    fn save(&self, ctx: &Context) -> GameResult {
+        let contents = toml::to_string(self).map_err(|e| GameError::ConfigError(e.to_string()))?;
+        create_user_data_file(ctx, Self::PATH)?.write_all(contents.as_bytes())?;
+        Ok(())
+    }
+
+    fn delete(ctx: &Context) -> GameResult {
+        fs::remove_file(user_data_path(ctx, Self::PATH))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use std::io::Read;
+
+use ggez::{graphics::Color, Context, GameError, GameResult};
+use serde::Deserialize;
+
+/// Replacements for the built-in assets, read from the `theme.toml` of a folder in
+/// `/themes`. Paths in it are relative to that folder, and anything it leaves out falls back
+/// to the built-in asset.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Where the theme was loaded from, or `None` for the built-in assets.
+    #[serde(skip)]
+    folder: Option<String>,
+    /// Tileset with a sprite for each piece, as described in `BlockSprites`.
+    pub blocks: Option<String>,
+    pub background: Option<String>,
+    pub cursor: Option<String>,
+    pub button: Option<String>,
+    pub game_over: Option<String>,
+    pub music: Option<String>,
+    /// Folder of stems for layered music, played instead of the built-in music. Ignored if
+    /// `music` is given.
+    pub music_layers: Option<String>,
+    pub move_sound: Option<String>,
+    pub soft_drop_sound: Option<String>,
+    pub bump_sound: Option<String>,
+    pub land_sound: Option<String>,
+    pub rotate_sound: Option<String>,
+    pub place_sound: Option<String>,
+    pub clear_sound: Option<String>,
+    pub t_spin_sound: Option<String>,
+    pub tetris_sound: Option<String>,
+    pub warning_sound: Option<String>,
+    pub countdown_sound: Option<String>,
+    /// Color drawn behind everything, as red, green and blue from 0 to 255.
+    pub clear_color: Option<[u8; 3]>,
+}
+
+impl Theme {
+    const DIRECTORY: &'static str = "/themes";
+    const MANIFEST: &'static str = "theme.toml";
+
+    /// Names of the themes that can be loaded, in alphabetical order.
+    pub fn list(ctx: &Context) -> Vec<String> {
+        let mut names: Vec<_> = ctx
+            .fs
+            .read_dir(Self::DIRECTORY)
+            .map(|paths| {
+                paths
+                    .filter(|path| ctx.fs.is_file(path.join(Self::MANIFEST)))
+                    .filter_map(|path| Some(path.file_name()?.to_str()?.to_owned()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort_unstable();
+        names
+    }
+
+    /// Loads the theme in the folder `name`, or the built-in assets if `name` is empty.
+    pub fn load(ctx: &Context, name: &str) -> GameResult<Self> {
+        if name.is_empty() {
+            return Ok(Self::default());
+        }
+        let folder = format!("{}/{}", Self::DIRECTORY, name);
+        let mut contents = String::new();
+        ctx.fs
+            .open(format!("{}/{}", folder, Self::MANIFEST))?
+            .read_to_string(&mut contents)?;
+        let mut theme: Self =
+            toml::from_str(&contents).map_err(|e| GameError::ConfigError(e.to_string()))?;
+        theme.folder = Some(folder);
+        Ok(theme)
+    }
+
+    /// Where the theme's `replacement` for an asset is, if it has one.
+    pub fn replacement(&self, replacement: &Option<String>) -> Option<String> {
+        match (&self.folder, replacement) {
+            (Some(folder), Some(file)) => Some(format!("{}/{}", folder, file)),
+            _ => None,
+        }
+    }
+
+    /// Where to load an asset from: the theme's `replacement` if it has one, or `default`.
+    pub fn path(&self, replacement: &Option<String>, default: &str) -> String {
+        self.replacement(replacement)
+            .unwrap_or_else(|| default.to_owned())
+    }
+
+    pub fn clear_color(&self) -> Color {
+        match self.clear_color {
+            Some([r, g, b]) => Color::from_rgb(r, g, b),
+            None => Color::from([0.1, 0.2, 0.3, 1.0]),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::{collections::VecDeque, time::Duration};
+
+use ggez::{
+    graphics::{self, Color, DrawParam, Quad, Rect, TextAlign, TextLayout},
+    mint::Point2,
+};
+
+use crate::{render::ui_text, SCREEN};
+
+/// Notices that show at the top of the screen for a moment over whatever scene is open, one
+/// after another.
+#[derive(Default)]
+pub struct Toasts {
+    /// Title and message of each notice waiting to show, the first one showing.
+    queue: VecDeque<(String, String)>,
+    /// How long the first notice has been showing.
+    shown_for: Duration,
+}
+
+impl Toasts {
+    const TIME: Duration = Duration::from_millis(3000);
+    const FADE_TIME: Duration = Duration::from_millis(300);
+    const AREA: Rect = Rect::new(100., 8., 200., 40.);
+
+    pub fn push(&mut self, title: impl Into<String>, message: impl Into<String>) {
+        self.queue.push_back((title.into(), message.into()));
+    }
+
+    /// Moves the notice showing on by `delta` of real time, moving on to the next once it
+    /// has shown for long enough.
+    pub fn update(&mut self, delta: Duration) {
+        if self.queue.is_empty() {
+            return;
+        }
+        self.shown_for += delta;
+        if self.shown_for >= Self::TIME {
+            self.queue.pop_front();
+            self.shown_for = Duration::ZERO;
+        }
+    }
+
+    pub fn draw(&self, canvas: &mut graphics::Canvas) {
+        let Some((title, message)) = self.queue.front() else {
+            return;
+        };
+        let faded_in = self.shown_for.as_secs_f32() / Self::FADE_TIME.as_secs_f32();
+        let fading_out =
+            Self::TIME.saturating_sub(self.shown_for).as_secs_f32() / Self::FADE_TIME.as_secs_f32();
+        let alpha = faded_in.min(fading_out).min(1.);
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest_rect(Self::AREA)
+                .color(Color::new(0., 0., 0., 0.8 * alpha)),
+        );
+        for (i, (line, size)) in [(title, 12.), (message, 16.)].into_iter().enumerate() {
+            let mut text = ui_text(line.as_str(), size);
+            text.set_layout(TextLayout {
+                h_align: TextAlign::Middle,
+                v_align: TextAlign::Middle,
+            });
+            canvas.draw(
+                &text,
+                DrawParam::default()
+                    .dest(Point2 {
+                        x: SCREEN.center().x,
+                        y: Self::AREA.y + 12. + i as f32 * 16.,
+                    })
+                    .color(Color::new(1., 1., 1., alpha)),
+            );
+        }
+    }
+}
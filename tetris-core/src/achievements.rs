@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use enum_map::{Enum, EnumMap};
+
+use crate::{
+    game::{ClearKind, Game, GameEvent},
+    mode::GameMode,
+};
+
+/// A goal players are rewarded for reaching once, in any game they play themselves.
+#[derive(Clone, Copy, PartialEq, Eq, Enum)]
+pub enum Achievement {
+    FirstTetris,
+    FirstTSpin,
+    PerfectClear,
+    Combo10,
+    Level15,
+    Lines100,
+    Lines1000,
+    SprintMinute,
+}
+
+impl Achievement {
+    /// Every achievement, in the order they are listed.
+    pub const ALL: [Achievement; 8] = [
+        Achievement::FirstTetris,
+        Achievement::FirstTSpin,
+        Achievement::PerfectClear,
+        Achievement::Combo10,
+        Achievement::Level15,
+        Achievement::Lines100,
+        Achievement::Lines1000,
+        Achievement::SprintMinute,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::FirstTetris => "TETRIS",
+            Achievement::FirstTSpin => "SPIN DOCTOR",
+            Achievement::PerfectClear => "ALL CLEAR",
+            Achievement::Combo10 => "COMBO BREAKER",
+            Achievement::Level15 => "HIGH ALTITUDE",
+            Achievement::Lines100 => "LINE WORKER",
+            Achievement::Lines1000 => "LINE VETERAN",
+            Achievement::SprintMinute => "SPRINTER",
+        }
+    }
+
+    /// What has to be done to unlock it.
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::FirstTetris => "CLEAR FOUR LINES AT ONCE",
+            Achievement::FirstTSpin => "CLEAR LINES WITH A T-SPIN",
+            Achievement::PerfectClear => "CLEAR THE WHOLE BOARD",
+            Achievement::Combo10 => "CLEAR WITH 10 PIECES IN A ROW",
+            Achievement::Level15 => "REACH LEVEL 15",
+            Achievement::Lines100 => "CLEAR 100 LINES IN ALL",
+            Achievement::Lines1000 => "CLEAR 1000 LINES IN ALL",
+            Achievement::SprintMinute => "FINISH A SPRINT IN UNDER A MINUTE",
+        }
+    }
+
+    /// How it's written in the file of unlocked achievements.
+    fn key(self) -> &'static str {
+        match self {
+            Achievement::FirstTetris => "first_tetris",
+            Achievement::FirstTSpin => "first_t_spin",
+            Achievement::PerfectClear => "perfect_clear",
+            Achievement::Combo10 => "combo_10",
+            Achievement::Level15 => "level_15",
+            Achievement::Lines100 => "lines_100",
+            Achievement::Lines1000 => "lines_1000",
+            Achievement::SprintMinute => "sprint_minute",
+        }
+    }
+}
+
+/// Which achievements a player has unlocked, along with the totals across games that some
+/// of them count towards.
+#[derive(Clone, Default)]
+pub struct Achievements {
+    unlocked: EnumMap<Achievement, bool>,
+    /// Lines cleared in every game so far.
+    total_lines: u32,
+}
+
+impl Achievements {
+    const SPRINT_TIME: Duration = Duration::from_secs(60);
+
+    /// Reads achievements written by `to_text`: the total lines on a line of their own
+    /// starting with `lines`, then an unlocked achievement per line. Lines that don't make
+    /// sense are skipped.
+    pub fn parse(contents: &str) -> Self {
+        let mut achievements = Self::default();
+        for line in contents.lines().map(str::trim) {
+            if let Some(total) = line.strip_prefix("lines ") {
+                achievements.total_lines = total.trim().parse().unwrap_or(0);
+            } else if let Some(&achievement) = Achievement::ALL
+                .iter()
+                .find(|achievement| achievement.key() == line)
+            {
+                achievements.unlocked[achievement] = true;
+            }
+        }
+        achievements
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("lines {}\n", self.total_lines);
+        for achievement in Achievement::ALL {
+            if self.unlocked[achievement] {
+                text.push_str(achievement.key());
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked[achievement]
+    }
+
+    pub fn unlocked_count(&self) -> usize {
+        self.unlocked.values().filter(|unlocked| **unlocked).count()
+    }
+
+    /// Goes through the events of a step of `game`, returning the achievements they unlocked
+    /// that weren't already.
+    pub fn observe(&mut self, game: &Game, events: &[GameEvent]) -> Vec<Achievement> {
+        let mut reached = vec![];
+        for event in events {
+            match *event {
+                GameEvent::LinesCleared { count, kind } => {
+                    self.total_lines += count;
+                    if count >= 4 {
+                        reached.push(Achievement::FirstTetris);
+                    }
+                    if kind == ClearKind::TSpin {
+                        reached.push(Achievement::FirstTSpin);
+                    }
+                    if game.combo() >= 10 {
+                        reached.push(Achievement::Combo10);
+                    }
+                }
+                GameEvent::LinesRemoved if game.grid().stack_height() == 0 => {
+                    reached.push(Achievement::PerfectClear);
+                }
+                GameEvent::LevelUp { level } if level >= 15 => reached.push(Achievement::Level15),
+                GameEvent::GameOver { completed: true }
+                    if game.mode() == GameMode::Sprint
+                        && game.stats().elapsed < Self::SPRINT_TIME =>
+                {
+                    reached.push(Achievement::SprintMinute);
+                }
+                _ => {}
+            }
+        }
+        if self.total_lines >= 100 {
+            reached.push(Achievement::Lines100);
+        }
+        if self.total_lines >= 1000 {
+            reached.push(Achievement::Lines1000);
+        }
+        reached.retain(|&achievement| !std::mem::replace(&mut self.unlocked[achievement], true));
+        reached
+    }
+}
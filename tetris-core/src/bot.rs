@@ -0,0 +1,257 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    game::Game,
+    grid::Grid,
+    input::{Action, FrameInput},
+    piece::{Piece, PieceRotation},
+};
+
+/// How well a bot plays.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "EASY",
+            Difficulty::Medium => "MEDIUM",
+            Difficulty::Hard => "HARD",
+        }
+    }
+
+    /// Steps spent looking at a new piece before moving it.
+    fn think_steps(self) -> u32 {
+        match self {
+            Difficulty::Easy => 40,
+            Difficulty::Medium => 16,
+            Difficulty::Hard => 4,
+        }
+    }
+
+    /// Steps between one input and the next.
+    fn input_steps(self) -> u32 {
+        match self {
+            Difficulty::Easy => 10,
+            Difficulty::Medium => 5,
+            Difficulty::Hard => 2,
+        }
+    }
+
+    /// How far the score of each placement can be off, which makes the bot misjudge them.
+    fn misjudgement(self) -> f32 {
+        match self {
+            Difficulty::Easy => 1.5,
+            Difficulty::Medium => 0.5,
+            Difficulty::Hard => 0.,
+        }
+    }
+}
+
+/// Where the bot is taking the falling piece.
+#[derive(Clone, Copy)]
+struct Placement {
+    /// Whether to swap the falling piece with the held one first.
+    hold: bool,
+    rotation: PieceRotation,
+    x: i32,
+}
+
+/// Plays a game by trying every place the falling piece (or the held one) can be dropped,
+/// scoring the stack each would leave behind, and pressing whatever takes the piece to the
+/// best one. Presses are spaced out so that it plays at a human pace.
+pub struct Bot {
+    difficulty: Difficulty,
+    rng: ChaCha8Rng,
+    plan: Option<Placement>,
+    /// Steps until the next input.
+    wait: u32,
+    /// Pieces placed in the game when the plan was made, to notice when a new piece comes.
+    planned_at: Option<u32>,
+}
+
+impl Bot {
+    /// Weights of the aggregate height of the columns, rows cleared, holes and bumpiness,
+    /// which are what placements are scored on.
+    const HEIGHT_WEIGHT: f32 = -0.51;
+    const LINES_WEIGHT: f32 = 0.76;
+    const HOLES_WEIGHT: f32 = -0.36;
+    const BUMPINESS_WEIGHT: f32 = -0.18;
+
+    /// A bot whose mistakes are picked with `seed`, so that it always plays the same game
+    /// the same way.
+    pub fn new(difficulty: Difficulty, seed: u32) -> Self {
+        Self {
+            difficulty,
+            rng: ChaCha8Rng::seed_from_u64(seed.into()),
+            plan: None,
+            wait: 0,
+            planned_at: None,
+        }
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// The input to step `game` with next.
+    pub fn input(&mut self, game: &Game) -> FrameInput {
+        // Nothing can be moved while rows are clearing or the next piece is coming in.
+        if game.is_over() || game.clearing_lines().next().is_some() || game.is_entering() {
+            return FrameInput::default();
+        }
+        if self.planned_at != Some(game.pieces_placed()) {
+            self.planned_at = Some(game.pieces_placed());
+            self.plan = self.best_placement(game);
+            self.wait = self.difficulty.think_steps();
+        }
+        if self.wait > 0 {
+            self.wait -= 1;
+            return FrameInput::default();
+        }
+        self.wait = self.difficulty.input_steps();
+
+        let piece = game.piece();
+        let action = match &mut self.plan {
+            Some(plan) if plan.hold && !game.hold_used() => {
+                plan.hold = false;
+                Action::Hold
+            }
+            Some(plan) if piece.rotation != plan.rotation => {
+                if piece.rotation.rotate_ccw() == plan.rotation {
+                    Action::RotateCcw
+                } else {
+                    Action::RotateCw
+                }
+            }
+            Some(plan) if piece.pos.x < plan.x => Action::MoveRight,
+            Some(plan) if piece.pos.x > plan.x => Action::MoveLeft,
+            // Without hard drops, soft drop the rest of the way, every step.
+            _ if !game.mode().rules().hard_drop => {
+                self.wait = 0;
+                return FrameInput::from_fn(|action| (action == Action::SoftDrop, false));
+            }
+            _ => Action::HardDrop,
+        };
+        FrameInput::tap(action)
+    }
+
+    /// Where the falling piece or the held one is best dropped, as it would land there,
+    /// judged without mistakes and from where it came in, so that it doesn't change as the
+    /// piece is moved. `None` if neither fits anywhere.
+    pub fn hint(game: &Game) -> Option<Piece> {
+        let piece = game.piece();
+        let start = Piece::spawned(piece.kind, piece.scale, game.grid());
+        Self::best(game, start, |score| score).map(|(_, landed)| landed)
+    }
+
+    /// The best place to drop the falling piece or the held one, or `None` if neither fits
+    /// anywhere.
+    fn best_placement(&mut self, game: &Game) -> Option<Placement> {
+        let misjudgement = self.difficulty.misjudgement();
+        let rng = &mut self.rng;
+        let judge = |score: f32| {
+            if misjudgement > 0. {
+                score + rng.gen_range(-misjudgement..misjudgement)
+            } else {
+                score
+            }
+        };
+        Self::best(game, game.piece(), judge).map(|(placement, _)| placement)
+    }
+
+    /// The placement of `falling`, or the held piece, whose stack `judge` scores best, with
+    /// where it lands.
+    fn best(
+        game: &Game,
+        falling: Piece,
+        mut judge: impl FnMut(f32) -> f32,
+    ) -> Option<(Placement, Piece)> {
+        let grid = game.grid();
+        let mut candidates = vec![(false, falling)];
+        // Holding with nothing held brings in a piece that isn't known yet.
+        if let Some(held) = game.held_piece().filter(|_| !game.hold_used()) {
+            candidates.push((true, Piece::spawned(held, falling.scale, grid)));
+        }
+
+        let rotations = [
+            PieceRotation::Deg0,
+            PieceRotation::Deg90,
+            PieceRotation::Deg180,
+            PieceRotation::Deg270,
+        ];
+        let mut best: Option<(f32, Placement, Piece)> = None;
+        for (hold, start) in candidates {
+            for rotation in rotations {
+                for x in -3..grid.width() as i32 {
+                    let Some(landed) = Self::drop(grid, start, rotation, x) else {
+                        continue;
+                    };
+                    let score = judge(Self::score(grid, landed));
+                    if best.is_none_or(|(best_score, ..)| score > best_score) {
+                        best = Some((score, Placement { hold, rotation, x }, landed));
+                    }
+                }
+            }
+        }
+        best.map(|(_, placement, landed)| (placement, landed))
+    }
+
+    /// Where `start` lands if it's rotated to `rotation` where it is, moved sideways to
+    /// column `x` and then dropped, or `None` if something is in the way.
+    fn drop(grid: &Grid, start: Piece, rotation: PieceRotation, x: i32) -> Option<Piece> {
+        let mut piece = Piece { rotation, ..start };
+        if piece.collides_with(grid) {
+            return None;
+        }
+        while piece.pos.x != x {
+            piece.pos.x += (x - piece.pos.x).signum();
+            if piece.collides_with(grid) {
+                return None;
+            }
+        }
+        piece.pos = piece.ghost_position(grid);
+        Some(piece)
+    }
+
+    /// How good the stack left by locking `piece` is. Higher is better.
+    fn score(grid: &Grid, piece: Piece) -> f32 {
+        let mut grid = grid.clone();
+        grid.overlay(piece.pos.x, piece.pos.y, piece.grid());
+        // Clearing a row only moves the ones above it, so the rest stay where they were found.
+        let full: Vec<_> = grid.full_rows().collect();
+        for &y in &full {
+            grid.clear_line(y);
+        }
+        let lines = full.len();
+
+        let height = grid.height() as i32;
+        let heights = grid.column_heights();
+        let holes: usize = heights
+            .iter()
+            .enumerate()
+            .map(|(x, &column)| {
+                (height - column as i32..height)
+                    .filter(|&y| grid.at(x as i32, y).is_none())
+                    .count()
+            })
+            .sum();
+        let bumpiness: i32 = heights
+            .windows(2)
+            .map(|pair| (pair[0] as i32 - pair[1] as i32).abs())
+            .sum();
+        let aggregate_height: i32 = heights.iter().sum::<usize>() as i32;
+
+        Self::HEIGHT_WEIGHT * aggregate_height as f32
+            + Self::LINES_WEIGHT * lines as f32
+            + Self::HOLES_WEIGHT * holes as f32
+            + Self::BUMPINESS_WEIGHT * bumpiness as f32
+    }
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Game time, which only advances in fixed steps so that games can be replayed exactly.
+/// Every timer in the rules is measured against it rather than the wall clock, so a frontend
+/// that stops stepping, because it is paused or its window lost focus, doesn't make time jump
+/// once it resumes.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct GameClock {
+    time: Duration,
+    /// Number of steps taken so far.
+    frame: u32,
+}
+
+impl GameClock {
+    pub const STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+    /// A clock that already shows `time`, for games continued from a save. Its steps are
+    /// counted from zero again.
+    pub fn starting_at(time: Duration) -> Self {
+        Self { time, frame: 0 }
+    }
+
+    pub fn tick(&mut self) {
+        self.frame += 1;
+        self.time += Self::STEP;
+    }
+
+    pub fn now(&self) -> Duration {
+        self.time
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Game time passed since `earlier`, a time read from `now` before.
+    pub fn since(&self, earlier: Duration) -> Duration {
+        self.time.saturating_sub(earlier)
+    }
+}
@@ -0,0 +1,105 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{BoardSize, Game},
+    grid::{Block, Grid},
+    mode::GameMode,
+    piece::{Piece, PieceKind},
+};
+
+/// Which pieces a combo drill deals.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ComboPieces {
+    All,
+    /// Everything but S and Z, which are the hardest to fit into a narrow well.
+    NoSZ,
+    TLJ,
+}
+
+impl ComboPieces {
+    /// Every choice, in the order they are listed in menus.
+    pub const ALL: [ComboPieces; 3] = [ComboPieces::All, ComboPieces::NoSZ, ComboPieces::TLJ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ComboPieces::All => "ALL",
+            ComboPieces::NoSZ => "NO S Z",
+            ComboPieces::TLJ => "T L J",
+        }
+    }
+
+    fn kinds(self) -> &'static [PieceKind] {
+        use PieceKind::*;
+        match self {
+            ComboPieces::All => &[I, J, L, O, S, T, Z],
+            ComboPieces::NoSZ => &[I, J, L, O, T],
+            ComboPieces::TLJ => &[T, L, J],
+        }
+    }
+}
+
+/// Practice at keeping a combo going, clearing a row with every piece in a narrow well
+/// whose walls build back up as rows clear.
+#[derive(Clone, Copy)]
+pub struct ComboDrill {
+    pub well_width: usize,
+    pub pieces: ComboPieces,
+}
+
+impl ComboDrill {
+    /// Widths of well to choose from, in the order they are listed in menus.
+    pub const WELL_WIDTHS: [usize; 2] = [4, 3];
+
+    /// A new attempt at the drill, with pieces picked by `seed`. The well starts with all
+    /// but one cell of its bottom row filled.
+    pub fn start(&self, seed: u32, board_size: BoardSize) -> Game {
+        let mut grid = Grid::new(board_size.width, board_size.height);
+        // Lined up with where pieces spawn, so they fall straight in.
+        let left = Piece::spawned(PieceKind::I, 1, &grid).pos.x as usize;
+        let well = Well {
+            columns: left..left + self.well_width,
+            // Left open above the walls, so that pieces spawn clear of them.
+            top: Game::HIDDEN_ROWS + Game::SPAWN_ROWS,
+        };
+        let bottom = board_size.height as i32 - 1;
+        for x in well.columns.start..well.columns.end - 1 {
+            grid.set(x as i32, bottom, Some(Block::GARBAGE));
+        }
+        let mut game = Game::from_setup(GameMode::Endless, seed, grid, []);
+        game.keep_well(well);
+        game.deal_only(self.pieces.kinds());
+        game
+    }
+}
+
+impl Default for ComboDrill {
+    fn default() -> Self {
+        Self {
+            well_width: Self::WELL_WIDTHS[0],
+            pieces: ComboPieces::All,
+        }
+    }
+}
+
+/// Walls filling every column but `columns`, from row `top` of the grid, hidden rows
+/// included, down to the floor.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Well {
+    pub columns: Range<usize>,
+    pub top: usize,
+}
+
+impl Well {
+    /// Fills whatever is missing of the walls.
+    pub fn build_walls(&self, grid: &mut Grid) {
+        for y in self.top..grid.height() {
+            for x in (0..grid.width()).filter(|x| !self.columns.contains(x)) {
+                if grid.at(x as i32, y as i32).is_none() {
+                    grid.set(x as i32, y as i32, Some(Block::GARBAGE));
+                }
+            }
+        }
+    }
+}
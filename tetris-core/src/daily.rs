@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use crate::mode::GameMode;
+
+/// The challenge of one day, which is the same for everyone who plays it that day: the
+/// mode and the seed both come from the date.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct DailyChallenge {
+    /// Days since the Unix epoch, in UTC.
+    pub day: u32,
+}
+
+impl DailyChallenge {
+    const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+    /// The challenge of the day that `timestamp`, in seconds since the Unix epoch, falls on.
+    pub fn at(timestamp: u64) -> Self {
+        Self {
+            day: (timestamp / Self::SECONDS_PER_DAY) as u32,
+        }
+    }
+
+    pub fn seed(self) -> u32 {
+        mix(self.day)
+    }
+
+    pub fn mode(self) -> GameMode {
+        // Mixed apart from the seed, so that modes don't follow the pieces.
+        let pick = mix(self.day ^ 0x5bd1_e995) as usize;
+        GameMode::ALL[pick % GameMode::ALL.len()]
+    }
+
+    /// Year, month from 1 and day of the month from 1.
+    pub fn date(self) -> (i32, u32, u32) {
+        civil_from_days(self.day as i64)
+    }
+
+    /// The challenge of the first day of the given month.
+    pub fn first_of_month(year: i32, month: u32) -> Self {
+        Self {
+            day: days_from_civil(year, month, 1).max(0) as u32,
+        }
+    }
+
+    /// Days from Sunday, from 0 to 6.
+    pub fn weekday(self) -> u32 {
+        // The epoch was a Thursday.
+        (self.day + 4) % 7
+    }
+
+    pub fn days_in_month(year: i32, month: u32) -> u32 {
+        let (next_year, next_month) = if month == 12 {
+            (year + 1, 1)
+        } else {
+            (year, month + 1)
+        };
+        (days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)) as u32
+    }
+}
+
+/// Scrambles `x` so that consecutive days give unrelated values.
+fn mix(x: u32) -> u32 {
+    let mut x = x.wrapping_add(0x9e37_79b9);
+    x = (x ^ x >> 16).wrapping_mul(0x85eb_ca6b);
+    x = (x ^ x >> 13).wrapping_mul(0xc2b2_ae35);
+    x ^ x >> 16
+}
+
+/// The date `days` after the Unix epoch, as in Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year as i32, month as u32, day as u32)
+}
+
+/// Days from the Unix epoch to a date, the other way around from `civil_from_days`.
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let shifted_month = (i64::from(month) + 9) % 12;
+    let day_of_year = (153 * shifted_month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The result of each day's challenge that was played, only the first attempt counting.
+#[derive(Clone, Default)]
+pub struct DailyResults {
+    /// The mode's record value by day, or `None` for a game without one, such as a sprint
+    /// that topped out.
+    results: BTreeMap<u32, Option<u64>>,
+}
+
+impl DailyResults {
+    /// Reads results written by `to_text`. Each line holds a day followed by its value, or
+    /// `-` if there was none; lines that don't make sense are skipped.
+    pub fn parse(contents: &str) -> Self {
+        let mut results = Self::default();
+        for line in contents.lines() {
+            let Some((day, value)) = line.trim().split_once(' ') else {
+                continue;
+            };
+            let Ok(day) = day.parse() else {
+                continue;
+            };
+            let value = match value.trim() {
+                "-" => None,
+                value => match value.parse() {
+                    Ok(value) => Some(value),
+                    Err(_) => continue,
+                },
+            };
+            results.results.insert(day, value);
+        }
+        results
+    }
+
+    pub fn to_text(&self) -> String {
+        self.results
+            .iter()
+            .map(|(day, value)| match value {
+                Some(value) => format!("{} {}\n", day, value),
+                None => format!("{} -\n", day),
+            })
+            .collect()
+    }
+
+    /// The result of a day, if it was played.
+    pub fn get(&self, challenge: DailyChallenge) -> Option<Option<u64>> {
+        self.results.get(&challenge.day).copied()
+    }
+
+    /// Records the result of a day's challenge, returning whether it was the first one.
+    pub fn record(&mut self, challenge: DailyChallenge, value: Option<u64>) -> bool {
+        if self.results.contains_key(&challenge.day) {
+            return false;
+        }
+        self.results.insert(challenge.day, value);
+        true
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+
+use mint::Point2;
+
+use crate::{
+    game::{BoardSize, Game},
+    grid::{Block, Grid},
+    mode::GameMode,
+    piece::PieceKind,
+};
+
+/// Returned when a drill can't be read, with the line that's wrong.
+#[derive(Debug)]
+pub struct InvalidDrill {
+    /// Counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for InvalidDrill {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for InvalidDrill {}
+
+/// How an attempt at a drill ended.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DrillOutcome {
+    Success,
+    Mistake,
+}
+
+/// A shape to build out of a set queue of pieces, starting from a set board, for practicing
+/// openers and setups.
+#[derive(Clone)]
+pub struct Drill {
+    pub name: String,
+    pieces: Vec<PieceKind>,
+    /// The board the drill starts on.
+    board: Grid,
+    /// Cells the pieces have to fill, and no others.
+    target: Vec<Point2<i32>>,
+}
+
+impl Drill {
+    /// Reads a drill written as text, with anything after a `#` ignored:
+    ///
+    /// ```text
+    /// name FILL AND CLEAR
+    /// pieces L J I
+    /// board
+    /// .........I
+    /// LLLJJJ...I
+    /// LXXXXJXXXI
+    /// XXXXXXXXXI
+    /// ```
+    ///
+    /// The rows after `board` are the bottom of the board, which is as wide as they are.
+    /// `X` is garbage the drill starts with, and the letters of pieces are the cells the
+    /// pieces have to fill. Which letter fills a cell doesn't matter, but naming the piece
+    /// makes the shape easier to follow.
+    pub fn parse(text: &str) -> Result<Self, InvalidDrill> {
+        let mut name = None;
+        let mut pieces = vec![];
+        let mut rows: Vec<(usize, &str)> = vec![];
+        let mut in_board = false;
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: String| InvalidDrill {
+                line: i + 1,
+                message,
+            };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if in_board {
+                rows.push((i, line));
+                continue;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "name" => name = Some(value.trim().to_owned()),
+                "pieces" => {
+                    for word in value.split_whitespace() {
+                        let piece = word
+                            .parse()
+                            .ok()
+                            .and_then(PieceKind::from_letter)
+                            .ok_or_else(|| error(format!("{} isn't a piece", word)))?;
+                        pieces.push(piece);
+                    }
+                }
+                "board" => in_board = true,
+                _ => return Err(error(format!("{} isn't a setting", key))),
+            }
+        }
+
+        let end = text.lines().count();
+        let name = name.ok_or(InvalidDrill {
+            line: end,
+            message: "the drill needs a name".to_owned(),
+        })?;
+        let width = rows.first().map_or(0, |(_, row)| row.len());
+        if !(BoardSize::MIN.width..=BoardSize::MAX.width).contains(&width) {
+            return Err(InvalidDrill {
+                line: rows.first().map_or(end, |(i, _)| i + 1),
+                message: format!("boards can't be {} cells wide", width),
+            });
+        }
+        let height = rows.len().max(BoardSize::default().height);
+        if height > BoardSize::MAX.height {
+            return Err(InvalidDrill {
+                line: end,
+                message: format!("boards can't be {} rows tall", height),
+            });
+        }
+
+        let mut board = Grid::new(width, height);
+        let mut target = vec![];
+        let top = (height - rows.len()) as i32;
+        for (y, (i, row)) in rows.iter().enumerate() {
+            let error = |message: String| InvalidDrill {
+                line: i + 1,
+                message,
+            };
+            if row.len() != width {
+                return Err(error("rows have to be as wide as the first".to_owned()));
+            }
+            let y = top + y as i32;
+            for (x, c) in row.chars().enumerate() {
+                let pos = Point2 { x: x as i32, y };
+                match c {
+                    '.' => {}
+                    'X' => board.set(pos.x, pos.y, Some(Block::GARBAGE)),
+                    c if PieceKind::from_letter(c).is_some() => target.push(pos),
+                    c => return Err(error(format!("{} isn't a cell", c))),
+                }
+            }
+        }
+        if target.len() != pieces.len() * 4 {
+            return Err(InvalidDrill {
+                line: end,
+                message: format!("{} pieces can't fill {} cells", pieces.len(), target.len()),
+            });
+        }
+        Ok(Self {
+            name,
+            pieces,
+            board,
+            target,
+        })
+    }
+
+    pub fn pieces(&self) -> &[PieceKind] {
+        &self.pieces
+    }
+
+    /// Cells the pieces have to fill, counting rows from the top of the field.
+    pub fn target(&self) -> &[Point2<i32>] {
+        &self.target
+    }
+
+    /// A new attempt at the drill, with pieces after the drill's picked by `seed`.
+    pub fn start(&self, seed: u32) -> Game {
+        Game::from_setup(
+            GameMode::Endless,
+            seed,
+            self.board.clone(),
+            self.pieces.iter().copied(),
+        )
+    }
+
+    /// How an attempt has gone after a piece locked, or `None` while it can still succeed.
+    pub fn judge(&self, game: &Game) -> Option<DrillOutcome> {
+        let grid = game.grid();
+        let hidden = grid.hidden_rows() as i32;
+        let mut filled = 0;
+        for (x, y, block) in grid.iter() {
+            // The drill's cells count from the top of the field, under the hidden rows.
+            let cell = Point2 { x, y: y - hidden };
+            let in_target = self.target.contains(&cell);
+            let was_garbage = self.board.at(cell.x, cell.y).is_some();
+            match block {
+                Some(_) if in_target => filled += 1,
+                Some(_) if !was_garbage => return Some(DrillOutcome::Mistake),
+                // Garbage that moved or was cleared means rows were cleared too early.
+                None if was_garbage => return Some(DrillOutcome::Mistake),
+                _ => {}
+            }
+        }
+        if filled == self.target.len() {
+            Some(DrillOutcome::Success)
+        } else if game.is_over() || game.pieces_placed() as usize >= self.pieces.len() {
+            Some(DrillOutcome::Mistake)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Finesse is placing each piece with as few presses as possible. Pieces are judged by
+//! where they ended up, ignoring the stack, so soft drops and tucks aren't accounted for.
+
+use std::collections::VecDeque;
+
+use crate::{
+    grid::Grid,
+    piece::{Piece, PieceRotation},
+    rotation::RotationSystem,
+};
+
+/// Cells `piece` covers, as columns and rows from its top, so that rotations that cover the
+/// same cells count as the same placement.
+fn footprint(piece: Piece) -> Vec<(i32, i32)> {
+    let (top_left, _) = piece.bounds();
+    let mut cells: Vec<_> = piece
+        .cells()
+        .map(|cell| (cell.x, cell.y - top_left.y))
+        .collect();
+    cells.sort_unstable();
+    cells
+}
+
+/// Fewest presses of move, rotate or a move held into the wall that take `start` to a
+/// placement covering the same columns as `target`, on an empty board `width` wide.
+pub(crate) fn min_presses(
+    width: usize,
+    rotation_system: &dyn RotationSystem,
+    start: Piece,
+    target: Piece,
+) -> u32 {
+    let grid = Grid::new(width, start.grid().height());
+    let goal = footprint(target);
+    let mut seen = vec![start];
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some((piece, presses)) = queue.pop_front() {
+        if footprint(piece) == goal {
+            return presses;
+        }
+        let rotated = |rotation: PieceRotation| {
+            let kicks = rotation_system.kicks(piece.kind, piece.rotation, rotation);
+            piece.rotated(rotation, kicks, &grid).unwrap_or(piece)
+        };
+        let slid = |direction: i32| {
+            let mut slid = piece;
+            while !moved_by(slid, direction).collides_with(&grid) {
+                slid = moved_by(slid, direction);
+            }
+            slid
+        };
+        let next = [
+            moved_by(piece, -1),
+            moved_by(piece, 1),
+            slid(-1),
+            slid(1),
+            rotated(piece.rotation.rotate_cw()),
+            rotated(piece.rotation.rotate_ccw()),
+        ];
+        for next in next {
+            let known = seen
+                .iter()
+                .any(|seen| seen.pos.x == next.pos.x && seen.rotation == next.rotation);
+            if !known && !next.collides_with(&grid) {
+                seen.push(next);
+                queue.push_back((next, presses + 1));
+            }
+        }
+    }
+    // The target can always be reached, since it's on the same board.
+    u32::MAX
+}
+
+/// `piece` moved one step left or right, which is as many cells as its blocks are wide.
+fn moved_by(mut piece: Piece, direction: i32) -> Piece {
+    piece.pos.x += direction * piece.scale;
+    piece
+}
@@ -0,0 +1,1540 @@
+use std::{collections::VecDeque, fmt, ops::Range, time::Duration};
+
+use enum_map::{Enum, EnumMap};
+use mint::Point2;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::GameClock,
+    combo::Well,
+    finesse,
+    grid::{Block, Grid},
+    heatmap::Heatmap,
+    input::{Action, FrameInput, Handling, InputBuffer, SoftDrop},
+    mode::{GameMode, GameStats},
+    piece::{Piece, PieceKind, PieceRotation},
+    piece_set::PieceSet,
+    rotation::RotationSystemKind,
+    rules::TopOut,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardSize {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl BoardSize {
+    /// Largest board allowed, past which cells get too small to make out.
+    pub const MAX: BoardSize = BoardSize {
+        width: 30,
+        height: 40,
+    };
+    /// Smallest board every piece fits in, turned any way, as pieces are up to
+    /// `PieceSet::MAX_SIZE` cells across.
+    pub const MIN: BoardSize = BoardSize {
+        width: PieceSet::MAX_SIZE,
+        height: PieceSet::MAX_SIZE,
+    };
+    /// Sizes the settings cycle through.
+    pub const PRESETS: [BoardSize; 5] = [
+        BoardSize {
+            width: 10,
+            height: 16,
+        },
+        BoardSize {
+            width: 10,
+            height: 20,
+        },
+        BoardSize {
+            width: 8,
+            height: 16,
+        },
+        BoardSize {
+            width: 16,
+            height: 20,
+        },
+        BoardSize {
+            width: 20,
+            height: 24,
+        },
+    ];
+
+    /// Whether this is between `MIN` and `MAX`, as sizes read from files and other players
+    /// have to be before a game is started on them.
+    pub fn is_allowed(self) -> bool {
+        (Self::MIN.width..=Self::MAX.width).contains(&self.width)
+            && (Self::MIN.height..=Self::MAX.height).contains(&self.height)
+    }
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        Self {
+            width: 10,
+            height: 16,
+        }
+    }
+}
+
+/// Returned when a saved game can't be continued, with what's wrong with it.
+#[derive(Debug)]
+pub struct InvalidSave {
+    pub message: String,
+}
+
+impl fmt::Display for InvalidSave {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for InvalidSave {}
+
+/// A game in progress, saved on quit so it can be continued later.
+#[derive(Serialize, Deserialize)]
+pub struct SavedGame {
+    width: usize,
+    /// Rows in all, hidden ones included.
+    height: usize,
+    #[serde(default)]
+    hidden_rows: usize,
+    /// Every cell in row order, as written by `block_code`.
+    blocks: Vec<u8>,
+    piece_x: i32,
+    piece_y: i32,
+    piece_rotation: PieceRotation,
+    piece_kind: PieceKind,
+    held_piece: Option<PieceKind>,
+    hold_used: bool,
+    lines_cleared: u32,
+    score: u32,
+    #[serde(default)]
+    pending_garbage: u32,
+    seed: u32,
+    /// Position in the random stream, so that the same pieces come next.
+    rng_position: u64,
+    /// How many of each kind of piece were dealt, in the order kinds are declared.
+    #[serde(default)]
+    dealt: Vec<u32>,
+    // Tables have to come last in TOML.
+    elapsed: Duration,
+    mode: GameMode,
+}
+
+impl SavedGame {
+    /// Garbage's code, right after the tetrominoes, where it was before there were any other
+    /// pieces. Those come after it, so that older saves still read the same.
+    const GARBAGE_CODE: u8 = PieceKind::TETROMINOES.len() as u8 + 1;
+
+    /// How a cell is saved: 0 if it's empty, and otherwise the piece it came from counting
+    /// from 1, or `GARBAGE_CODE`.
+    fn block_code(block: &Option<Block>) -> u8 {
+        match block {
+            None => 0,
+            Some(block) if block.garbage => Self::GARBAGE_CODE,
+            Some(block) => {
+                let code = block.kind.into_usize() as u8 + 1;
+                if code < Self::GARBAGE_CODE {
+                    code
+                } else {
+                    code + 1
+                }
+            }
+        }
+    }
+
+    /// The cell saved as `code`, which is garbage if it isn't any piece.
+    fn block(code: u8) -> Option<Block> {
+        let kind = match code {
+            0 => return None,
+            code if code < Self::GARBAGE_CODE => code - 1,
+            code if code > Self::GARBAGE_CODE => code - 2,
+            _ => return Some(Block::GARBAGE),
+        };
+        Some(if (kind as usize) < PieceKind::LENGTH {
+            Block::of(PieceKind::from_usize(kind.into()))
+        } else {
+            Block::GARBAGE
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct LineDestroyAnimation {
+    lines_to_destroy: Vec<Range<u32>>,
+    // 0.0 to 1.0
+    progress: f32,
+}
+
+/// The stack turning gray a row at a time from the bottom, after topping out.
+#[derive(Clone, Serialize, Deserialize)]
+struct CollapseAnimation {
+    // 0.0 to 1.0
+    progress: f32,
+}
+
+/// A held horizontal direction that repeats once DAS has charged.
+#[derive(Clone, Serialize, Deserialize)]
+struct AutoShift {
+    direction: i32,
+    /// Game time of the next step.
+    next_shift: Duration,
+}
+
+/// Something that happened during a step, so that frontends can react to it.
+#[derive(Clone, Copy)]
+pub enum GameEvent {
+    /// The falling piece moved sideways, by one or more columns.
+    Moved,
+    /// The falling piece was pushed sideways into a wall or the stack and didn't move.
+    Bumped,
+    /// The falling piece moved down a row while soft dropping.
+    SoftDropped,
+    /// The falling piece came to rest on the stack or the floor, and will lock if it falls
+    /// again.
+    Landed,
+    /// The falling piece was rotated.
+    Rotated,
+    /// The falling piece was swapped with the held one, or with the next piece if none was
+    /// held yet.
+    Held,
+    /// The falling piece was hard dropped, falling `rows` rows at once.
+    HardDropped {
+        rows: u32,
+    },
+    /// A piece locked into the grid, where it landed.
+    PieceLocked(Piece),
+    /// The piece that locked was moved and rotated with `presses` presses, when `needed`
+    /// would have been enough.
+    FinesseFault {
+        presses: u32,
+        needed: u32,
+    },
+    /// A T piece locked after being rotated into a spot with at least three of the corners
+    /// around its center filled.
+    TSpin,
+    /// Rows were completed and started flashing.
+    LinesCleared {
+        count: u32,
+        kind: ClearKind,
+    },
+    /// The flashing rows were removed, moving the rows above them down.
+    LinesRemoved,
+    /// Blocks left hanging by a clear fell a row, in cascade mode.
+    BlocksFell,
+    /// The rows just cleared were completed by blocks that fell after an earlier clear, as
+    /// the `chain`th clear set off by the same piece.
+    Chain {
+        chain: u32,
+    },
+    /// Rows were cleared with enough of them, or in a hard enough way, to send `rows` rows of
+    /// garbage to an opponent, after cancelling out any garbage on its way to this game.
+    GarbageSent {
+        rows: u32,
+    },
+    /// Garbage rows rose under the stack, pushing it up by `rows` rows.
+    GarbageReceived {
+        rows: u32,
+    },
+    LevelUp {
+        level: u32,
+    },
+    /// The game ended, by reaching the mode's goal if `completed` and by topping out if not.
+    GameOver {
+        completed: bool,
+    },
+}
+
+/// How the piece that completed rows was placed.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ClearKind {
+    Normal,
+    TSpin,
+}
+
+/// How many times rows were cleared in each way.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ClearCounts {
+    pub singles: u32,
+    pub doubles: u32,
+    pub triples: u32,
+    pub tetrises: u32,
+    /// T-spins that cleared rows, however many.
+    pub t_spins: u32,
+}
+
+/// Where the points of a game came from.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub lines: u32,
+    pub soft_drop: u32,
+    pub hard_drop: u32,
+    /// Points for every second survived, in survival.
+    pub survival: u32,
+}
+
+/// A game being played, advanced one fixed step at a time. Stepping a clone with the same
+/// inputs always gives the same game, which is what lets online matches roll back, and so
+/// does stepping one serialized and read back, random state included.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Game {
+    mode: GameMode,
+
+    grid: Grid,
+
+    time_last_moved_piece: Duration,
+    auto_shift: Option<AutoShift>,
+    /// Whether the last thing that moved the falling piece was a rotation, which T-spins
+    /// have to end with.
+    last_move_rotated: bool,
+
+    game_over: bool,
+
+    /// Whether the game ended by reaching the mode's goal rather than by topping out.
+    completed: bool,
+
+    lines_cleared: u32,
+    score: u32,
+    /// Pieces locked into the grid so far.
+    pieces_placed: u32,
+    clears: ClearCounts,
+    score_breakdown: ScoreBreakdown,
+    /// Pieces in a row that cleared rows, up to the last one placed.
+    combo: u32,
+    max_combo: u32,
+    /// Which of the clears set off by the last piece placed the next one would be, counting
+    /// from 1. Only goes past 1 in cascade mode.
+    chain: u32,
+    max_chain: u32,
+    /// Rows of garbage clears were worth, including any that only cancelled garbage out.
+    attack: u32,
+    /// Move and rotate presses made with the falling piece, to judge its finesse by.
+    presses: u32,
+    /// Pieces placed with more presses than needed.
+    finesse_faults: u32,
+    heatmap: Heatmap,
+    /// How many of each kind of piece were dealt, the falling one included.
+    dealt: EnumMap<PieceKind, u32>,
+    clock: GameClock,
+    time_finished: Option<Duration>,
+
+    piece_falling: Piece,
+    /// The falling piece as it spawned.
+    piece_spawned: Piece,
+    held_piece: Option<PieceKind>,
+    /// Whether hold was already used for the falling piece; it can only be used once per piece.
+    hold_used: bool,
+    /// Whether the falling piece spawned since it could last be moved, so that it can be
+    /// turned as it comes in.
+    just_spawned: bool,
+    /// How pieces come in and turn, kept up to date with the handling every step.
+    rotation_system: RotationSystemKind,
+    /// Kept up to date with the handling every step, like the rotation system.
+    top_out: TopOut,
+    /// Time between a piece locking and the next coming in, from the handling.
+    entry_delay: Duration,
+    /// How long auto shift holds off after the piece turns or comes in, from the handling.
+    das_cut_delay: Duration,
+    /// Whether the player allows holding, from the handling.
+    hold_enabled: bool,
+    /// How long completed rows flash before they are removed, from the handling.
+    line_clear_delay: Duration,
+    /// Game time the next piece comes in, while waiting for it after one locked.
+    next_spawn: Option<Duration>,
+    /// Presses made while the falling piece couldn't be moved, for initial rotation and hold.
+    input_buffer: InputBuffer,
+
+    line_destroy_animations: Option<LineDestroyAnimation>,
+    /// Started once the game tops out, after which stepping only moves it on. It stays at
+    /// the end once it gets there.
+    collapse_animation: Option<CollapseAnimation>,
+    /// Game time blocks left hanging by a clear next fall a row, while they're falling.
+    next_cascade_fall: Option<Duration>,
+
+    /// Rows of garbage sent by an opponent that will rise once a piece locks without
+    /// clearing any rows.
+    pending_garbage: u32,
+    /// Game time the next row of garbage rises on its own, in modes where it does.
+    next_garbage_rise: Option<Duration>,
+
+    /// Pieces that come next before random ones do, for games set up to practice something.
+    queue: VecDeque<PieceKind>,
+    /// Kinds random pieces are picked from, or `None` for all of them.
+    piece_kinds: Option<Box<[PieceKind]>>,
+    /// Pieces dealt over and over instead of random ones, for drilling a sequence, along
+    /// with the index of the next one.
+    repeated: Option<(Box<[PieceKind]>, usize)>,
+    /// A well whose walls are built back up as rows clear, for combo practice.
+    well: Option<Well>,
+    seed: u32,
+    rng: ChaCha8Rng,
+    /// Picks where the holes of garbage rows are, apart from `rng` so that garbage doesn't
+    /// change which pieces come next.
+    garbage_rng: ChaCha8Rng,
+}
+
+impl Game {
+    pub const FRAME_TIME: Duration = GameClock::STEP;
+    /// How long a piece falling at 20G rests on the stack before it locks, counting from
+    /// when it last fell a row.
+    const LOCK_DELAY: Duration = Duration::from_millis(500);
+    /// How long blocks left hanging by a clear take to fall each row.
+    const CASCADE_FALL_TIME: Duration = Duration::from_millis(50);
+    /// How long the stack takes to turn gray after topping out.
+    const COLLAPSE_TIME: Duration = Duration::from_millis(1000);
+    /// Rows at the top of the field that pieces spawn in.
+    pub(crate) const SPAWN_ROWS: usize = 4;
+    /// Rows out of sight above the field, as many as it has by the guideline's 10 by 20.
+    pub const HIDDEN_ROWS: usize = 20;
+
+    /// A new game on a board of `board_size`, measured in blocks of the mode's pieces. In big
+    /// mode, that makes the grid larger by as much as the pieces are, as far as it can be.
+    pub fn new(mode: GameMode, seed: u32, board_size: BoardSize) -> Self {
+        let scale = mode.piece_scale();
+        let mut grid = Grid::new(
+            board_size.width.min(Grid::MAX_WIDTH / scale as usize) * scale as usize,
+            board_size.height * scale as usize,
+        )
+        .with_hidden_rows(Self::HIDDEN_ROWS * scale as usize);
+        let piece = Piece {
+            rotation: PieceRotation::Deg90,
+            ..Piece::spawned(PieceKind::J, scale, &grid)
+        };
+        let mut garbage_rng = ChaCha8Rng::seed_from_u64(seed.into());
+        garbage_rng.set_stream(1);
+        // Garbage stops short of where pieces spawn, however much the mode asks for.
+        let garbage_rows =
+            (mode.garbage_rows() as usize).min(board_size.height.saturating_sub(Self::SPAWN_ROWS));
+        for _ in 0..garbage_rows {
+            let hole = garbage_rng.gen_range(0..board_size.width);
+            grid.push_garbage(1, hole);
+        }
+
+        let mut game = Game {
+            mode,
+            game_over: false,
+
+            completed: false,
+
+            lines_cleared: 0,
+            score: 0,
+            pieces_placed: 0,
+            clears: ClearCounts::default(),
+            score_breakdown: ScoreBreakdown::default(),
+            combo: 0,
+            max_combo: 0,
+            chain: 1,
+            max_chain: 0,
+            attack: 0,
+            presses: 0,
+            finesse_faults: 0,
+            heatmap: Heatmap::new(grid.width(), grid.visible_height()),
+            dealt: EnumMap::default(),
+            clock: GameClock::default(),
+            time_finished: None,
+
+            grid,
+            piece_falling: piece,
+            piece_spawned: piece,
+            held_piece: None,
+            hold_used: false,
+            just_spawned: true,
+            rotation_system: mode.rules().rotation_system.unwrap_or_default(),
+            top_out: TopOut::default(),
+            entry_delay: Handling::default().are,
+            das_cut_delay: Handling::default().dcd,
+            hold_enabled: Handling::default().hold,
+            line_clear_delay: Handling::default().line_clear_delay,
+            next_spawn: None,
+            input_buffer: InputBuffer::default(),
+            time_last_moved_piece: Duration::ZERO,
+            auto_shift: None,
+            last_move_rotated: false,
+            line_destroy_animations: None,
+            collapse_animation: None,
+            next_cascade_fall: None,
+            pending_garbage: 0,
+            next_garbage_rise: mode.garbage_interval(Duration::ZERO),
+            queue: VecDeque::new(),
+            piece_kinds: None,
+            repeated: None,
+            well: None,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed.into()),
+            garbage_rng,
+        };
+        game.dealt[piece.kind] = 1;
+        if let Some(kinds) = mode.piece_kinds() {
+            game.deal_only(&kinds);
+        }
+        game
+    }
+
+    /// A game starting on `grid`, which is the field without hidden rows, whose first pieces
+    /// are `queue` in order before random ones come.
+    pub fn from_setup(
+        mode: GameMode,
+        seed: u32,
+        grid: Grid,
+        queue: impl IntoIterator<Item = PieceKind>,
+    ) -> Self {
+        let board_size = BoardSize {
+            width: grid.width(),
+            height: grid.height(),
+        };
+        let mut game = Self::new(mode, seed, board_size);
+        game.grid = grid.with_hidden_rows(Self::HIDDEN_ROWS * mode.piece_scale() as usize);
+        game.queue = queue.into_iter().collect();
+        game.deal_again();
+        game
+    }
+
+    /// Deals only pieces of `kinds` once the queue runs out, starting with the falling piece.
+    pub(crate) fn deal_only(&mut self, kinds: &[PieceKind]) {
+        self.piece_kinds = Some(kinds.into());
+        self.deal_again();
+    }
+
+    /// Deals `sequence` over and over once the queue runs out, instead of random pieces.
+    /// The falling piece is dealt again, so this is for games that have just started.
+    pub fn repeat_sequence(&mut self, sequence: &[PieceKind]) {
+        if sequence.is_empty() {
+            return;
+        }
+        self.repeated = Some((sequence.into(), 0));
+        self.deal_again();
+    }
+
+    /// Deals the falling piece again, as if the one dealt before never was, for games that
+    /// have just started.
+    fn deal_again(&mut self) {
+        self.dealt = EnumMap::default();
+        let first = self.next_piece();
+        self.spawn_piece(first);
+    }
+
+    /// Builds the walls of `well` and keeps building them back up whenever rows clear.
+    pub(crate) fn keep_well(&mut self, well: Well) {
+        well.build_walls(&mut self.grid);
+        self.well = Some(well);
+    }
+
+    /// Continues a saved game, unless the save's board is of a size no game can be played on,
+    /// as it can be in saves that were edited by hand.
+    pub fn from_saved(saved: SavedGame) -> Result<Self, InvalidSave> {
+        let scale = saved.mode.piece_scale() as usize;
+        let Some(height) = saved.height.checked_sub(saved.hidden_rows) else {
+            return Err(InvalidSave {
+                message: format!(
+                    "{} hidden rows on a board {} rows high",
+                    saved.hidden_rows, saved.height
+                ),
+            });
+        };
+        let board_size = BoardSize {
+            width: saved.width / scale,
+            height: height / scale,
+        };
+        if !board_size.is_allowed() {
+            return Err(InvalidSave {
+                message: format!("the board is {}x{}", board_size.width, board_size.height),
+            });
+        }
+        let mut game = Self::new(saved.mode, saved.seed, board_size);
+        // Saves from before there were hidden rows, or with fewer of them, are lined up with
+        // the top of the field. Blocks that don't fit, as in saves that were edited by hand,
+        // are dropped.
+        let hidden_rows = game.grid.hidden_rows() as i32 - saved.hidden_rows as i32;
+        for (i, code) in saved.blocks.into_iter().enumerate() {
+            let x = (i % game.grid.width()) as i32;
+            let y = (i / game.grid.width()) as i32 + hidden_rows;
+            let _ = game.grid.try_set(x, y, SavedGame::block(code));
+        }
+        game.piece_falling = Piece {
+            pos: Point2 {
+                x: saved.piece_x,
+                y: saved.piece_y + hidden_rows,
+            },
+            rotation: saved.piece_rotation,
+            kind: saved.piece_kind,
+            scale: scale as i32,
+        };
+        game.piece_spawned = game.piece_falling;
+        game.held_piece = saved.held_piece;
+        game.hold_used = saved.hold_used;
+        game.lines_cleared = saved.lines_cleared;
+        game.score = saved.score;
+        game.pending_garbage = saved.pending_garbage;
+        game.clock = GameClock::starting_at(saved.elapsed);
+        game.next_garbage_rise = saved
+            .mode
+            .garbage_interval(saved.elapsed)
+            .map(|interval| saved.elapsed + interval);
+        game.time_last_moved_piece = saved.elapsed;
+        game.rng.set_word_pos(saved.rng_position as u128);
+        for (kind, count) in &mut game.dealt {
+            *count = saved.dealt.get(kind.into_usize()).copied().unwrap_or(0);
+        }
+        // Saves from before pieces were counted only count the falling one.
+        if saved.dealt.is_empty() {
+            game.dealt[saved.piece_kind] = 1;
+        }
+        Ok(game)
+    }
+
+    /// The game as it is, to be continued with `from_saved`. Games that are over can't be
+    /// continued.
+    pub fn saved(&self) -> Option<SavedGame> {
+        if self.game_over {
+            return None;
+        }
+        // Between pieces, the next one comes in straight away once the game is continued.
+        if self.is_entering() {
+            let mut game = self.clone();
+            game.next_spawn = None;
+            game.spawn_next_piece();
+            return game.saved();
+        }
+        // Lines that are still flashing are cleared in the save, as they would be once the
+        // animation ends, and blocks still falling are dropped the rest of the way.
+        let mut grid = self.grid.clone();
+        let mut rows = 0;
+        for line in self.clearing_lines() {
+            grid.clear_line(line as i32);
+            rows += 1;
+        }
+        if self.mode.cascades() {
+            while grid.drop_loose_groups() {}
+        }
+        let lines_cleared = self.lines_cleared + rows / self.piece_falling.scale as u32;
+        let blocks = grid
+            .iter()
+            .map(|(_, _, block)| SavedGame::block_code(block))
+            .collect();
+        Some(SavedGame {
+            mode: self.mode,
+            width: grid.width(),
+            height: grid.height(),
+            hidden_rows: grid.hidden_rows(),
+            blocks,
+            piece_x: self.piece_falling.pos.x,
+            piece_y: self.piece_falling.pos.y,
+            piece_rotation: self.piece_falling.rotation,
+            piece_kind: self.piece_falling.kind,
+            held_piece: self.held_piece,
+            hold_used: self.hold_used,
+            lines_cleared,
+            score: self.score,
+            pending_garbage: self.pending_garbage,
+            elapsed: self.stats().elapsed,
+            seed: self.seed,
+            rng_position: self.rng.get_word_pos() as u64,
+            dealt: self.dealt.values().copied().collect(),
+        })
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// What the pieces were picked with.
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn piece(&self) -> Piece {
+        self.piece_falling
+    }
+
+    pub fn held_piece(&self) -> Option<PieceKind> {
+        self.held_piece
+    }
+
+    pub fn hold_used(&self) -> bool {
+        self.hold_used
+    }
+
+    /// Pieces set to come next, in order. Random pieces come once it runs out.
+    pub fn queue(&self) -> impl Iterator<Item = PieceKind> + '_ {
+        self.queue.iter().copied()
+    }
+
+    /// The next `count` pieces to come, set or random, in order. Pieces that are held come
+    /// out of the same line, so holding with nothing held takes the first of these.
+    pub fn next_pieces(&self, count: usize) -> Vec<PieceKind> {
+        let dealt: Box<dyn Iterator<Item = PieceKind>> = match &self.repeated {
+            Some((sequence, next)) => Box::new(sequence.iter().copied().cycle().skip(*next)),
+            None => {
+                let mut rng = self.rng.clone();
+                let kinds = self.piece_kinds.as_deref();
+                Box::new(std::iter::repeat_with(move || {
+                    Self::random_piece(kinds, &mut rng)
+                }))
+            }
+        };
+        self.queue().chain(dealt).take(count).collect()
+    }
+
+    /// Whether the mode and the handling both allow holding pieces.
+    pub fn can_hold(&self) -> bool {
+        self.mode.rules().hold && self.hold_enabled
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.game_over
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    /// Number of pieces locked into the grid so far, since the game was started or continued.
+    pub fn pieces_placed(&self) -> u32 {
+        self.pieces_placed
+    }
+
+    /// How many times rows were cleared in each way, since the game was started or continued.
+    pub fn clear_counts(&self) -> ClearCounts {
+        self.clears
+    }
+
+    /// Where the points were scored, since the game was started or continued.
+    pub fn score_breakdown(&self) -> ScoreBreakdown {
+        self.score_breakdown
+    }
+
+    /// Pieces in a row that cleared rows, up to the last one placed.
+    pub fn combo(&self) -> u32 {
+        self.combo
+    }
+
+    /// Most pieces in a row that cleared rows, since the game was started or continued.
+    pub fn max_combo(&self) -> u32 {
+        self.max_combo
+    }
+
+    /// Rows of garbage all clears were worth, since the game was started or continued.
+    pub fn total_attack(&self) -> u32 {
+        self.attack
+    }
+
+    /// Number of pieces placed with more presses than needed, since the game was started or
+    /// continued.
+    pub fn finesse_faults(&self) -> u32 {
+        self.finesse_faults
+    }
+
+    /// How many pieces of `kind` were dealt this game, the falling one included.
+    pub fn dealt(&self, kind: PieceKind) -> u32 {
+        self.dealt[kind]
+    }
+
+    /// Where the pieces of this game were locked and the holes they left.
+    pub fn heatmap(&self) -> &Heatmap {
+        &self.heatmap
+    }
+
+    /// Number of steps simulated so far.
+    pub fn frame(&self) -> u32 {
+        self.clock.frame()
+    }
+
+    pub fn clock(&self) -> GameClock {
+        self.clock
+    }
+
+    /// Rows of garbage waiting to rise under the stack.
+    pub fn pending_garbage(&self) -> u32 {
+        self.pending_garbage
+    }
+
+    /// Queues rows of garbage sent by an opponent. They rise under the stack once a piece
+    /// locks without clearing any rows, unless clears cancel them out first.
+    pub fn receive_garbage(&mut self, rows: u32) {
+        self.pending_garbage += rows;
+    }
+
+    /// Rows that have been completed and are flashing before they are removed.
+    pub fn clearing_lines(&self) -> impl Iterator<Item = u32> + '_ {
+        self.line_destroy_animations
+            .iter()
+            .flat_map(|anim| anim.lines_to_destroy.iter().flat_map(Range::clone))
+    }
+
+    /// How far the rows in `clearing_lines` are through their flash, from 0 to 1.
+    /// `interpolation` is the fraction of a step that has passed since the last one, so that
+    /// frontends drawing faster than the game steps can still animate smoothly.
+    pub fn line_clear_progress(&self, interpolation: f32) -> f32 {
+        self.line_destroy_animations.as_ref().map_or(0., |anim| {
+            (anim.progress + interpolation * self.line_clear_step()).min(1.)
+        })
+    }
+
+    /// How far the stack is through turning gray after topping out, from 0 to 1, or `None`
+    /// if the game didn't top out. It turns from the bottom up, a row at a time.
+    pub fn collapse_progress(&self, interpolation: f32) -> Option<f32> {
+        let step = Self::FRAME_TIME.as_secs_f32() / Self::COLLAPSE_TIME.as_secs_f32();
+        self.collapse_animation
+            .as_ref()
+            .map(|anim| (anim.progress + interpolation * step).min(1.))
+    }
+
+    /// Whether the game is over but the stack is still turning gray, which it only does
+    /// while the game keeps being stepped.
+    pub fn is_collapsing(&self) -> bool {
+        self.collapse_animation
+            .as_ref()
+            .is_some_and(|anim| anim.progress < 1.)
+    }
+
+    /// How far rows get through their flash each step. Rows always flash for at least one.
+    fn line_clear_step(&self) -> f32 {
+        Self::FRAME_TIME.as_secs_f32() / self.line_clear_delay.max(Self::FRAME_TIME).as_secs_f32()
+    }
+
+    /// Whether a piece locked and the next one hasn't come in yet, during the entry delay.
+    /// `piece` is still the piece that locked until it does.
+    pub fn is_entering(&self) -> bool {
+        self.next_spawn.is_some()
+    }
+
+    /// Moves the falling piece horizontally, returning whether it could move.
+    fn try_shift(&mut self, direction: i32) -> bool {
+        let cells = direction * self.piece_falling.scale;
+        self.piece_falling.pos.x += cells;
+        if self.piece_falling.collides_with(&self.grid) {
+            self.piece_falling.pos.x -= cells;
+            false
+        } else {
+            self.last_move_rotated = false;
+            true
+        }
+    }
+
+    fn update_auto_shift(
+        &mut self,
+        input: FrameInput,
+        handling: Handling,
+        events: &mut Vec<GameEvent>,
+    ) {
+        for (action, direction) in [(Action::MoveLeft, -1), (Action::MoveRight, 1)] {
+            if input.just_pressed(action) {
+                events.push(if self.try_shift(direction) {
+                    GameEvent::Moved
+                } else {
+                    GameEvent::Bumped
+                });
+                self.auto_shift = Some(AutoShift {
+                    direction,
+                    next_shift: self.clock.now() + handling.das,
+                });
+            }
+        }
+
+        let Some(AutoShift {
+            direction,
+            mut next_shift,
+        }) = self.auto_shift
+        else {
+            return;
+        };
+        let action = if direction < 0 {
+            Action::MoveLeft
+        } else {
+            Action::MoveRight
+        };
+        if !input.pressed(action) {
+            self.auto_shift = None;
+            return;
+        }
+        let now = self.clock.now();
+        let mut moved = false;
+        if handling.arr.is_zero() {
+            if next_shift <= now {
+                while self.try_shift(direction) {
+                    moved = true;
+                }
+            }
+        } else {
+            while next_shift <= now {
+                moved |= self.try_shift(direction);
+                next_shift += handling.arr;
+            }
+        }
+        if moved {
+            events.push(GameEvent::Moved);
+        }
+        self.auto_shift = Some(AutoShift {
+            direction,
+            next_shift,
+        });
+    }
+
+    /// Turns the falling piece to `rotation`, kicking it if it has to, unless it can't fit.
+    fn rotate(&mut self, rotation: PieceRotation, events: &mut Vec<GameEvent>) {
+        let piece = self.piece_falling;
+        let kicks = self
+            .rotation_system
+            .system()
+            .kicks(piece.kind, piece.rotation, rotation);
+        if let Some(rotated) = piece.rotated(rotation, kicks, &self.grid) {
+            self.piece_falling = rotated;
+            self.last_move_rotated = true;
+            self.cut_auto_shift();
+            events.push(GameEvent::Rotated);
+        }
+    }
+
+    /// Holds off auto shift for the DAS cut delay, if a direction is held.
+    fn cut_auto_shift(&mut self) {
+        let resume = self.clock.now() + self.das_cut_delay;
+        if let Some(shift) = &mut self.auto_shift {
+            shift.next_shift = shift.next_shift.max(resume);
+        }
+    }
+
+    fn spawn_piece(&mut self, kind: PieceKind) {
+        self.piece_falling = Piece {
+            rotation: self.rotation_system.system().spawn_rotation(kind),
+            ..Piece::spawned(kind, self.piece_falling.scale, &self.grid)
+        };
+        self.piece_spawned = self.piece_falling;
+        self.just_spawned = true;
+        self.presses = 0;
+        self.last_move_rotated = false;
+        self.cut_auto_shift();
+        if !self.top_out.block_out {
+            let mut raised = self.piece_falling;
+            let top = -(raised.grid().height() as i32);
+            while raised.collides_with(&self.grid) && raised.pos.y > top {
+                raised.pos.y -= raised.scale;
+            }
+            if !raised.collides_with(&self.grid) {
+                self.piece_falling = raised;
+                self.piece_spawned = raised;
+            }
+        }
+        if self.piece_falling.collides_with(&self.grid) {
+            self.end_game();
+        }
+    }
+
+    /// Whether the falling piece lies entirely in the hidden rows, above the field.
+    fn is_above_field(&self) -> bool {
+        let (_, bottom_right) = self.piece_falling.bounds();
+        bottom_right.y < self.grid.hidden_rows() as i32
+    }
+
+    fn next_piece(&mut self) -> PieceKind {
+        let kind = match (self.queue.pop_front(), &mut self.repeated) {
+            (Some(kind), _) => kind,
+            (None, Some((sequence, next))) => {
+                let kind = sequence[*next];
+                *next = (*next + 1) % sequence.len();
+                kind
+            }
+            (None, None) => Self::random_piece(self.piece_kinds.as_deref(), &mut self.rng),
+        };
+        self.dealt[kind] += 1;
+        kind
+    }
+
+    /// A piece picked with `rng` out of `kinds`, or out of all of them if it's `None`.
+    fn random_piece(kinds: Option<&[PieceKind]>, rng: &mut ChaCha8Rng) -> PieceKind {
+        match kinds {
+            Some(kinds) => kinds[rng.gen_range(0..kinds.len())],
+            None => PieceKind::random(rng),
+        }
+    }
+
+    fn hold_piece(&mut self, events: &mut Vec<GameEvent>) {
+        if self.hold_used {
+            return;
+        }
+        let next = self
+            .held_piece
+            .replace(self.piece_falling.kind)
+            .unwrap_or_else(|| self.next_piece());
+        self.spawn_piece(next);
+        self.time_last_moved_piece = self.clock.now();
+        self.hold_used = true;
+        events.push(GameEvent::Held);
+    }
+
+    /// Where the falling piece would land if it was hard dropped.
+    pub fn ghost_piece(&self) -> Piece {
+        Piece {
+            pos: self.piece_falling.ghost_position(&self.grid),
+            ..self.piece_falling
+        }
+    }
+
+    /// Whether the falling piece is a T that was just spun into place, going by how many
+    /// corners around its center are blocked by the stack or the walls.
+    fn is_t_spin(&self) -> bool {
+        let piece = self.piece_falling;
+        if piece.kind != PieceKind::T || !self.last_move_rotated {
+            return false;
+        }
+        // Every rotation of the standard T grid has its center at (1, 1), in blocks.
+        let blocked = [(0, 0), (2, 0), (0, 2), (2, 2)]
+            .into_iter()
+            .filter(|(x, y)| {
+                let (x, y) = (piece.pos.x + x * piece.scale, piece.pos.y + y * piece.scale);
+                !self.grid.contains_pos(x, y) || self.grid.at(x, y).is_some()
+            })
+            .count();
+        blocked >= 3
+    }
+
+    fn place_current_piece(&mut self, events: &mut Vec<GameEvent>) {
+        let kind = if self.mode.rules().t_spins && self.is_t_spin() {
+            events.push(GameEvent::TSpin);
+            ClearKind::TSpin
+        } else {
+            ClearKind::Normal
+        };
+        events.push(GameEvent::PieceLocked(self.piece_falling));
+        self.heatmap.record(&self.grid, self.piece_falling);
+        let locked_out = self.top_out.lock_out && self.is_above_field();
+        self.grid.overlay(
+            self.piece_falling.pos.x,
+            self.piece_falling.pos.y,
+            self.piece_falling.grid(),
+        );
+        self.pieces_placed += 1;
+        self.chain = 1;
+        let needed = finesse::min_presses(
+            self.grid.width(),
+            self.rotation_system.system(),
+            self.piece_spawned,
+            self.piece_falling,
+        );
+        if self.presses > needed {
+            self.finesse_faults += 1;
+            events.push(GameEvent::FinesseFault {
+                presses: self.presses,
+                needed,
+            });
+        }
+        if locked_out {
+            self.end_game();
+            return;
+        }
+        if self.check_lines(events, kind) {
+            self.combo += 1;
+            self.max_combo = self.max_combo.max(self.combo);
+        } else {
+            self.combo = 0;
+            self.raise_garbage(events);
+        }
+        if self.game_over {
+            return;
+        }
+        if self.entry_delay.is_zero() {
+            self.spawn_next_piece();
+        } else {
+            self.next_spawn = Some(self.clock.now() + self.entry_delay);
+        }
+    }
+
+    fn spawn_next_piece(&mut self) {
+        let next = self.next_piece();
+        self.spawn_piece(next);
+        self.hold_used = false;
+    }
+
+    /// Rows of garbage a clear sends to an opponent.
+    pub fn attack(line_count: u32, kind: ClearKind) -> u32 {
+        match (kind, line_count) {
+            (ClearKind::TSpin, count) => count * 2,
+            (ClearKind::Normal, 1) => 0,
+            (ClearKind::Normal, 2) => 1,
+            (ClearKind::Normal, 3) => 2,
+            (ClearKind::Normal, _) => 4,
+        }
+    }
+
+    /// Raises the pending garbage under the stack, which tops out if it's pushed past the
+    /// top and garbage-out is on.
+    fn raise_garbage(&mut self, events: &mut Vec<GameEvent>) {
+        if self.pending_garbage == 0 {
+            return;
+        }
+        let rows = std::mem::take(&mut self.pending_garbage);
+        // Every row that rises at once shares the same hole.
+        let hole = self.garbage_rng.gen_range(0..self.grid.width());
+        let overflowed = self.grid.push_garbage(rows as usize, hole);
+        events.push(GameEvent::GarbageReceived { rows });
+        if overflowed && self.top_out.garbage_out {
+            self.end_game();
+        }
+    }
+
+    /// Raises a row of garbage if it's time for one, in modes where garbage rises on its own.
+    /// The falling piece is pushed up along with the stack, and tops out if there's no room.
+    fn raise_garbage_on_time(&mut self, events: &mut Vec<GameEvent>) {
+        let now = self.clock.now();
+        if self.next_garbage_rise.is_none_or(|time| now < time) {
+            return;
+        }
+        self.next_garbage_rise = self
+            .mode
+            .garbage_interval(now)
+            .map(|interval| now + interval);
+        let hole = self.garbage_rng.gen_range(0..self.grid.width());
+        let overflowed = self.grid.push_garbage(1, hole);
+        events.push(GameEvent::GarbageReceived { rows: 1 });
+        let mut topped_out = overflowed && self.top_out.garbage_out;
+        // Between pieces, only the stack can top out.
+        if !self.is_entering() && self.piece_falling.collides_with(&self.grid) {
+            self.piece_falling.pos.y -= 1;
+            topped_out |= self.piece_falling.collides_with(&self.grid);
+        }
+        if topped_out {
+            self.end_game();
+        }
+    }
+
+    /// Scores every second survived, in survival.
+    fn score_time_survived(&mut self) {
+        let now = self.clock.now();
+        let new_second = now.as_secs() > now.saturating_sub(Self::FRAME_TIME).as_secs();
+        if self.mode == GameMode::Survival && new_second {
+            self.score += GameMode::SURVIVAL_POINTS_PER_SECOND;
+            self.score_breakdown.survival += GameMode::SURVIVAL_POINTS_PER_SECOND;
+        }
+    }
+
+    /// Starts clearing the rows that are full, returning whether there were any.
+    fn check_lines(&mut self, events: &mut Vec<GameEvent>, kind: ClearKind) -> bool {
+        let mut last_line_to_destroy = None;
+        let mut lines_to_destroy = vec![];
+        for y in 0..self.grid.height() as u32 {
+            if self.grid.is_row_full(y as i32) {
+                if last_line_to_destroy.is_none() {
+                    last_line_to_destroy = Some(y);
+                }
+            } else if let Some(l) = last_line_to_destroy {
+                last_line_to_destroy = None;
+                lines_to_destroy.push(l..y);
+            }
+        }
+        if let Some(l) = last_line_to_destroy {
+            lines_to_destroy.push(l..self.grid.height() as u32);
+        }
+        if !lines_to_destroy.is_empty() {
+            // Rows of big blocks always clear together, and count as one.
+            let line_count = lines_to_destroy
+                .iter()
+                .map(|lines| lines.len() as u32)
+                .sum::<u32>()
+                / self.piece_falling.scale as u32;
+            let points = self.mode.level(self.lines_cleared)
+                * self.chain
+                * self.mode.rules().scoring.clear_points(line_count);
+            self.score += points;
+            self.score_breakdown.lines += points;
+            let clears = &mut self.clears;
+            *match (kind, line_count) {
+                (ClearKind::TSpin, _) => &mut clears.t_spins,
+                (ClearKind::Normal, 1) => &mut clears.singles,
+                (ClearKind::Normal, 2) => &mut clears.doubles,
+                (ClearKind::Normal, 3) => &mut clears.triples,
+                (ClearKind::Normal, _) => &mut clears.tetrises,
+            } += 1;
+            self.line_destroy_animations = Some(LineDestroyAnimation {
+                lines_to_destroy,
+                progress: 0.,
+            });
+            events.push(GameEvent::LinesCleared {
+                count: line_count,
+                kind,
+            });
+            if self.chain > 1 {
+                events.push(GameEvent::Chain { chain: self.chain });
+            }
+            self.max_chain = self.max_chain.max(self.chain);
+            let attack = Self::attack(line_count, kind);
+            self.attack += attack;
+            let cancelled = attack.min(self.pending_garbage);
+            self.pending_garbage -= cancelled;
+            if attack > cancelled {
+                events.push(GameEvent::GarbageSent {
+                    rows: attack - cancelled,
+                });
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Soft drops the falling piece all the way to where it lands, scoring every row, and
+    /// leaves it to lock once gravity next pulls it down.
+    fn soft_drop_instantly(&mut self, events: &mut Vec<GameEvent>) {
+        let ghost = self.ghost_piece();
+        let rows = ((ghost.pos.y - self.piece_falling.pos.y) / ghost.scale) as u32;
+        if rows == 0 {
+            return;
+        }
+        self.piece_falling = ghost;
+        self.time_last_moved_piece = self.clock.now();
+        self.last_move_rotated = false;
+        self.score += rows;
+        self.score_breakdown.soft_drop += rows;
+        events.push(GameEvent::SoftDropped);
+    }
+
+    /// Drops the falling piece all the way at once, as it falls at 20G, and locks it once it
+    /// has rested for the lock delay, or straight away when soft dropping.
+    fn fall_instantly(&mut self, soft_dropping: bool, events: &mut Vec<GameEvent>) {
+        let ghost = self.ghost_piece();
+        if ghost.pos.y != self.piece_falling.pos.y {
+            self.piece_falling = ghost;
+            self.time_last_moved_piece = self.clock.now();
+            self.last_move_rotated = false;
+        }
+        if soft_dropping || self.clock.since(self.time_last_moved_piece) >= Self::LOCK_DELAY {
+            self.time_last_moved_piece = self.clock.now();
+            self.place_current_piece(events);
+        }
+    }
+
+    /// Drops the blocks left hanging by a clear a row, and once they have all landed, clears
+    /// any rows they completed as the next clear of the chain.
+    fn fall_cascade(&mut self, events: &mut Vec<GameEvent>) {
+        if self.grid.drop_loose_groups() {
+            self.next_cascade_fall = Some(self.clock.now() + Self::CASCADE_FALL_TIME);
+            events.push(GameEvent::BlocksFell);
+            // Only blocks in the rows pieces spawn in can fall into the falling piece.
+            if !self.is_entering() && self.piece_falling.collides_with(&self.grid) {
+                self.end_game();
+            }
+            return;
+        }
+        self.next_cascade_fall = None;
+        self.chain += 1;
+        self.check_lines(events, ClearKind::Normal);
+    }
+
+    /// Whether rows are clearing or blocks are falling after a clear, which the falling
+    /// piece waits for.
+    fn is_clearing(&self) -> bool {
+        self.line_destroy_animations.is_some() || self.next_cascade_fall.is_some()
+    }
+
+    /// Whether the falling piece is resting on the stack or the floor.
+    fn is_resting(&self) -> bool {
+        let mut below = self.piece_falling;
+        below.pos.y += below.scale;
+        below.collides_with(&self.grid)
+    }
+
+    /// Advances the game by one fixed step.
+    pub fn step(&mut self, handling: Handling, input: FrameInput) -> Vec<GameEvent> {
+        let mut events = vec![];
+        if let Some(anim) = &mut self.collapse_animation {
+            let step = Self::FRAME_TIME.as_secs_f32() / Self::COLLAPSE_TIME.as_secs_f32();
+            anim.progress = (anim.progress + step).min(1.);
+            return events;
+        }
+        let was_over = self.game_over;
+        let was_resting = self.is_resting();
+        self.clock.tick();
+        self.top_out = handling.top_out;
+        self.entry_delay = handling.are;
+        self.das_cut_delay = handling.dcd;
+        self.hold_enabled = handling.hold;
+        self.line_clear_delay = handling.line_clear_delay;
+        let rotation_system =
+            (self.mode.rules().rotation_system).unwrap_or(handling.rotation_system);
+        if rotation_system != self.rotation_system {
+            self.rotation_system = rotation_system;
+            // A piece that came in before the system was known comes in again its way.
+            if self.just_spawned && !self.game_over {
+                self.spawn_piece(self.piece_falling.kind);
+            }
+        }
+
+        if !self.game_over && !self.is_clearing() && self.mode.is_complete(&self.stats()) {
+            self.complete_game();
+        }
+        if !self.game_over {
+            self.score_time_survived();
+            if !self.is_clearing() {
+                self.raise_garbage_on_time(&mut events);
+            }
+        }
+
+        if !self.game_over && (self.is_clearing() || self.is_entering()) {
+            self.input_buffer.record(input);
+        }
+        let line_clear_step = self.line_clear_step();
+        if let Some(anim) = &mut self.line_destroy_animations {
+            anim.progress += line_clear_step;
+            if anim.progress >= 1. {
+                let level = self.mode.level(self.lines_cleared);
+                let mut rows = 0;
+                for lines in &anim.lines_to_destroy {
+                    for line in lines.clone() {
+                        self.grid.clear_line(line as i32);
+                        rows += 1;
+                    }
+                }
+                self.lines_cleared += rows / self.piece_falling.scale as u32;
+                self.line_destroy_animations = None;
+                if let Some(well) = &self.well {
+                    well.build_walls(&mut self.grid);
+                }
+                events.push(GameEvent::LinesRemoved);
+                let new_level = self.mode.level(self.lines_cleared);
+                if new_level > level {
+                    events.push(GameEvent::LevelUp { level: new_level });
+                }
+                if self.mode.cascades() {
+                    self.next_cascade_fall = Some(self.clock.now() + Self::CASCADE_FALL_TIME);
+                }
+                // The entry delay starts over once the rows are gone.
+                if self.next_spawn.is_some() {
+                    self.next_spawn = Some(self.clock.now() + self.entry_delay);
+                }
+            }
+        } else if let Some(time) = self.next_cascade_fall {
+            if !self.game_over && self.clock.now() >= time {
+                self.fall_cascade(&mut events);
+            }
+        } else if let Some(time) = self.next_spawn {
+            if !self.game_over && self.clock.now() >= time {
+                self.next_spawn = None;
+                self.spawn_next_piece();
+            }
+        } else if !self.game_over {
+            let input = std::mem::take(&mut self.input_buffer).release(input);
+            self.presses += [
+                Action::MoveLeft,
+                Action::MoveRight,
+                Action::RotateCw,
+                Action::RotateCcw,
+                Action::Rotate180,
+            ]
+            .into_iter()
+            .filter(|&action| input.just_pressed(action))
+            .count() as u32;
+            let spawned = std::mem::take(&mut self.just_spawned);
+            // Held as the piece came in, or pressed while waiting for it.
+            let initial = |action| spawned && (input.pressed(action) || input.just_pressed(action));
+            let initial_rotation =
+                spawned && (self.mode.initial_rotation() || handling.initial_rotation);
+            self.update_auto_shift(input, handling, &mut events);
+            let rules = self.mode.rules();
+            if self.can_hold()
+                && (input.just_pressed(Action::Hold)
+                    || handling.initial_hold && initial(Action::Hold))
+            {
+                self.hold_piece(&mut events);
+                // The piece swapped in is turned as it comes in below, not again next step.
+                if initial_rotation {
+                    self.just_spawned = false;
+                }
+            }
+            if initial_rotation {
+                if initial(Action::RotateCw) {
+                    self.rotate(self.piece_falling.rotation.rotate_cw(), &mut events);
+                } else if initial(Action::RotateCcw) {
+                    self.rotate(self.piece_falling.rotation.rotate_ccw(), &mut events);
+                } else if initial(Action::Rotate180) && self.rotation_system.system().turns_180() {
+                    let rotation = self.piece_falling.rotation.rotate_cw().rotate_cw();
+                    self.rotate(rotation, &mut events);
+                }
+            } else {
+                if input.just_pressed(Action::RotateCw) {
+                    self.rotate(self.piece_falling.rotation.rotate_cw(), &mut events);
+                }
+                if input.just_pressed(Action::RotateCcw) {
+                    self.rotate(self.piece_falling.rotation.rotate_ccw(), &mut events);
+                }
+                if input.just_pressed(Action::Rotate180)
+                    && self.rotation_system.system().turns_180()
+                {
+                    let rotation = self.piece_falling.rotation.rotate_cw().rotate_cw();
+                    self.rotate(rotation, &mut events);
+                }
+            }
+            let soft_dropping = input.pressed(Action::SoftDrop);
+            let gravity = self.mode.gravity(self.mode.level(self.lines_cleared));
+            let time_per_fall = match handling.soft_drop {
+                SoftDrop::Factor(factor) if soft_dropping => gravity / factor.max(1).into(),
+                _ => gravity,
+            };
+            if rules.hard_drop && input.just_pressed(Action::HardDrop) {
+                self.time_last_moved_piece = self.clock.now();
+                let ghost = self.ghost_piece();
+                let rows = ((ghost.pos.y - self.piece_falling.pos.y) / ghost.scale) as u32;
+                if rows > 0 {
+                    self.last_move_rotated = false;
+                }
+                events.push(GameEvent::HardDropped { rows });
+                self.score += 2 * rows;
+                self.score_breakdown.hard_drop += 2 * rows;
+                self.piece_falling = ghost;
+                self.place_current_piece(&mut events);
+            }
+            if soft_dropping
+                && handling.soft_drop == SoftDrop::Instant
+                && !self.is_entering()
+                && !self.game_over
+                && !time_per_fall.is_zero()
+            {
+                self.soft_drop_instantly(&mut events);
+            }
+            if self.is_entering() {
+                // The piece was hard dropped, and the next one hasn't come in to fall yet.
+            } else if time_per_fall.is_zero() {
+                // A piece that just came in waits a step, to be turned before it lands.
+                if !self.just_spawned && !self.game_over {
+                    self.fall_instantly(soft_dropping, &mut events);
+                }
+            } else if self.clock.since(self.time_last_moved_piece) > time_per_fall {
+                self.time_last_moved_piece = self.clock.now();
+                self.piece_falling.pos.y += self.piece_falling.scale;
+                if self.piece_falling.collides_with(&self.grid) {
+                    self.piece_falling.pos.y -= self.piece_falling.scale;
+                    self.place_current_piece(&mut events);
+                } else {
+                    self.last_move_rotated = false;
+                    if soft_dropping {
+                        self.score += 1;
+                        self.score_breakdown.soft_drop += 1;
+                        events.push(GameEvent::SoftDropped);
+                    }
+                }
+            }
+        }
+        let locked = events
+            .iter()
+            .any(|event| matches!(event, GameEvent::PieceLocked(_)));
+        if !self.game_over && !locked && !self.is_entering() && !was_resting && self.is_resting() {
+            events.push(GameEvent::Landed);
+        }
+        if self.game_over && !was_over {
+            events.push(GameEvent::GameOver {
+                completed: self.completed,
+            });
+        }
+        events
+    }
+
+    fn end_game(&mut self) {
+        self.game_over = true;
+        self.time_finished = Some(self.clock.now());
+        if !self.completed {
+            self.collapse_animation = Some(CollapseAnimation { progress: 0. });
+        }
+    }
+
+    fn complete_game(&mut self) {
+        self.completed = true;
+        self.end_game();
+    }
+
+    pub fn stats(&self) -> GameStats {
+        GameStats {
+            lines_cleared: self.lines_cleared,
+            score: self.score,
+            elapsed: self.time_finished.unwrap_or(self.clock.now()),
+            garbage_rows: self
+                .grid
+                .rows()
+                .filter(|row| row.iter().flatten().any(|block| block.garbage))
+                .count() as u32,
+            max_chain: self.max_chain,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setup::Setup;
+
+    /// Steps `game` for `steps` steps with `input` on the first, and returns what happened.
+    fn step(game: &mut Game, input: FrameInput, steps: u32) -> Vec<GameEvent> {
+        let mut events = game.step(Handling::default(), input);
+        for _ in 1..steps {
+            events.extend(game.step(Handling::default(), FrameInput::default()));
+        }
+        events
+    }
+
+    /// Whether row `y` of the field, counted from the bottom, has every cell but `hole`
+    /// filled with garbage.
+    fn is_garbage_row(game: &Game, y: i32, hole: i32) -> bool {
+        let grid = game.grid();
+        let row = grid.height() as i32 - 1 - y;
+        (0..grid.width() as i32).all(|x| match grid.at(x, row) {
+            Some(block) => block.garbage && x != hole,
+            None => x == hole,
+        })
+    }
+
+    #[test]
+    fn completed_rows_are_cleared_after_flashing() {
+        let mut game = Setup::parse("10x20:3X2_5X/3X2_5X/X:OT").unwrap().game(0);
+        let events = step(&mut game, FrameInput::tap(Action::HardDrop), 1);
+        assert!(events.iter().any(|event| matches!(
+            event,
+            GameEvent::LinesCleared {
+                count: 2,
+                kind: ClearKind::Normal,
+            }
+        )));
+        assert_eq!(game.clearing_lines().count(), 2);
+
+        let events = step(&mut game, FrameInput::default(), 60);
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, GameEvent::LinesRemoved)));
+        assert_eq!(game.clearing_lines().count(), 0);
+        assert_eq!(game.stats().lines_cleared, 2);
+        // The row under the cleared ones is all that's left, at the bottom.
+        let grid = game.grid();
+        let bottom = grid.height() as i32 - 1;
+        assert!(grid.at(0, bottom).is_some_and(|block| block.garbage));
+        assert!((1..10).all(|x| grid.at(x, bottom).is_none()));
+        assert_eq!(grid.stack_height(), 1);
+    }
+
+    #[test]
+    fn garbage_rises_once_a_piece_locks_without_clearing() {
+        let mut game = Setup::parse("10x20::OO").unwrap().game(0);
+        game.receive_garbage(2);
+        assert_eq!(game.pending_garbage(), 2);
+        let events = step(&mut game, FrameInput::tap(Action::HardDrop), 1);
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, GameEvent::GarbageReceived { rows: 2 })));
+        assert_eq!(game.pending_garbage(), 0);
+        assert!((0..2).all(|y| (0..10).any(|hole| is_garbage_row(&game, y, hole))));
+        assert_eq!(game.grid().stack_height(), 4);
+    }
+
+    #[test]
+    fn clears_cancel_garbage_on_its_way() {
+        let mut game = Setup::parse("10x20:3X2_5X/3X2_5X:OO").unwrap().game(0);
+        game.receive_garbage(1);
+        let events = step(&mut game, FrameInput::tap(Action::HardDrop), 60);
+        assert_eq!(game.pending_garbage(), 0);
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::GarbageReceived { .. })));
+        assert_eq!(game.grid().stack_height(), 0);
+    }
+}
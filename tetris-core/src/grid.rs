@@ -0,0 +1,516 @@
+use std::fmt;
+
+use mint::Point2;
+use serde::{Deserialize, Serialize};
+
+use crate::piece::PieceKind;
+
+/// A filled cell, which remembers the piece it came from so that frontends can color it.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Block {
+    pub kind: PieceKind,
+    /// Whether the block rose in with a garbage row sent by an opponent, in which case it
+    /// didn't come from any piece and `kind` is only a placeholder.
+    pub garbage: bool,
+}
+
+impl Block {
+    /// A block of a garbage row.
+    pub const GARBAGE: Block = Block {
+        kind: PieceKind::I,
+        garbage: true,
+    };
+
+    pub fn of(kind: PieceKind) -> Self {
+        Self {
+            kind,
+            garbage: false,
+        }
+    }
+}
+
+/// Returned when a grid is changed at a cell or row that isn't in it, as can happen with
+/// coordinates read from files or sent over the network.
+#[derive(Debug)]
+pub struct OutOfBounds {
+    pub message: String,
+}
+
+impl fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for OutOfBounds {}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "GridData", into = "GridData")]
+pub struct Grid {
+    blocks: Box<[Option<Block>]>,
+    /// Which cells of each row are filled, one bit per column, so that collisions can be
+    /// checked a row at a time.
+    rows: Box<[u32]>,
+    width: usize,
+    height: usize,
+    /// Rows at the top that are out of sight, above the field players see.
+    hidden: usize,
+}
+
+/// A grid as it's serialized, without the row masks, which are worked out again when it's
+/// read so that they can't disagree with the blocks.
+#[derive(Serialize, Deserialize)]
+struct GridData {
+    width: usize,
+    height: usize,
+    hidden: usize,
+    /// Every cell in row order.
+    blocks: Vec<Option<Block>>,
+}
+
+impl TryFrom<GridData> for Grid {
+    type Error = String;
+
+    fn try_from(data: GridData) -> Result<Self, String> {
+        if data.width > Grid::MAX_WIDTH {
+            return Err(format!("grids can't be wider than {}", Grid::MAX_WIDTH));
+        }
+        if data.width == 0 || data.height == 0 {
+            return Err(format!(
+                "a {} by {} grid has no cells",
+                data.width, data.height
+            ));
+        }
+        if data.width.checked_mul(data.height) != Some(data.blocks.len()) {
+            return Err(format!(
+                "{} cells don't make a {} by {} grid",
+                data.blocks.len(),
+                data.width,
+                data.height
+            ));
+        }
+        if data.hidden > data.height {
+            return Err(format!("{} rows can't hide {}", data.height, data.hidden));
+        }
+        Ok(Self {
+            hidden: data.hidden,
+            ..Self::with_data(data.width, data.height, data.blocks.into_boxed_slice())
+        })
+    }
+}
+
+impl From<Grid> for GridData {
+    fn from(grid: Grid) -> Self {
+        Self {
+            width: grid.width,
+            height: grid.height,
+            hidden: grid.hidden,
+            blocks: grid.blocks.into_vec(),
+        }
+    }
+}
+
+impl Grid {
+    /// Widest grid that fits in the row masks.
+    pub const MAX_WIDTH: usize = u32::BITS as usize;
+
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::with_data(width, height, vec![None; width * height].into_boxed_slice())
+    }
+
+    pub fn with_data(width: usize, height: usize, blocks: Box<[Option<Block>]>) -> Self {
+        assert_eq!(width * height, blocks.len());
+        assert!(width <= Self::MAX_WIDTH);
+        let rows = blocks
+            .chunks(width.max(1))
+            .take(height)
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, block)| block.is_some())
+                    .fold(0, |mask, (x, _)| mask | 1 << x)
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            blocks,
+            rows,
+            hidden: 0,
+        }
+    }
+
+    /// The grid under `count` more rows, which are out of sight. Pieces can still move and
+    /// lock in them, so that they have room to come in and turn over a stack that reaches
+    /// the top of the field.
+    pub fn with_hidden_rows(self, count: usize) -> Self {
+        let mut blocks = vec![None; self.width * count];
+        blocks.extend_from_slice(&self.blocks);
+        Self {
+            hidden: self.hidden + count,
+            ..Self::with_data(self.width, self.height + count, blocks.into_boxed_slice())
+        }
+    }
+
+    pub fn at(&self, x: i32, y: i32) -> &Option<Block> {
+        if self.contains_pos(x, y) {
+            &self.blocks[x as usize + y as usize * self.width]
+        } else {
+            &None
+        }
+    }
+
+    /// Every cell with its column and row, a row at a time from the top.
+    pub fn iter(&self) -> impl Iterator<Item = (i32, i32, &Option<Block>)> + '_ {
+        self.rows().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(x, block)| (x as i32, y as i32, block))
+        })
+    }
+
+    /// The cells of every row, from the top.
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[Option<Block>]> + '_ {
+        self.blocks.chunks(self.width.max(1)).take(self.height)
+    }
+
+    /// The cells of row `y`, or `None` if it isn't in the grid.
+    pub fn row(&self, y: i32) -> Option<&[Option<Block>]> {
+        (0..self.height as i32).contains(&y).then(|| {
+            let start = y as usize * self.width;
+            &self.blocks[start..start + self.width]
+        })
+    }
+
+    /// Fills or empties a cell. Panics if it isn't in the grid; see `try_set`.
+    pub fn set(&mut self, x: i32, y: i32, value: Option<Block>) {
+        if let Err(e) = self.try_set(x, y, value) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Fills or empties a cell, or leaves the grid as it is if the cell isn't in it.
+    pub fn try_set(&mut self, x: i32, y: i32, value: Option<Block>) -> Result<(), OutOfBounds> {
+        if !self.contains_pos(x, y) {
+            return Err(self.out_of_bounds(format!("{}, {} isn't a cell", x, y)));
+        }
+        self.blocks[x as usize + y as usize * self.width] = value;
+        let bit = 1 << x;
+        if value.is_some() {
+            self.rows[y as usize] |= bit;
+        } else {
+            self.rows[y as usize] &= !bit;
+        }
+        Ok(())
+    }
+
+    /// Removes row `y`, moving the rows above it down. Panics if the row isn't in the grid;
+    /// see `try_clear_line`.
+    pub fn clear_line(&mut self, y: i32) {
+        if let Err(e) = self.try_clear_line(y) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Removes row `y`, moving the rows above it down, or leaves the grid as it is if the
+    /// row isn't in it.
+    pub fn try_clear_line(&mut self, y: i32) -> Result<(), OutOfBounds> {
+        if !(0..self.height as i32).contains(&y) {
+            return Err(self.out_of_bounds(format!("{} isn't a row", y)));
+        }
+        let end = (y as usize + 1) * self.width;
+        self.blocks[..end].rotate_right(self.width);
+        self.blocks[..self.width].fill(None);
+        self.rows[..=y as usize].rotate_right(1);
+        self.rows[0] = 0;
+        Ok(())
+    }
+
+    fn out_of_bounds(&self, what: String) -> OutOfBounds {
+        OutOfBounds {
+            message: format!("{} of a {} by {} grid", what, self.width, self.height),
+        }
+    }
+
+    /// Pushes every row up by `count` and fills the rows left at the bottom with garbage,
+    /// leaving column `hole` empty in each. Returns whether any block was pushed off the top.
+    pub fn push_garbage(&mut self, count: usize, hole: usize) -> bool {
+        let count = count.min(self.height);
+        let overflowed = self.rows[..count].iter().any(|row| *row != 0);
+        self.blocks.rotate_left(count * self.width);
+        self.rows.rotate_left(count);
+        let first = self.height - count;
+        for y in first..self.height {
+            for x in 0..self.width {
+                let block = (x != hole).then_some(Block::GARBAGE);
+                self.blocks[x + y * self.width] = block;
+            }
+            self.rows[y] = Self::full_row(self.width) & !(1 << hole);
+        }
+        overflowed
+    }
+
+    /// The mask of a row with every one of `width` cells filled.
+    fn full_row(width: usize) -> u32 {
+        u32::MAX
+            .checked_shr((Self::MAX_WIDTH - width) as u32)
+            .unwrap_or(0)
+    }
+
+    /// Whether every cell of row `y` is filled.
+    pub fn is_row_full(&self, y: i32) -> bool {
+        (0..self.height as i32).contains(&y) && self.rows[y as usize] == Self::full_row(self.width)
+    }
+
+    /// The rows with every cell filled, from the top.
+    pub fn full_rows(&self) -> impl Iterator<Item = i32> + '_ {
+        let full = Self::full_row(self.width);
+        (0..self.height as i32).filter(move |&y| self.rows[y as usize] == full)
+    }
+
+    /// Number of rows from the bottom up to the highest filled cell of each column.
+    pub fn column_heights(&self) -> Vec<usize> {
+        (0..self.width)
+            .map(|x| {
+                self.rows
+                    .iter()
+                    .position(|row| row & 1 << x != 0)
+                    .map_or(0, |top| self.height - top)
+            })
+            .collect()
+    }
+
+    /// The mask of row `y` moved `x` columns to the right, dropping the columns that end up
+    /// left of the grid.
+    fn shifted_row(&self, y: usize, x: i32) -> u64 {
+        let row = u64::from(self.rows[y]);
+        if x >= 0 {
+            row.checked_shl(x as u32)
+        } else {
+            row.checked_shr(x.unsigned_abs())
+        }
+        .unwrap_or(0)
+    }
+
+    /// Number of rows from the bottom up to the highest filled cell.
+    pub fn stack_height(&self) -> usize {
+        self.rows
+            .iter()
+            .position(|row| *row != 0)
+            .map_or(0, |top| self.height - top)
+    }
+
+    pub fn contains_pos(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32
+    }
+
+    pub fn intersects(&self, x: i32, y: i32, other: &Grid) -> bool {
+        (0..other.height).any(|iy| {
+            let self_y = iy as i32 + y;
+            (0..self.height as i32).contains(&self_y)
+                && other.shifted_row(iy, x) & u64::from(self.rows[self_y as usize]) != 0
+        })
+    }
+
+    pub fn contains(&self, x: i32, y: i32, other: &Grid) -> bool {
+        (0..other.height).all(|iy| {
+            let row = other.rows[iy];
+            if row == 0 {
+                return true;
+            }
+            let first = row.trailing_zeros() as i32 + x;
+            let last = (u32::BITS - 1 - row.leading_zeros()) as i32 + x;
+            (0..self.height as i32).contains(&(iy as i32 + y))
+                && first >= 0
+                && last < self.width as i32
+        })
+    }
+
+    /// Rows in all, hidden ones included.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn hidden_rows(&self) -> usize {
+        self.hidden
+    }
+
+    /// Rows in sight, under the hidden ones.
+    pub fn visible_height(&self) -> usize {
+        self.height - self.hidden
+    }
+
+    /// The grid with every cell blown up into a square of `factor` by `factor` cells.
+    pub fn scaled(&self, factor: usize) -> Grid {
+        let mut scaled = Grid::new(self.width * factor, self.height * factor);
+        for x in 0..scaled.width {
+            for y in 0..scaled.height {
+                let block = *self.at((x / factor) as i32, (y / factor) as i32);
+                scaled.set(x as i32, y as i32, block);
+            }
+        }
+        scaled
+    }
+
+    /// Groups of filled cells that touch across or down, as the cells in each.
+    pub fn groups(&self) -> Vec<Vec<Point2<i32>>> {
+        let mut seen = vec![false; self.blocks.len()];
+        let mut groups = vec![];
+        for start in 0..self.blocks.len() {
+            if seen[start] || self.blocks[start].is_none() {
+                continue;
+            }
+            seen[start] = true;
+            let mut group = vec![];
+            let mut to_visit = vec![start];
+            while let Some(i) = to_visit.pop() {
+                let (x, y) = ((i % self.width) as i32, (i / self.width) as i32);
+                group.push(Point2 { x, y });
+                for (x, y) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                    if self.at(x, y).is_none() {
+                        continue;
+                    }
+                    let neighbor = x as usize + y as usize * self.width;
+                    if !seen[neighbor] {
+                        seen[neighbor] = true;
+                        to_visit.push(neighbor);
+                    }
+                }
+            }
+            groups.push(group);
+        }
+        groups
+    }
+
+    /// Moves every group of blocks down a row unless it rests on the floor, or on a group
+    /// that does. Returns whether any moved.
+    pub fn drop_loose_groups(&mut self) -> bool {
+        let groups = self.groups();
+        let mut group_at = vec![None; self.blocks.len()];
+        for (i, group) in groups.iter().enumerate() {
+            for pos in group {
+                group_at[pos.x as usize + pos.y as usize * self.width] = Some(i);
+            }
+        }
+        // Resting groups can hold up others above them, so go over them until no more are
+        // found.
+        let mut resting = vec![false; groups.len()];
+        let mut found = true;
+        while found {
+            found = false;
+            for (i, group) in groups.iter().enumerate() {
+                if resting[i] {
+                    continue;
+                }
+                resting[i] = group.iter().any(|pos| {
+                    pos.y + 1 == self.height as i32
+                        || group_at[pos.x as usize + (pos.y + 1) as usize * self.width]
+                            .is_some_and(|below| below != i && resting[below])
+                });
+                found |= resting[i];
+            }
+        }
+
+        let loose: Vec<_> = groups
+            .iter()
+            .zip(&resting)
+            .filter(|(_, &resting)| !resting)
+            .flat_map(|(group, _)| group)
+            .map(|&pos| (pos, *self.at(pos.x, pos.y)))
+            .collect();
+        for &(pos, _) in &loose {
+            self.set(pos.x, pos.y, None);
+        }
+        for &(pos, block) in &loose {
+            self.set(pos.x, pos.y + 1, block);
+        }
+        !loose.is_empty()
+    }
+
+    /// Fills the cells under the blocks of `other` placed at `x`, `y`. Blocks that end up
+    /// outside the grid are dropped.
+    pub fn overlay(&mut self, x: i32, y: i32, other: Grid) {
+        for ix in 0..self.width as i32 {
+            for iy in 0..self.height as i32 {
+                if let Some(block) = other.at(ix - x, iy - y) {
+                    self.set(ix, iy, Some(*block));
+                }
+            }
+        }
+    }
+
+    /// Fills the cells under the blocks of `other` placed at `x`, `y`, or leaves the grid as
+    /// it is if any of them would end up outside it.
+    pub fn try_overlay(&mut self, x: i32, y: i32, other: Grid) -> Result<(), OutOfBounds> {
+        if !self.contains(x, y, &other) {
+            return Err(self.out_of_bounds(format!("a grid at {}, {} isn't inside", x, y)));
+        }
+        self.overlay(x, y, other);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(width: usize, height: usize, cells: usize) -> GridData {
+        GridData {
+            width,
+            height,
+            hidden: 0,
+            blocks: vec![None; cells],
+        }
+    }
+
+    #[test]
+    fn clearing_a_line_moves_the_rows_above_it_down() {
+        let mut grid = Grid::new(4, 4);
+        grid.set(0, 1, Some(Block::GARBAGE));
+        for x in 0..4 {
+            grid.set(x, 2, Some(Block::GARBAGE));
+        }
+        grid.set(3, 3, Some(Block::GARBAGE));
+        assert!(grid.is_row_full(2));
+        assert_eq!(grid.full_rows().collect::<Vec<_>>(), [2]);
+
+        grid.clear_line(2);
+        assert!(grid.full_rows().next().is_none());
+        assert!(grid.at(0, 1).is_none());
+        assert!(grid.at(0, 2).is_some());
+        assert!(grid.at(3, 3).is_some());
+        assert_eq!(grid.stack_height(), 2);
+        assert!(grid.try_clear_line(4).is_err());
+    }
+
+    #[test]
+    fn garbage_rises_from_the_bottom_with_a_hole() {
+        let mut grid = Grid::new(4, 4);
+        grid.set(1, 3, Some(Block::GARBAGE));
+        assert!(!grid.push_garbage(2, 2));
+        assert!(grid.at(1, 1).is_some());
+        for y in 2..4 {
+            assert!(grid.at(2, y).is_none());
+            assert!([0, 1, 3].iter().all(|&x| grid.at(x, y).is_some()));
+        }
+        assert_eq!(grid.column_heights(), [2, 3, 0, 2]);
+        // The block at the top of the second column is pushed off.
+        assert!(grid.push_garbage(2, 0));
+        assert_eq!(grid.stack_height(), 4);
+    }
+
+    #[test]
+    fn grids_without_cells_are_invalid() {
+        assert!(Grid::try_from(data(0, 4, 0)).is_err());
+        assert!(Grid::try_from(data(4, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn grids_too_large_to_count_are_invalid() {
+        assert!(Grid::try_from(data(10, usize::MAX, 10)).is_err());
+    }
+}
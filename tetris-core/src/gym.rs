@@ -0,0 +1,177 @@
+//! A game wrapped for training agents on, in the style of Gym environments: each step takes
+//! one action and gives back what the agent can see, its reward and whether the game ended.
+
+use crate::{
+    game::{BoardSize, Game},
+    input::{Action, FrameInput, Handling},
+    mode::GameMode,
+    piece::{PieceKind, PieceRotation},
+};
+
+/// What an agent can see of the game.
+#[derive(Clone)]
+pub struct Observation {
+    pub width: usize,
+    pub height: usize,
+    /// Whether each cell of the stack is filled, in rows from the top. The falling piece
+    /// isn't included.
+    pub cells: Vec<bool>,
+    pub piece_kind: PieceKind,
+    pub piece_rotation: PieceRotation,
+    /// Column and row of the top left corner of the falling piece's grid.
+    pub piece_x: i32,
+    pub piece_y: i32,
+    pub held_piece: Option<PieceKind>,
+    pub hold_used: bool,
+    /// Rows of garbage waiting to rise, always 0 unless `Env::receive_garbage` is used.
+    pub pending_garbage: u32,
+}
+
+/// What came of a step.
+#[derive(Clone)]
+pub struct Step {
+    pub observation: Observation,
+    /// Points scored during the step.
+    pub reward: f32,
+    /// Whether the game is over, after which it has to be reset.
+    pub done: bool,
+}
+
+/// A game that's stepped one action at a time.
+pub struct Env {
+    game: Game,
+    mode: GameMode,
+    board_size: BoardSize,
+    handling: Handling,
+}
+
+impl Env {
+    pub fn new(mode: GameMode, board_size: BoardSize, handling: Handling, seed: u32) -> Self {
+        Self {
+            game: Game::new(mode, seed, board_size),
+            mode,
+            board_size,
+            handling,
+        }
+    }
+
+    /// Starts a new game, with pieces picked by `seed`.
+    pub fn reset(&mut self, seed: u32) -> Observation {
+        self.game = Game::new(self.mode, seed, self.board_size);
+        self.observation()
+    }
+
+    /// Simulates one step, pressing `action` if there is one.
+    pub fn step(&mut self, action: Option<Action>) -> Step {
+        let input = action.map_or(FrameInput::default(), FrameInput::tap);
+        let score = self.game.stats().score;
+        self.game.step(self.handling, input);
+        Step {
+            observation: self.observation(),
+            reward: (self.game.stats().score - score) as f32,
+            done: self.game.is_over(),
+        }
+    }
+
+    /// Sends rows of garbage to the game, as an opponent would.
+    pub fn receive_garbage(&mut self, rows: u32) {
+        self.game.receive_garbage(rows);
+    }
+
+    pub fn observation(&self) -> Observation {
+        let grid = self.game.grid();
+        let piece = self.game.piece();
+        let cells = grid.iter().map(|(_, _, block)| block.is_some()).collect();
+        Observation {
+            width: grid.width(),
+            height: grid.height(),
+            cells,
+            piece_kind: piece.kind,
+            piece_rotation: piece.rotation,
+            piece_x: piece.pos.x,
+            piece_y: piece.pos.y,
+            held_piece: self.game.held_piece(),
+            hold_used: self.game.hold_used(),
+            pending_garbage: self.game.pending_garbage(),
+        }
+    }
+
+    /// The game being played, for anything the observation leaves out.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+}
+
+/// C bindings for `Env`, for training from other languages. Actions are passed as their
+/// index in `Action`, or -1 for none.
+#[cfg(feature = "ffi")]
+pub mod ffi {
+    use enum_map::Enum;
+
+    use super::*;
+
+    /// Creates an endless game on a board of the given size, clamped to the sizes allowed.
+    /// It has to be freed with `tetris_env_free`.
+    #[no_mangle]
+    pub extern "C" fn tetris_env_new(seed: u32, width: u32, height: u32) -> *mut Env {
+        let board_size = BoardSize {
+            width: (width as usize).clamp(BoardSize::MIN.width, BoardSize::MAX.width),
+            height: (height as usize).clamp(BoardSize::MIN.height, BoardSize::MAX.height),
+        };
+        let env = Env::new(GameMode::Endless, board_size, Handling::default(), seed);
+        Box::into_raw(Box::new(env))
+    }
+
+    /// # Safety
+    ///
+    /// `env` has to come from `tetris_env_new`, and can't be used again afterwards.
+    #[no_mangle]
+    pub unsafe extern "C" fn tetris_env_free(env: *mut Env) {
+        drop(Box::from_raw(env));
+    }
+
+    /// # Safety
+    ///
+    /// `env` has to come from `tetris_env_new`.
+    #[no_mangle]
+    pub unsafe extern "C" fn tetris_env_reset(env: *mut Env, seed: u32) {
+        (*env).reset(seed);
+    }
+
+    /// Steps the game, returning the reward and writing whether the game ended to `done`.
+    ///
+    /// # Safety
+    ///
+    /// `env` has to come from `tetris_env_new`, and `done` has to be valid to write to.
+    #[no_mangle]
+    pub unsafe extern "C" fn tetris_env_step(env: *mut Env, action: i32, done: *mut bool) -> f32 {
+        let action = usize::try_from(action)
+            .ok()
+            .filter(|&action| action < Action::LENGTH)
+            .map(Action::from_usize);
+        let step = (*env).step(action);
+        *done = step.done;
+        step.reward
+    }
+
+    /// Writes whether each cell of the stack is filled, 1 or 0, in rows from the top, to
+    /// `cells`, as far as `len` allows. Returns the number of cells of the board.
+    ///
+    /// # Safety
+    ///
+    /// `env` has to come from `tetris_env_new`, and `cells` has to be valid to write `len`
+    /// bytes to.
+    #[no_mangle]
+    pub unsafe extern "C" fn tetris_env_cells(
+        env: *const Env,
+        cells: *mut u8,
+        len: usize,
+    ) -> usize {
+        let observation = (*env).observation();
+        let out = std::slice::from_raw_parts_mut(cells, len);
+        for (out, filled) in out.iter_mut().zip(&observation.cells) {
+            *out = u8::from(*filled);
+        }
+        observation.cells.len()
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{grid::Grid, piece::Piece};
+
+/// Where pieces were locked and where they left holes, to show players what their
+/// stacking leans towards.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Heatmap {
+    width: usize,
+    height: usize,
+    /// Blocks locked in each column.
+    locks: Vec<u32>,
+    /// Holes left in each cell, in rows from the top of the field, under the hidden rows.
+    holes: Vec<u32>,
+}
+
+impl Heatmap {
+    /// An empty heatmap for a field of this size.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            locks: vec![0; width],
+            holes: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn locks(&self, x: usize) -> u32 {
+        self.locks[x]
+    }
+
+    pub fn holes(&self, x: usize, y: usize) -> u32 {
+        self.holes[y * self.width + x]
+    }
+
+    pub fn max_locks(&self) -> u32 {
+        self.locks.iter().copied().max().unwrap_or(0)
+    }
+
+    pub fn max_holes(&self) -> u32 {
+        self.holes.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Counts `piece` locking on `grid`, as the grid was before it locked. The empty cells it
+    /// covers, down to the stack or the floor, are the holes it leaves.
+    pub fn record(&mut self, grid: &Grid, piece: Piece) {
+        let shape = piece.grid();
+        for x in 0..shape.width() as i32 {
+            let Some(lowest) = (0..shape.height() as i32)
+                .rev()
+                .find(|&y| shape.at(x, y).is_some())
+            else {
+                continue;
+            };
+            let column = piece.pos.x + x;
+            if !(0..self.width as i32).contains(&column) {
+                continue;
+            }
+            let blocks = (0..shape.height() as i32)
+                .filter(|&y| shape.at(x, y).is_some())
+                .count();
+            self.locks[column as usize] += blocks as u32;
+            let below = piece.pos.y + lowest + 1;
+            let hidden = grid.hidden_rows() as i32;
+            for y in (below..grid.height() as i32).take_while(|&y| grid.at(column, y).is_none()) {
+                if y >= hidden {
+                    self.holes[(y - hidden) as usize * self.width + column as usize] += 1;
+                }
+            }
+        }
+    }
+
+    /// Adds the counts of `other`, or starts over from them if it's for a field of another
+    /// size.
+    pub fn add(&mut self, other: &Heatmap) {
+        if (self.width, self.height) != (other.width, other.height) {
+            *self = other.clone();
+            return;
+        }
+        for (count, other) in self.locks.iter_mut().zip(&other.locks) {
+            *count += other;
+        }
+        for (count, other) in self.holes.iter_mut().zip(&other.holes) {
+            *count += other;
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use crate::{
+    game::{ClearCounts, Game, ScoreBreakdown},
+    mode::GameMode,
+};
+
+/// How a finished game went, kept as a line of the stats history so that players can look
+/// through their games with other tools.
+#[derive(Clone)]
+pub struct GameSummary {
+    pub mode: GameMode,
+    pub seed: u32,
+    /// Seconds since the Unix epoch when the game ended.
+    pub finished_at: u64,
+    pub score: u32,
+    pub score_breakdown: ScoreBreakdown,
+    pub lines_cleared: u32,
+    pub clears: ClearCounts,
+    pub max_combo: u32,
+    /// Rows of garbage all clears were worth.
+    pub attack: u32,
+    pub pieces_placed: u32,
+    pub duration: Duration,
+    pub finesse_faults: u32,
+}
+
+impl GameSummary {
+    /// First line of the history, naming the values in each line after it.
+    pub const CSV_HEADER: &'static str = "finished_at,mode,seed,score,lines,singles,doubles,\
+        triples,tetrises,t_spins,pieces,duration,pps,finesse_faults,max_combo,attack,line_score,\
+        soft_drop_score,hard_drop_score,survival_score";
+
+    pub fn new(game: &Game, finished_at: u64) -> Self {
+        let stats = game.stats();
+        Self {
+            mode: game.mode(),
+            seed: game.seed(),
+            finished_at,
+            score: stats.score,
+            score_breakdown: game.score_breakdown(),
+            lines_cleared: stats.lines_cleared,
+            clears: game.clear_counts(),
+            max_combo: game.max_combo(),
+            attack: game.total_attack(),
+            pieces_placed: game.pieces_placed(),
+            duration: stats.elapsed,
+            finesse_faults: game.finesse_faults(),
+        }
+    }
+
+    /// Pieces placed per second, on average.
+    pub fn pieces_per_second(&self) -> f32 {
+        let seconds = self.duration.as_secs_f32();
+        if seconds > 0. {
+            self.pieces_placed as f32 / seconds
+        } else {
+            0.
+        }
+    }
+
+    /// The summary as a line of comma separated values, in the order of `CSV_HEADER`.
+    pub fn to_csv(&self) -> String {
+        let clears = self.clears;
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{:.3},{:.2},{},{},{},{},{},{},{}",
+            self.finished_at,
+            self.mode.name().to_lowercase(),
+            self.seed,
+            self.score,
+            self.lines_cleared,
+            clears.singles,
+            clears.doubles,
+            clears.triples,
+            clears.tetrises,
+            clears.t_spins,
+            self.pieces_placed,
+            self.duration.as_secs_f32(),
+            self.pieces_per_second(),
+            self.finesse_faults,
+            self.max_combo,
+            self.attack,
+            self.score_breakdown.lines,
+            self.score_breakdown.soft_drop,
+            self.score_breakdown.hard_drop,
+            self.score_breakdown.survival,
+        )
+    }
+}
@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use enum_map::Enum;
+use serde::{Deserialize, Serialize};
+
+use crate::{rotation::RotationSystemKind, rules::TopOut};
+
+/// Settings that change how the game plays, which replays need to be reproduced.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Handling {
+    /// Delayed auto shift: how long a direction must be held before the piece starts sliding.
+    pub das: Duration,
+    /// Auto repeat rate: time between each slide step once DAS has charged. Zero slides
+    /// the piece straight to the wall.
+    pub arr: Duration,
+    /// DAS cut delay: how long auto shift holds off after the piece turns or comes in, so that
+    /// a direction still held doesn't carry it off straight away.
+    pub dcd: Duration,
+    pub soft_drop: SoftDrop,
+    /// Entry delay: time between a piece locking and the next one coming in, after any rows
+    /// it completed are removed.
+    pub are: Duration,
+    /// How long completed rows flash before they are removed, holding up the game.
+    pub line_clear_delay: Duration,
+    /// Initial rotation: turns pieces as they come in while a rotation is held, or if one was
+    /// pressed since the last piece locked.
+    pub initial_rotation: bool,
+    /// Initial hold: swaps pieces with the held one as they come in, the same way.
+    pub initial_hold: bool,
+    /// Whether pieces can be held, in modes that allow it.
+    pub hold: bool,
+    /// Used unless the mode has a rotation system of its own.
+    pub rotation_system: RotationSystemKind,
+    pub top_out: TopOut,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Self {
+            das: Duration::from_millis(170),
+            arr: Duration::from_millis(50),
+            dcd: Duration::ZERO,
+            soft_drop: SoftDrop::Factor(10),
+            are: Duration::ZERO,
+            line_clear_delay: Duration::from_millis(500),
+            initial_rotation: false,
+            initial_hold: false,
+            hold: true,
+            rotation_system: RotationSystemKind::default(),
+            top_out: TopOut::default(),
+        }
+    }
+}
+
+/// How fast pieces fall while soft dropping.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SoftDrop {
+    /// This many times as fast as gravity.
+    Factor(u16),
+    /// Straight to the floor, without locking until gravity would.
+    Instant,
+}
+
+impl SoftDrop {
+    /// The factor, or 0 for instant, for replays.
+    pub fn to_bits(self) -> u16 {
+        match self {
+            SoftDrop::Factor(factor) => factor.max(1),
+            SoftDrop::Instant => 0,
+        }
+    }
+
+    pub fn from_bits(bits: u16) -> Self {
+        match bits {
+            0 => SoftDrop::Instant,
+            factor => SoftDrop::Factor(factor),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Enum)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateCw,
+    RotateCcw,
+    Rotate180,
+    Hold,
+    Pause,
+    /// Starts the game over with another seed, once held for a moment.
+    Restart,
+    /// Takes back the last piece placed, in games played for practice.
+    Undo,
+}
+
+impl Action {
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "MOVE LEFT",
+            Action::MoveRight => "MOVE RIGHT",
+            Action::SoftDrop => "SOFT DROP",
+            Action::HardDrop => "HARD DROP",
+            Action::RotateCw => "ROTATE CW",
+            Action::RotateCcw => "ROTATE CCW",
+            Action::Rotate180 => "ROTATE 180",
+            Action::Hold => "HOLD",
+            Action::Pause => "PAUSE",
+            Action::Restart => "RESTART",
+            Action::Undo => "UNDO",
+        }
+    }
+}
+
+/// The actions pressed during one simulation step, which is all a game needs to be
+/// reproduced from a replay.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameInput {
+    /// One bit per action that is held down.
+    pub pressed: u16,
+    /// One bit per action that was pressed this step.
+    pub just_pressed: u16,
+}
+
+impl FrameInput {
+    /// Builds an input by asking whether each action is held and whether it was just pressed.
+    pub fn from_fn(mut state: impl FnMut(Action) -> (bool, bool)) -> Self {
+        let mut input = Self::default();
+        for action in (0..Action::LENGTH).map(Action::from_usize) {
+            let (pressed, just_pressed) = state(action);
+            if pressed {
+                input.pressed |= 1 << action.into_usize();
+            }
+            if just_pressed {
+                input.just_pressed |= 1 << action.into_usize();
+            }
+        }
+        input
+    }
+
+    /// Only `action`, pressed this step.
+    pub fn tap(action: Action) -> Self {
+        Self::from_fn(|other| (other == action, other == action))
+    }
+
+    pub fn pressed(self, action: Action) -> bool {
+        self.pressed & 1 << action.into_usize() != 0
+    }
+
+    pub fn just_pressed(self, action: Action) -> bool {
+        self.just_pressed & 1 << action.into_usize() != 0
+    }
+
+    /// The same input held for another step, without any new presses.
+    pub fn held(self) -> Self {
+        Self {
+            pressed: self.pressed,
+            just_pressed: 0,
+        }
+    }
+}
+
+/// Presses made while the falling piece can't be moved, during clears and the entry delay,
+/// kept so that they still move, turn or hold the piece as soon as it can be, even if they
+/// were let go of by then.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub(crate) struct InputBuffer {
+    presses: u16,
+}
+
+impl InputBuffer {
+    /// Presses that are kept. Drops aren't, so that a piece never falls from one made before
+    /// it came in.
+    const ACTIONS: [Action; 6] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::RotateCw,
+        Action::RotateCcw,
+        Action::Rotate180,
+        Action::Hold,
+    ];
+
+    pub fn record(&mut self, input: FrameInput) {
+        for action in Self::ACTIONS {
+            if input.just_pressed(action) {
+                self.presses |= 1 << action.into_usize();
+            }
+        }
+    }
+
+    /// `input` with the kept presses added to it, as if they were made this step.
+    pub fn release(self, input: FrameInput) -> FrameInput {
+        FrameInput {
+            pressed: input.pressed,
+            just_pressed: input.just_pressed | self.presses,
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::{collections::HashMap, fmt, sync::OnceLock};
+
+use enum_map::{Enum, EnumMap};
+use mint::Point2;
+
+use crate::{
+    game::BoardSize,
+    piece::{PieceKind, PieceRotation},
+    rotation::RotationSystemKind,
+};
+
+/// Returned when kick tables can't be read, with what's wrong with them.
+#[derive(Debug)]
+pub struct InvalidKickTables {
+    pub message: String,
+}
+
+impl fmt::Display for InvalidKickTables {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for InvalidKickTables {}
+
+/// Offsets of one piece, by the rotation it turns from and the one it turns to.
+type PieceKicks = EnumMap<PieceRotation, EnumMap<PieceRotation, Vec<Point2<i32>>>>;
+
+/// As read from a kicks file, by system, then piece or `default`, then turn.
+type Definitions = HashMap<String, HashMap<String, HashMap<String, Vec<[i32; 2]>>>>;
+
+/// The kicks of every rotation system, by piece and turn. The standard tables are read from
+/// `kicks.toml`, built into the game, and others are read over them so that they only need
+/// to list the pieces they change.
+///
+/// Like the piece set, the tables that were installed are used by every game.
+pub struct KickTables {
+    /// `None` for systems that kick as SRS does.
+    systems: EnumMap<RotationSystemKind, Option<EnumMap<PieceKind, PieceKicks>>>,
+}
+
+static INSTALLED: OnceLock<KickTables> = OnceLock::new();
+
+impl KickTables {
+    /// Most kicks a turn can try.
+    pub const MAX_KICKS: usize = u8::MAX as usize;
+    const STANDARD: &'static str = include_str!("../kicks.toml");
+
+    pub fn standard() -> Self {
+        Self::read(Self::STANDARD, Self::empty()).expect("the standard kicks are valid")
+    }
+
+    /// Reads a kicks file over the standard tables, replacing the pieces it lists.
+    pub fn parse(text: &str) -> Result<Self, InvalidKickTables> {
+        Self::read(text, Self::standard())
+    }
+
+    /// Makes every game from now on use these tables. Panics if kicks were used already,
+    /// since they can't change under a game.
+    pub fn install(self) {
+        if INSTALLED.set(self).is_err() {
+            panic!("kicks have to be installed before any are used");
+        }
+    }
+
+    /// The installed tables, or the standard ones if none were.
+    pub fn current() -> &'static Self {
+        INSTALLED.get_or_init(Self::standard)
+    }
+
+    /// Offsets tried in order when `kind` turned from `from` to `to` doesn't fit where it
+    /// is, in blocks right and down.
+    pub fn kicks(
+        &self,
+        system: RotationSystemKind,
+        kind: PieceKind,
+        from: PieceRotation,
+        to: PieceRotation,
+    ) -> &[Point2<i32>] {
+        let pieces = self.systems[system]
+            .as_ref()
+            .or(self.systems[RotationSystemKind::Srs].as_ref())
+            .expect("SRS has kicks");
+        &pieces[kind][from][to]
+    }
+
+    /// Adds every kick of every system to `bytes`, for `RulesHash`.
+    pub(crate) fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        for pieces in self.systems.values() {
+            let Some(pieces) = pieces else {
+                bytes.push(0);
+                continue;
+            };
+            bytes.push(1);
+            for kicks in pieces
+                .values()
+                .flat_map(EnumMap::values)
+                .flat_map(EnumMap::values)
+            {
+                bytes.push(kicks.len() as u8);
+                for offset in kicks {
+                    bytes.extend(offset.x.to_le_bytes());
+                    bytes.extend(offset.y.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            systems: EnumMap::default(),
+        }
+    }
+
+    fn read(text: &str, mut base: Self) -> Result<Self, InvalidKickTables> {
+        let mut definitions: Definitions = toml::from_str(text).map_err(|e| InvalidKickTables {
+            message: e.to_string(),
+        })?;
+        for (system, pieces) in &mut base.systems {
+            let name = system.name().to_lowercase();
+            let Some(mut definition) = definitions.remove(&name) else {
+                continue;
+            };
+            let default = definition
+                .remove("default")
+                .map(|turns| {
+                    read_turns(turns).map_err(|message| invalid(&name, "default", message))
+                })
+                .transpose()?;
+            let pieces = pieces.get_or_insert_with(EnumMap::default);
+            for (kind, kicks) in pieces {
+                let piece_name = format!("{:?}", kind);
+                match (definition.remove(&piece_name), &default) {
+                    (Some(turns), _) => {
+                        *kicks = read_turns(turns).map_err(|m| invalid(&name, &piece_name, m))?;
+                    }
+                    (None, Some(default)) => *kicks = default.clone(),
+                    (None, None) => {}
+                }
+            }
+            if let Some(piece_name) = definition.keys().next() {
+                return Err(InvalidKickTables {
+                    message: format!("{}: {} isn't a piece", name, piece_name),
+                });
+            }
+        }
+        if let Some(name) = definitions.keys().next() {
+            return Err(InvalidKickTables {
+                message: format!("{} isn't a rotation system", name),
+            });
+        }
+        if base.systems[RotationSystemKind::Srs].is_none() {
+            return Err(InvalidKickTables {
+                message: "srs is missing".to_owned(),
+            });
+        }
+        Ok(base)
+    }
+}
+
+fn invalid(system: &str, piece: &str, message: String) -> InvalidKickTables {
+    InvalidKickTables {
+        message: format!("{}.{}: {}", system, piece, message),
+    }
+}
+
+/// The offsets of a piece, from turns written like `0>90`.
+fn read_turns(turns: HashMap<String, Vec<[i32; 2]>>) -> Result<PieceKicks, String> {
+    let mut kicks = PieceKicks::default();
+    for (turn, offsets) in turns {
+        let rotations = turn
+            .split_once('>')
+            .and_then(|(from, to)| Some((rotation(from)?, rotation(to)?)));
+        let (from, to) = match rotations {
+            Some((from, to)) if from != to => (from, to),
+            _ => return Err(format!("{} isn't a turn", turn)),
+        };
+        if offsets.len() > KickTables::MAX_KICKS {
+            return Err(format!(
+                "{} has more than {} kicks",
+                turn,
+                KickTables::MAX_KICKS
+            ));
+        }
+        // Kicks past the largest board could only land a piece off it, and would overflow
+        // once they're scaled up for big pieces.
+        let max = BoardSize::MAX;
+        if let Some([x, y]) = offsets.iter().find(|[x, y]| {
+            x.unsigned_abs() as usize > max.width || y.unsigned_abs() as usize > max.height
+        }) {
+            return Err(format!(
+                "{}: {}, {} is further than {} by {}",
+                turn, x, y, max.width, max.height
+            ));
+        }
+        kicks[from][to] = offsets.into_iter().map(|[x, y]| Point2 { x, y }).collect();
+    }
+    Ok(kicks)
+}
+
+/// The rotation `degrees` clockwise from a piece's first shape.
+fn rotation(degrees: &str) -> Option<PieceRotation> {
+    match degrees.trim().parse::<u32>().ok()? {
+        degrees if degrees % 90 == 0 && degrees < 360 => {
+            Some(PieceRotation::from_usize(degrees as usize / 90))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(kicks: &[Point2<i32>]) -> Vec<(i32, i32)> {
+        kicks.iter().map(|kick| (kick.x, kick.y)).collect()
+    }
+
+    #[test]
+    fn srs_kicks_match_the_guideline() {
+        let tables = KickTables::standard();
+        let kicks = |kind, from, to| offsets(tables.kicks(RotationSystemKind::Srs, kind, from, to));
+        let (deg0, deg90) = (PieceRotation::Deg0, PieceRotation::Deg90);
+        assert_eq!(
+            kicks(PieceKind::T, deg0, deg90),
+            [(-1, 0), (-1, -1), (0, 2), (-1, 2)]
+        );
+        assert_eq!(
+            kicks(PieceKind::S, deg90, deg0),
+            [(1, 0), (1, 1), (0, -2), (1, -2)]
+        );
+        assert_eq!(
+            kicks(PieceKind::I, deg0, deg90),
+            [(-2, 0), (1, 0), (-2, 1), (1, -2)]
+        );
+        assert!(kicks(PieceKind::O, deg0, deg90).is_empty());
+    }
+
+    #[test]
+    fn systems_without_tables_kick_as_srs_does() {
+        let tables = KickTables::standard();
+        let (from, to) = (PieceRotation::Deg0, PieceRotation::Deg90);
+        assert_eq!(
+            offsets(tables.kicks(RotationSystemKind::Custom, PieceKind::T, from, to)),
+            offsets(tables.kicks(RotationSystemKind::Srs, PieceKind::T, from, to))
+        );
+        assert!(tables
+            .kicks(RotationSystemKind::Ars, PieceKind::I, from, to)
+            .is_empty());
+    }
+
+    #[test]
+    fn kicks_files_only_replace_the_pieces_they_list() {
+        let tables = KickTables::parse("[srs.T]\n\"0>90\" = [[0, -1]]").unwrap();
+        let (from, to) = (PieceRotation::Deg0, PieceRotation::Deg90);
+        let kicks = |kind| offsets(tables.kicks(RotationSystemKind::Srs, kind, from, to));
+        assert_eq!(kicks(PieceKind::T), [(0, -1)]);
+        // Turns the file leaves out aren't kicked.
+        let back = tables.kicks(RotationSystemKind::Srs, PieceKind::T, to, from);
+        assert!(back.is_empty());
+        assert_eq!(kicks(PieceKind::J), [(-1, 0), (-1, -1), (0, 2), (-1, 2)]);
+    }
+
+    #[test]
+    fn turns_between_rotations_that_dont_exist_are_invalid() {
+        assert!(KickTables::parse("[srs.T]\n\"0>0\" = [[1, 0]]").is_err());
+        assert!(KickTables::parse("[srs.T]\n\"45>90\" = [[1, 0]]").is_err());
+        assert!(KickTables::parse("[srs.T]\n\"0-90\" = [[1, 0]]").is_err());
+    }
+
+    #[test]
+    fn kicks_past_the_largest_board_are_invalid() {
+        assert!(KickTables::parse("[srs.T]\n\"0>90\" = [[0, 0], [-1, 0]]").is_ok());
+        assert!(KickTables::parse("[srs.T]\n\"0>90\" = [[2147483647, 0]]").is_err());
+        assert!(KickTables::parse("[srs.T]\n\"0>90\" = [[0, -41]]").is_err());
+    }
+
+    #[test]
+    fn turns_with_too_many_kicks_are_invalid() {
+        let kicks = vec!["[0, 0]"; KickTables::MAX_KICKS + 1].join(", ");
+        assert!(KickTables::parse(&format!("[srs.T]\n\"0>90\" = [{}]", kicks)).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::mode::RecordOrder;
+
+#[derive(Clone)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub value: u64,
+}
+
+/// Best results for a game mode, sorted from best to worst.
+#[derive(Clone)]
+pub struct Leaderboard {
+    entries: Vec<LeaderboardEntry>,
+    order: RecordOrder,
+}
+
+impl Leaderboard {
+    pub const MAX_ENTRIES: usize = 10;
+    pub const NAME_LENGTH: usize = 6;
+
+    pub fn new(order: RecordOrder) -> Self {
+        Self {
+            entries: vec![],
+            order,
+        }
+    }
+
+    /// Reads a table written by `to_text`. Each line holds a value followed by the name of
+    /// whoever got it; lines that don't make sense are skipped.
+    pub fn parse(contents: &str, order: RecordOrder) -> Self {
+        let mut leaderboard = Self::new(order);
+        for line in contents.lines() {
+            let (value, name) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+            if let Ok(value) = value.parse() {
+                leaderboard.record(name.trim().to_owned(), value);
+            }
+        }
+        leaderboard
+    }
+
+    pub fn to_text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| format!("{} {}\n", entry.value, entry.name))
+            .collect()
+    }
+
+    pub fn entries(&self) -> &[LeaderboardEntry] {
+        &self.entries
+    }
+
+    /// Where a value would rank in the table, if it would make the cut.
+    pub fn rank(&self, value: u64) -> Option<usize> {
+        let rank = self.entries.partition_point(|entry| match self.order {
+            RecordOrder::LowestFirst => entry.value <= value,
+            RecordOrder::HighestFirst => entry.value >= value,
+        });
+        (rank < Self::MAX_ENTRIES).then_some(rank)
+    }
+
+    /// Inserts a value into the table, returning its rank if it made the cut.
+    pub fn record(&mut self, name: String, value: u64) -> Option<usize> {
+        let rank = self.rank(value)?;
+        self.entries.insert(rank, LeaderboardEntry { name, value });
+        self.entries.truncate(Self::MAX_ENTRIES);
+        Some(rank)
+    }
+
+    pub fn best(&self) -> Option<u64> {
+        self.entries.first().map(|entry| entry.value)
+    }
+}
@@ -0,0 +1,50 @@
+//! The rules of the game, without any rendering, audio or windowing, so that games can be
+//! played, simulated and replayed by any frontend.
+
+mod achievements;
+mod bot;
+mod clock;
+mod combo;
+mod daily;
+mod drill;
+mod finesse;
+mod game;
+mod grid;
+pub mod gym;
+mod heatmap;
+mod history;
+mod input;
+mod kick_table;
+mod leaderboard;
+mod mode;
+mod piece;
+mod piece_set;
+mod replay;
+mod rotation;
+mod rules;
+mod script;
+mod setup;
+
+pub use achievements::{Achievement, Achievements};
+pub use bot::{Bot, Difficulty};
+pub use clock::GameClock;
+pub use combo::{ComboDrill, ComboPieces};
+pub use daily::{DailyChallenge, DailyResults};
+pub use drill::{Drill, DrillOutcome, InvalidDrill};
+pub use game::{
+    BoardSize, ClearCounts, ClearKind, Game, GameEvent, InvalidSave, SavedGame, ScoreBreakdown,
+};
+pub use grid::{Block, Grid, OutOfBounds};
+pub use heatmap::Heatmap;
+pub use history::GameSummary;
+pub use input::{Action, FrameInput, Handling, SoftDrop};
+pub use kick_table::{InvalidKickTables, KickTables};
+pub use leaderboard::{Leaderboard, LeaderboardEntry};
+pub use mode::{format_time, GameMode, GameStats, RecordOrder};
+pub use piece::{Piece, PieceKind, PieceRotation};
+pub use piece_set::{InvalidPieceSet, PieceSet};
+pub use replay::{InvalidReplay, Replay};
+pub use rotation::{Ars, CustomRotation, Nrs, RotationSystem, RotationSystemKind, Srs};
+pub use rules::{Rules, RulesHash, Scoring, TopOut};
+pub use script::InvalidScript;
+pub use setup::{InvalidSetup, Setup};
@@ -0,0 +1,442 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{clock::GameClock, piece::PieceKind, rules::Rules};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum GameMode {
+    Endless,
+    Sprint,
+    Ultra,
+    /// Level-based mode that ends in victory once `goal_level` has been cleared.
+    Marathon {
+        goal_level: u32,
+    },
+    /// Starts with `garbage_rows` rows of garbage and ends once all of them are cleared.
+    Dig {
+        garbage_rows: u32,
+    },
+    /// Garbage rises on its own, faster and faster, until the stack tops out.
+    Survival,
+    /// Endless, except that the stack fades out of sight shortly after every piece locks.
+    Invisible,
+    /// Endless with pieces twice as big, on a board twice as big to match.
+    Big,
+    /// Endless with pentominoes, mixed in with the tetrominoes if `with_tetrominoes`.
+    Pentomino {
+        with_tetrominoes: bool,
+    },
+    /// Endless, except that blocks left hanging by a clear fall until they land, and clear
+    /// any rows they complete as a chain worth more and more.
+    Cascade,
+    /// Pieces fall all the way as soon as they come in (20G), and get a grade by score, up to
+    /// GM for clearing every line fast enough.
+    Master,
+    /// Played as on the NES, with its rules, its gravity and its scoring, levelling up
+    /// every 10 lines without end.
+    Classic,
+}
+
+#[derive(Clone, Copy)]
+pub struct GameStats {
+    pub lines_cleared: u32,
+    pub score: u32,
+    pub elapsed: Duration,
+    /// Rows of the board with any garbage left in them.
+    pub garbage_rows: u32,
+    /// Most clears set off one after another by a single piece, in cascade mode.
+    pub max_chain: u32,
+}
+
+impl GameMode {
+    pub const SPRINT_LINES: u32 = 40;
+    pub const ULTRA_TIME: Duration = Duration::from_secs(120);
+    pub const MARATHON_GOAL_LEVEL: u32 = 15;
+    pub const LINES_PER_LEVEL: u32 = 10;
+    pub const DIG_GARBAGE_ROWS: u32 = 10;
+    /// Time between rows of garbage rising at the start of a survival game.
+    pub const SURVIVAL_START_INTERVAL: Duration = Duration::from_secs(8);
+    pub const SURVIVAL_MIN_INTERVAL: Duration = Duration::from_millis(1500);
+    /// How long a survival game goes on before the interval shrinks by a second.
+    const SURVIVAL_SPEED_UP_TIME: Duration = Duration::from_secs(20);
+    /// Frames it takes a piece to fall a row on the NES, by level from 1. Levels past the
+    /// end fall a row every frame.
+    const CLASSIC_FRAMES_PER_ROW: [u32; 29] = [
+        48, 43, 38, 33, 28, 23, 18, 13, 8, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+        2,
+    ];
+    /// Points for every second survived.
+    pub const SURVIVAL_POINTS_PER_SECOND: u32 = 10;
+    pub const MASTER_LINES: u32 = 100;
+    /// How fast every line of a master game has to be cleared, with the highest grade's
+    /// score, to be graded GM.
+    pub const MASTER_GM_TIME: Duration = Duration::from_secs(8 * 60);
+    /// Score each grade of master mode takes, from the lowest up.
+    const MASTER_GRADES: [(u32, &'static str); 18] = [
+        (0, "9"),
+        (400, "8"),
+        (800, "7"),
+        (1400, "6"),
+        (2000, "5"),
+        (3000, "4"),
+        (4000, "3"),
+        (5000, "2"),
+        (6000, "1"),
+        (7000, "S1"),
+        (8000, "S2"),
+        (9000, "S3"),
+        (10000, "S4"),
+        (11000, "S5"),
+        (12000, "S6"),
+        (13000, "S7"),
+        (14000, "S8"),
+        (15000, "S9"),
+    ];
+
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "endless" => Some(GameMode::Endless),
+            "sprint" => Some(GameMode::Sprint),
+            "ultra" => Some(GameMode::Ultra),
+            "marathon" => Some(GameMode::Marathon {
+                goal_level: Self::MARATHON_GOAL_LEVEL,
+            }),
+            "dig" => Some(GameMode::Dig {
+                garbage_rows: Self::DIG_GARBAGE_ROWS,
+            }),
+            "survival" => Some(GameMode::Survival),
+            "invisible" => Some(GameMode::Invisible),
+            "big" => Some(GameMode::Big),
+            "pentomino" => Some(GameMode::Pentomino {
+                with_tetrominoes: false,
+            }),
+            "pentomix" => Some(GameMode::Pentomino {
+                with_tetrominoes: true,
+            }),
+            "cascade" => Some(GameMode::Cascade),
+            "master" => Some(GameMode::Master),
+            "classic" => Some(GameMode::Classic),
+            _ => None,
+        }
+    }
+
+    /// The name `from_arg` reads the mode by, which online leaderboards key scores by too.
+    pub fn arg(self) -> &'static str {
+        match self {
+            GameMode::Endless => "endless",
+            GameMode::Sprint => "sprint",
+            GameMode::Ultra => "ultra",
+            GameMode::Marathon { .. } => "marathon",
+            GameMode::Dig { .. } => "dig",
+            GameMode::Survival => "survival",
+            GameMode::Invisible => "invisible",
+            GameMode::Big => "big",
+            GameMode::Pentomino {
+                with_tetrominoes: false,
+            } => "pentomino",
+            GameMode::Pentomino {
+                with_tetrominoes: true,
+            } => "pentomix",
+            GameMode::Cascade => "cascade",
+            GameMode::Master => "master",
+            GameMode::Classic => "classic",
+        }
+    }
+
+    /// Every mode, in the order they are listed in menus.
+    pub const ALL: [GameMode; 13] = [
+        GameMode::Marathon {
+            goal_level: Self::MARATHON_GOAL_LEVEL,
+        },
+        GameMode::Sprint,
+        GameMode::Ultra,
+        GameMode::Dig {
+            garbage_rows: Self::DIG_GARBAGE_ROWS,
+        },
+        GameMode::Survival,
+        GameMode::Invisible,
+        GameMode::Big,
+        GameMode::Pentomino {
+            with_tetrominoes: false,
+        },
+        GameMode::Pentomino {
+            with_tetrominoes: true,
+        },
+        GameMode::Cascade,
+        GameMode::Master,
+        GameMode::Classic,
+        GameMode::Endless,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Endless => "ENDLESS",
+            GameMode::Sprint => "SPRINT",
+            GameMode::Ultra => "ULTRA",
+            GameMode::Marathon { .. } => "MARATHON",
+            GameMode::Dig { .. } => "DIG",
+            GameMode::Survival => "SURVIVAL",
+            GameMode::Invisible => "INVISIBLE",
+            GameMode::Big => "BIG",
+            GameMode::Pentomino {
+                with_tetrominoes: false,
+            } => "PENTOMINO",
+            GameMode::Pentomino {
+                with_tetrominoes: true,
+            } => "PENTOMIX",
+            GameMode::Cascade => "CASCADE",
+            GameMode::Master => "MASTER",
+            GameMode::Classic => "CLASSIC",
+        }
+    }
+
+    pub fn line_goal(self) -> Option<u32> {
+        match self {
+            GameMode::Sprint => Some(Self::SPRINT_LINES),
+            GameMode::Marathon { goal_level } => Some(goal_level * Self::LINES_PER_LEVEL),
+            GameMode::Master => Some(Self::MASTER_LINES),
+            _ => None,
+        }
+    }
+
+    /// The current level, starting at 1. Only Marathon and Classic level up.
+    pub fn level(self, lines_cleared: u32) -> u32 {
+        match self {
+            GameMode::Classic => 1 + lines_cleared / Self::LINES_PER_LEVEL,
+            GameMode::Marathon { goal_level } => {
+                (1 + lines_cleared / Self::LINES_PER_LEVEL).min(goal_level)
+            }
+            _ => 1,
+        }
+    }
+
+    /// Time it takes for the falling piece to move down one row at the given level. Zero
+    /// means it falls all the way at once, which is called 20G.
+    pub fn gravity(self, level: u32) -> Duration {
+        match self {
+            GameMode::Master => Duration::ZERO,
+            GameMode::Classic => {
+                let frames = Self::CLASSIC_FRAMES_PER_ROW
+                    .get(level as usize - 1)
+                    .copied()
+                    .unwrap_or(1);
+                GameClock::STEP * frames
+            }
+            GameMode::Marathon { .. } => {
+                // Guideline gravity curve
+                let level = (level - 1) as f64;
+                Duration::from_secs_f64((0.8 - level * 0.007).powf(level))
+            }
+            _ => Duration::from_millis(500),
+        }
+    }
+
+    pub fn time_limit(self) -> Option<Duration> {
+        match self {
+            GameMode::Ultra => Some(Self::ULTRA_TIME),
+            _ => None,
+        }
+    }
+
+    /// Cells each block of a piece covers across and down.
+    pub fn piece_scale(self) -> i32 {
+        match self {
+            GameMode::Big => 2,
+            _ => 1,
+        }
+    }
+
+    /// Kinds of pieces dealt, or `None` for the tetrominoes, dealt the usual way.
+    pub fn piece_kinds(self) -> Option<Vec<PieceKind>> {
+        match self {
+            GameMode::Pentomino { with_tetrominoes } => {
+                let mut kinds = PieceKind::PENTOMINOES.to_vec();
+                if with_tetrominoes {
+                    kinds.extend(PieceKind::TETROMINOES);
+                }
+                Some(kinds)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn rules(self) -> Rules {
+        match self {
+            GameMode::Master => Rules::MASTER,
+            GameMode::Classic => Rules::CLASSIC,
+            _ => Rules::MODERN,
+        }
+    }
+
+    /// Whether holding a rotation as a piece comes in turns it straight away, whatever the
+    /// handling says, since at 20G there's no time to once it has landed.
+    pub fn initial_rotation(self) -> bool {
+        self == GameMode::Master
+    }
+
+    /// The grade of a master game with `score`, or GM if it also cleared every line within
+    /// `MASTER_GM_TIME`.
+    pub fn master_grade(score: u32, lines_cleared: u32, elapsed: Duration) -> &'static str {
+        let (top_score, _) = Self::MASTER_GRADES[Self::MASTER_GRADES.len() - 1];
+        if score >= top_score
+            && lines_cleared >= Self::MASTER_LINES
+            && elapsed <= Self::MASTER_GM_TIME
+        {
+            return "GM";
+        }
+        Self::MASTER_GRADES
+            .iter()
+            .rev()
+            .find(|(min_score, _)| score >= *min_score)
+            .map_or("9", |(_, grade)| grade)
+    }
+
+    /// Whether blocks left hanging by a clear fall until they land.
+    pub fn cascades(self) -> bool {
+        self == GameMode::Cascade
+    }
+
+    /// Rows of garbage the board starts with.
+    pub fn garbage_rows(self) -> u32 {
+        match self {
+            GameMode::Dig { garbage_rows } => garbage_rows,
+            _ => 0,
+        }
+    }
+
+    /// Time until the next row of garbage rises on its own, `elapsed` into the game, if
+    /// garbage rises in this mode at all.
+    pub fn garbage_interval(self, elapsed: Duration) -> Option<Duration> {
+        match self {
+            GameMode::Survival => {
+                let speed_ups = elapsed.as_secs() / Self::SURVIVAL_SPEED_UP_TIME.as_secs();
+                Some(
+                    Self::SURVIVAL_START_INTERVAL
+                        .saturating_sub(Duration::from_secs(speed_ups))
+                        .max(Self::SURVIVAL_MIN_INTERVAL),
+                )
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the mode's goal has been reached, ending the game without a top out.
+    pub fn is_complete(self, stats: &GameStats) -> bool {
+        (matches!(self, GameMode::Dig { .. }) && stats.garbage_rows == 0)
+            || self
+                .line_goal()
+                .is_some_and(|goal| stats.lines_cleared >= goal)
+            || self
+                .time_limit()
+                .is_some_and(|limit| stats.elapsed >= limit)
+    }
+
+    /// How this mode's records are ranked.
+    pub fn record_order(self) -> RecordOrder {
+        match self {
+            GameMode::Sprint | GameMode::Dig { .. } => RecordOrder::LowestFirst,
+            GameMode::Endless
+            | GameMode::Ultra
+            | GameMode::Marathon { .. }
+            | GameMode::Survival
+            | GameMode::Invisible
+            | GameMode::Big
+            | GameMode::Pentomino { .. }
+            | GameMode::Cascade
+            | GameMode::Master
+            | GameMode::Classic => RecordOrder::HighestFirst,
+        }
+    }
+
+    /// The value submitted to the leaderboard at the end of a run, if the run counts: sprint
+    /// and dig times only count once the goal is reached, and scores as long as they aren't
+    /// zero.
+    pub fn record_value(self, stats: &GameStats, completed: bool) -> Option<u64> {
+        match self {
+            GameMode::Sprint | GameMode::Dig { .. } => {
+                completed.then_some(stats.elapsed.as_millis() as u64)
+            }
+            GameMode::Endless
+            | GameMode::Ultra
+            | GameMode::Marathon { .. }
+            | GameMode::Survival
+            | GameMode::Invisible
+            | GameMode::Big
+            | GameMode::Pentomino { .. }
+            | GameMode::Cascade
+            | GameMode::Master
+            | GameMode::Classic => (stats.score > 0).then_some(stats.score as u64),
+        }
+    }
+
+    pub fn format_record(self, value: u64) -> String {
+        match self {
+            GameMode::Sprint | GameMode::Dig { .. } => format_time(Duration::from_millis(value)),
+            GameMode::Endless
+            | GameMode::Ultra
+            | GameMode::Marathon { .. }
+            | GameMode::Survival
+            | GameMode::Invisible
+            | GameMode::Big
+            | GameMode::Pentomino { .. }
+            | GameMode::Cascade
+            | GameMode::Master
+            | GameMode::Classic => value.to_string(),
+        }
+    }
+
+    pub fn hud_lines(self, stats: &GameStats) -> Vec<String> {
+        let mut lines = vec![format!("SCORE {}", stats.score)];
+        match self {
+            GameMode::Marathon { goal_level } => lines.push(format!(
+                "LEVEL {}/{}",
+                self.level(stats.lines_cleared),
+                goal_level
+            )),
+            GameMode::Classic => lines.push(format!("LEVEL {}", self.level(stats.lines_cleared))),
+            _ => {}
+        }
+        match self.line_goal() {
+            Some(goal) => lines.push(format!("LINES {}/{}", stats.lines_cleared, goal)),
+            None => lines.push(format!("LINES {}", stats.lines_cleared)),
+        }
+        if let GameMode::Dig { .. } = self {
+            lines.push(format!("GARBAGE {}", stats.garbage_rows));
+        }
+        if self.cascades() {
+            lines.push(format!("CHAIN {}", stats.max_chain));
+        }
+        if self == GameMode::Master {
+            lines.push(format!(
+                "GRADE {}",
+                Self::master_grade(stats.score, stats.lines_cleared, stats.elapsed)
+            ));
+        }
+        match self.time_limit() {
+            Some(limit) => lines.push(format!(
+                "TIME {}",
+                format_time(limit.saturating_sub(stats.elapsed))
+            )),
+            None => lines.push(format!("TIME {}", format_time(stats.elapsed))),
+        }
+        lines
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RecordOrder {
+    LowestFirst,
+    HighestFirst,
+}
+
+pub fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
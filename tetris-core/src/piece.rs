@@ -0,0 +1,285 @@
+use enum_map::Enum;
+use mint::Point2;
+use serde::{Deserialize, Serialize};
+
+use crate::{grid::Grid, piece_set::PieceSet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum, Serialize, Deserialize)]
+pub enum PieceRotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl PieceRotation {
+    pub fn rotate_cw(self) -> Self {
+        use PieceRotation::*;
+        match self {
+            Deg0 => Deg90,
+            Deg90 => Deg180,
+            Deg180 => Deg270,
+            Deg270 => Deg0,
+        }
+    }
+    pub fn rotate_ccw(self) -> Self {
+        use PieceRotation::*;
+        match self {
+            Deg0 => Deg270,
+            Deg90 => Deg0,
+            Deg180 => Deg90,
+            Deg270 => Deg180,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum, Serialize, Deserialize)]
+pub enum PieceKind {
+    I,
+    J,
+    L,
+    O,
+    S,
+    T,
+    Z,
+    // The 18 pentominoes, counting mirror images as different pieces since pieces can't be
+    // flipped. Their names follow the letters they look like.
+    PentoF,
+    PentoFMirror,
+    PentoI,
+    PentoL,
+    PentoLMirror,
+    PentoN,
+    PentoNMirror,
+    PentoP,
+    PentoPMirror,
+    PentoT,
+    PentoU,
+    PentoV,
+    PentoW,
+    PentoX,
+    PentoY,
+    PentoYMirror,
+    PentoZ,
+    PentoZMirror,
+}
+
+impl PieceKind {
+    pub const TETROMINOES: [PieceKind; 7] = {
+        use PieceKind::*;
+        [I, J, L, O, S, T, Z]
+    };
+    pub const PENTOMINOES: [PieceKind; 18] = {
+        use PieceKind::*;
+        [
+            PentoF,
+            PentoFMirror,
+            PentoI,
+            PentoL,
+            PentoLMirror,
+            PentoN,
+            PentoNMirror,
+            PentoP,
+            PentoPMirror,
+            PentoT,
+            PentoU,
+            PentoV,
+            PentoW,
+            PentoX,
+            PentoY,
+            PentoYMirror,
+            PentoZ,
+            PentoZMirror,
+        ]
+    };
+
+    /// A random tetromino.
+    pub fn random(rng: &mut impl rand::Rng) -> Self {
+        let num = rng.gen_range(0..7);
+        match num {
+            0 => PieceKind::I,
+            1 => PieceKind::J,
+            2 => PieceKind::L,
+            3 => PieceKind::O,
+            4 => PieceKind::S,
+            5 => PieceKind::T,
+            _ => PieceKind::Z,
+        }
+    }
+
+    /// The tetromino a letter names, as in drills and setups.
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'I' => Some(PieceKind::I),
+            'J' => Some(PieceKind::J),
+            'L' => Some(PieceKind::L),
+            'O' => Some(PieceKind::O),
+            'S' => Some(PieceKind::S),
+            'T' => Some(PieceKind::T),
+            'Z' => Some(PieceKind::Z),
+            _ => None,
+        }
+    }
+
+    /// The letter of a tetromino, or `None` for pentominoes, which have no letter of their
+    /// own.
+    pub fn letter(self) -> Option<char> {
+        match self {
+            PieceKind::I => Some('I'),
+            PieceKind::J => Some('J'),
+            PieceKind::L => Some('L'),
+            PieceKind::O => Some('O'),
+            PieceKind::S => Some('S'),
+            PieceKind::T => Some('T'),
+            PieceKind::Z => Some('Z'),
+            _ => None,
+        }
+    }
+
+    /// The tetromino whose sprite the piece is drawn with from a tileset, so that themes only
+    /// need a sprite for each tetromino.
+    pub fn palette(self) -> PieceKind {
+        use PieceKind::*;
+        match self {
+            I | J | L | O | S | T | Z => self,
+            PentoI | PentoY | PentoYMirror => I,
+            PentoL | PentoP | PentoU => L,
+            PentoLMirror | PentoPMirror | PentoV => J,
+            PentoN | PentoFMirror | PentoW => S,
+            PentoNMirror | PentoF | PentoZ | PentoZMirror => Z,
+            PentoT | PentoX => T,
+        }
+    }
+}
+
+impl PieceKind {
+    /// The cells the piece covers in `rotation`, as given by the installed `PieceSet`.
+    pub fn get_grid(&self, rotation: PieceRotation) -> Grid {
+        PieceSet::current().grid(*self, rotation).clone()
+    }
+
+    /// Red, green and blue from 0 to 255, as given by the installed `PieceSet`.
+    pub fn color(self) -> [u8; 3] {
+        PieceSet::current().color(self)
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Piece {
+    pub pos: Point2<i32>,
+    pub rotation: PieceRotation,
+    pub kind: PieceKind,
+    /// Cells each block covers across and down, which is more than one in big mode. The
+    /// piece moves that many cells at a time.
+    pub scale: i32,
+}
+
+impl Piece {
+    /// A piece of `kind` as it spawns, at the top of the field of `grid`, under its hidden
+    /// rows, and centered on it, a whole number of blocks from the left.
+    pub fn spawned(kind: PieceKind, scale: i32, grid: &Grid) -> Self {
+        let width = kind.get_grid(PieceRotation::Deg0).width() as i32 * scale;
+        Self {
+            pos: Point2 {
+                x: (grid.width() as i32 - width) / 2 / scale * scale,
+                y: grid.hidden_rows() as i32,
+            },
+            rotation: PieceRotation::Deg0,
+            kind,
+            scale,
+        }
+    }
+
+    /// The cells the piece covers, counting from `pos`.
+    pub fn grid(&self) -> Grid {
+        let grid = self.kind.get_grid(self.rotation);
+        if self.scale == 1 {
+            grid
+        } else {
+            grid.scaled(self.scale as usize)
+        }
+    }
+
+    /// The cells the piece covers on the board.
+    pub fn cells(&self) -> impl Iterator<Item = Point2<i32>> {
+        let pos = self.pos;
+        let cells: Vec<_> = self
+            .grid()
+            .iter()
+            .filter(|(_, _, block)| block.is_some())
+            .map(|(x, y, _)| Point2 {
+                x: pos.x + x,
+                y: pos.y + y,
+            })
+            .collect();
+        cells.into_iter()
+    }
+
+    /// The top left and bottom right cells of the smallest rectangle around the cells the
+    /// piece covers on the board.
+    pub fn bounds(&self) -> (Point2<i32>, Point2<i32>) {
+        self.cells().fold(
+            (
+                Point2 {
+                    x: i32::MAX,
+                    y: i32::MAX,
+                },
+                Point2 {
+                    x: i32::MIN,
+                    y: i32::MIN,
+                },
+            ),
+            |(min, max), cell| {
+                (
+                    Point2 {
+                        x: min.x.min(cell.x),
+                        y: min.y.min(cell.y),
+                    },
+                    Point2 {
+                        x: max.x.max(cell.x),
+                        y: max.y.max(cell.y),
+                    },
+                )
+            },
+        )
+    }
+
+    /// Where the piece ends up if it's dropped straight down onto the stack of `grid`.
+    pub fn ghost_position(&self, grid: &Grid) -> Point2<i32> {
+        let mut ghost = *self;
+        while !ghost.collides_with(grid) {
+            ghost.pos.y += ghost.scale;
+        }
+        Point2 {
+            x: ghost.pos.x,
+            y: ghost.pos.y - ghost.scale,
+        }
+    }
+
+    /// The piece turned to `rotation`, or if it doesn't fit in `grid` that way, moved by the
+    /// first of `kicks` that it does fit with. `None` if none do.
+    pub fn rotated(
+        &self,
+        rotation: PieceRotation,
+        kicks: &[Point2<i32>],
+        grid: &Grid,
+    ) -> Option<Self> {
+        std::iter::once(&Point2 { x: 0, y: 0 })
+            .chain(kicks)
+            .map(|kick| Piece {
+                pos: Point2 {
+                    x: self.pos.x + kick.x * self.scale,
+                    y: self.pos.y + kick.y * self.scale,
+                },
+                rotation,
+                ..*self
+            })
+            .find(|piece| !piece.collides_with(grid))
+    }
+
+    pub fn collides_with(&self, grid: &Grid) -> bool {
+        let piece_grid = self.grid();
+        grid.intersects(self.pos.x, self.pos.y, &piece_grid)
+            || !grid.contains(self.pos.x, self.pos.y, &piece_grid)
+    }
+}
@@ -0,0 +1,212 @@
+use std::{collections::HashMap, fmt, sync::OnceLock};
+
+use enum_map::EnumMap;
+use serde::Deserialize;
+
+use crate::{
+    grid::{Block, Grid},
+    piece::{PieceKind, PieceRotation},
+};
+
+/// Returned when a set of pieces can't be read, with what's wrong with it.
+#[derive(Debug)]
+pub struct InvalidPieceSet {
+    pub message: String,
+}
+
+impl fmt::Display for InvalidPieceSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for InvalidPieceSet {}
+
+/// A piece as it's written in a pieces file, see `pieces.toml`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PieceDefinition {
+    color: [u8; 3],
+    shapes: Vec<Vec<String>>,
+    #[serde(default)]
+    rotates: bool,
+}
+
+struct PieceData {
+    grids: EnumMap<PieceRotation, Grid>,
+    color: [u8; 3],
+}
+
+/// The shape and color of every kind of piece. The standard set is read from
+/// `pieces.toml`, built into the game, and others are read over it so that they only need
+/// to list the pieces they change.
+///
+/// Every part of the game uses the set that was installed, so that games started with a
+/// different set play differently, replays included.
+pub struct PieceSet {
+    pieces: EnumMap<PieceKind, PieceData>,
+}
+
+static INSTALLED: OnceLock<PieceSet> = OnceLock::new();
+
+impl PieceSet {
+    /// Boxes can't be bigger than this across or down, so that pieces fit in the rows they
+    /// spawn in and in the previews.
+    pub const MAX_SIZE: usize = 5;
+    const STANDARD: &'static str = include_str!("../pieces.toml");
+
+    pub fn standard() -> Self {
+        Self::read(Self::STANDARD, None).expect("the standard pieces are valid")
+    }
+
+    /// Reads a pieces file over the standard set, replacing the pieces it lists.
+    pub fn parse(text: &str) -> Result<Self, InvalidPieceSet> {
+        Self::read(text, Some(Self::standard()))
+    }
+
+    /// Makes every piece from now on use this set. Panics if pieces were used already, since
+    /// the set can't change under them.
+    pub fn install(self) {
+        if INSTALLED.set(self).is_err() {
+            panic!("pieces have to be installed before any are used");
+        }
+    }
+
+    /// The installed set, or the standard one if none was.
+    pub fn current() -> &'static Self {
+        INSTALLED.get_or_init(Self::standard)
+    }
+
+    pub fn grid(&self, kind: PieceKind, rotation: PieceRotation) -> &Grid {
+        &self.pieces[kind].grids[rotation]
+    }
+
+    /// Red, green and blue from 0 to 255.
+    pub fn color(&self, kind: PieceKind) -> [u8; 3] {
+        self.pieces[kind].color
+    }
+
+    /// Adds every shape and color to `bytes`, for `RulesHash`.
+    pub(crate) fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        for piece in self.pieces.values() {
+            for grid in piece.grids.values() {
+                bytes.push(grid.width() as u8);
+                bytes.push(grid.height() as u8);
+                bytes.extend(grid.iter().map(|(_, _, block)| u8::from(block.is_some())));
+            }
+            bytes.extend(piece.color);
+        }
+    }
+
+    /// Reads `text`, taking pieces it doesn't list from `base`, or failing if there's none.
+    fn read(text: &str, base: Option<Self>) -> Result<Self, InvalidPieceSet> {
+        // Keyed by name, since pieces can't be read as keys.
+        let mut definitions: HashMap<String, PieceDefinition> =
+            toml::from_str(text).map_err(|e| InvalidPieceSet {
+                message: e.to_string(),
+            })?;
+        let mut base = base.map(|base| base.pieces.map(|_, data| Some(data)));
+        let mut pieces = EnumMap::<PieceKind, Option<PieceData>>::default();
+        for (kind, piece) in &mut pieces {
+            let base = base.as_mut().and_then(|base| base[kind].take());
+            *piece = Some(match (definitions.remove(&format!("{:?}", kind)), base) {
+                (Some(definition), _) => {
+                    PieceData::new(kind, definition).map_err(|message| InvalidPieceSet {
+                        message: format!("{:?}: {}", kind, message),
+                    })?
+                }
+                (None, Some(base)) => base,
+                (None, None) => {
+                    return Err(InvalidPieceSet {
+                        message: format!("{:?} is missing", kind),
+                    })
+                }
+            });
+        }
+        if let Some(name) = definitions.keys().next() {
+            return Err(InvalidPieceSet {
+                message: format!("{} isn't a piece", name),
+            });
+        }
+        Ok(Self {
+            pieces: pieces.map(|_, piece| piece.unwrap()),
+        })
+    }
+}
+
+impl PieceData {
+    fn new(kind: PieceKind, definition: PieceDefinition) -> Result<Self, String> {
+        let shapes = definition
+            .shapes
+            .iter()
+            .map(|rows| shape_grid(kind, rows))
+            .collect::<Result<Vec<_>, _>>()?;
+        let grids = match (shapes.as_slice(), definition.rotates) {
+            ([shape], true) => {
+                if shape.width() != shape.height() {
+                    return Err("only square boxes can be turned".to_owned());
+                }
+                let quarter = turned_cw(shape);
+                let half = turned_cw(&quarter);
+                let three_quarters = turned_cw(&half);
+                EnumMap::from_array([shape.clone(), quarter, half, three_quarters])
+            }
+            (_, true) => return Err("only a single shape can be turned".to_owned()),
+            ([shape], false) => {
+                EnumMap::from_array([shape.clone(), shape.clone(), shape.clone(), shape.clone()])
+            }
+            ([first, second], false) => {
+                EnumMap::from_array([first.clone(), second.clone(), first.clone(), second.clone()])
+            }
+            ([deg0, deg90, deg180, deg270], false) => {
+                EnumMap::from_array([deg0.clone(), deg90.clone(), deg180.clone(), deg270.clone()])
+            }
+            _ => return Err("pieces need 1, 2 or 4 shapes".to_owned()),
+        };
+        Ok(Self {
+            grids,
+            color: definition.color,
+        })
+    }
+}
+
+/// The grid of a shape written as rows of `#` and `.`.
+fn shape_grid(kind: PieceKind, rows: &[String]) -> Result<Grid, String> {
+    let width = rows.first().map_or(0, |row| row.chars().count());
+    if !(1..=PieceSet::MAX_SIZE).contains(&width) || !(1..=PieceSet::MAX_SIZE).contains(&rows.len())
+    {
+        return Err(format!(
+            "shapes have to be from 1 by 1 to {0} by {0}",
+            PieceSet::MAX_SIZE
+        ));
+    }
+    let mut grid = Grid::new(width, rows.len());
+    for (y, row) in rows.iter().enumerate() {
+        if row.chars().count() != width {
+            return Err("rows have to be as wide as the first".to_owned());
+        }
+        for (x, c) in row.chars().enumerate() {
+            match c {
+                '.' => {}
+                '#' => grid.set(x as i32, y as i32, Some(Block::of(kind))),
+                c => return Err(format!("{} isn't a cell", c)),
+            }
+        }
+    }
+    if !(0..rows.len() as i32).any(|y| (0..width as i32).any(|x| grid.at(x, y).is_some())) {
+        return Err("shapes need at least one block".to_owned());
+    }
+    Ok(grid)
+}
+
+/// `grid`, which is square, turned a quarter clockwise.
+fn turned_cw(grid: &Grid) -> Grid {
+    let size = grid.width() as i32;
+    let mut turned = Grid::new(grid.width(), grid.height());
+    for y in 0..size {
+        for x in 0..size {
+            turned.set(size - 1 - y, x, *grid.at(x, y));
+        }
+    }
+    turned
+}
@@ -0,0 +1,399 @@
+use std::{fmt, time::Duration};
+
+use crate::{
+    game::{BoardSize, Game},
+    input::{FrameInput, Handling, SoftDrop},
+    mode::GameMode,
+    rotation::RotationSystemKind,
+    rules::{fnv1a, RulesHash, TopOut},
+};
+
+/// Every input of a game along with what it started from, so that it can be simulated again
+/// exactly as it was played.
+#[derive(Clone)]
+pub struct Replay {
+    pub mode: GameMode,
+    pub seed: u32,
+    /// The handling the game started with.
+    pub handling: Handling,
+    pub board_size: BoardSize,
+    /// The rules the game was played with, which it has to be played back with too.
+    pub rules_hash: RulesHash,
+    /// Number of steps the game lasted.
+    pub length: u32,
+    /// Inputs along with the step they happened on, only for steps where they changed.
+    pub inputs: Vec<(u32, FrameInput)>,
+    /// Handling changed in the middle of the game, as from the pause menu, along with the
+    /// step it was first used on.
+    pub handling_changes: Vec<(u32, Handling)>,
+}
+
+impl Replay {
+    const MAGIC: &'static [u8; 4] = b"TRPL";
+    const VERSION: u8 = 11;
+
+    pub fn new(mode: GameMode, seed: u32, handling: Handling, board_size: BoardSize) -> Self {
+        Self {
+            mode,
+            seed,
+            handling,
+            board_size,
+            rules_hash: RulesHash::installed(),
+            length: 0,
+            inputs: vec![],
+            handling_changes: vec![],
+        }
+    }
+
+    /// How long the recorded game lasted.
+    pub fn duration(&self) -> Duration {
+        Game::FRAME_TIME * self.length
+    }
+
+    pub fn record(&mut self, frame: u32, input: FrameInput) {
+        self.length = frame.saturating_add(1);
+        let last = self
+            .inputs
+            .last()
+            .map_or(FrameInput::default(), |(_, input)| *input);
+        if input != last.held() {
+            self.inputs.push((frame, input));
+        }
+    }
+
+    /// Records the handling a step is played with, if it isn't what the step before it was.
+    pub fn record_handling(&mut self, frame: u32, handling: Handling) {
+        if handling != self.handling_at(frame) {
+            self.handling_changes.push((frame, handling));
+        }
+    }
+
+    /// The handling the given step was played with.
+    pub fn handling_at(&self, frame: u32) -> Handling {
+        let changed = self
+            .handling_changes
+            .partition_point(|(change, _)| *change <= frame);
+        match changed.checked_sub(1) {
+            Some(i) => self.handling_changes[i].1,
+            None => self.handling,
+        }
+    }
+
+    /// A 64-bit FNV-1a hash of the replay as written by `to_bytes`, to tell whether a
+    /// replay is the one a score was sent with.
+    pub fn hash(&self) -> u64 {
+        fnv1a(&self.to_bytes())
+    }
+
+    /// Whether the replay was played with the rules that are installed, and so can be
+    /// played back.
+    pub fn has_installed_rules(&self) -> bool {
+        self.rules_hash == RulesHash::installed()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (mode, goal_level) = match self.mode {
+            GameMode::Endless => (0u8, 0),
+            GameMode::Sprint => (1, 0),
+            GameMode::Ultra => (2, 0),
+            GameMode::Marathon { goal_level } => (3, goal_level),
+            GameMode::Dig { garbage_rows } => (4, garbage_rows),
+            GameMode::Survival => (5, 0),
+            GameMode::Invisible => (6, 0),
+            GameMode::Big => (7, 0),
+            GameMode::Pentomino { with_tetrominoes } => (8, with_tetrominoes.into()),
+            GameMode::Cascade => (9, 0),
+            GameMode::Master => (10, 0),
+            GameMode::Classic => (11, 0),
+        };
+        let mut bytes = Self::MAGIC.to_vec();
+        bytes.push(Self::VERSION);
+        bytes.push(mode);
+        bytes.extend(goal_level.to_le_bytes());
+        bytes.extend(self.seed.to_le_bytes());
+        write_handling(&mut bytes, &self.handling);
+        bytes.push(self.board_size.width as u8);
+        bytes.push(self.board_size.height as u8);
+        bytes.extend(self.rules_hash.0.to_le_bytes());
+        bytes.extend(self.length.to_le_bytes());
+        bytes.extend((self.inputs.len() as u32).to_le_bytes());
+        for (frame, input) in &self.inputs {
+            bytes.extend(frame.to_le_bytes());
+            bytes.extend(input.pressed.to_le_bytes());
+            bytes.extend(input.just_pressed.to_le_bytes());
+        }
+        bytes.extend((self.handling_changes.len() as u32).to_le_bytes());
+        for (frame, handling) in &self.handling_changes {
+            bytes.extend(frame.to_le_bytes());
+            write_handling(&mut bytes, handling);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, InvalidReplay> {
+        let mut reader = ByteReader(bytes);
+        if reader.take(4) != Some(Self::MAGIC) || reader.u8() != Some(Self::VERSION) {
+            return Err(InvalidReplay);
+        }
+        let mut read = || -> Option<Self> {
+            let mode = match (reader.u8()?, reader.u32()?) {
+                (0, _) => GameMode::Endless,
+                (1, _) => GameMode::Sprint,
+                (2, _) => GameMode::Ultra,
+                (3, goal_level) => GameMode::Marathon { goal_level },
+                (4, garbage_rows) => GameMode::Dig { garbage_rows },
+                (5, _) => GameMode::Survival,
+                (6, _) => GameMode::Invisible,
+                (7, _) => GameMode::Big,
+                (8, with_tetrominoes) => GameMode::Pentomino {
+                    with_tetrominoes: with_tetrominoes != 0,
+                },
+                (9, _) => GameMode::Cascade,
+                (10, _) => GameMode::Master,
+                (11, _) => GameMode::Classic,
+                _ => return None,
+            };
+            let seed = reader.u32()?;
+            let handling = read_handling(&mut reader)?;
+            let board_size = BoardSize {
+                width: reader.u8()?.into(),
+                height: reader.u8()?.into(),
+            };
+            if !board_size.is_allowed() {
+                return None;
+            }
+            let rules_hash = RulesHash(reader.u64()?);
+            let length = reader.u32()?;
+            let inputs = (0..reader.u32()?)
+                .map(|_| {
+                    let frame = reader.u32()?;
+                    let input = FrameInput {
+                        pressed: reader.u16()?,
+                        just_pressed: reader.u16()?,
+                    };
+                    Some((frame, input))
+                })
+                .collect::<Option<_>>()?;
+            let handling_changes = (0..reader.u32()?)
+                .map(|_| Some((reader.u32()?, read_handling(&mut reader)?)))
+                .collect::<Option<_>>()?;
+            Some(Self {
+                mode,
+                seed,
+                handling,
+                board_size,
+                rules_hash,
+                length,
+                inputs,
+                handling_changes,
+            })
+        };
+        read().ok_or(InvalidReplay)
+    }
+}
+
+fn write_handling(bytes: &mut Vec<u8>, handling: &Handling) {
+    let millis = |duration: Duration| (duration.as_millis() as u16).to_le_bytes();
+    bytes.extend(millis(handling.das));
+    bytes.extend(millis(handling.arr));
+    bytes.extend(millis(handling.dcd));
+    bytes.extend(millis(handling.are));
+    bytes.extend(millis(handling.line_clear_delay));
+    bytes.extend(handling.soft_drop.to_bits().to_le_bytes());
+    bytes.push(handling.rotation_system as u8);
+    bytes.push(handling.top_out.to_bits());
+    bytes.push(
+        u8::from(handling.initial_rotation)
+            | u8::from(handling.initial_hold) << 1
+            | u8::from(handling.hold) << 2,
+    );
+}
+
+fn read_handling(reader: &mut ByteReader) -> Option<Handling> {
+    let mut millis = || Some(Duration::from_millis(reader.u16()?.into()));
+    let mut handling = Handling {
+        das: millis()?,
+        arr: millis()?,
+        dcd: millis()?,
+        are: millis()?,
+        line_clear_delay: millis()?,
+        soft_drop: SoftDrop::from_bits(reader.u16()?),
+        rotation_system: *RotationSystemKind::ALL.get(reader.u8()? as usize)?,
+        top_out: TopOut::from_bits(reader.u8()?),
+        ..Handling::default()
+    };
+    let flags = reader.u8()?;
+    handling.initial_rotation = flags & 1 != 0;
+    handling.initial_hold = flags & 1 << 1 != 0;
+    handling.hold = flags & 1 << 2 != 0;
+    Some(handling)
+}
+
+/// Returned when bytes don't hold a replay this version of the game can read.
+#[derive(Debug)]
+pub struct InvalidReplay;
+
+impl fmt::Display for InvalidReplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid replay file")
+    }
+}
+
+impl std::error::Error for InvalidReplay {}
+
+/// Reads little endian values from the front of a byte slice.
+struct ByteReader<'a>(&'a [u8]);
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bot::{Bot, Difficulty};
+
+    use super::*;
+
+    /// Has a bot play a game of `mode`, with a change of handling halfway, recording it as
+    /// the gameplay scene does.
+    fn record(mode: GameMode, steps: u32) -> (Game, Replay) {
+        let board_size = BoardSize::default();
+        let mut game = Game::new(mode, 7, board_size);
+        let mut replay = Replay::new(mode, 7, Handling::default(), board_size);
+        let mut bot = Bot::new(Difficulty::Hard, 7);
+        let fast = Handling {
+            das: Duration::from_millis(60),
+            arr: Duration::ZERO,
+            are: Duration::from_millis(100),
+            line_clear_delay: Duration::from_millis(200),
+            ..Handling::default()
+        };
+        while game.frame() < steps && !game.is_over() {
+            let frame = game.frame();
+            let input = bot.input(&game);
+            let handling = if frame < steps / 2 {
+                Handling::default()
+            } else {
+                fast
+            };
+            replay.record(frame, input);
+            replay.record_handling(frame, handling);
+            game.step(handling, input);
+        }
+        (game, replay)
+    }
+
+    /// Whether two games ended up in the same place.
+    fn same_game(a: &Game, b: &Game) -> bool {
+        let (a_stats, b_stats) = (a.stats(), b.stats());
+        a.frame() == b.frame()
+            && a.is_over() == b.is_over()
+            && a.pieces_placed() == b.pieces_placed()
+            && a_stats.score == b_stats.score
+            && a_stats.lines_cleared == b_stats.lines_cleared
+            && a.grid()
+                .iter()
+                .zip(b.grid().iter())
+                .all(|((.., a), (.., b))| a.is_some() == b.is_some())
+    }
+
+    #[test]
+    fn played_back_games_end_as_they_were_played() {
+        for mode in [GameMode::Endless, GameMode::Sprint, GameMode::Classic] {
+            let (game, replay) = record(mode, 3000);
+            assert!(game.stats().lines_cleared > 0);
+            assert!(same_game(&replay.play(), &game));
+        }
+    }
+
+    #[test]
+    fn replays_are_read_back_as_they_were_written() {
+        let (game, replay) = record(GameMode::Marathon { goal_level: 15 }, 2000);
+        let read = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert!(read.mode == replay.mode);
+        assert_eq!(read.seed, replay.seed);
+        assert!(read.handling == replay.handling);
+        assert!(read.board_size == replay.board_size);
+        assert_eq!(read.rules_hash, replay.rules_hash);
+        assert_eq!(read.length, replay.length);
+        assert!(read.inputs == replay.inputs);
+        assert!(read.handling_changes == replay.handling_changes);
+        assert_eq!(read.hash(), replay.hash());
+        assert!(same_game(&read.play(), &game));
+    }
+
+    #[test]
+    fn damaged_replays_are_invalid() {
+        let (_, replay) = record(GameMode::Endless, 600);
+        let bytes = replay.to_bytes();
+        assert!(Replay::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Replay::from_bytes(&bytes[1..]).is_err());
+        let mut newer = bytes.clone();
+        newer[4] += 1;
+        assert!(Replay::from_bytes(&newer).is_err());
+        let mut too_small = bytes;
+        // The board's width, after the magic, version, mode, seed and handling.
+        too_small[4 + 1 + 5 + 4 + 15] = 1;
+        assert!(Replay::from_bytes(&too_small).is_err());
+    }
+
+    #[test]
+    fn recording_on_the_last_frame_saturates() {
+        let mut replay = Replay::new(
+            GameMode::Endless,
+            0,
+            Handling::default(),
+            BoardSize::default(),
+        );
+        replay.record(u32::MAX, FrameInput::default());
+        assert_eq!(replay.length, u32::MAX);
+    }
+
+    #[test]
+    fn handling_changes_apply_from_the_step_they_were_made_on() {
+        let mut replay = Replay::new(
+            GameMode::Endless,
+            0,
+            Handling::default(),
+            BoardSize::default(),
+        );
+        let fast = Handling {
+            das: Duration::from_millis(80),
+            ..Handling::default()
+        };
+        replay.record_handling(0, Handling::default());
+        replay.record_handling(10, fast);
+        replay.record_handling(11, fast);
+        assert_eq!(replay.handling_changes.len(), 1);
+        assert!(replay.handling_at(9) == Handling::default());
+        assert!(replay.handling_at(10) == fast);
+        assert!(replay.handling_at(100) == fast);
+
+        let read = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(read.handling_changes.len(), 1);
+        assert!(read.handling_at(10) == fast);
+    }
+}
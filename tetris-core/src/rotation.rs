@@ -0,0 +1,163 @@
+use enum_map::Enum;
+use mint::Point2;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    kick_table::KickTables,
+    piece::{PieceKind, PieceRotation},
+};
+
+/// How pieces come in and turn. Every system turns the shapes of the installed piece set,
+/// and only differs in which way up pieces come in, whether they turn around and where
+/// they are tried when they don't fit after turning, as the installed `KickTables` say.
+pub trait RotationSystem: Sync {
+    fn spawn_rotation(&self, kind: PieceKind) -> PieceRotation;
+
+    /// Offsets tried in order when a piece turned from `from` to `to` doesn't fit where it
+    /// is, in blocks right and down.
+    fn kicks(
+        &self,
+        kind: PieceKind,
+        from: PieceRotation,
+        to: PieceRotation,
+    ) -> &'static [Point2<i32>];
+
+    /// Whether pieces can be turned around in one go.
+    fn turns_180(&self) -> bool;
+}
+
+/// The Super Rotation System of modern games.
+pub struct Srs;
+
+impl RotationSystem for Srs {
+    fn spawn_rotation(&self, _kind: PieceKind) -> PieceRotation {
+        PieceRotation::Deg0
+    }
+
+    fn kicks(
+        &self,
+        kind: PieceKind,
+        from: PieceRotation,
+        to: PieceRotation,
+    ) -> &'static [Point2<i32>] {
+        KickTables::current().kicks(RotationSystemKind::Srs, kind, from, to)
+    }
+
+    fn turns_180(&self) -> bool {
+        true
+    }
+}
+
+/// The Arika Rotation System of arcade games, where pieces come in flat side up.
+pub struct Ars;
+
+impl RotationSystem for Ars {
+    fn spawn_rotation(&self, kind: PieceKind) -> PieceRotation {
+        flat_side_up(kind)
+    }
+
+    fn kicks(
+        &self,
+        kind: PieceKind,
+        from: PieceRotation,
+        to: PieceRotation,
+    ) -> &'static [Point2<i32>] {
+        KickTables::current().kicks(RotationSystemKind::Ars, kind, from, to)
+    }
+
+    fn turns_180(&self) -> bool {
+        false
+    }
+}
+
+/// The Nintendo Rotation System of the NES, where pieces come in flat side up.
+pub struct Nrs;
+
+impl RotationSystem for Nrs {
+    fn spawn_rotation(&self, kind: PieceKind) -> PieceRotation {
+        flat_side_up(kind)
+    }
+
+    fn kicks(
+        &self,
+        kind: PieceKind,
+        from: PieceRotation,
+        to: PieceRotation,
+    ) -> &'static [Point2<i32>] {
+        KickTables::current().kicks(RotationSystemKind::Nrs, kind, from, to)
+    }
+
+    fn turns_180(&self) -> bool {
+        false
+    }
+}
+
+/// Comes in and turns as SRS does, with kicks of its own for players to make up.
+pub struct CustomRotation;
+
+impl RotationSystem for CustomRotation {
+    fn spawn_rotation(&self, _kind: PieceKind) -> PieceRotation {
+        PieceRotation::Deg0
+    }
+
+    fn kicks(
+        &self,
+        kind: PieceKind,
+        from: PieceRotation,
+        to: PieceRotation,
+    ) -> &'static [Point2<i32>] {
+        KickTables::current().kicks(RotationSystemKind::Custom, kind, from, to)
+    }
+
+    fn turns_180(&self) -> bool {
+        true
+    }
+}
+
+/// Which way up the standard T, J and L are with their flat side on top.
+fn flat_side_up(kind: PieceKind) -> PieceRotation {
+    match kind {
+        PieceKind::T => PieceRotation::Deg180,
+        PieceKind::J => PieceRotation::Deg270,
+        PieceKind::L => PieceRotation::Deg90,
+        _ => PieceRotation::Deg0,
+    }
+}
+
+/// A rotation system to pick in settings and to keep in replays.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Enum, Serialize, Deserialize)]
+pub enum RotationSystemKind {
+    #[default]
+    Srs,
+    Ars,
+    Nrs,
+    Custom,
+}
+
+impl RotationSystemKind {
+    /// Every system, in the order they are listed in menus and numbered in replays.
+    pub const ALL: [RotationSystemKind; 4] = [
+        RotationSystemKind::Srs,
+        RotationSystemKind::Ars,
+        RotationSystemKind::Nrs,
+        RotationSystemKind::Custom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RotationSystemKind::Srs => "SRS",
+            RotationSystemKind::Ars => "ARS",
+            RotationSystemKind::Nrs => "NRS",
+            RotationSystemKind::Custom => "CUSTOM",
+        }
+    }
+
+    pub fn system(self) -> &'static dyn RotationSystem {
+        match self {
+            RotationSystemKind::Srs => &Srs,
+            RotationSystemKind::Ars => &Ars,
+            RotationSystemKind::Nrs => &Nrs,
+            RotationSystemKind::Custom => &CustomRotation,
+        }
+    }
+}
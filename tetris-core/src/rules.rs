@@ -0,0 +1,177 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{kick_table::KickTables, piece_set::PieceSet, rotation::RotationSystemKind};
+
+/// How the core of the game plays, under whatever each mode adds on top. Modes pick one of
+/// these profiles with `GameMode::rules`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    /// Whether the falling piece can be swapped with a held one, if the player wants to.
+    pub hold: bool,
+    pub hard_drop: bool,
+    /// Whether T pieces spun into place score and send more.
+    pub t_spins: bool,
+    /// The rotation system every game plays with, or `None` for the player's choice.
+    pub rotation_system: Option<RotationSystemKind>,
+    pub scoring: Scoring,
+    /// Most pieces coming next that are shown, however many the player asks for.
+    pub preview: usize,
+}
+
+impl Rules {
+    pub const MODERN: Rules = Rules {
+        hold: true,
+        hard_drop: true,
+        t_spins: true,
+        rotation_system: None,
+        scoring: Scoring::Modern,
+        preview: 6,
+    };
+    /// Turned as in the arcade games that 20G comes from, to be able to turn pieces against
+    /// the stack they land on straight away, and showing as few pieces ahead.
+    pub const MASTER: Rules = Rules {
+        rotation_system: Some(RotationSystemKind::Ars),
+        preview: 3,
+        ..Rules::MODERN
+    };
+    /// As on the NES: no hold, no hard drop, no kicks and a single piece shown ahead.
+    pub const CLASSIC: Rules = Rules {
+        hold: false,
+        hard_drop: false,
+        t_spins: false,
+        rotation_system: Some(RotationSystemKind::Nrs),
+        scoring: Scoring::Classic,
+        preview: 1,
+    };
+}
+
+/// A hash of the pieces and kicks that were installed, which custom rules change for every
+/// mode at once. Replays and online matches carry it, so that games are only played back or
+/// against each other with the same pieces and kicks they were played with.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RulesHash(pub u64);
+
+impl RulesHash {
+    pub fn installed() -> Self {
+        static INSTALLED: OnceLock<RulesHash> = OnceLock::new();
+        *INSTALLED.get_or_init(|| Self::of(PieceSet::current(), KickTables::current()))
+    }
+
+    pub fn standard() -> Self {
+        static STANDARD: OnceLock<RulesHash> = OnceLock::new();
+        *STANDARD.get_or_init(|| Self::of(&PieceSet::standard(), &KickTables::standard()))
+    }
+
+    /// Whether the installed rules aren't the standard ones, so that games played with them
+    /// can't be compared with anyone else's.
+    pub fn is_custom() -> bool {
+        Self::installed() != Self::standard()
+    }
+
+    fn of(pieces: &PieceSet, kicks: &KickTables) -> Self {
+        let mut bytes = vec![];
+        pieces.write_bytes(&mut bytes);
+        kicks.write_bytes(&mut bytes);
+        Self(fnv1a(&bytes))
+    }
+}
+
+/// A 64-bit FNV-1a hash of `bytes`.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Ways the stack can top out and end the game, which players can pick for themselves.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopOut {
+    /// Ends the game when a piece comes in on top of the stack. Without it, pieces are
+    /// raised over the stack as far as the top allows, and only top out if there's no room.
+    pub block_out: bool,
+    /// Ends the game when a piece locks entirely in the hidden rows, above the field.
+    pub lock_out: bool,
+    /// Ends the game when garbage pushes the stack past the top. Without it, whatever is
+    /// pushed past the top is lost.
+    pub garbage_out: bool,
+}
+
+impl TopOut {
+    /// As one bit per rule, for replays.
+    pub fn to_bits(self) -> u8 {
+        u8::from(self.block_out) | u8::from(self.lock_out) << 1 | u8::from(self.garbage_out) << 2
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            block_out: bits & 1 != 0,
+            lock_out: bits & 1 << 1 != 0,
+            garbage_out: bits & 1 << 2 != 0,
+        }
+    }
+}
+
+impl Default for TopOut {
+    fn default() -> Self {
+        Self {
+            block_out: true,
+            lock_out: false,
+            garbage_out: true,
+        }
+    }
+}
+
+/// How clears are scored, before the level multiplies the points.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Scoring {
+    Modern,
+    /// As on the NES, where tetrises are worth much more than anything else.
+    Classic,
+}
+
+impl Scoring {
+    pub fn clear_points(self, line_count: u32) -> u32 {
+        match (self, line_count) {
+            (Scoring::Modern, 1) => 100,
+            (Scoring::Modern, 2) => 300,
+            (Scoring::Modern, 3) => 500,
+            (Scoring::Modern, _) => 800,
+            (Scoring::Classic, 1) => 40,
+            (Scoring::Classic, 2) => 100,
+            (Scoring::Classic, 3) => 300,
+            (Scoring::Classic, _) => 1200,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_matches_the_reference_hashes() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn the_standard_rules_are_installed_unless_others_are() {
+        assert_eq!(RulesHash::installed(), RulesHash::standard());
+        assert!(!RulesHash::is_custom());
+    }
+
+    #[test]
+    fn other_kicks_change_the_hash() {
+        let pieces = PieceSet::standard();
+        let standard = RulesHash::of(&pieces, &KickTables::standard());
+        assert_eq!(standard, RulesHash::standard());
+        let kicks = KickTables::parse("[srs.T]\n\"0>90\" = [[0, -1]]").unwrap();
+        assert_ne!(RulesHash::of(&pieces, &kicks), standard);
+        // Listing the standard kicks again changes nothing.
+        let kicks = KickTables::parse("[srs.O]").unwrap();
+        assert_eq!(RulesHash::of(&pieces, &kicks), standard);
+    }
+}
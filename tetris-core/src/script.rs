@@ -0,0 +1,220 @@
+use std::{fmt, time::Duration};
+
+use crate::{
+    game::{BoardSize, Game},
+    input::{Action, FrameInput, Handling, SoftDrop},
+    mode::GameMode,
+    replay::Replay,
+};
+
+/// Returned when a script can't be read, with the line that's wrong.
+#[derive(Debug)]
+pub struct InvalidScript {
+    /// Counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for InvalidScript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for InvalidScript {}
+
+impl Replay {
+    /// Reads a replay written by hand, or by a tool, as text. Every line is either a setting
+    /// or the step some actions start being held on, and anything after a `#` is ignored:
+    ///
+    /// ```text
+    /// mode sprint      # any mode, as for --mode
+    /// seed 42
+    /// board 10x20
+    /// das 170          # handling, in milliseconds
+    /// arr 50
+    /// dcd 0
+    /// soft_drop 10     # times as fast as gravity, or instant
+    /// 0 left           # held from step 0 on, pressed on step 0
+    /// 12 left cw       # still held, and rotate pressed on step 12
+    /// 13 hard          # left and cw released
+    /// 14               # everything released
+    /// ```
+    ///
+    /// Actions are `left`, `right`, `soft`, `hard`, `cw`, `ccw`, `180` and `hold`. An action only
+    /// counts as pressed on the step it starts being held, so pressing it again takes a line
+    /// that releases it first. Steps have to go up, and the game ends after the last one.
+    pub fn from_script(script: &str) -> Result<Self, InvalidScript> {
+        let mut replay = Replay::new(
+            GameMode::Endless,
+            0,
+            Handling::default(),
+            BoardSize::default(),
+        );
+        let mut held = FrameInput::default();
+        let mut next_frame = 0;
+        for (i, line) in script.lines().enumerate() {
+            let error = |message: String| InvalidScript {
+                line: i + 1,
+                message,
+            };
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(first) = words.next() else {
+                continue;
+            };
+            let mut value = || {
+                words
+                    .next()
+                    .ok_or_else(|| error(format!("{} needs a value", first)))
+            };
+            let millis = |value: &str| {
+                value
+                    .parse()
+                    .map(Duration::from_millis)
+                    .map_err(|_| error(format!("{} isn't a number of milliseconds", value)))
+            };
+            match first {
+                "mode" => {
+                    let value = value()?;
+                    replay.mode = GameMode::from_arg(value)
+                        .ok_or_else(|| error(format!("{} isn't a mode", value)))?;
+                }
+                "seed" => {
+                    let value = value()?;
+                    replay.seed = value
+                        .parse()
+                        .map_err(|_| error(format!("{} isn't a seed", value)))?;
+                }
+                "board" => {
+                    let value = value()?;
+                    replay.board_size = parse_board_size(value)
+                        .ok_or_else(|| error(format!("{} isn't a board size like 10x20", value)))?;
+                }
+                "das" => replay.handling.das = millis(value()?)?,
+                "arr" => replay.handling.arr = millis(value()?)?,
+                "dcd" => replay.handling.dcd = millis(value()?)?,
+                "soft_drop" => {
+                    let value = value()?;
+                    replay.handling.soft_drop = match value {
+                        "instant" => SoftDrop::Instant,
+                        factor => match factor.parse() {
+                            Ok(factor) if factor > 0 => SoftDrop::Factor(factor),
+                            _ => return Err(error(format!("{} isn't a soft drop speed", value))),
+                        },
+                    };
+                }
+                frame => {
+                    let frame: u32 = frame
+                        .parse()
+                        .map_err(|_| error(format!("{} isn't a setting or a step", frame)))?;
+                    if frame < next_frame {
+                        return Err(error(format!("step {} comes too early", frame)));
+                    }
+                    let after = frame
+                        .checked_add(1)
+                        .ok_or_else(|| error(format!("step {} comes too late", frame)))?;
+                    let mut pressed = 0;
+                    for word in words.by_ref() {
+                        let action = parse_action(word)
+                            .ok_or_else(|| error(format!("{} isn't an action", word)))?;
+                        pressed |= FrameInput::tap(action).pressed;
+                    }
+                    held = FrameInput {
+                        pressed,
+                        just_pressed: pressed & !held.pressed,
+                    };
+                    replay.record(frame, held);
+                    next_frame = after;
+                }
+            }
+            if let Some(extra) = words.next() {
+                return Err(error(format!("unexpected {}", extra)));
+            }
+        }
+        Ok(replay)
+    }
+
+    /// Simulates the whole game without showing it, returning it as it was at the end.
+    pub fn play(&self) -> Game {
+        let mut game = Game::new(self.mode, self.seed, self.board_size);
+        let mut inputs = self.inputs.iter().peekable();
+        let mut input = FrameInput::default();
+        while game.frame() < self.length && !game.is_over() {
+            if let Some((_, next)) = inputs.next_if(|(frame, _)| *frame == game.frame()) {
+                input = *next;
+            }
+            game.step(self.handling_at(game.frame()), input);
+            input = input.held();
+        }
+        game
+    }
+}
+
+fn parse_board_size(value: &str) -> Option<BoardSize> {
+    let (width, height) = value.split_once('x')?;
+    let size = BoardSize {
+        width: width.parse().ok()?,
+        height: height.parse().ok()?,
+    };
+    let (min, max) = (BoardSize::MIN, BoardSize::MAX);
+    ((min.width..=max.width).contains(&size.width)
+        && (min.height..=max.height).contains(&size.height))
+    .then_some(size)
+}
+
+fn parse_action(word: &str) -> Option<Action> {
+    match word {
+        "left" => Some(Action::MoveLeft),
+        "right" => Some(Action::MoveRight),
+        "soft" => Some(Action::SoftDrop),
+        "hard" => Some(Action::HardDrop),
+        "cw" => Some(Action::RotateCw),
+        "ccw" => Some(Action::RotateCcw),
+        "180" => Some(Action::Rotate180),
+        "hold" => Some(Action::Hold),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_set_up_and_play_a_game() {
+        let replay = Replay::from_script(
+            "mode sprint\nseed 42\nboard 8x16\ndas 100 # faster\n\n0 left hard\n1\n2 hard\n3",
+        )
+        .unwrap();
+        assert!(replay.mode == GameMode::Sprint);
+        assert_eq!(replay.seed, 42);
+        assert!(
+            replay.board_size
+                == BoardSize {
+                    width: 8,
+                    height: 16
+                }
+        );
+        assert_eq!(replay.handling.das, Duration::from_millis(100));
+        assert_eq!(replay.length, 4);
+        assert_eq!(replay.play().pieces_placed(), 2);
+    }
+
+    #[test]
+    fn mistakes_in_scripts_are_told_by_line() {
+        let line = |script: &str| Replay::from_script(script).err().map(|error| error.line);
+        assert_eq!(line("seed forty"), Some(1));
+        assert_eq!(line("mode sprint\nmode nothing"), Some(2));
+        assert_eq!(line("0 left\n\n5 jump"), Some(3));
+        assert_eq!(line("5 left\n4 right"), Some(2));
+        assert_eq!(line("board 2x2"), Some(1));
+        assert_eq!(line("soft_drop 0"), Some(1));
+    }
+
+    #[test]
+    fn steps_on_the_last_frame_are_invalid() {
+        assert!(Replay::from_script(&format!("{} left", u32::MAX)).is_err());
+        assert!(Replay::from_script(&format!("{} left", u32::MAX - 1)).is_ok());
+    }
+}
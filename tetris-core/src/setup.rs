@@ -0,0 +1,279 @@
+use std::fmt;
+
+use crate::{
+    game::{BoardSize, Game},
+    grid::{Block, Grid},
+    mode::GameMode,
+    piece::PieceKind,
+};
+
+/// Returned when a setup can't be read, with what's wrong with it.
+#[derive(Debug)]
+pub struct InvalidSetup {
+    pub message: String,
+}
+
+impl fmt::Display for InvalidSetup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for InvalidSetup {}
+
+fn invalid(message: String) -> InvalidSetup {
+    InvalidSetup { message }
+}
+
+/// A board and the pieces coming onto it, to share with other players and practice from.
+#[derive(Clone)]
+pub struct Setup {
+    /// The field, without hidden rows.
+    pub board: Grid,
+    /// Starting with the falling piece.
+    pub queue: Vec<PieceKind>,
+    /// Whether the queue is dealt over and over, rather than random pieces coming after it.
+    pub repeat: bool,
+}
+
+impl Setup {
+    /// Pieces of a game's queue that are kept, as many as the previews show at most.
+    const QUEUE_LENGTH: usize = 7;
+
+    /// Where a game is at. Blocks of pentominoes are kept as the tetromino they're drawn
+    /// like, while the queue stops at the first pentomino. Big games have no setup, since
+    /// their blocks cover more than one cell.
+    pub fn of(game: &Game) -> Option<Self> {
+        if game.mode().piece_scale() != 1 {
+            return None;
+        }
+        let grid = game.grid();
+        let top = grid.hidden_rows() as i32;
+        let mut board = Grid::new(grid.width(), grid.visible_height());
+        for (x, y, block) in grid.iter().filter(|&(_, y, _)| y >= top) {
+            let block = block.map(|block| Block {
+                kind: block.kind.palette(),
+                ..block
+            });
+            board.set(x, y - top, block);
+        }
+        let queue = std::iter::once(game.piece().kind)
+            .chain(game.next_pieces(Self::QUEUE_LENGTH - 1))
+            .take_while(|kind| kind.letter().is_some())
+            .collect();
+        Some(Self {
+            board,
+            queue,
+            repeat: false,
+        })
+    }
+
+    /// Reads a setup written as a line of text, like
+    ///
+    /// ```text
+    /// 10x20:I3_3L/X4J4X:TLJSZOI
+    /// ```
+    ///
+    /// That's the size of the board, then its rows from the bottom up separated by `/`, then
+    /// the queue. Rows are cells from the left: `_` for an empty one, `X` for garbage and a
+    /// piece's letter for one of its blocks, each after how many times it repeats if it does.
+    /// Rows left out at the top and cells left out at the right are empty. A `*` after the
+    /// queue deals it over and over.
+    pub fn parse(text: &str) -> Result<Self, InvalidSetup> {
+        let mut parts = text.trim().split(':');
+        let (Some(size), Some(rows), Some(queue), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid("setups have a size, rows and a queue".to_owned()));
+        };
+        let (width, height) = size
+            .split_once('x')
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+            .filter(|&(width, height)| {
+                (BoardSize::MIN.width..=BoardSize::MAX.width).contains(&width)
+                    && (BoardSize::MIN.height..=BoardSize::MAX.height).contains(&height)
+            })
+            .ok_or_else(|| invalid(format!("{} isn't a board size", size)))?;
+
+        let mut board = Grid::new(width, height);
+        let rows: Vec<&str> = rows.split('/').collect();
+        if rows.len() > height {
+            return Err(invalid(format!(
+                "{} rows don't fit in {}",
+                rows.len(),
+                height
+            )));
+        }
+        for (i, row) in rows.into_iter().enumerate() {
+            let y = (height - 1 - i) as i32;
+            let mut x = 0;
+            let mut count = None;
+            let too_wide = || invalid(format!("{} is wider than {}", row, width));
+            for c in row.chars() {
+                if let Some(digit) = c.to_digit(10) {
+                    // Counts past the width are too wide anyway, so they're stopped there
+                    // rather than being let grow until they overflow.
+                    let digits = count.unwrap_or(0_usize).checked_mul(10);
+                    count = Some(
+                        digits
+                            .and_then(|count| count.checked_add(digit as usize))
+                            .filter(|&count| count <= width)
+                            .ok_or_else(too_wide)?,
+                    );
+                    continue;
+                }
+                let block = match c {
+                    '_' => None,
+                    'X' => Some(Block::GARBAGE),
+                    c => Some(Block::of(
+                        PieceKind::from_letter(c)
+                            .ok_or_else(|| invalid(format!("{} isn't a cell", c)))?,
+                    )),
+                };
+                let count = count.take().unwrap_or(1);
+                if x + count > width {
+                    return Err(too_wide());
+                }
+                for _ in 0..count {
+                    board.set(x as i32, y, block);
+                    x += 1;
+                }
+            }
+        }
+
+        let (queue, repeat) = match queue.strip_suffix('*') {
+            Some(queue) => (queue, true),
+            None => (queue, false),
+        };
+        Ok(Self {
+            board,
+            queue: Self::parse_queue(queue)?,
+            repeat,
+        })
+    }
+
+    /// Reads pieces written as their letters, like `TLJ SZO`, ignoring whitespace.
+    pub fn parse_queue(text: &str) -> Result<Vec<PieceKind>, InvalidSetup> {
+        text.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| {
+                PieceKind::from_letter(c).ok_or_else(|| invalid(format!("{} isn't a piece", c)))
+            })
+            .collect()
+    }
+
+    pub fn to_text(&self) -> String {
+        let board = &self.board;
+        let mut rows: Vec<String> = board
+            .rows()
+            .rev()
+            .map(|row| {
+                let cells: Vec<char> = row
+                    .iter()
+                    .map(|block| match block {
+                        None => '_',
+                        Some(block) if block.garbage => 'X',
+                        Some(block) => block.kind.palette().letter().unwrap_or('X'),
+                    })
+                    .collect();
+                let end = cells.iter().rposition(|&c| c != '_').map_or(0, |i| i + 1);
+                let mut row = String::new();
+                for run in cells[..end].chunk_by(|a, b| a == b) {
+                    if run.len() > 1 {
+                        row.push_str(&run.len().to_string());
+                    }
+                    row.push(run[0]);
+                }
+                row
+            })
+            .collect();
+        while rows.len() > 1 && rows.last().is_some_and(String::is_empty) {
+            rows.pop();
+        }
+        let queue: String = self.queue.iter().filter_map(|kind| kind.letter()).collect();
+        format!(
+            "{}x{}:{}:{}{}",
+            board.width(),
+            board.height(),
+            rows.join("/"),
+            queue,
+            if self.repeat { "*" } else { "" }
+        )
+    }
+
+    /// An endless game starting from the setup, with random pieces once the queue runs out
+    /// unless it repeats.
+    pub fn game(&self, seed: u32) -> Game {
+        let board = self.board.clone();
+        if self.repeat {
+            let mut game = Game::from_setup(GameMode::Endless, seed, board, []);
+            game.repeat_sequence(&self.queue);
+            game
+        } else {
+            Game::from_setup(GameMode::Endless, seed, board, self.queue.iter().copied())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setups_are_read_row_by_row_from_the_bottom() {
+        let setup = Setup::parse("10x20:I3_3L/X4J4X:TLJSZOI*").unwrap();
+        let board = &setup.board;
+        assert_eq!((board.width(), board.height()), (10, 20));
+        assert!(board.at(0, 19).is_some_and(|block| !block.garbage));
+        assert!(board.at(1, 19).is_none() && board.at(3, 19).is_none());
+        assert!(board.at(6, 19).is_some() && board.at(7, 19).is_none());
+        assert!(board.at(0, 18).is_some_and(|block| block.garbage));
+        assert!((0..9).all(|x| board.at(x, 18).is_some()) && board.at(9, 18).is_none());
+        assert!((0..10).all(|x| board.at(x, 17).is_none()));
+        assert_eq!(setup.queue.len(), 7);
+        assert!(setup.repeat);
+    }
+
+    #[test]
+    fn setups_are_written_as_they_are_read() {
+        for text in [
+            "10x20:I3_3L/X4J4X:TLJSZOI",
+            "10x20::T",
+            "8x16:_X/2_X/3_X:IO*",
+            "30x40:30X:Z",
+        ] {
+            assert_eq!(Setup::parse(text).unwrap().to_text(), text);
+        }
+    }
+
+    #[test]
+    fn malformed_setups_are_invalid() {
+        for text in [
+            "",
+            "10x20:T",
+            "10x20:::T",
+            "10by20::T",
+            "31x20::T",
+            "10x41::T",
+            "10x20:Q:T",
+            "10x20::TQ",
+            "10x5:X/X/X/X/X/X:T",
+        ] {
+            assert!(Setup::parse(text).is_err(), "{} was read", text);
+        }
+    }
+
+    #[test]
+    fn counts_too_large_for_the_board_are_invalid() {
+        assert!(Setup::parse("10x20:99999999999999999999X:T").is_err());
+        assert!(Setup::parse("10x20:11X:T").is_err());
+        assert!(Setup::parse("10x20:5_6X:T").is_err());
+    }
+
+    #[test]
+    fn boards_too_narrow_for_every_piece_are_invalid() {
+        assert!(Setup::parse("4x20::T").is_err());
+        assert!(Setup::parse("5x4::T").is_err());
+        assert!(Setup::parse("5x5::T").is_ok());
+    }
+}
@@ -0,0 +1,172 @@
+//! The lobby server lists games being hosted, so that players can find each other without
+//! trading addresses. This is its protocol, along with the client side of it.
+//!
+//! Clients send one request per line, and the server answers each with lines of its own:
+//!
+//! - `HOST <port> <name>` lists a game hosted on `port` of the client's address, under
+//!   `name`, for as long as the connection stays open. Answered with `OK`.
+//! - `LIST` is answered with a `LOBBY <address> <name>` line for each game listed, then `END`.
+//!
+//! Anything else is answered with `ERROR <message>`.
+
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    net::{SocketAddr, TcpStream},
+};
+
+/// Port the lobby server listens on unless another one is given.
+pub const DEFAULT_PORT: u16 = 7778;
+/// Longest name a game can be listed under. Longer ones are cut.
+pub const MAX_NAME_LENGTH: usize = 16;
+
+/// A game waiting for a player to join it.
+#[derive(Clone)]
+pub struct Lobby {
+    pub name: String,
+    /// Where the game is hosted, as seen by the server.
+    pub address: SocketAddr,
+}
+
+pub enum Request {
+    Host { port: u16, name: String },
+    List,
+}
+
+impl Request {
+    pub fn parse(line: &str) -> Option<Self> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "HOST" => {
+                let (port, name) = rest.split_once(' ')?;
+                Some(Request::Host {
+                    port: port.parse().ok()?,
+                    name: clean_name(name),
+                })
+            }
+            "LIST" if rest.is_empty() => Some(Request::List),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Request::Host { port, name } => write!(f, "HOST {} {}", port, clean_name(name)),
+            Request::List => write!(f, "LIST"),
+        }
+    }
+}
+
+pub enum Response {
+    Ok,
+    Lobby(Lobby),
+    End,
+    Error(String),
+}
+
+impl Response {
+    pub fn parse(line: &str) -> Option<Self> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "OK" => Some(Response::Ok),
+            "LOBBY" => {
+                let (address, name) = rest.split_once(' ')?;
+                Some(Response::Lobby(Lobby {
+                    name: name.to_owned(),
+                    address: address.parse().ok()?,
+                }))
+            }
+            "END" => Some(Response::End),
+            "ERROR" => Some(Response::Error(rest.to_owned())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Response::Ok => write!(f, "OK"),
+            Response::Lobby(lobby) => write!(f, "LOBBY {} {}", lobby.address, lobby.name),
+            Response::End => write!(f, "END"),
+            Response::Error(message) => write!(f, "ERROR {}", message),
+        }
+    }
+}
+
+/// `name` made fit to be listed: on one line, and no longer than `MAX_NAME_LENGTH`.
+fn clean_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_LENGTH)
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+/// A connection to the lobby server.
+struct Client {
+    reader: BufReader<TcpStream>,
+    stream: TcpStream,
+}
+
+impl Client {
+    /// Connects to `server`, a host name or IP with an optional port.
+    fn connect(server: &str) -> io::Result<Self> {
+        let stream = if server.contains(':') {
+            TcpStream::connect(server)?
+        } else {
+            TcpStream::connect((server, DEFAULT_PORT))?
+        };
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            stream,
+        })
+    }
+
+    fn send(&mut self, request: Request) -> io::Result<()> {
+        writeln!(self.stream, "{}", request)
+    }
+
+    fn receive(&mut self) -> io::Result<Response> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        match Response::parse(line.trim_end()) {
+            Some(Response::Error(message)) => Err(io::Error::other(message)),
+            Some(response) => Ok(response),
+            None => Err(ErrorKind::InvalidData.into()),
+        }
+    }
+}
+
+/// The games listed on `server`. Blocks until the server answers.
+pub fn list(server: &str) -> io::Result<Vec<Lobby>> {
+    let mut client = Client::connect(server)?;
+    client.send(Request::List)?;
+    let mut lobbies = vec![];
+    loop {
+        match client.receive()? {
+            Response::Lobby(lobby) => lobbies.push(lobby),
+            Response::End => return Ok(lobbies),
+            _ => return Err(ErrorKind::InvalidData.into()),
+        }
+    }
+}
+
+/// Lists a game hosted on `port` on `server` under `name`. It stays listed until the
+/// returned connection is dropped. Blocks until the server answers.
+pub fn host(server: &str, port: u16, name: &str) -> io::Result<TcpStream> {
+    let mut client = Client::connect(server)?;
+    client.send(Request::Host {
+        port,
+        name: name.to_owned(),
+    })?;
+    match client.receive()? {
+        Response::Ok => Ok(client.stream),
+        _ => Err(ErrorKind::InvalidData.into()),
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use clap::Parser;
+use tetris_lobby::{Lobby, Request, Response, DEFAULT_PORT};
+
+/// Lobby server for online versus matches, which lists the games being hosted so that
+/// players can find them.
+#[derive(Parser)]
+struct Args {
+    /// Port to listen on
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+}
+
+/// Games listed right now, by the order they were listed in.
+#[derive(Default)]
+struct Lobbies {
+    listed: BTreeMap<u64, Lobby>,
+    next_id: u64,
+}
+
+impl Lobbies {
+    fn add(&mut self, lobby: Lobby) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.listed.insert(id, lobby);
+        id
+    }
+}
+
+/// Answers the requests of one client until it disconnects, unlisting its game once it does.
+fn serve(stream: TcpStream, lobbies: &Mutex<Lobbies>) -> io::Result<()> {
+    let peer = stream.peer_addr()?;
+    let reader = BufReader::new(stream.try_clone()?);
+    let mut hosted = None;
+    let result = answer(reader, stream, peer, lobbies, &mut hosted);
+    if let Some(id) = hosted {
+        lobbies.lock().unwrap().listed.remove(&id);
+    }
+    result
+}
+
+fn answer(
+    reader: BufReader<TcpStream>,
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    lobbies: &Mutex<Lobbies>,
+    hosted: &mut Option<u64>,
+) -> io::Result<()> {
+    for line in reader.lines() {
+        let responses = match Request::parse(line?.trim_end()) {
+            Some(Request::Host { port, name }) => {
+                let mut lobbies = lobbies.lock().unwrap();
+                // A client hosts one game at a time.
+                if let Some(id) = hosted.take() {
+                    lobbies.listed.remove(&id);
+                }
+                let address = SocketAddr::new(peer.ip(), port);
+                *hosted = Some(lobbies.add(Lobby { name, address }));
+                vec![Response::Ok]
+            }
+            Some(Request::List) => {
+                let lobbies = lobbies.lock().unwrap();
+                let listed = lobbies.listed.values().cloned().map(Response::Lobby);
+                listed.chain([Response::End]).collect()
+            }
+            None => vec![Response::Error("unknown request".to_owned())],
+        };
+        for response in responses {
+            writeln!(stream, "{}", response)?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+    let listener = TcpListener::bind(("0.0.0.0", args.port))?;
+    println!("Listening on port {}", args.port);
+    let lobbies = Arc::new(Mutex::new(Lobbies::default()));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Couldn't accept a client: {}", e);
+                continue;
+            }
+        };
+        let lobbies = Arc::clone(&lobbies);
+        thread::spawn(move || {
+            if let Err(e) = serve(stream, &lobbies) {
+                eprintln!("Lost a client: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
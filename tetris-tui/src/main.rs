@@ -0,0 +1,16 @@
+// Raw mode is set up through termios, which only Unix terminals have.
+#[cfg(unix)]
+mod play;
+#[cfg(unix)]
+mod terminal;
+
+#[cfg(unix)]
+fn main() -> std::io::Result<()> {
+    play::run()
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("tetris-tui is unsupported on this platform, since it needs a Unix terminal");
+    std::process::exit(1);
+}
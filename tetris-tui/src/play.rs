@@ -0,0 +1,234 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write as _},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use rand::Rng;
+use tetris_core::{
+    format_time, Action, FrameInput, Game, GameMode, Handling, Piece, PieceKind, PieceRotation,
+};
+
+use crate::terminal::{Key, Terminal};
+
+/// Tetris in the terminal, to play over SSH or without a GPU.
+#[derive(Parser)]
+struct Args {
+    /// Mode to play: endless, sprint, ultra, marathon, dig, survival, invisible, big,
+    /// pentomino, pentomix, cascade, master or classic
+    #[arg(long, default_value = "endless", value_parser = parse_mode)]
+    mode: GameMode,
+    /// Use this seed for every game, so that pieces always come in the same order
+    #[arg(long)]
+    seed: Option<u32>,
+}
+
+fn parse_mode(arg: &str) -> Result<GameMode, String> {
+    GameMode::from_arg(arg).ok_or_else(|| {
+        "expected endless, sprint, ultra, marathon, dig, survival, invisible, big, pentomino, \
+         pentomix, cascade, master or classic"
+            .to_owned()
+    })
+}
+
+/// How long soft drop stays held after its key is pressed. Terminals press held keys again
+/// every few dozen milliseconds, so this keeps it held until the key is let go.
+const SOFT_DROP_HOLD: Duration = Duration::from_millis(100);
+/// Most pieces coming next that are shown.
+const PREVIEW: usize = 5;
+const GARBAGE_COLOR: [u8; 3] = [128, 128, 128];
+const CLEARING_COLOR: [u8; 3] = [255, 255, 255];
+const CONTROLS: &str = "\u{2190}\u{2192} MOVE  \u{2193} SOFT DROP  SPACE HARD DROP  \
+                        \u{2191}/X ROTATE  Z CCW  A 180  C HOLD  P PAUSE  R RESTART  Q QUIT";
+
+fn action(key: Key) -> Option<Action> {
+    match key {
+        Key::Left => Some(Action::MoveLeft),
+        Key::Right => Some(Action::MoveRight),
+        Key::Down => Some(Action::SoftDrop),
+        Key::Up | Key::Char('x') => Some(Action::RotateCw),
+        Key::Char(' ') => Some(Action::HardDrop),
+        Key::Char('z') => Some(Action::RotateCcw),
+        Key::Char('a') => Some(Action::Rotate180),
+        Key::Char('c') => Some(Action::Hold),
+        _ => None,
+    }
+}
+
+fn new_game(args: &Args) -> Game {
+    let seed = args.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    Game::new(args.mode, seed, Default::default())
+}
+
+pub fn run() -> io::Result<()> {
+    let args = Args::parse();
+    let mut terminal = Terminal::enter()?;
+    let handling = Handling::default();
+    let mut game = new_game(&args);
+    let mut paused = false;
+    let mut soft_drop_until = None;
+    let mut next_step = Instant::now();
+    let mut shown = String::new();
+    loop {
+        let now = Instant::now();
+        let mut presses = vec![];
+        for key in terminal.read_keys()? {
+            match key {
+                // Ctrl+C, which comes in as a key in raw mode.
+                Key::Char('q') | Key::Char('\u{3}') => return Ok(()),
+                Key::Char('p') => paused = !paused,
+                Key::Char('r') => {
+                    game = new_game(&args);
+                    paused = false;
+                }
+                key => {
+                    let Some(action) = action(key) else {
+                        continue;
+                    };
+                    if action == Action::SoftDrop {
+                        soft_drop_until = Some(now + SOFT_DROP_HOLD);
+                    }
+                    presses.push(action);
+                }
+            }
+        }
+        let soft_drop = soft_drop_until.is_some_and(|until| now < until);
+        let mut input = FrameInput::from_fn(|action| {
+            let pressed = presses.contains(&action);
+            (pressed || action == Action::SoftDrop && soft_drop, pressed)
+        });
+
+        if paused || game.is_over() {
+            next_step = now + Game::FRAME_TIME;
+        }
+        while next_step <= now {
+            game.step(handling, input);
+            input = input.held();
+            next_step += Game::FRAME_TIME;
+        }
+
+        let frame = draw(&game, paused);
+        if frame != shown {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(frame.as_bytes())?;
+            stdout.flush()?;
+            shown = frame;
+        }
+        thread::sleep(next_step.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// Adds `text` to `out` in `color`.
+fn paint(out: &mut String, color: [u8; 3], text: &str) {
+    let [r, g, b] = color;
+    let _ = write!(out, "\x1b[38;2;{};{};{}m{}\x1b[0m", r, g, b, text);
+}
+
+/// The whole screen: the board, what's held and coming next beside it and the controls
+/// under it.
+fn draw(game: &Game, paused: bool) -> String {
+    let grid = game.grid();
+    let hidden = grid.hidden_rows();
+    let playing = !game.is_over() && !game.is_entering();
+    let cells = |piece: Piece| -> Vec<(i32, i32)> {
+        if playing {
+            piece.cells().map(|cell| (cell.x, cell.y)).collect()
+        } else {
+            vec![]
+        }
+    };
+    let falling = cells(game.piece());
+    let ghost = cells(game.ghost_piece());
+    let clearing: Vec<_> = game.clearing_lines().map(|y| y as usize).collect();
+    // Invisible games only show the stack once they're over.
+    let stack_shown = game.mode() != GameMode::Invisible || game.is_over();
+    let color = game.piece().kind.color();
+
+    let mut out = String::from("\x1b[H");
+    for (y, row) in grid.rows().enumerate().skip(hidden) {
+        out.push('\u{2502}');
+        for (x, block) in row.iter().enumerate() {
+            let pos = (x as i32, y as i32);
+            match block {
+                _ if falling.contains(&pos) => paint(&mut out, color, "\u{2588}\u{2588}"),
+                Some(_) if clearing.contains(&y) => {
+                    paint(&mut out, CLEARING_COLOR, "\u{2588}\u{2588}")
+                }
+                Some(block) if stack_shown => {
+                    let block_color = if block.garbage {
+                        GARBAGE_COLOR
+                    } else {
+                        block.kind.color()
+                    };
+                    paint(&mut out, block_color, "\u{2588}\u{2588}");
+                }
+                None if ghost.contains(&pos) => paint(&mut out, color, "\u{2591}\u{2591}"),
+                _ => out.push_str("  "),
+            }
+        }
+        out.push_str("\u{2502}\x1b[K\r\n");
+    }
+    let _ = write!(
+        out,
+        "\u{2514}{}\u{2518}\x1b[K\r\n\x1b[K\r\n{}\x1b[K\x1b[J",
+        "\u{2500}".repeat(grid.width() * 2),
+        CONTROLS
+    );
+
+    // Beside the board, a line at a time from the top of the screen.
+    let mut side = vec![game.mode().name().to_owned(), String::new()];
+    if game.can_hold() {
+        side.push("HOLD".to_owned());
+        match game.held_piece() {
+            Some(kind) => side.extend(shape(kind)),
+            None => side.push(String::new()),
+        }
+        side.push(String::new());
+    }
+    side.push("NEXT".to_owned());
+    for kind in game.next_pieces(PREVIEW) {
+        side.extend(shape(kind));
+        side.push(String::new());
+    }
+    let stats = game.stats();
+    side.push(format!("SCORE {}", stats.score));
+    side.push(format!("LINES {}", stats.lines_cleared));
+    side.push(format!("LEVEL {}", game.mode().level(stats.lines_cleared)));
+    // To the tenth of a second, so that the screen isn't sent again every step for the time
+    // alone.
+    let time = format_time(stats.elapsed);
+    side.push(format!("TIME  {}", &time[..time.len() - 2]));
+    side.push(String::new());
+    if game.is_completed() {
+        side.push("COMPLETE!".to_owned());
+    } else if game.is_over() {
+        side.push("GAME OVER".to_owned());
+    } else if paused {
+        side.push("PAUSED".to_owned());
+    }
+    let column = grid.width() * 2 + 5;
+    for (row, line) in side.iter().enumerate() {
+        let _ = write!(out, "\x1b[{};{}H{}\x1b[K", row + 1, column, line);
+    }
+    out
+}
+
+/// The rows of `kind`'s shape that have blocks in them, as it comes in.
+fn shape(kind: PieceKind) -> Vec<String> {
+    let grid = kind.get_grid(PieceRotation::Deg0);
+    grid.rows()
+        .filter(|row| row.iter().any(Option::is_some))
+        .map(|row| {
+            let mut line = String::new();
+            for block in row {
+                match block {
+                    Some(_) => paint(&mut line, kind.color(), "\u{2588}\u{2588}"),
+                    None => line.push_str("  "),
+                }
+            }
+            line
+        })
+        .collect()
+}
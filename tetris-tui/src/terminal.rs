@@ -0,0 +1,107 @@
+use std::{
+    io::{self, Read, Write},
+    mem::MaybeUninit,
+};
+
+/// A key read from the terminal. Terminals only say when keys are pressed, and press them
+/// again while they're held, so there's no telling when they are let go.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Char(char),
+}
+
+/// The terminal in raw mode, on a screen of its own with the cursor hidden, for as long as
+/// this is kept. It's put back as it was when dropped, even when unwinding from a panic.
+pub struct Terminal {
+    original: libc::termios,
+    /// Bytes read that don't make up a whole key yet.
+    pending: Vec<u8>,
+}
+
+impl Terminal {
+    pub fn enter() -> io::Result<Self> {
+        let mut termios = MaybeUninit::uninit();
+        // SAFETY: `tcgetattr` fills in the struct when it succeeds.
+        let original = unsafe {
+            if libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            termios.assume_init()
+        };
+        let mut raw = original;
+        // Keys come in one at a time without echoing, and Ctrl+C comes in as a key too, so
+        // that quitting goes through `Drop`.
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        // Reads return straight away, with whatever keys there are.
+        raw.c_cc[libc::VMIN] = 0;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: `raw` is a valid termios, copied from the one the terminal had.
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b[?1049h\x1b[?25l\x1b[2J")?;
+        stdout.flush()?;
+        Ok(Self {
+            original,
+            pending: vec![],
+        })
+    }
+
+    /// Keys pressed since the last time this was called.
+    pub fn read_keys(&mut self) -> io::Result<Vec<Key>> {
+        let mut buf = [0; 64];
+        loop {
+            let read = io::stdin().read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            self.pending.extend_from_slice(&buf[..read]);
+        }
+        let mut keys = vec![];
+        let mut i = 0;
+        while i < self.pending.len() {
+            let (key, len) = match self.pending[i..] {
+                [0x1b, b'[', b'A', ..] => (Some(Key::Up), 3),
+                [0x1b, b'[', b'B', ..] => (Some(Key::Down), 3),
+                [0x1b, b'[', b'C', ..] => (Some(Key::Right), 3),
+                [0x1b, b'[', b'D', ..] => (Some(Key::Left), 3),
+                // The rest of an escape sequence might still be on its way.
+                [0x1b] | [0x1b, b'['] => break,
+                // Other sequences, like function keys, end with a letter or a tilde.
+                [0x1b, b'[', ..] => {
+                    let end = self.pending[i + 2..]
+                        .iter()
+                        .position(|b| b.is_ascii_alphabetic() || *b == b'~');
+                    match end {
+                        Some(end) => (None, end + 3),
+                        None => break,
+                    }
+                }
+                [byte, ..] => (Some(Key::Char(char::from(byte))), 1),
+                [] => unreachable!(),
+            };
+            keys.extend(key);
+            i += len;
+        }
+        self.pending.drain(..i);
+        Ok(keys)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = stdout.flush();
+        // SAFETY: `original` is the termios the terminal had when raw mode was entered.
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
@@ -0,0 +1,10 @@
+#!/bin/sh
+# Builds the web version into tetris-web/dist, ready to be served as it is, for example with
+# `python3 -m http.server --directory tetris-web/dist`.
+set -e
+cd "$(dirname "$0")"
+cargo build -p tetris-web --target wasm32-unknown-unknown --release
+rm -rf dist
+mkdir -p dist/assets
+cp www/index.html www/main.js ../target/wasm32-unknown-unknown/release/tetris_web.wasm dist
+cp -r ../assets/sound ../assets/music ../assets/fonts dist/assets
@@ -0,0 +1,347 @@
+//! Tetris in a browser. This is built for wasm32-unknown-unknown and driven by `www/main.js`,
+//! which loads the assets, reads the keyboard, draws on a canvas and plays the sounds.
+//! `build.sh` builds it and puts it together with the page and the game's assets, ready
+//! to be served.
+//!
+//! The page uses the functions below without any generated bindings, so that nothing past
+//! what comes with Rust is needed. There's no clock in here either: the page tells how much
+//! time went by each frame, and games are stepped through it in fixed steps.
+
+use std::{cell::RefCell, fmt::Write};
+
+use enum_map::Enum;
+use tetris_core::{
+    format_time, Action, ClearKind, FrameInput, Game, GameEvent, GameMode, Handling, Piece,
+    PieceKind, PieceRotation, PieceSet,
+};
+
+/// Most pieces coming next that are shown.
+const PREVIEW: usize = 5;
+/// Most milliseconds caught up on at once, so that coming back to the page after a while
+/// doesn't play out all the time it was away.
+const MAX_LAG: f64 = 250.;
+const GARBAGE_COLOR: [u8; 3] = [128, 128, 128];
+const CLEARING_COLOR: [u8; 3] = [255, 255, 255];
+
+/// How a cell is drawn, in the last of its 4 bytes.
+#[derive(Clone, Copy)]
+enum CellStyle {
+    Empty,
+    Block,
+    Ghost,
+}
+
+/// Sounds to play, as the bits of what `tetris_web_update` returns. `main.js` loads them in
+/// this order.
+#[derive(Clone, Copy)]
+enum Sound {
+    Move,
+    SoftDrop,
+    Bump,
+    Land,
+    Rotate,
+    Place,
+    Clear,
+    TSpinClear,
+    Tetris,
+}
+
+impl Sound {
+    fn of(event: GameEvent) -> Option<Self> {
+        Some(match event {
+            GameEvent::Moved => Sound::Move,
+            GameEvent::Bumped | GameEvent::GarbageReceived { .. } => Sound::Bump,
+            GameEvent::SoftDropped => Sound::SoftDrop,
+            GameEvent::Landed => Sound::Land,
+            GameEvent::Rotated => Sound::Rotate,
+            GameEvent::PieceLocked(_) => Sound::Place,
+            GameEvent::LinesCleared { count, .. } if count >= 4 => Sound::Tetris,
+            GameEvent::LinesCleared {
+                kind: ClearKind::TSpin,
+                ..
+            } => Sound::TSpinClear,
+            GameEvent::LinesCleared { .. } => Sound::Clear,
+            _ => return None,
+        })
+    }
+}
+
+/// The game being played on the page, along with what it's drawn from.
+struct WebGame {
+    game: Game,
+    paused: bool,
+    /// Actions held down, one bit each by their index in `Action`.
+    held: u16,
+    /// Actions pressed since the game was last stepped, even if they were let go since.
+    pressed: u16,
+    /// Milliseconds that went by and haven't been stepped through yet.
+    lag: f64,
+    /// See `tetris_web_draw`.
+    cells: Vec<u8>,
+    /// See `tetris_web_text`.
+    text: String,
+}
+
+thread_local! {
+    static GAME: RefCell<Option<WebGame>> = const { RefCell::new(None) };
+}
+
+/// Calls `f` with the game being played, or gives back `default` if none was started.
+fn with_game<T>(default: T, f: impl FnOnce(&mut WebGame) -> T) -> T {
+    GAME.with(|game| game.borrow_mut().as_mut().map_or(default, f))
+}
+
+fn mode(index: u32) -> GameMode {
+    GameMode::ALL
+        .get(index as usize)
+        .copied()
+        .unwrap_or(GameMode::Endless)
+}
+
+impl WebGame {
+    fn new(mode: GameMode, seed: u32) -> Self {
+        Self {
+            game: Game::new(mode, seed, Default::default()),
+            paused: false,
+            held: 0,
+            pressed: 0,
+            lag: 0.,
+            cells: vec![],
+            text: String::new(),
+        }
+    }
+
+    fn key(&mut self, action: Action, down: bool) {
+        let bit = 1 << action.into_usize();
+        if down && self.held & bit == 0 {
+            self.pressed |= bit;
+            if action == Action::Pause && !self.game.is_over() {
+                self.paused = !self.paused;
+            }
+        }
+        if down {
+            self.held |= bit;
+        } else {
+            self.held &= !bit;
+        }
+    }
+
+    fn update(&mut self, elapsed: f64) -> u32 {
+        if self.paused || self.game.is_over() {
+            self.lag = 0.;
+            self.pressed = 0;
+            return 0;
+        }
+        let step = Game::FRAME_TIME.as_secs_f64() * 1000.;
+        self.lag = (self.lag + elapsed).min(MAX_LAG);
+        let mut sounds = 0;
+        while self.lag >= step {
+            let input = FrameInput {
+                pressed: self.held | self.pressed,
+                just_pressed: self.pressed,
+            };
+            self.pressed = 0;
+            for event in self.game.step(Handling::default(), input) {
+                if let Some(sound) = Sound::of(event) {
+                    sounds |= 1 << sound as u32;
+                }
+            }
+            self.lag -= step;
+        }
+        sounds
+    }
+
+    fn push_cell(&mut self, color: [u8; 3], style: CellStyle) {
+        self.cells.extend(color);
+        self.cells.push(style as u8);
+    }
+
+    fn draw(&mut self) {
+        self.cells.clear();
+        let game = &self.game;
+        let grid = game.grid();
+        let playing = !game.is_over() && !game.is_entering();
+        let cells = |piece: Piece| -> Vec<(i32, i32)> {
+            if playing {
+                piece.cells().map(|cell| (cell.x, cell.y)).collect()
+            } else {
+                vec![]
+            }
+        };
+        let falling = cells(game.piece());
+        let ghost = cells(game.ghost_piece());
+        let clearing: Vec<_> = game.clearing_lines().map(|y| y as usize).collect();
+        // Invisible games only show the stack once they're over.
+        let stack_shown = game.mode() != GameMode::Invisible || game.is_over();
+        let color = game.piece().kind.color();
+
+        let mut board = Vec::with_capacity(grid.width() * grid.height());
+        for (y, row) in grid.rows().enumerate().skip(grid.hidden_rows()) {
+            for (x, block) in row.iter().enumerate() {
+                let pos = (x as i32, y as i32);
+                board.push(match block {
+                    _ if falling.contains(&pos) => (color, CellStyle::Block),
+                    Some(_) if clearing.contains(&y) => (CLEARING_COLOR, CellStyle::Block),
+                    Some(block) if stack_shown && block.garbage => {
+                        (GARBAGE_COLOR, CellStyle::Block)
+                    }
+                    Some(block) if stack_shown => (block.kind.color(), CellStyle::Block),
+                    None if ghost.contains(&pos) => (color, CellStyle::Ghost),
+                    _ => ([0; 3], CellStyle::Empty),
+                });
+            }
+        }
+
+        let held = game.held_piece().filter(|_| game.can_hold());
+        let next = game.next_pieces(PREVIEW.min(game.mode().rules().preview));
+        let slots: Vec<_> = std::iter::once(held)
+            .chain((0..PREVIEW).map(|i| next.get(i).copied()))
+            .collect();
+
+        let stats = game.stats();
+        self.text.clear();
+        let _ = writeln!(self.text, "{}", game.mode().name());
+        let _ = writeln!(self.text, "SCORE {}", stats.score);
+        let _ = writeln!(self.text, "LINES {}", stats.lines_cleared);
+        let _ = writeln!(
+            self.text,
+            "LEVEL {}",
+            game.mode().level(stats.lines_cleared)
+        );
+        let _ = writeln!(self.text, "TIME  {}", format_time(stats.elapsed));
+        if game.is_completed() {
+            self.text.push_str("COMPLETE!");
+        } else if game.is_over() {
+            self.text.push_str("GAME OVER");
+        } else if self.paused {
+            self.text.push_str("PAUSED");
+        }
+
+        for (color, style) in board {
+            self.push_cell(color, style);
+        }
+        for kind in slots {
+            self.push_shape(kind);
+        }
+    }
+
+    /// Adds a slot of `PieceSet::MAX_SIZE` by `PieceSet::MAX_SIZE` cells with the shape of
+    /// `kind` as it comes in, in the middle of it, or empty if there's no piece.
+    fn push_shape(&mut self, kind: Option<PieceKind>) {
+        let size = PieceSet::MAX_SIZE as i32;
+        let mut blocks = vec![];
+        if let Some(kind) = kind {
+            let grid = kind.get_grid(PieceRotation::Deg0);
+            blocks = grid
+                .iter()
+                .filter(|(_, _, block)| block.is_some())
+                .map(|(x, y, _)| (x, y))
+                .collect();
+        }
+        let min = |axis: fn(&(i32, i32)) -> i32| blocks.iter().map(axis).min().unwrap_or(0);
+        let max = |axis: fn(&(i32, i32)) -> i32| blocks.iter().map(axis).max().unwrap_or(0);
+        let offset_x = (size - (max(|b| b.0) - min(|b| b.0) + 1)) / 2 - min(|b| b.0);
+        let offset_y = (size - (max(|b| b.1) - min(|b| b.1) + 1)) / 2 - min(|b| b.1);
+        let color = kind.map_or([0; 3], PieceKind::color);
+        for y in 0..size {
+            for x in 0..size {
+                if blocks.contains(&(x - offset_x, y - offset_y)) {
+                    self.push_cell(color, CellStyle::Block);
+                } else {
+                    self.push_cell([0; 3], CellStyle::Empty);
+                }
+            }
+        }
+    }
+}
+
+/// Number of modes that can be played, as in `GameMode::ALL`.
+#[no_mangle]
+pub extern "C" fn tetris_web_mode_count() -> u32 {
+    GameMode::ALL.len() as u32
+}
+
+/// Where the name of the mode at `index` in `GameMode::ALL` starts, in UTF-8
+/// `tetris_web_mode_name_len` bytes long.
+#[no_mangle]
+pub extern "C" fn tetris_web_mode_name(index: u32) -> *const u8 {
+    mode(index).name().as_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn tetris_web_mode_name_len(index: u32) -> usize {
+    mode(index).name().len()
+}
+
+/// Starts a game of the mode at `index` in `GameMode::ALL`, replacing the one being played.
+/// The page picks the seed, since there's no source of randomness in here.
+#[no_mangle]
+pub extern "C" fn tetris_web_start(index: u32, seed: u32) {
+    GAME.with(|game| *game.borrow_mut() = Some(WebGame::new(mode(index), seed)));
+}
+
+/// Presses or lets go of the action at `action` in `Action`. Pressing pause pauses the game
+/// or goes on with it.
+#[no_mangle]
+pub extern "C" fn tetris_web_key(action: u32, down: bool) {
+    if action as usize >= Action::LENGTH {
+        return;
+    }
+    with_game((), |web| web.key(Action::from_usize(action as usize), down));
+}
+
+/// Steps the game through `elapsed` milliseconds, along with any left over from before.
+/// Returns the sounds to play, one bit each by their order in `Sound`.
+#[no_mangle]
+pub extern "C" fn tetris_web_update(elapsed: f64) -> u32 {
+    with_game(0, |web| web.update(elapsed))
+}
+
+/// Draws the game into cells of 4 bytes each, red, green, blue and how it's drawn (0 for
+/// empty, 1 for a block and 2 for the ghost piece), and returns where they start. The board
+/// comes first, in rows from the top, without the hidden rows. After it come the held piece
+/// and then the next ones, in `tetris_web_preview_count` + 1 slots of
+/// `tetris_web_preview_size` by `tetris_web_preview_size` cells. They're kept until the next
+/// time this is called.
+#[no_mangle]
+pub extern "C" fn tetris_web_draw() -> *const u8 {
+    with_game(std::ptr::null(), |web| {
+        web.draw();
+        web.cells.as_ptr()
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn tetris_web_board_width() -> u32 {
+    with_game(0, |web| web.game.grid().width() as u32)
+}
+
+#[no_mangle]
+pub extern "C" fn tetris_web_board_height() -> u32 {
+    with_game(0, |web| {
+        let grid = web.game.grid();
+        (grid.height() - grid.hidden_rows()) as u32
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn tetris_web_preview_count() -> u32 {
+    PREVIEW as u32
+}
+
+#[no_mangle]
+pub extern "C" fn tetris_web_preview_size() -> u32 {
+    PieceSet::MAX_SIZE as u32
+}
+
+/// Where the lines of text beside the board start as of the last `tetris_web_draw`, in UTF-8
+/// `tetris_web_text_len` bytes long: the mode, the stats and how the game is going.
+#[no_mangle]
+pub extern "C" fn tetris_web_text() -> *const u8 {
+    with_game(std::ptr::null(), |web| web.text.as_ptr())
+}
+
+#[no_mangle]
+pub extern "C" fn tetris_web_text_len() -> usize {
+    with_game(0, |web| web.text.len())
+}
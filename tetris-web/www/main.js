@@ -0,0 +1,228 @@
+// Loads the game and its assets, then runs it on the canvas: keys go in as actions, each
+// frame tells the game how much time went by, and the sounds it gives back are played with
+// Web Audio.
+
+const CELL = 24;
+const PREVIEW_CELL = 12;
+const MARGIN = 16;
+const LINE_HEIGHT = 20;
+const CONTROLS = "ARROWS MOVE  SPACE DROP  UP/X CW  Z CCW  A 180  C HOLD  P PAUSE  R RESTART";
+
+// Actions by their index in `Action`, in tetris-core/src/input.rs.
+const MOVE_LEFT = 0;
+const MOVE_RIGHT = 1;
+const SOFT_DROP = 2;
+const HARD_DROP = 3;
+const ROTATE_CW = 4;
+const ROTATE_CCW = 5;
+const ROTATE_180 = 6;
+const HOLD = 7;
+const PAUSE = 8;
+const KEYS = {
+  ArrowLeft: MOVE_LEFT,
+  ArrowRight: MOVE_RIGHT,
+  ArrowDown: SOFT_DROP,
+  Space: HARD_DROP,
+  ArrowUp: ROTATE_CW,
+  KeyX: ROTATE_CW,
+  KeyZ: ROTATE_CCW,
+  KeyA: ROTATE_180,
+  KeyC: HOLD,
+  ShiftLeft: HOLD,
+  KeyP: PAUSE,
+  Escape: PAUSE,
+};
+
+// In the order of `Sound` in src/lib.rs.
+const SOUNDS = [
+  "move.wav",
+  "soft_drop.wav",
+  "bump.wav",
+  "land.wav",
+  "rotate.ogg",
+  "place.ogg",
+  "clear.wav",
+  "t_spin.wav",
+  "tetris.wav",
+];
+
+async function loadModule(url) {
+  try {
+    const { instance } = await WebAssembly.instantiateStreaming(fetch(url), {});
+    return instance;
+  } catch {
+    // Servers that don't say the module is application/wasm can't stream it.
+    const bytes = await (await fetch(url)).arrayBuffer();
+    const { instance } = await WebAssembly.instantiate(bytes, {});
+    return instance;
+  }
+}
+
+async function loadSound(audio, url) {
+  const response = await fetch(url);
+  return audio.decodeAudioData(await response.arrayBuffer());
+}
+
+async function loadFont() {
+  const font = new FontFace("Big Apple 3PM", "url('assets/fonts/Big Apple 3PM.ttf')");
+  document.fonts.add(await font.load());
+}
+
+function playSound(audio, buffer, loop = false) {
+  const source = audio.createBufferSource();
+  source.buffer = buffer;
+  source.loop = loop;
+  source.connect(audio.destination);
+  source.start();
+}
+
+// Draws `columns` by `rows` cells of 4 bytes each, as `tetris_web_draw` writes them, from
+// `offset` in `cells`, with the top left corner at `x`, `y`.
+function drawCells(context, cells, offset, columns, rows, x, y, size) {
+  for (let row = 0; row < rows; row++) {
+    for (let column = 0; column < columns; column++) {
+      const i = offset + (row * columns + column) * 4;
+      const [r, g, b, style] = cells.subarray(i, i + 4);
+      const left = x + column * size;
+      const top = y + row * size;
+      if (style === 1) {
+        context.fillStyle = `rgb(${r}, ${g}, ${b})`;
+        context.fillRect(left, top, size, size);
+        context.fillStyle = "rgba(255, 255, 255, 0.25)";
+        context.fillRect(left, top, size, size / 6);
+        context.fillStyle = "rgba(0, 0, 0, 0.25)";
+        context.fillRect(left, top + size * 5 / 6, size, size / 6);
+      } else if (style === 2) {
+        context.strokeStyle = `rgba(${r}, ${g}, ${b}, 0.6)`;
+        context.strokeRect(left + 1.5, top + 1.5, size - 3, size - 3);
+      }
+    }
+  }
+}
+
+async function main() {
+  const canvas = document.getElementById("game");
+  const context = canvas.getContext("2d");
+  const modes = document.getElementById("mode");
+  const status = document.getElementById("status");
+  // Sounds are decoded while the page loads, and start playing once a key is pressed,
+  // since browsers don't let pages make sound before that.
+  const audio = new AudioContext();
+  const [instance, sounds, music] = await Promise.all([
+    loadModule("tetris_web.wasm"),
+    Promise.all(SOUNDS.map((name) => loadSound(audio, `assets/sound/${name}`))),
+    loadSound(audio, "assets/music/game.mp3"),
+    loadFont(),
+  ]);
+  const wasm = instance.exports;
+  const decoder = new TextDecoder();
+  const text = (start, len) => decoder.decode(new Uint8Array(wasm.memory.buffer, start, len));
+
+  for (let i = 0; i < wasm.tetris_web_mode_count(); i++) {
+    const option = document.createElement("option");
+    option.value = i;
+    option.textContent = text(wasm.tetris_web_mode_name(i), wasm.tetris_web_mode_name_len(i));
+    modes.append(option);
+  }
+  const start = () => {
+    wasm.tetris_web_start(Number(modes.value), (Math.random() * 2 ** 32) >>> 0);
+    modes.blur();
+  };
+  modes.addEventListener("change", start);
+  start();
+  status.textContent = CONTROLS;
+
+  let musicStarted = false;
+  addEventListener("keydown", (event) => {
+    if (audio.state === "suspended") {
+      audio.resume();
+    }
+    if (!musicStarted) {
+      playSound(audio, music, true);
+      musicStarted = true;
+    }
+    if (event.code === "KeyR") {
+      start();
+    } else if (event.code in KEYS) {
+      event.preventDefault();
+      if (!event.repeat) {
+        wasm.tetris_web_key(KEYS[event.code], true);
+      }
+    }
+  });
+  addEventListener("keyup", (event) => {
+    if (event.code in KEYS) {
+      wasm.tetris_web_key(KEYS[event.code], false);
+    }
+  });
+  // Keys let go of while the page is in the background never come up.
+  addEventListener("blur", () => {
+    for (const action of Object.values(KEYS)) {
+      wasm.tetris_web_key(action, false);
+    }
+  });
+
+  let last;
+  const frame = (time) => {
+    const elapsed = last === undefined ? 0 : time - last;
+    last = time;
+    const played = wasm.tetris_web_update(elapsed);
+    sounds.forEach((sound, i) => {
+      if (played & (1 << i)) {
+        playSound(audio, sound);
+      }
+    });
+    draw();
+    requestAnimationFrame(frame);
+  };
+
+  const draw = () => {
+    const start = wasm.tetris_web_draw();
+    const width = wasm.tetris_web_board_width();
+    const height = wasm.tetris_web_board_height();
+    const slots = wasm.tetris_web_preview_count() + 1;
+    const size = wasm.tetris_web_preview_size();
+    const len = (width * height + slots * size * size) * 4;
+    // Read after drawing, since memory can grow and leave older views empty.
+    const cells = new Uint8Array(wasm.memory.buffer, start, len);
+    const lines = text(wasm.tetris_web_text(), wasm.tetris_web_text_len()).split("\n");
+
+    const slot = size * PREVIEW_CELL;
+    const boardX = MARGIN * 2 + slot * 2;
+    const nextX = boardX + width * CELL + MARGIN;
+    const canvasWidth = nextX + slot + MARGIN;
+    const canvasHeight = Math.max(height * CELL, (slots - 1) * slot + LINE_HEIGHT) + MARGIN * 2;
+    if (canvas.width !== canvasWidth || canvas.height !== canvasHeight) {
+      canvas.width = canvasWidth;
+      canvas.height = canvasHeight;
+    }
+    context.fillStyle = "#000";
+    context.fillRect(0, 0, canvas.width, canvas.height);
+    context.fillStyle = "#1a1a1a";
+    context.fillRect(boardX, MARGIN, width * CELL, height * CELL);
+    drawCells(context, cells, 0, width, height, boardX, MARGIN, CELL);
+
+    context.fillStyle = "#eee";
+    context.font = "16px 'Big Apple 3PM', monospace";
+    context.textBaseline = "top";
+    context.fillText("HOLD", MARGIN, MARGIN);
+    context.fillText("NEXT", nextX, MARGIN);
+    const pieces = width * height * 4;
+    drawCells(context, cells, pieces, size, size, MARGIN, MARGIN + LINE_HEIGHT, PREVIEW_CELL);
+    for (let i = 1; i < slots; i++) {
+      const offset = pieces + i * size * size * 4;
+      const y = MARGIN + LINE_HEIGHT + (i - 1) * slot;
+      drawCells(context, cells, offset, size, size, nextX, y, PREVIEW_CELL);
+    }
+    context.font = "12px 'Big Apple 3PM', monospace";
+    lines.forEach((line, i) => {
+      context.fillText(line, MARGIN, MARGIN * 2 + LINE_HEIGHT + slot + i * LINE_HEIGHT);
+    });
+  };
+
+  requestAnimationFrame(frame);
+}
+
+main().catch((error) => {
+  document.getElementById("status").textContent = `COULDN'T LOAD THE GAME: ${error}`;
+});